@@ -48,7 +48,9 @@ use serde::{Deserialize, Deserializer};
 
 use crate::defaults::{Defaults, Testnet1};
 use partial_struct::PartialStruct;
-use witnet_data_structures::chain::{ConsensusConstants, Environment, PartialConsensusConstants};
+use witnet_data_structures::chain::{
+    ConsensusConstants, Environment, PartialConsensusConstants, PublicKeyHash,
+};
 use witnet_protected::Protected;
 
 /// The total configuration object that contains all other, more
@@ -105,6 +107,10 @@ pub struct Connections {
     /// (acting as a client) should maintain
     pub outbound_limit: u16,
 
+    /// Maximum number of outbound connections to peers in the same
+    /// network group (/16 for IPv4, /32 for IPv6)
+    pub outbound_limit_per_network_group: u16,
+
     /// List of other peer addresses this node knows at start, it is
     /// used as a bootstrap mechanism to gain access to the P2P
     /// network
@@ -146,6 +152,70 @@ pub struct Connections {
 
     /// Number of seconds before giving up waiting for requested blocks
     pub blocks_timeout: i64,
+
+    /// Period between the pings sent to each consolidated session
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "ping_period_seconds"
+    ))]
+    pub ping_period: Duration,
+
+    /// Time to wait for the reply to a ping before disconnecting the peer
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "ping_timeout_seconds"
+    ))]
+    pub ping_timeout: Duration,
+
+    /// Maximum difference between the timestamp of a peer and the local one
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "handshake_max_time_skew_seconds"
+    ))]
+    pub handshake_max_time_skew: Duration,
+
+    /// Ban score at which a misbehaving peer gets banned
+    pub ban_threshold: u32,
+
+    /// Time during which a peer stays banned
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "ban_duration_seconds"
+    ))]
+    pub ban_duration: Duration,
+
+    /// Offer the peers to encrypt the sessions, which only happens if they also offer it
+    pub encryption: bool,
+
+    /// Only accept encrypted sessions, disconnecting the peers which do not offer encryption.
+    /// Implies `encryption`
+    pub require_encryption: bool,
+
+    /// Identities of the only peers allowed to complete the handshake, as the hashes of their
+    /// public keys. If empty, any peer is allowed. Implies `require_encryption`
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default, deserialize_with = "from_addresses"))]
+    pub trusted_peers: HashSet<PublicKeyHash>,
+}
+
+/// Parse a list of bech32 addresses. The environment of the addresses cannot be checked here
+/// because it is set after loading the configuration, so any known environment is accepted
+fn from_addresses<'de, D>(deserializer: D) -> Result<HashSet<PublicKeyHash>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|address| {
+            PublicKeyHash::from_bech32_any_environment(address)
+                .map(|(_, pkh)| pkh)
+                .map_err(serde::de::Error::custom)
+        })
+        .collect()
 }
 
 fn from_secs<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
@@ -190,6 +260,12 @@ pub struct Storage {
     /// Path to the directory that will contain the database. Used
     /// only if backend is RocksDB.
     pub db_path: PathBuf,
+    /// Path to a file containing the master key of the node, either
+    /// as a BIP39 mnemonic or as an hex-encoded extended key. When
+    /// set, this key is used instead of the one in the storage
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub master_key_path: Option<PathBuf>,
 }
 
 fn as_protected_string<'de, D>(deserializer: D) -> Result<Option<Protected>, D::Error>
@@ -209,6 +285,60 @@ pub struct JsonRPC {
     /// JSON-RPC server address, that is, the socket address (interface ip and
     /// port) for the JSON-RPC server
     pub server_address: SocketAddr,
+    /// Socket address for the HTTP JSON-RPC server, which accepts
+    /// requests as POST. When not set, the HTTP server is not started
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub http_server_address: Option<SocketAddr>,
+    /// Socket address for the WebSockets JSON-RPC server. When not set,
+    /// the WebSockets server is not started
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub ws_server_address: Option<SocketAddr>,
+    /// Maximum size in bytes of a single JSON-RPC request. Clients
+    /// sending longer requests are disconnected
+    pub max_request_size: usize,
+    /// Connections which have not sent any request nor received any
+    /// notification during this time are closed
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "idle_timeout_seconds"
+    ))]
+    pub idle_timeout: Duration,
+    /// Maximum number of concurrent connections each of the TCP, HTTP
+    /// and WebSockets servers should accept
+    pub max_connections: u16,
+    /// Maximum number of requests per second accepted from a single
+    /// connection, or from a single IP address over HTTP. Requests over
+    /// this limit are answered with an error
+    pub max_requests_per_second: u32,
+    /// Token which the clients must send to authenticate. When not
+    /// set, the token is generated and written to `auth_cookie_path`
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub auth_token: Option<String>,
+    /// File where the node writes a random authentication token at
+    /// start, so that the local clients can read it. When neither this
+    /// nor `auth_token` are set, authentication is disabled
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub auth_cookie_path: Option<PathBuf>,
+    /// Origins allowed to call the HTTP and WebSockets servers from a
+    /// browser. Requests without an `Origin` header are always allowed
+    pub allowed_origins: Vec<String>,
+    /// Accepted values of the `Host` header of the HTTP and WebSockets
+    /// requests, besides the address of the server itself
+    pub allowed_hosts: Vec<String>,
+    /// Binary flag telling whether to enable the methods which read
+    /// the state of the node
+    pub read_only_methods: bool,
+    /// Binary flag telling whether to enable the methods which submit
+    /// new blocks and transactions to the node
+    pub submit_methods: bool,
+    /// Binary flag telling whether to enable the methods which manage
+    /// the node, such as exporting its master key
+    pub admin_methods: bool,
 }
 
 /// Mining-related configuration
@@ -217,6 +347,14 @@ pub struct JsonRPC {
 pub struct Mining {
     /// Binary flag telling whether to enable the MiningManager or not
     pub enabled: bool,
+    /// Time to wait after each checkpoint before mining a block, so that the transactions of
+    /// the data requests mined in the same epoch can be included in it
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "block_delay_seconds"
+    ))]
+    pub block_delay: Duration,
 }
 
 impl Config {
@@ -310,6 +448,10 @@ impl Connections {
                 .outbound_limit
                 .to_owned()
                 .unwrap_or_else(|| defaults.connections_outbound_limit()),
+            outbound_limit_per_network_group: config
+                .outbound_limit_per_network_group
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_outbound_limit_per_network_group()),
             known_peers: config
                 .known_peers
                 .union(&defaults.connections_known_peers())
@@ -334,6 +476,39 @@ impl Connections {
                 .blocks_timeout
                 .to_owned()
                 .unwrap_or_else(|| defaults.connections_blocks_timeout()),
+            ping_period: config
+                .ping_period
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_ping_period()),
+            ping_timeout: config
+                .ping_timeout
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_ping_timeout()),
+            handshake_max_time_skew: config
+                .handshake_max_time_skew
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_handshake_max_time_skew()),
+            ban_threshold: config
+                .ban_threshold
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_ban_threshold()),
+            ban_duration: config
+                .ban_duration
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_ban_duration()),
+            encryption: config
+                .encryption
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_encryption()),
+            require_encryption: config
+                .require_encryption
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_require_encryption()),
+            trusted_peers: config
+                .trusted_peers
+                .union(&defaults.connections_trusted_peers())
+                .cloned()
+                .collect(),
         }
     }
 }
@@ -347,6 +522,7 @@ impl Storage {
                 .db_path
                 .to_owned()
                 .unwrap_or_else(|| defaults.storage_db_path()),
+            master_key_path: config.master_key_path.clone(),
         }
     }
 }
@@ -362,6 +538,46 @@ impl JsonRPC {
                 .server_address
                 .to_owned()
                 .unwrap_or_else(|| defaults.jsonrpc_server_address()),
+            http_server_address: config.http_server_address,
+            ws_server_address: config.ws_server_address,
+            max_request_size: config
+                .max_request_size
+                .to_owned()
+                .unwrap_or_else(|| defaults.jsonrpc_max_request_size()),
+            idle_timeout: config
+                .idle_timeout
+                .to_owned()
+                .unwrap_or_else(|| defaults.jsonrpc_idle_timeout()),
+            max_connections: config
+                .max_connections
+                .to_owned()
+                .unwrap_or_else(|| defaults.jsonrpc_max_connections()),
+            max_requests_per_second: config
+                .max_requests_per_second
+                .to_owned()
+                .unwrap_or_else(|| defaults.jsonrpc_max_requests_per_second()),
+            auth_token: config.auth_token.clone(),
+            auth_cookie_path: config.auth_cookie_path.clone(),
+            allowed_origins: config
+                .allowed_origins
+                .to_owned()
+                .unwrap_or_else(|| defaults.jsonrpc_allowed_origins()),
+            allowed_hosts: config
+                .allowed_hosts
+                .to_owned()
+                .unwrap_or_else(|| defaults.jsonrpc_allowed_hosts()),
+            read_only_methods: config
+                .read_only_methods
+                .to_owned()
+                .unwrap_or_else(|| defaults.jsonrpc_read_only_methods()),
+            submit_methods: config
+                .submit_methods
+                .to_owned()
+                .unwrap_or_else(|| defaults.jsonrpc_submit_methods()),
+            admin_methods: config
+                .admin_methods
+                .to_owned()
+                .unwrap_or_else(|| defaults.jsonrpc_admin_methods()),
         }
     }
 }
@@ -373,6 +589,10 @@ impl Mining {
                 .enabled
                 .to_owned()
                 .unwrap_or_else(|| defaults.mining_enabled()),
+            block_delay: config
+                .block_delay
+                .to_owned()
+                .unwrap_or_else(|| defaults.mining_block_delay()),
         }
     }
 }
@@ -395,10 +615,12 @@ mod tests {
             backend: StorageBackend::RocksDB,
             password: None,
             db_path: Some(PathBuf::from("other")),
+            master_key_path: Some(PathBuf::from("master.key")),
         };
         let config = Storage::from_partial(&partial_config, &Testnet1);
 
         assert_eq!(config.db_path.to_str(), Some("other"));
+        assert_eq!(config.master_key_path, Some(PathBuf::from("master.key")));
     }
 
     #[test]
@@ -409,6 +631,10 @@ mod tests {
         assert_eq!(config.server_addr, Testnet1.connections_server_addr());
         assert_eq!(config.inbound_limit, Testnet1.connections_inbound_limit());
         assert_eq!(config.outbound_limit, Testnet1.connections_outbound_limit());
+        assert_eq!(
+            config.outbound_limit_per_network_group,
+            Testnet1.connections_outbound_limit_per_network_group()
+        );
         assert_eq!(config.known_peers, Testnet1.connections_known_peers());
         assert_eq!(
             config.bootstrap_peers_period,
@@ -427,33 +653,66 @@ mod tests {
             Testnet1.connections_handshake_timeout()
         );
         assert_eq!(config.blocks_timeout, Testnet1.connections_blocks_timeout());
+        assert_eq!(config.ping_period, Testnet1.connections_ping_period());
+        assert_eq!(config.ping_timeout, Testnet1.connections_ping_timeout());
+        assert_eq!(
+            config.handshake_max_time_skew,
+            Testnet1.connections_handshake_max_time_skew()
+        );
+        assert_eq!(config.ban_threshold, Testnet1.connections_ban_threshold());
+        assert_eq!(config.ban_duration, Testnet1.connections_ban_duration());
+        assert_eq!(config.encryption, Testnet1.connections_encryption());
+        assert_eq!(
+            config.require_encryption,
+            Testnet1.connections_require_encryption()
+        );
+        assert_eq!(config.trusted_peers, Testnet1.connections_trusted_peers());
     }
 
     #[test]
     fn test_connections_from_partial() {
         let addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let identity = PublicKeyHash::from_bytes(&[0xab; 20]).unwrap();
         let partial_config = PartialConnections {
             server_addr: Some(addr),
             inbound_limit: Some(3),
             outbound_limit: Some(4),
+            outbound_limit_per_network_group: Some(1),
             known_peers: [addr].iter().cloned().collect(),
             bootstrap_peers_period: Some(Duration::from_secs(10)),
             storage_peers_period: Some(Duration::from_secs(60)),
             discovery_peers_period: Some(Duration::from_secs(100)),
             handshake_timeout: Some(Duration::from_secs(3)),
             blocks_timeout: Some(5),
+            ping_period: Some(Duration::from_secs(20)),
+            ping_timeout: Some(Duration::from_secs(10)),
+            handshake_max_time_skew: Some(Duration::from_secs(45)),
+            ban_threshold: Some(50),
+            ban_duration: Some(Duration::from_secs(3600)),
+            encryption: Some(false),
+            require_encryption: Some(true),
+            trusted_peers: [identity].iter().cloned().collect(),
         };
         let config = Connections::from_partial(&partial_config, &Testnet1);
 
         assert_eq!(config.server_addr, addr);
         assert_eq!(config.inbound_limit, 3);
         assert_eq!(config.outbound_limit, 4);
+        assert_eq!(config.outbound_limit_per_network_group, 1);
         assert!(config.known_peers.contains(&addr));
         assert_eq!(config.bootstrap_peers_period, Duration::from_secs(10));
         assert_eq!(config.storage_peers_period, Duration::from_secs(60));
         assert_eq!(config.discovery_peers_period, Duration::from_secs(100));
         assert_eq!(config.handshake_timeout, Duration::from_secs(3));
         assert_eq!(config.blocks_timeout, 5);
+        assert_eq!(config.ping_period, Duration::from_secs(20));
+        assert_eq!(config.ping_timeout, Duration::from_secs(10));
+        assert_eq!(config.handshake_max_time_skew, Duration::from_secs(45));
+        assert_eq!(config.ban_threshold, 50);
+        assert_eq!(config.ban_duration, Duration::from_secs(3600));
+        assert!(!config.encryption);
+        assert!(config.require_encryption);
+        assert!(config.trusted_peers.contains(&identity));
     }
 
     #[test]
@@ -462,18 +721,61 @@ mod tests {
         let config = JsonRPC::from_partial(&partial_config, &Testnet1);
 
         assert_eq!(config.server_address, Testnet1.jsonrpc_server_address());
+        assert_eq!(config.max_request_size, Testnet1.jsonrpc_max_request_size());
+        assert_eq!(config.idle_timeout, Testnet1.jsonrpc_idle_timeout());
+        assert_eq!(config.max_connections, Testnet1.jsonrpc_max_connections());
+        assert_eq!(
+            config.max_requests_per_second,
+            Testnet1.jsonrpc_max_requests_per_second()
+        );
+        assert_eq!(config.auth_token, None);
+        assert_eq!(config.auth_cookie_path, None);
+        assert_eq!(config.allowed_origins, Testnet1.jsonrpc_allowed_origins());
+        assert_eq!(config.allowed_hosts, Testnet1.jsonrpc_allowed_hosts());
+        assert_eq!(
+            config.read_only_methods,
+            Testnet1.jsonrpc_read_only_methods()
+        );
+        assert_eq!(config.submit_methods, Testnet1.jsonrpc_submit_methods());
+        assert_eq!(config.admin_methods, Testnet1.jsonrpc_admin_methods());
     }
 
     #[test]
     fn test_jsonrpc_from_partial() {
         let addr: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let ws_addr: SocketAddr = "127.0.0.1:4001".parse().unwrap();
         let partial_config = PartialJsonRPC {
             enabled: None,
             server_address: Some(addr),
+            http_server_address: None,
+            ws_server_address: Some(ws_addr),
+            max_request_size: Some(1024),
+            idle_timeout: Some(Duration::from_secs(20)),
+            max_connections: Some(2),
+            max_requests_per_second: Some(10),
+            auth_token: Some("secret".to_string()),
+            auth_cookie_path: None,
+            allowed_origins: Some(vec!["*".to_string()]),
+            allowed_hosts: None,
+            read_only_methods: None,
+            submit_methods: Some(false),
+            admin_methods: Some(true),
         };
         let config = JsonRPC::from_partial(&partial_config, &Testnet1);
 
         assert_eq!(config.server_address, addr);
+        assert_eq!(config.http_server_address, None);
+        assert_eq!(config.ws_server_address, Some(ws_addr));
+        assert_eq!(config.max_request_size, 1024);
+        assert_eq!(config.idle_timeout, Duration::from_secs(20));
+        assert_eq!(config.max_connections, 2);
+        assert_eq!(config.max_requests_per_second, 10);
+        assert_eq!(config.auth_token, Some("secret".to_string()));
+        assert_eq!(config.allowed_origins, vec!["*".to_string()]);
+        assert_eq!(config.allowed_hosts, Testnet1.jsonrpc_allowed_hosts());
+        assert!(config.read_only_methods);
+        assert!(!config.submit_methods);
+        assert!(config.admin_methods);
     }
 
     #[test]
@@ -7,7 +7,7 @@ use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
 use std::time::Duration;
 
-use witnet_data_structures::chain::Hash;
+use witnet_data_structures::chain::{Hash, PublicKeyHash};
 
 // When changing the defaults, remember to update the documentation!
 // https://github.com/witnet/witnet-rust/blob/master/docs/configuration/toml-file.md
@@ -30,6 +30,11 @@ pub trait Defaults {
         8
     }
 
+    /// Default outbound limit for connections to peers in the same network group: `2`
+    fn connections_outbound_limit_per_network_group(&self) -> u16 {
+        2
+    }
+
     /// Default known peers: none
     fn connections_known_peers(&self) -> HashSet<SocketAddr> {
         HashSet::new()
@@ -92,11 +97,66 @@ pub trait Defaults {
     /// Default JSON-RPC server addr
     fn jsonrpc_server_address(&self) -> SocketAddr;
 
+    /// Default maximum size of a JSON-RPC request: 1 MiB
+    fn jsonrpc_max_request_size(&self) -> usize {
+        1024 * 1024
+    }
+
+    /// Default timeout for idle JSON-RPC connections
+    fn jsonrpc_idle_timeout(&self) -> Duration {
+        Duration::from_secs(300)
+    }
+
+    /// Default maximum number of concurrent JSON-RPC connections
+    fn jsonrpc_max_connections(&self) -> u16 {
+        32
+    }
+
+    /// Default maximum number of requests per second of a JSON-RPC connection
+    fn jsonrpc_max_requests_per_second(&self) -> u32 {
+        100
+    }
+
+    /// Default origins allowed to call the HTTP and WebSockets JSON-RPC servers from a browser:
+    /// only local pages, because authentication is disabled by default
+    fn jsonrpc_allowed_origins(&self) -> Vec<String> {
+        vec![
+            "http://localhost:*".to_string(),
+            "http://127.0.0.1:*".to_string(),
+        ]
+    }
+
+    /// Default values of the `Host` header accepted by the HTTP and WebSockets JSON-RPC
+    /// servers: only local names, to prevent DNS rebinding attacks
+    fn jsonrpc_allowed_hosts(&self) -> Vec<String> {
+        vec!["localhost:*".to_string(), "127.0.0.1:*".to_string()]
+    }
+
+    /// JSON-RPC methods which read the state of the node, enabled by default
+    fn jsonrpc_read_only_methods(&self) -> bool {
+        true
+    }
+
+    /// JSON-RPC methods which submit blocks and transactions, enabled by default
+    fn jsonrpc_submit_methods(&self) -> bool {
+        true
+    }
+
+    /// JSON-RPC methods which manage the node, disabled by default
+    fn jsonrpc_admin_methods(&self) -> bool {
+        false
+    }
+
     /// MiningManager, enabled by default
     fn mining_enabled(&self) -> bool {
         true
     }
 
+    /// Default time to wait after each checkpoint before mining a block: 5 seconds
+    fn mining_block_delay(&self) -> Duration {
+        Duration::from_secs(5)
+    }
+
     fn consensus_constants_max_block_weight(&self) -> u32 {
         // TODO: Replace  with real max_block_weight value used in mainnet
         10_000
@@ -107,6 +167,46 @@ pub trait Defaults {
     fn connections_blocks_timeout(&self) -> i64 {
         400
     }
+
+    /// Default period between pings to each consolidated session
+    fn connections_ping_period(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+
+    /// Default time to wait for the reply to a ping
+    fn connections_ping_timeout(&self) -> Duration {
+        Duration::from_secs(20)
+    }
+
+    /// Default maximum clock difference allowed with a peer during the handshake
+    fn connections_handshake_max_time_skew(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    /// Default ban score at which a misbehaving peer gets banned
+    fn connections_ban_threshold(&self) -> u32 {
+        100
+    }
+
+    /// Default time during which a peer stays banned: 24 hours
+    fn connections_ban_duration(&self) -> Duration {
+        Duration::from_secs(24 * 60 * 60)
+    }
+
+    /// Sessions are encrypted by default
+    fn connections_encryption(&self) -> bool {
+        true
+    }
+
+    /// Sessions without encryption are accepted by default
+    fn connections_require_encryption(&self) -> bool {
+        false
+    }
+
+    /// Any peer is trusted by default
+    fn connections_trusted_peers(&self) -> HashSet<PublicKeyHash> {
+        HashSet::new()
+    }
 }
 
 /// Struct that will implement all the mainnet defaults
@@ -132,12 +132,17 @@ known_peers = ['192.168.1.12:1234']
             r"
 [storage]
 db_path = 'dbfiles'
+master_key_path = 'master.key'
     ",
         )
         .unwrap();
 
         assert_eq!(empty_config.storage, PartialStorage::default());
         assert_eq!(config.storage.db_path, Some(PathBuf::from("dbfiles")));
+        assert_eq!(
+            config.storage.master_key_path,
+            Some(PathBuf::from("master.key"))
+        );
     }
 
     #[test]
@@ -151,6 +156,11 @@ db_path = 'dbfiles'
 bootstrap_peers_period_seconds = 11
 storage_peers_period_seconds = 7
 handshake_timeout_seconds = 21
+ping_period_seconds = 13
+ping_timeout_seconds = 17
+handshake_max_time_skew_seconds = 90
+ban_threshold = 40
+ban_duration_seconds = 600
     ",
         )
         .unwrap();
@@ -182,10 +192,29 @@ handshake_timeout_seconds = 21
             config.connections.handshake_timeout,
             Some(Duration::from_secs(21))
         );
+        assert_eq!(
+            config.connections.ping_period,
+            Some(Duration::from_secs(13))
+        );
+        assert_eq!(
+            config.connections.ping_timeout,
+            Some(Duration::from_secs(17))
+        );
+        assert_eq!(
+            config.connections.handshake_max_time_skew,
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(config.connections.ban_threshold, Some(40));
+        assert_eq!(
+            config.connections.ban_duration,
+            Some(Duration::from_secs(600))
+        );
     }
 
     #[test]
     fn test_configure_jsonrpc() {
+        use std::time::Duration;
+
         let empty_config = super::from_str("[jsonrpc]").unwrap();
         let config = super::from_str(
             r"
@@ -198,6 +227,28 @@ server_address = '127.0.0.1:1234'
             r"
 [jsonrpc]
 enabled = false
+    ",
+        )
+        .unwrap();
+        let config_limits = super::from_str(
+            r"
+[jsonrpc]
+max_request_size = 4096
+idle_timeout_seconds = 60
+max_connections = 4
+max_requests_per_second = 20
+auth_cookie_path = '.witnet-rust-testnet-1/jsonrpc.cookie'
+admin_methods = true
+    ",
+        )
+        .unwrap();
+        let config_transports = super::from_str(
+            r"
+[jsonrpc]
+http_server_address = '127.0.0.1:1235'
+ws_server_address = '127.0.0.1:1236'
+allowed_origins = ['https://sheikah.app']
+allowed_hosts = ['witnet.local:*']
     ",
         )
         .unwrap();
@@ -207,21 +258,58 @@ enabled = false
             config.jsonrpc.server_address,
             Some("127.0.0.1:1234".parse().unwrap())
         );
+        assert_eq!(config.jsonrpc.http_server_address, None);
         assert_eq!(config_disabled.jsonrpc.enabled, Some(false),);
+        assert_eq!(
+            config_transports.jsonrpc.http_server_address,
+            Some("127.0.0.1:1235".parse().unwrap())
+        );
+        assert_eq!(
+            config_transports.jsonrpc.ws_server_address,
+            Some("127.0.0.1:1236".parse().unwrap())
+        );
+        assert_eq!(
+            config_transports.jsonrpc.allowed_origins,
+            Some(vec!["https://sheikah.app".to_string()])
+        );
+        assert_eq!(
+            config_transports.jsonrpc.allowed_hosts,
+            Some(vec!["witnet.local:*".to_string()])
+        );
+        assert_eq!(config_limits.jsonrpc.max_request_size, Some(4096));
+        assert_eq!(
+            config_limits.jsonrpc.idle_timeout,
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(config_limits.jsonrpc.max_connections, Some(4));
+        assert_eq!(config_limits.jsonrpc.max_requests_per_second, Some(20));
+        assert_eq!(
+            config_limits.jsonrpc.auth_cookie_path,
+            Some(PathBuf::from(".witnet-rust-testnet-1/jsonrpc.cookie"))
+        );
+        assert_eq!(config_limits.jsonrpc.admin_methods, Some(true));
+        assert_eq!(config_limits.jsonrpc.submit_methods, None);
     }
 
     #[test]
     fn test_configure_mining() {
+        use std::time::Duration;
+
         let empty_config = super::from_str("[mining]").unwrap();
         let config_disabled = super::from_str(
             r"
 [mining]
 enabled = false
+block_delay_seconds = 0
     ",
         )
         .unwrap();
 
         assert_eq!(empty_config.mining, PartialMining::default());
         assert_eq!(config_disabled.mining.enabled, Some(false),);
+        assert_eq!(
+            config_disabled.mining.block_delay,
+            Some(Duration::from_secs(0))
+        );
     }
 }
@@ -1,8 +1,10 @@
 //! Cipher
 use crypto::{
+    aead::{AeadDecryptor, AeadEncryptor},
     aes,
     blockmodes::PkcsPadding,
     buffer::{self, ReadBuffer, WriteBuffer},
+    chacha20poly1305::ChaCha20Poly1305,
     symmetriccipher,
 };
 use failure::Fail;
@@ -17,8 +19,18 @@ pub enum Error {
     /// Wrapper for random generation errors
     #[fail(display = "Random generation error")]
     Rng(rand::Error),
+    /// The authentication tag does not match, because the secret is wrong or the data was
+    /// tampered with
+    #[fail(display = "Authentication error")]
+    Authentication,
 }
 
+/// Size in bytes of the nonce of ChaCha20-Poly1305
+pub const CHACHA20_POLY1305_NONCE_LENGTH: usize = 8;
+
+/// Size in bytes of the authentication tag of ChaCha20-Poly1305
+pub const CHACHA20_POLY1305_TAG_LENGTH: usize = 16;
+
 /// Encrypt data with AES CBC using the supplied secret
 pub fn encrypt_aes_cbc(secret: &[u8], data: &[u8], iv: &[u8]) -> Result<Vec<u8>, Error> {
     let mut encryptor = aes::cbc_encryptor(aes::KeySize::KeySize256, secret, iv, PkcsPadding);
@@ -79,6 +91,37 @@ pub fn decrypt_aes_cbc(secret: &[u8], data: &[u8], iv: &[u8]) -> Result<Vec<u8>,
     Ok(final_result)
 }
 
+/// Encrypt data with ChaCha20-Poly1305 using the supplied 32 bytes secret and 8 bytes nonce,
+/// appending the authentication tag. A nonce must never be reused with the same secret
+pub fn encrypt_chacha20_poly1305(secret: &[u8], data: &[u8], nonce: &[u8]) -> Vec<u8> {
+    let mut cipher = ChaCha20Poly1305::new(secret, nonce, &[]);
+    let mut encrypted = vec![0; data.len() + CHACHA20_POLY1305_TAG_LENGTH];
+    let (output, tag) = encrypted.split_at_mut(data.len());
+    cipher.encrypt(data, output, tag);
+
+    encrypted
+}
+
+/// Decrypt data encrypted with `encrypt_chacha20_poly1305`, checking its authentication tag
+pub fn decrypt_chacha20_poly1305(
+    secret: &[u8],
+    data: &[u8],
+    nonce: &[u8],
+) -> Result<Vec<u8>, Error> {
+    if data.len() < CHACHA20_POLY1305_TAG_LENGTH {
+        return Err(Error::Authentication);
+    }
+
+    let mut cipher = ChaCha20Poly1305::new(secret, nonce, &[]);
+    let (input, tag) = data.split_at(data.len() - CHACHA20_POLY1305_TAG_LENGTH);
+    let mut decrypted = vec![0; input.len()];
+    if cipher.decrypt(input, &mut decrypted, tag) {
+        Ok(decrypted)
+    } else {
+        Err(Error::Authentication)
+    }
+}
+
 /// Generate a random initialization vector of the given size in bytes
 pub fn generate_random(size: usize) -> Result<Vec<u8>, Error> {
     let mut iv = vec![0u8; size];
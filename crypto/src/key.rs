@@ -9,13 +9,24 @@
 //! let ext_key = key::MasterKeyGen::new(seed).generate();
 //! ```
 
+use std::iter;
+
 use failure::Fail;
 use hmac::{Hmac, Mac};
 use secp256k1::{PublicKey, Secp256k1, SecretKey};
-use sha2;
+use sha2::{self, Digest};
 
 const HARDENED_BIT: u32 = 1 << 31;
 
+/// Version bytes of a BIP32 serialized extended private key, which make it start with `xprv`
+const XPRV_VERSION: [u8; 4] = [0x04, 0x88, 0xAD, 0xE4];
+
+/// Length of a BIP32 serialized extended key, without the checksum
+const XPRV_LENGTH: usize = 78;
+
+/// Alphabet of the base58 encoding
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
 /// Default HMAC key used when generating a Master Key with
 /// [generate_master](generate_master)
 pub static DEFAULT_HMAC_KEY: &[u8] = b"Bitcoin seed";
@@ -97,6 +108,37 @@ pub enum KeyDerivationError {
     Secp256k1Error(secp256k1::Error),
 }
 
+/// The error type for [from_xprv](ExtendedSK::from_xprv)
+#[derive(Debug, PartialEq, Fail)]
+pub enum XprvError {
+    /// Invalid base58 character
+    #[fail(
+        display = "Invalid character {:?} at index {}, the key must be encoded in base58",
+        character, index
+    )]
+    InvalidCharacter {
+        /// Invalid character
+        character: char,
+        /// Position of the invalid character
+        index: usize,
+    },
+    /// Invalid length of the serialized key
+    #[fail(display = "The length of the serialized key is invalid")]
+    InvalidLength,
+    /// The checksum does not match the serialized key
+    #[fail(display = "The checksum of the serialized key does not match")]
+    InvalidChecksum,
+    /// The serialized key is not an extended private key
+    #[fail(display = "The serialized key is not an extended private key (xprv)")]
+    InvalidVersion,
+    /// The serialized key is not a master key
+    #[fail(display = "The serialized key is not a master key, its depth must be 0")]
+    NotMasterKey,
+    /// Secp256k1 internal error
+    #[fail(display = "Error in secp256k1 crate")]
+    Secp256k1Error(secp256k1::Error),
+}
+
 /// Secret Key
 pub type SK = SecretKey;
 
@@ -120,10 +162,20 @@ pub struct ExtendedSK {
 }
 
 /// A child number for a derived key
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub struct ChildNumber(u32);
 
 impl ChildNumber {
+    /// Create a normal (non-hardened) child number
+    pub fn normal(index: u32) -> Self {
+        ChildNumber(index & !HARDENED_BIT)
+    }
+
+    /// Create a hardened child number
+    pub fn hardened(index: u32) -> Self {
+        ChildNumber(index | HARDENED_BIT)
+    }
+
     /// check if a child is hardened
     pub fn is_hardened(self) -> bool {
         self.0 & HARDENED_BIT == HARDENED_BIT
@@ -136,6 +188,78 @@ impl ChildNumber {
 }
 
 impl ExtendedSK {
+    /// Try to build an extended private key from the 32 bytes of the secret key followed by
+    /// the 32 bytes of the chain code
+    pub fn from_slice(bytes: &[u8]) -> Result<ExtendedSK, KeyDerivationError> {
+        if bytes.len() != 64 {
+            Err(KeyDerivationError::InvalidKeyLength)?
+        }
+        let (sk_bytes, chain_code_bytes) = bytes.split_at(32);
+        let secret_key =
+            SecretKey::from_slice(sk_bytes).map_err(KeyDerivationError::Secp256k1Error)?;
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(chain_code_bytes);
+
+        Ok(ExtendedSK {
+            secret_key,
+            chain_code,
+        })
+    }
+
+    /// Serialize the secret key followed by the chain code
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&self.secret_key[..]);
+        bytes[32..].copy_from_slice(&self.chain_code);
+
+        bytes
+    }
+
+    /// Serialize as a BIP32 master extended private key, encoded in base58 with a checksum
+    /// (`xprv...`)
+    pub fn to_xprv(&self) -> String {
+        let mut bytes = Vec::with_capacity(XPRV_LENGTH + 4);
+        bytes.extend_from_slice(&XPRV_VERSION);
+        // Depth, parent fingerprint and child number of a master key
+        bytes.extend_from_slice(&[0; 9]);
+        bytes.extend_from_slice(&self.chain_code);
+        bytes.push(0);
+        bytes.extend_from_slice(&self.secret_key[..]);
+        let checksum = xprv_checksum(&bytes);
+        bytes.extend_from_slice(&checksum);
+
+        base58_encode(&bytes)
+    }
+
+    /// Try to parse a BIP32 master extended private key encoded in base58 with a checksum
+    /// (`xprv...`), failing if any character is invalid or if the checksum does not match
+    pub fn from_xprv(xprv: &str) -> Result<ExtendedSK, XprvError> {
+        let bytes = base58_decode(xprv)?;
+        if bytes.len() != XPRV_LENGTH + 4 {
+            Err(XprvError::InvalidLength)?
+        }
+        let (payload, checksum) = bytes.split_at(XPRV_LENGTH);
+        if checksum[..] != xprv_checksum(payload)[..] {
+            Err(XprvError::InvalidChecksum)?
+        }
+        if payload[..4] != XPRV_VERSION || payload[45] != 0 {
+            Err(XprvError::InvalidVersion)?
+        }
+        // Depth, parent fingerprint and child number of a master key are 0
+        if payload[4..13].iter().any(|byte| *byte != 0) {
+            Err(XprvError::NotMasterKey)?
+        }
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&payload[13..45]);
+        let secret_key =
+            SecretKey::from_slice(&payload[46..]).map_err(XprvError::Secp256k1Error)?;
+
+        Ok(ExtendedSK {
+            secret_key,
+            chain_code,
+        })
+    }
+
     /// Try to derive an extended private key from a given path
     pub fn derive(&self, path: Vec<ChildNumber>) -> Result<ExtendedSK, KeyDerivationError> {
         let mut extended_sk = self.clone();
@@ -197,6 +321,73 @@ fn get_chain_code_and_secret(
     Ok((chain_code, secret_key))
 }
 
+/// First 4 bytes of the double SHA-256 of a serialized extended key
+fn xprv_checksum(payload: &[u8]) -> [u8; 4] {
+    let hash = sha2::Sha256::digest(&sha2::Sha256::digest(payload));
+    let mut checksum = [0u8; 4];
+    checksum.copy_from_slice(&hash[..4]);
+
+    checksum
+}
+
+/// Encode bytes in base58, keeping their leading zeros
+fn base58_encode(bytes: &[u8]) -> String {
+    // Digits of the number in base 58, the least significant first
+    let mut digits: Vec<u8> = vec![];
+    for byte in bytes {
+        let mut carry = u32::from(*byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+
+    iter::repeat(BASE58_ALPHABET[0])
+        .take(zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|digit| BASE58_ALPHABET[*digit as usize]),
+        )
+        .map(char::from)
+        .collect()
+}
+
+/// Decode a base58 string, failing on characters which are not in the alphabet
+fn base58_decode(string: &str) -> Result<Vec<u8>, XprvError> {
+    // Bytes of the number, the least significant first
+    let mut bytes: Vec<u8> = vec![];
+    for (index, character) in string.chars().enumerate() {
+        let digit = BASE58_ALPHABET
+            .iter()
+            .position(|c| char::from(*c) == character)
+            .ok_or(XprvError::InvalidCharacter { character, index })?;
+        let mut carry = digit as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let zeros = string.chars().take_while(|c| *c == '1').count();
+
+    Ok(iter::repeat(0)
+        .take(zeros)
+        .chain(bytes.into_iter().rev())
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -296,4 +487,59 @@ mod tests {
             "Secret key is invalid"
         );
     }
+
+    #[test]
+    fn test_child_number() {
+        assert_eq!(ChildNumber::hardened(44), ChildNumber(0x8000_002c));
+        assert_eq!(ChildNumber::normal(0x8000_002c), ChildNumber(44));
+        assert!(ChildNumber::hardened(0).is_hardened());
+        assert!(!ChildNumber::normal(0).is_hardened());
+    }
+
+    #[test]
+    fn test_extended_key_bytes() {
+        let seed = [0; 32];
+        let master_key = MasterKeyGen::new(seed).generate().unwrap();
+        let bytes = master_key.to_bytes();
+
+        assert_eq!(ExtendedSK::from_slice(&bytes).unwrap(), master_key);
+        assert_eq!(
+            ExtendedSK::from_slice(&bytes[..63]),
+            Err(KeyDerivationError::InvalidKeyLength)
+        );
+    }
+
+    /// Master key of the test vector 1 of BIP32
+    const XPRV: &str = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+
+    #[test]
+    fn test_extended_key_xprv() {
+        let seed = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d,
+            0x0e, 0x0f,
+        ];
+        let master_key = MasterKeyGen::new(seed).generate().unwrap();
+
+        assert_eq!(master_key.to_xprv(), XPRV);
+        assert_eq!(ExtendedSK::from_xprv(XPRV), Ok(master_key));
+    }
+
+    #[test]
+    fn test_extended_key_xprv_invalid() {
+        assert_eq!(
+            ExtendedSK::from_xprv(&XPRV.replace("PHi", "PHj")),
+            Err(XprvError::InvalidChecksum)
+        );
+        assert_eq!(
+            ExtendedSK::from_xprv(&XPRV.replace("xprv", "0prv")),
+            Err(XprvError::InvalidCharacter {
+                character: '0',
+                index: 0
+            })
+        );
+        assert_eq!(
+            ExtendedSK::from_xprv(&XPRV[..100]),
+            Err(XprvError::InvalidLength)
+        );
+    }
 }
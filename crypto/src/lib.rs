@@ -19,3 +19,4 @@ pub mod key;
 pub mod mnemonic;
 pub mod pbkdf2;
 pub mod signature;
+pub mod transport;
@@ -0,0 +1,205 @@
+//! Encryption of the P2P transport
+//!
+//! Peers agree on the keys used to encrypt their messages with an ephemeral elliptic curve
+//! Diffie-Hellman key exchange, similar to the `NN` handshake of the Noise protocol framework:
+//!
+//! 1. Each peer generates an [`EphemeralKey`](EphemeralKey) and sends its public part to the
+//!    other one, signed with its long-term identity key so that the other peer can authenticate
+//!    it with [`verify_ephemeral_key`](verify_ephemeral_key).
+//! 2. Both peers compute the same shared secret from their own ephemeral key and the one of the
+//!    other peer, and derive from it with HKDF-SHA256 one key for each direction. The salt of
+//!    the derivation includes both ephemeral keys and the transcript of the handshake, so the
+//!    peers end up with different keys if any handshake message was tampered with.
+//! 3. Messages are encrypted with ChaCha20-Poly1305, using a [`CipherState`](CipherState) per
+//!    direction whose nonce is incremented with each message.
+use crypto::{
+    aead::{AeadDecryptor, AeadEncryptor},
+    chacha20poly1305::ChaCha20Poly1305,
+    hkdf::{hkdf_expand, hkdf_extract},
+    sha2,
+};
+use failure::Fail;
+use rand::{rngs::OsRng, RngCore};
+use secp256k1::{ecdh::SharedSecret, PublicKey, Secp256k1, SecretKey, Signature};
+
+use crate::{
+    hash::{calculate_sha256, Sha256},
+    signature,
+};
+
+/// Size in bytes of the authentication tag appended to each encrypted message
+pub const TAG_LENGTH: usize = 16;
+
+/// Context of the key derivation of the messages sent by the peer which started the connection
+const INITIATOR_INFO: &[u8] = b"witnet transport initiator";
+
+/// Context of the key derivation of the messages sent by the peer which accepted the connection
+const RESPONDER_INFO: &[u8] = b"witnet transport responder";
+
+/// The error type for the operations of the encrypted transport
+#[derive(Debug, PartialEq, Fail)]
+pub enum Error {
+    /// A key or signature received from the peer is malformed
+    #[fail(display = "Invalid key or signature")]
+    InvalidKey,
+    /// The signature of the ephemeral key does not match the identity key of the peer
+    #[fail(display = "The ephemeral key is not signed by the identity key")]
+    InvalidSignature,
+    /// The random number generator of the system is not available
+    #[fail(display = "Random generation error")]
+    Rng,
+    /// The message was not encrypted with the expected key or was tampered with
+    #[fail(display = "Failed to decrypt message")]
+    Decryption,
+    /// All the nonces of the key have been used
+    #[fail(display = "Nonces exhausted")]
+    NoncesExhausted,
+}
+
+/// Ephemeral secp256k1 key pair, only used for the key exchange of one connection
+pub struct EphemeralKey {
+    secret: SecretKey,
+    public: PublicKey,
+}
+
+impl EphemeralKey {
+    /// Generate a random key pair
+    pub fn generate() -> Result<Self, Error> {
+        let mut rng = OsRng::new().map_err(|_| Error::Rng)?;
+        let mut bytes = [0; 32];
+        // Out of range values are so unlikely that retrying is not worth it
+        rng.fill_bytes(&mut bytes);
+        let secret = SecretKey::from_slice(&bytes).map_err(|_| Error::Rng)?;
+        let public = PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret);
+
+        Ok(EphemeralKey { secret, public })
+    }
+
+    /// Public key serialized in compressed form, to be sent to the peer
+    pub fn public_key(&self) -> Vec<u8> {
+        self.public.serialize().to_vec()
+    }
+
+    /// Agree on the keys used to encrypt the messages with a peer, given its ephemeral public
+    /// key. `initiator` is true for the peer which started the connection. `handshake` is the
+    /// transcript of the handshake messages exchanged before the agreement, which both peers
+    /// must build in the same order.
+    ///
+    /// Returns the cipher for the messages sent to the peer and the cipher for the messages
+    /// received from it.
+    pub fn agree(
+        &self,
+        peer_key: &[u8],
+        initiator: bool,
+        handshake: &[u8],
+    ) -> Result<(CipherState, CipherState), Error> {
+        let peer_public = PublicKey::from_slice(peer_key).map_err(|_| Error::InvalidKey)?;
+        let shared_secret = SharedSecret::new(&peer_public, &self.secret);
+
+        // Both ephemeral keys are used as salt, so that each connection gets different keys,
+        // along with the handshake, so that it cannot be tampered with. The ephemeral keys have
+        // a fixed length, so the concatenation is not ambiguous
+        let peer_key = peer_public.serialize().to_vec();
+        let (initiator_key, responder_key) = if initiator {
+            (self.public_key(), peer_key)
+        } else {
+            (peer_key, self.public_key())
+        };
+        let mut transcript = initiator_key;
+        transcript.extend(responder_key);
+        transcript.extend_from_slice(handshake);
+        let Sha256(salt) = calculate_sha256(&transcript);
+
+        let mut prk = [0; 32];
+        hkdf_extract(sha2::Sha256::new(), &salt, &shared_secret[..], &mut prk);
+        let mut initiator_cipher = [0; 32];
+        hkdf_expand(
+            sha2::Sha256::new(),
+            &prk,
+            INITIATOR_INFO,
+            &mut initiator_cipher,
+        );
+        let mut responder_cipher = [0; 32];
+        hkdf_expand(
+            sha2::Sha256::new(),
+            &prk,
+            RESPONDER_INFO,
+            &mut responder_cipher,
+        );
+
+        let initiator_cipher = CipherState::new(initiator_cipher);
+        let responder_cipher = CipherState::new(responder_cipher);
+        if initiator {
+            Ok((initiator_cipher, responder_cipher))
+        } else {
+            Ok((responder_cipher, initiator_cipher))
+        }
+    }
+}
+
+/// Check that an ephemeral key has been signed by the given identity key, both serialized in
+/// compressed form. The signature is serialized in DER and signs the SHA-256 of the ephemeral
+/// key.
+///
+/// Returns the identity public key of the peer.
+pub fn verify_ephemeral_key(
+    identity_key: &[u8],
+    ephemeral_key: &[u8],
+    signature: &[u8],
+) -> Result<PublicKey, Error> {
+    let identity_key = PublicKey::from_slice(identity_key).map_err(|_| Error::InvalidKey)?;
+    let signature = Signature::from_der(signature).map_err(|_| Error::InvalidKey)?;
+    let Sha256(data) = calculate_sha256(ephemeral_key);
+
+    signature::verify(&identity_key, &data, &signature).map_err(|_| Error::InvalidSignature)?;
+
+    Ok(identity_key)
+}
+
+/// Key and nonce used to encrypt or decrypt the messages sent in one direction
+pub struct CipherState {
+    key: [u8; 32],
+    nonce: u64,
+}
+
+impl CipherState {
+    fn new(key: [u8; 32]) -> Self {
+        CipherState { key, nonce: 0 }
+    }
+
+    /// Return the nonce for the next message, as the 8 bytes expected by ChaCha20-Poly1305
+    fn next_nonce(&mut self) -> Result<[u8; 8], Error> {
+        let nonce = self.nonce.to_le_bytes();
+        self.nonce = self.nonce.checked_add(1).ok_or(Error::NoncesExhausted)?;
+
+        Ok(nonce)
+    }
+
+    /// Encrypt a message, appending its authentication tag
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let nonce = self.next_nonce()?;
+        let mut cipher = ChaCha20Poly1305::new(&self.key, &nonce, &[]);
+        let mut ciphertext = vec![0; plaintext.len() + TAG_LENGTH];
+        let (output, tag) = ciphertext.split_at_mut(plaintext.len());
+        cipher.encrypt(plaintext, output, tag);
+
+        Ok(ciphertext)
+    }
+
+    /// Decrypt a message, checking its authentication tag
+    pub fn decrypt(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        if ciphertext.len() < TAG_LENGTH {
+            return Err(Error::Decryption);
+        }
+
+        let nonce = self.next_nonce()?;
+        let mut cipher = ChaCha20Poly1305::new(&self.key, &nonce, &[]);
+        let (input, tag) = ciphertext.split_at(ciphertext.len() - TAG_LENGTH);
+        let mut plaintext = vec![0; input.len()];
+        if cipher.decrypt(input, &mut plaintext, tag) {
+            Ok(plaintext)
+        } else {
+            Err(Error::Decryption)
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use witnet_crypto::{
+    hash::{calculate_sha256, Sha256},
+    signature::sign,
+    transport::{verify_ephemeral_key, EphemeralKey, Error},
+};
+
+#[test]
+fn agreed_keys_decrypt_messages_of_the_peer() {
+    let initiator = EphemeralKey::generate().unwrap();
+    let responder = EphemeralKey::generate().unwrap();
+
+    let (mut initiator_tx, mut initiator_rx) = initiator
+        .agree(&responder.public_key(), true, b"HANDSHAKE")
+        .unwrap();
+    let (mut responder_tx, mut responder_rx) = responder
+        .agree(&initiator.public_key(), false, b"HANDSHAKE")
+        .unwrap();
+
+    for msg in [&b"VERSION"[..], b"", b"LAST_BEACON"].iter() {
+        let encrypted = initiator_tx.encrypt(msg).unwrap();
+        assert_eq!(responder_rx.decrypt(&encrypted).unwrap(), msg.to_vec());
+    }
+
+    let encrypted = responder_tx.encrypt(b"BLOCK").unwrap();
+    assert_eq!(initiator_rx.decrypt(&encrypted).unwrap(), b"BLOCK".to_vec());
+}
+
+#[test]
+fn tampered_handshake_leads_to_different_keys() {
+    let initiator = EphemeralKey::generate().unwrap();
+    let responder = EphemeralKey::generate().unwrap();
+    let (mut tx, _) = initiator
+        .agree(&responder.public_key(), true, b"HANDSHAKE")
+        .unwrap();
+    let (_, mut rx) = responder
+        .agree(&initiator.public_key(), false, b"HANDSHAKE!")
+        .unwrap();
+
+    let encrypted = tx.encrypt(b"VERACK").unwrap();
+    assert_eq!(rx.decrypt(&encrypted), Err(Error::Decryption));
+}
+
+#[test]
+fn tampered_messages_are_rejected() {
+    let initiator = EphemeralKey::generate().unwrap();
+    let responder = EphemeralKey::generate().unwrap();
+    let (mut tx, _) = initiator
+        .agree(&responder.public_key(), true, b"HANDSHAKE")
+        .unwrap();
+    let (_, mut rx) = responder
+        .agree(&initiator.public_key(), false, b"HANDSHAKE")
+        .unwrap();
+
+    let mut encrypted = tx.encrypt(b"TRANSACTION").unwrap();
+    encrypted[0] ^= 1;
+    assert_eq!(rx.decrypt(&encrypted), Err(Error::Decryption));
+    assert_eq!(rx.decrypt(&[0; 4]), Err(Error::Decryption));
+}
+
+#[test]
+fn replayed_messages_are_rejected() {
+    let initiator = EphemeralKey::generate().unwrap();
+    let responder = EphemeralKey::generate().unwrap();
+    let (mut tx, _) = initiator
+        .agree(&responder.public_key(), true, b"HANDSHAKE")
+        .unwrap();
+    let (_, mut rx) = responder
+        .agree(&initiator.public_key(), false, b"HANDSHAKE")
+        .unwrap();
+
+    let encrypted = tx.encrypt(b"PING").unwrap();
+    assert!(rx.decrypt(&encrypted).is_ok());
+    assert_eq!(rx.decrypt(&encrypted), Err(Error::Decryption));
+}
+
+#[test]
+fn ephemeral_key_signed_by_identity_key() {
+    let secret_key = SecretKey::from_slice(&[0xcd; 32]).unwrap();
+    let public_key = PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+    let identity_key = public_key.serialize().to_vec();
+
+    let ephemeral_key = EphemeralKey::generate().unwrap().public_key();
+    let Sha256(data) = calculate_sha256(&ephemeral_key);
+    let signature = sign(secret_key, &data).serialize_der();
+
+    assert_eq!(
+        verify_ephemeral_key(&identity_key, &ephemeral_key, &signature),
+        Ok(public_key)
+    );
+
+    // A different ephemeral key
+    let other_key = EphemeralKey::generate().unwrap().public_key();
+    assert_eq!(
+        verify_ephemeral_key(&identity_key, &other_key, &signature),
+        Err(Error::InvalidSignature)
+    );
+
+    // A malformed identity key
+    assert_eq!(
+        verify_ephemeral_key(&identity_key[1..], &ephemeral_key, &signature),
+        Err(Error::InvalidKey)
+    );
+}
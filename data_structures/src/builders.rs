@@ -6,11 +6,15 @@ use rand::{thread_rng, Rng};
 use witnet_util::timestamp::get_timestamp;
 
 use super::{
-    chain::{Block, BlockHeader, CheckpointBeacon, InventoryEntry, LeadershipProof, Transaction},
+    chain::{
+        Block, BlockHeader, CheckpointBeacon, Hash, InventoryEntry, LeadershipProof,
+        SignedBlockHeader, Transaction,
+    },
     error::BuildersError,
     types::{
-        Address, Command, GetPeers, InventoryAnnouncement, InventoryRequest, IpAddress, LastBeacon,
-        Message, Peers, Ping, Pong, Verack, Version,
+        Address, BlockTransactions, Command, CompactBlock, EncryptionKey, GetBlockTransactions,
+        GetHeaders, GetPeers, Headers, InventoryAnnouncement, InventoryRequest, IpAddress,
+        LastBeacon, Message, Peers, Ping, Pong, Verack, Version,
     },
 };
 
@@ -18,7 +22,19 @@ use super::{
 // PROTOCOL MESSAGES CONSTANTS
 ////////////////////////////////////////////////////////////////////////////////////////
 /// Protocol version (used in handshake)
-pub const PROTOCOL_VERSION: u32 = 0x0000_0001;
+pub const PROTOCOL_VERSION: u32 = 0x0000_0004;
+
+/// Oldest protocol version of the peers accepted in the handshake
+/// Version 2 introduced the `GetHeaders` and `Headers` messages, needed for synchronization
+pub const MIN_PROTOCOL_VERSION: u32 = 0x0000_0002;
+
+/// Oldest protocol version of the peers which can receive compact blocks
+/// Older peers receive the full blocks instead
+pub const COMPACT_BLOCKS_PROTOCOL_VERSION: u32 = 0x0000_0003;
+
+/// Oldest protocol version of the peers which request the announced transactions
+/// Older peers receive the full transactions instead
+pub const TRANSACTION_ANNOUNCEMENTS_PROTOCOL_VERSION: u32 = 0x0000_0004;
 
 /// Capabilities
 pub const CAPABILITIES: u64 = 0x0000_0000_0000_0001;
@@ -70,11 +86,16 @@ impl Message {
     }
 
     /// Function to build Version messages
+    ///
+    /// The nonce identifies the local node, so that it can detect connections to itself. The
+    /// encryption key is empty if the session should not be encrypted.
     pub fn build_version(
         magic: u16,
         sender_addr: SocketAddr,
         receiver_addr: SocketAddr,
         last_epoch: u32,
+        nonce: u64,
+        encryption_key: EncryptionKey,
     ) -> Message {
         Message::build_message(
             magic,
@@ -86,7 +107,8 @@ impl Message {
                 receiver_address: to_address(receiver_addr),
                 user_agent: USER_AGENT.to_string(),
                 last_epoch,
-                nonce: random_nonce(),
+                nonce,
+                encryption_key,
             }),
         )
     }
@@ -166,6 +188,59 @@ impl Message {
         )
     }
 
+    /// Function to build GetHeaders messages
+    pub fn build_get_headers(magic: u16, highest_block_checkpoint: CheckpointBeacon) -> Message {
+        Message::build_message(
+            magic,
+            Command::GetHeaders(GetHeaders {
+                highest_block_checkpoint,
+            }),
+        )
+    }
+
+    /// Function to build Headers messages
+    pub fn build_headers(magic: u16, headers: Vec<SignedBlockHeader>) -> Message {
+        Message::build_message(magic, Command::Headers(Headers { headers }))
+    }
+
+    /// Function to build CompactBlock messages
+    pub fn build_compact_block(magic: u16, block: &Block) -> Message {
+        Message::build_message(
+            magic,
+            Command::CompactBlock(CompactBlock::new(block, random_nonce())),
+        )
+    }
+
+    /// Function to build GetBlockTransactions messages
+    pub fn build_get_block_transactions(
+        magic: u16,
+        block_hash: Hash,
+        indexes: Vec<u32>,
+    ) -> Message {
+        Message::build_message(
+            magic,
+            Command::GetBlockTransactions(GetBlockTransactions {
+                block_hash,
+                indexes,
+            }),
+        )
+    }
+
+    /// Function to build BlockTransactions messages
+    pub fn build_block_transactions(
+        magic: u16,
+        block_hash: Hash,
+        transactions: Vec<Transaction>,
+    ) -> Message {
+        Message::build_message(
+            magic,
+            Command::BlockTransactions(BlockTransactions {
+                block_hash,
+                transactions,
+            }),
+        )
+    }
+
     /// Function to build a message from a command
     fn build_message(magic: u16, command: Command) -> Message {
         Message {
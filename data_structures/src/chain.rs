@@ -1,3 +1,4 @@
+use bech32::{Bech32, FromBase32, ToBase32};
 use failure::Fail;
 use partial_struct::PartialStruct;
 use protobuf::Message;
@@ -21,7 +22,8 @@ use witnet_util::parser::parse_hex;
 
 use super::{
     data_request::DataRequestPool,
-    error::OutputPointerParseError,
+    error::{OutputPointerParseError, PublicKeyHashParseError},
+    get_environment,
     proto::{schema::witnet, ProtobufConvert},
     serializers::decoders::{TryFrom, TryInto},
 };
@@ -60,6 +62,25 @@ impl Default for Environment {
     }
 }
 
+impl Environment {
+    /// Human-readable part of the bech32 addresses used in this environment
+    pub fn bech32_prefix(&self) -> &'static str {
+        match self {
+            Environment::Mainnet => "wit",
+            Environment::Testnet1 => "twit",
+        }
+    }
+
+    /// Environment whose addresses use the given human-readable part
+    pub fn from_bech32_prefix(prefix: &str) -> Option<Environment> {
+        match prefix {
+            "wit" => Some(Environment::Mainnet),
+            "twit" => Some(Environment::Testnet1),
+            _ => None,
+        }
+    }
+}
+
 /// Consensus-critical configuration
 #[derive(PartialStruct, Debug, Clone, PartialEq, Serialize, Deserialize, ProtobufConvert)]
 #[partial_struct(derive(Deserialize, Default, Debug, Clone, PartialEq))]
@@ -134,6 +155,22 @@ impl Hashable for Block {
     }
 }
 
+impl Block {
+    /// Get the header of the block along with its proof of leadership
+    pub fn signed_header(&self) -> SignedBlockHeader {
+        SignedBlockHeader {
+            block_header: self.block_header.clone(),
+            proof: self.proof.clone(),
+        }
+    }
+}
+
+impl Hashable for SignedBlockHeader {
+    fn hash(&self) -> Hash {
+        calculate_sha256(&self.block_header.to_pb_bytes().unwrap()).into()
+    }
+}
+
 impl Hashable for CheckpointBeacon {
     fn hash(&self) -> Hash {
         calculate_sha256(&self.to_pb_bytes().unwrap()).into()
@@ -172,6 +209,17 @@ pub struct LeadershipProof {
     pub block_sig: KeyedSignature,
 }
 
+/// Block header along with its proof of leadership, which is enough to validate the chain of
+/// blocks without their transactions
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Default)]
+#[protobuf_convert(pb = "witnet::SignedBlockHeader")]
+pub struct SignedBlockHeader {
+    /// The header of the block
+    pub block_header: BlockHeader,
+    /// A miner-provided proof of leadership
+    pub proof: LeadershipProof,
+}
+
 /// Digital signatures structure (based on supported cryptosystems)
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
 #[protobuf_convert(pb = "witnet::Signature")]
@@ -391,7 +439,148 @@ impl<'de> Deserialize<'de> for Hash {
 pub type SHA256 = [u8; 32];
 
 /// Public Key Hash: slice of the digest of a public key (20 bytes)
-pub type PublicKeyHash = [u8; 20];
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default, Hash, Debug)]
+pub struct PublicKeyHash {
+    pub hash: [u8; 20],
+}
+
+/// Public key hashes are serialized as bech32 addresses of the environment set with
+/// `set_environment`
+impl Serialize for PublicKeyHash {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.bech32(&get_environment()))
+    }
+}
+
+/// Helper type to allow deserialization of public key hashes stored as byte arrays before they
+/// were serialized as addresses: [0x01, 0x23, ...] instead of "twit1..."
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PublicKeyHashSerializationHelper {
+    Bytes([u8; 20]),
+    AsString(String),
+}
+
+impl<'de> Deserialize<'de> for PublicKeyHash {
+    fn deserialize<D>(deserializer: D) -> Result<PublicKeyHash, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match PublicKeyHashSerializationHelper::deserialize(deserializer)? {
+            PublicKeyHashSerializationHelper::Bytes(hash) => Ok(PublicKeyHash { hash }),
+            PublicKeyHashSerializationHelper::AsString(address) => {
+                address.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    }
+}
+
+impl AsRef<[u8]> for PublicKeyHash {
+    fn as_ref(&self) -> &[u8] {
+        &self.hash
+    }
+}
+
+/// Display the bech32 address of the environment set with `set_environment`
+impl fmt::Display for PublicKeyHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.bech32(&get_environment()))
+    }
+}
+
+/// Parse a bech32 address, which must belong to the environment set with `set_environment`
+impl FromStr for PublicKeyHash {
+    type Err = PublicKeyHashParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_bech32(&get_environment(), s)
+    }
+}
+
+impl PublicKeyHash {
+    /// Build a public key hash from a slice which must be exactly 20 bytes long
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PublicKeyHashParseError> {
+        if bytes.len() != 20 {
+            return Err(PublicKeyHashParseError::InvalidLength {
+                length: bytes.len(),
+            });
+        }
+        let mut hash = [0; 20];
+        hash.copy_from_slice(bytes);
+
+        Ok(PublicKeyHash { hash })
+    }
+
+    /// Encode the public key hash as a checksummed bech32 address, using the prefix of the
+    /// given environment: `wit` for mainnet and `twit` for testnet
+    pub fn bech32(&self, environment: &Environment) -> String {
+        // The human-readable part is always valid and the data is short enough, so this
+        // cannot fail
+        Bech32::new(
+            environment.bech32_prefix().to_string(),
+            self.hash.to_base32(),
+        )
+        .expect("Invalid bech32 address")
+        .to_string()
+    }
+
+    /// Decode a bech32 address, checking its checksum and that it belongs to the given
+    /// environment
+    pub fn from_bech32(
+        environment: &Environment,
+        address: &str,
+    ) -> Result<Self, PublicKeyHashParseError> {
+        let (address_environment, pkh) = Self::from_bech32_any_environment(address)?;
+        if address_environment != *environment {
+            return Err(PublicKeyHashParseError::WrongEnvironment {
+                expected: environment.bech32_prefix().to_string(),
+                prefix: address_environment.bech32_prefix().to_string(),
+            });
+        }
+
+        Ok(pkh)
+    }
+
+    /// Decode a bech32 address of any known environment, checking its checksum. Returns the
+    /// environment of the address along with the public key hash
+    pub fn from_bech32_any_environment(
+        address: &str,
+    ) -> Result<(Environment, Self), PublicKeyHashParseError> {
+        let address =
+            Bech32::from_str(address.trim()).map_err(|e| PublicKeyHashParseError::Bech32 {
+                reason: e.to_string(),
+            })?;
+        let environment = Environment::from_bech32_prefix(address.hrp()).ok_or_else(|| {
+            PublicKeyHashParseError::UnknownPrefix {
+                prefix: address.hrp().to_string(),
+            }
+        })?;
+        let bytes = Vec::<u8>::from_base32(address.data()).map_err(|e| {
+            PublicKeyHashParseError::Bech32 {
+                reason: e.to_string(),
+            }
+        })?;
+
+        Ok((environment, Self::from_bytes(&bytes)?))
+    }
+}
+
+impl ProtobufConvert for PublicKeyHash {
+    type ProtoStruct = Vec<u8>;
+
+    fn to_pb(&self) -> Self::ProtoStruct {
+        self.hash.to_pb()
+    }
+
+    fn from_pb(pb: Self::ProtoStruct) -> Result<Self, failure::Error> {
+        Ok(PublicKeyHash {
+            hash: ProtobufConvert::from_pb(pb)?,
+        })
+    }
+}
 
 /// Transaction data structure
 #[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
@@ -580,6 +769,18 @@ impl Output {
             Output::ValueTransfer(output) => output.value,
         }
     }
+
+    /// Return the public key hash an output is locked to. Commit outputs are not locked to any
+    /// public key hash.
+    pub fn pkh(&self) -> Option<PublicKeyHash> {
+        match self {
+            Output::ValueTransfer(output) => Some(output.pkh),
+            Output::DataRequest(output) => Some(output.pkh),
+            Output::Reveal(output) => Some(output.pkh),
+            Output::Tally(output) => Some(output.pkh),
+            Output::Commit(_) => None,
+        }
+    }
 }
 
 /// Value transfer output transaction data structure
@@ -645,6 +846,22 @@ pub struct PublicKey {
     pub bytes: [u8; 32],
 }
 
+impl PublicKey {
+    /// Hash of the compressed public key, used as the address which can spend outputs locked
+    /// to this key: the first 20 bytes of its SHA-256
+    pub fn pkh(&self) -> PublicKeyHash {
+        let mut bytes = [0; 33];
+        bytes[0] = self.compressed;
+        bytes[1..].copy_from_slice(&self.bytes);
+
+        let Sha256(hash) = calculate_sha256(&bytes);
+        let mut pkh = PublicKeyHash::default();
+        pkh.hash.copy_from_slice(&hash[..20]);
+
+        pkh
+    }
+}
+
 /// Secret Key data structure
 #[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub struct SecretKey {
@@ -1092,6 +1309,17 @@ impl<'de> Deserialize<'de> for OutputPointer {
     }
 }
 
+/// Location of a consolidated transaction in the block chain
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TransactionPointer {
+    /// Hash of the block which includes the transaction
+    pub block_hash: Hash,
+    /// Epoch of the block which includes the transaction
+    pub epoch: Epoch,
+    /// Position of the transaction in the block
+    pub transaction_index: u32,
+}
+
 /// Inventory entry data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
 #[protobuf_convert(pb = "witnet::InventoryEntry")]
@@ -0,0 +1,237 @@
+//! Compact block relay
+//!
+//! Peers usually know most of the transactions of a new block, because they have already been
+//! relayed and stored in their transactions pool. A [`CompactBlock`](CompactBlock) only carries
+//! the header of the block and a short identifier of each transaction, computed from the hash of
+//! the transaction salted with the hash of the block and a random nonce. The receiver rebuilds the
+//! block using a [`PartialBlock`](PartialBlock), and requests the transactions it does not know.
+use std::collections::HashMap;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use witnet_crypto::{
+    hash::{calculate_sha256, Sha256},
+    merkle::merkle_tree_root,
+};
+
+use crate::{
+    chain::{Block, BlockHeader, Hash, Hashable, LeadershipProof, Transaction},
+    error::CompactBlockError,
+    types::{CompactBlock, PrefilledTransaction},
+};
+
+/// Compute the short identifier of a transaction for the compact block with the given hash and
+/// nonce: the first 8 bytes of `sha256(block_hash || nonce || tx_hash)`
+pub fn short_transaction_id(block_hash: Hash, nonce: u64, tx_hash: Hash) -> u64 {
+    let Hash::SHA256(block_hash) = block_hash;
+    let Hash::SHA256(tx_hash) = tx_hash;
+
+    let mut bytes = Vec::with_capacity(72);
+    bytes.extend_from_slice(&block_hash);
+    bytes.write_u64::<LittleEndian>(nonce).unwrap();
+    bytes.extend_from_slice(&tx_hash);
+
+    let Sha256(digest) = calculate_sha256(&bytes);
+
+    (&digest[..8]).read_u64::<LittleEndian>().unwrap()
+}
+
+impl CompactBlock {
+    /// Build the compact version of a block
+    ///
+    /// The mint transaction is always prefilled, as the receiver cannot know it in advance.
+    pub fn new(block: &Block, nonce: u64) -> Self {
+        let block_hash = block.hash();
+        let mut short_ids = vec![];
+        let mut prefilled_txns = vec![];
+
+        for (index, transaction) in block.txns.iter().enumerate() {
+            if index == 0 {
+                prefilled_txns.push(PrefilledTransaction {
+                    index: index as u32,
+                    transaction: transaction.clone(),
+                });
+            } else {
+                short_ids.push(short_transaction_id(block_hash, nonce, transaction.hash()));
+            }
+        }
+
+        CompactBlock {
+            block_header: block.block_header.clone(),
+            proof: block.proof.clone(),
+            nonce,
+            short_ids,
+            prefilled_txns,
+        }
+    }
+}
+
+/// Block being rebuilt from a compact block
+#[derive(Debug, Clone)]
+pub struct PartialBlock {
+    /// Hash of the block
+    hash: Hash,
+    /// The header of the block
+    block_header: BlockHeader,
+    /// A miner-provided proof of leadership
+    proof: LeadershipProof,
+    /// Transactions of the block, `None` for the ones which are still missing
+    txns: Vec<Option<Transaction>>,
+    /// Short identifier of each transaction which was not prefilled, along with its index
+    short_ids: Vec<(usize, u64)>,
+    /// Nonce used to compute the short identifiers
+    nonce: u64,
+    /// Indexes of the transactions taken from the transactions pool
+    from_mempool: Vec<usize>,
+}
+
+impl PartialBlock {
+    /// Start rebuilding a compact block, placing its prefilled transactions
+    pub fn new(compact_block: CompactBlock) -> Result<Self, CompactBlockError> {
+        let hash = compact_block.hash();
+        let txns_len = compact_block.short_ids.len() + compact_block.prefilled_txns.len();
+        let mut txns = vec![None; txns_len];
+
+        // Prefilled transactions must be sorted by index, without duplicates
+        let mut next_index = 0;
+        for PrefilledTransaction { index, transaction } in compact_block.prefilled_txns {
+            let i = index as usize;
+            if i < next_index || i >= txns_len {
+                return Err(CompactBlockError::InvalidPrefilledIndex { index, txns_len });
+            }
+            txns[i] = Some(transaction);
+            next_index = i + 1;
+        }
+
+        // The short identifiers fill the gaps left by the prefilled transactions
+        let short_ids = txns
+            .iter()
+            .enumerate()
+            .filter(|(_, tx)| tx.is_none())
+            .map(|(i, _)| i)
+            .zip(compact_block.short_ids)
+            .collect();
+
+        Ok(PartialBlock {
+            hash,
+            block_header: compact_block.block_header,
+            proof: compact_block.proof,
+            txns,
+            short_ids,
+            nonce: compact_block.nonce,
+            from_mempool: vec![],
+        })
+    }
+
+    /// Hash of the block
+    pub fn hash(&self) -> Hash {
+        self.hash
+    }
+
+    /// Fill the missing transactions with the ones of the transactions pool whose short
+    /// identifier matches
+    ///
+    /// Short identifiers matched by more than one transaction of the pool, or repeated in the
+    /// compact block, are left missing.
+    pub fn fill_from_mempool<'a, I>(&mut self, transactions: I)
+    where
+        I: IntoIterator<Item = &'a Transaction>,
+    {
+        // Index of each missing short identifier, or `None` if it is repeated
+        let mut wanted: HashMap<u64, Option<usize>> = HashMap::new();
+        for (index, short_id) in self.short_ids.iter() {
+            if self.txns[*index].is_none() {
+                wanted
+                    .entry(*short_id)
+                    .and_modify(|i| *i = None)
+                    .or_insert_with(|| Some(*index));
+            }
+        }
+
+        // Transaction of the pool matching each short identifier, or `None` if many of them do
+        let mut found: HashMap<usize, Option<&Transaction>> = HashMap::new();
+        for transaction in transactions {
+            let short_id = short_transaction_id(self.hash, self.nonce, transaction.hash());
+            if let Some(Some(index)) = wanted.get(&short_id) {
+                found
+                    .entry(*index)
+                    .and_modify(|tx| *tx = None)
+                    .or_insert_with(|| Some(transaction));
+            }
+        }
+
+        for (index, transaction) in found {
+            if let Some(transaction) = transaction {
+                self.txns[index] = Some(transaction.clone());
+                self.from_mempool.push(index);
+            }
+        }
+    }
+
+    /// Indexes of the transactions which are still missing
+    pub fn missing_indexes(&self) -> Vec<u32> {
+        self.txns
+            .iter()
+            .enumerate()
+            .filter(|(_, tx)| tx.is_none())
+            .map(|(i, _)| i as u32)
+            .collect()
+    }
+
+    /// Fill the missing transactions, in order, with the ones received from the peer
+    pub fn add_transactions(
+        &mut self,
+        transactions: Vec<Transaction>,
+    ) -> Result<(), CompactBlockError> {
+        let missing = self.missing_indexes();
+        if transactions.len() != missing.len() {
+            return Err(CompactBlockError::UnexpectedTransactions {
+                received: transactions.len(),
+                missing: missing.len(),
+            });
+        }
+
+        for (index, transaction) in missing.into_iter().zip(transactions) {
+            self.txns[index as usize] = Some(transaction);
+        }
+
+        Ok(())
+    }
+
+    /// Forget the transactions taken from the transactions pool, so that they can be requested
+    /// to the peer when one of them is wrong because of a short identifier collision
+    ///
+    /// Returns false if no transaction was taken from the pool.
+    pub fn reset_mempool_transactions(&mut self) -> bool {
+        for index in self.from_mempool.iter() {
+            self.txns[*index] = None;
+        }
+
+        !std::mem::replace(&mut self.from_mempool, vec![]).is_empty()
+    }
+
+    /// Build the block, checking that its transactions match the merkle root of the header
+    pub fn try_build(&self) -> Result<Block, CompactBlockError> {
+        let txns: Vec<Transaction> = self.txns.iter().filter_map(Clone::clone).collect();
+        if txns.len() != self.txns.len() {
+            return Err(CompactBlockError::MissingTransactions {
+                missing: self.txns.len() - txns.len(),
+            });
+        }
+
+        let hashes: Vec<Sha256> = txns
+            .iter()
+            .map(|tx| match tx.hash() {
+                Hash::SHA256(x) => Sha256(x),
+            })
+            .collect();
+        if Hash::from(merkle_tree_root(&hashes)) != self.block_header.hash_merkle_root {
+            return Err(CompactBlockError::MerkleRootMismatch);
+        }
+
+        Ok(Block {
+            block_header: self.block_header.clone(),
+            proof: self.proof.clone(),
+            txns,
+        })
+    }
+}
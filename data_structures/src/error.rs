@@ -1,6 +1,7 @@
 //! Error type definitions for the data structure module.
 
 use failure::Fail;
+use serde::Serialize;
 use std::num::ParseIntError;
 
 use super::chain::{Epoch, Hash, OutputPointer};
@@ -25,7 +26,7 @@ pub enum BuildersError {
 }
 
 /// The error type for operations on a [`Transaction`](Transaction)
-#[derive(Debug, PartialEq, Fail)]
+#[derive(Debug, PartialEq, Fail, Serialize)]
 pub enum TransactionError {
     #[fail(display = "The transaction is invalid")]
     NotValidTransaction,
@@ -98,7 +99,7 @@ pub enum TransactionError {
 }
 
 /// The error type for operations on a [`Block`](Block)
-#[derive(Debug, PartialEq, Fail)]
+#[derive(Debug, PartialEq, Fail, Serialize)]
 pub enum BlockError {
     /// The block has no transactions in it.
     #[fail(display = "The block has no transactions")]
@@ -161,6 +162,29 @@ pub enum BlockError {
     VerifySignatureFail { hash: Hash },
 }
 
+/// The error type for operations on a [`PartialBlock`](PartialBlock)
+#[derive(Debug, PartialEq, Fail)]
+pub enum CompactBlockError {
+    /// The prefilled transactions are not sorted or point outside the block
+    #[fail(
+        display = "Invalid prefilled transaction index {} in a block of {} transactions",
+        index, txns_len
+    )]
+    InvalidPrefilledIndex { index: u32, txns_len: usize },
+    /// Some transactions of the block have not been received yet
+    #[fail(display = "{} transactions of the block are still missing", missing)]
+    MissingTransactions { missing: usize },
+    /// The number of received transactions does not match the missing ones
+    #[fail(
+        display = "Received {} transactions, but {} were missing",
+        received, missing
+    )]
+    UnexpectedTransactions { received: usize, missing: usize },
+    /// The transactions of the rebuilt block do not match its merkle root
+    #[fail(display = "The transactions do not match the merkle root of the block")]
+    MerkleRootMismatch,
+}
+
 #[derive(Debug, Fail)]
 pub enum OutputPointerParseError {
     #[fail(display = "output pointer has an invalid length")]
@@ -172,3 +196,23 @@ pub enum OutputPointerParseError {
     #[fail(display = "could not parse output index as an integer")]
     ParseIntError(ParseIntError),
 }
+
+/// The error type for parsing a [`PublicKeyHash`](PublicKeyHash)
+#[derive(Debug, PartialEq, Fail)]
+pub enum PublicKeyHashParseError {
+    /// The public key hash does not have 20 bytes
+    #[fail(display = "public key hash has an invalid length: {} bytes", length)]
+    InvalidLength { length: usize },
+    /// The address is not valid bech32, or its checksum does not match
+    #[fail(display = "invalid bech32 address: {}", reason)]
+    Bech32 { reason: String },
+    /// The address does not belong to any known environment
+    #[fail(display = "unknown address prefix \"{}\"", prefix)]
+    UnknownPrefix { prefix: String },
+    /// The address belongs to another environment
+    #[fail(
+        display = "address prefix \"{}\" does not match the environment, expected \"{}\"",
+        prefix, expected
+    )]
+    WrongEnvironment { expected: String, prefix: String },
+}
@@ -7,6 +7,10 @@ extern crate serde_derive;
 #[macro_use]
 extern crate protobuf_convert;
 
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use self::chain::Environment;
+
 /// Module containing functions to generate Witnet's protocol messages
 pub mod builders;
 
@@ -28,5 +32,31 @@ pub mod error;
 /// Module containing data_request structures
 pub mod data_request;
 
+/// Module containing functions to relay blocks as compact blocks and rebuild them
+pub mod compact_block;
+
 #[cfg(test)]
 pub mod tests;
+
+/// Environment of the addresses encoded and decoded by this process: 0 for testnet-1 and 1 for
+/// mainnet. Serializers have no access to the configuration, so it is set when loading it
+static ENVIRONMENT: AtomicUsize = AtomicUsize::new(0);
+
+/// Environment used to encode and decode the addresses of public key hashes, which is the one of
+/// the loaded configuration
+pub fn get_environment() -> Environment {
+    match ENVIRONMENT.load(Ordering::SeqCst) {
+        1 => Environment::Mainnet,
+        _ => Environment::Testnet1,
+    }
+}
+
+/// Set the environment used to encode and decode addresses. Must be called with the environment
+/// of the configuration before serializing or parsing any public key hash
+pub fn set_environment(environment: Environment) {
+    let value = match environment {
+        Environment::Testnet1 => 0,
+        Environment::Mainnet => 1,
+    };
+    ENVIRONMENT.store(value, Ordering::SeqCst);
+}
@@ -1,4 +1,5 @@
 use super::chain::*;
+use super::error::PublicKeyHashParseError;
 
 #[test]
 fn test_block_hashable_trait() {
@@ -314,3 +315,115 @@ fn test_output_value() {
 
     assert_eq!(output.value(), 123);
 }
+
+#[test]
+fn test_output_pkh() {
+    let pkh = PublicKeyHash { hash: [7; 20] };
+    let output = Output::ValueTransfer(ValueTransferOutput { pkh, value: 1 });
+    assert_eq!(output.pkh(), Some(pkh));
+
+    let output = Output::Commit(CommitOutput {
+        commitment: Hash::default(),
+        value: 1,
+    });
+    assert_eq!(output.pkh(), None);
+}
+
+#[test]
+fn test_public_key_hash() {
+    let public_key = PublicKey {
+        compressed: 2,
+        bytes: [0; 32],
+    };
+    let pkh = public_key.pkh();
+
+    let mut bytes = vec![2];
+    bytes.extend_from_slice(&[0; 32]);
+    let witnet_crypto::hash::Sha256(hash) = witnet_crypto::hash::calculate_sha256(&bytes);
+
+    assert_eq!(&pkh.hash[..], &hash[..20]);
+    assert_ne!(
+        pkh,
+        PublicKey {
+            compressed: 3,
+            bytes: [0; 32],
+        }
+        .pkh()
+    );
+}
+
+#[test]
+fn test_public_key_hash_bech32() {
+    let pkh = PublicKeyHash {
+        hash: [
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19,
+        ],
+    };
+    let address = "twit1qqqsyqcyq5rqwzqfpg9scrgwpugpzysnsrnstq";
+
+    assert_eq!(pkh.bech32(&Environment::Testnet1), address);
+    assert_eq!(
+        PublicKeyHash::from_bech32(&Environment::Testnet1, address),
+        Ok(pkh)
+    );
+    assert_eq!(
+        PublicKeyHash::default().bech32(&Environment::Mainnet),
+        "wit1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqwrt3a4"
+    );
+
+    // Addresses of another environment are rejected
+    assert_eq!(
+        PublicKeyHash::from_bech32(&Environment::Mainnet, address),
+        Err(PublicKeyHashParseError::WrongEnvironment {
+            expected: "wit".to_string(),
+            prefix: "twit".to_string(),
+        })
+    );
+
+    // A mistyped character breaks the checksum
+    let mistyped = "twit1qqqsyqcyq5rqwzqfpg9scrgwpugpzysnsrnstp";
+    assert!(PublicKeyHash::from_bech32(&Environment::Testnet1, mistyped).is_err());
+}
+
+#[test]
+fn test_public_key_hash_from_str() {
+    let pkh = PublicKeyHash { hash: [0xab; 20] };
+
+    // The environment is testnet-1 unless `set_environment` is called
+    assert_eq!(pkh.to_string(), pkh.bech32(&Environment::Testnet1));
+    assert_eq!(pkh.to_string().parse::<PublicKeyHash>(), Ok(pkh));
+
+    // Addresses of other environments are rejected
+    assert_eq!(
+        pkh.bech32(&Environment::Mainnet).parse::<PublicKeyHash>(),
+        Err(PublicKeyHashParseError::WrongEnvironment {
+            expected: "twit".to_string(),
+            prefix: "wit".to_string(),
+        })
+    );
+    assert_eq!(
+        PublicKeyHash::from_bech32_any_environment(&pkh.bech32(&Environment::Mainnet)),
+        Ok((Environment::Mainnet, pkh))
+    );
+
+    // Hex is not accepted
+    assert!("abababababababababababababababababababab"
+        .parse::<PublicKeyHash>()
+        .is_err());
+}
+
+#[test]
+fn test_public_key_hash_serde() {
+    let pkh = PublicKeyHash { hash: [0xab; 20] };
+    let json = format!("\"{}\"", pkh.bech32(&Environment::Testnet1));
+
+    assert_eq!(serde_json::to_string(&pkh).unwrap(), json);
+    assert_eq!(serde_json::from_str::<PublicKeyHash>(&json).unwrap(), pkh);
+
+    // Byte arrays of previous versions can still be read
+    let bytes = serde_json::to_string(&pkh.hash).unwrap();
+    assert_eq!(serde_json::from_str::<PublicKeyHash>(&bytes).unwrap(), pkh);
+
+    // Strings which are not addresses are rejected
+    assert!(serde_json::from_str::<PublicKeyHash>("\"twit1invalid\"").is_err());
+}
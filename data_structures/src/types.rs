@@ -1,7 +1,11 @@
 use std::fmt;
 
-use crate::chain::{Block, CheckpointBeacon, Hashable, InventoryEntry, Transaction};
+use crate::chain::{
+    Block, BlockHeader, CheckpointBeacon, Hash, Hashable, InventoryEntry, LeadershipProof,
+    SignedBlockHeader, Transaction,
+};
 use crate::proto::{schema::witnet, ProtobufConvert};
+use witnet_crypto::hash::calculate_sha256;
 
 /// Witnet's protocol messages
 #[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
@@ -33,6 +37,15 @@ pub enum Command {
     InventoryAnnouncement(InventoryAnnouncement),
     InventoryRequest(InventoryRequest),
     LastBeacon(LastBeacon),
+
+    // Synchronization messages
+    GetHeaders(GetHeaders),
+    Headers(Headers),
+
+    // Compact block messages
+    CompactBlock(CompactBlock),
+    GetBlockTransactions(GetBlockTransactions),
+    BlockTransactions(BlockTransactions),
 }
 
 impl fmt::Display for Command {
@@ -49,6 +62,13 @@ impl fmt::Display for Command {
             Command::InventoryRequest(_) => f.write_str(&"INVENTORY_REQUEST".to_string()),
             Command::LastBeacon(_) => f.write_str(&"LAST_BEACON".to_string()),
             Command::Transaction(_) => f.write_str(&"TRANSACTION".to_string()),
+            Command::GetHeaders(_) => f.write_str(&"GET_HEADERS".to_string()),
+            Command::Headers(_) => f.write_str(&"HEADERS".to_string()),
+            Command::CompactBlock(compact_block) => {
+                f.write_str(&format!("COMPACT_BLOCK: {}", compact_block.hash()))
+            }
+            Command::GetBlockTransactions(_) => f.write_str(&"GET_BLOCK_TRANSACTIONS".to_string()),
+            Command::BlockTransactions(_) => f.write_str(&"BLOCK_TRANSACTIONS".to_string()),
         }
     }
 }
@@ -99,6 +119,28 @@ pub struct Version {
     pub user_agent: String,
     pub last_epoch: u32,
     pub nonce: u64,
+    pub encryption_key: EncryptionKey,
+}
+
+/// Ephemeral key offered by a peer to encrypt the session, signed by its identity key
+///
+/// All the fields are empty if the peer does not want to encrypt the session.
+#[derive(Debug, Default, Eq, PartialEq, Clone, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::Version_EncryptionKey")]
+pub struct EncryptionKey {
+    /// Ephemeral secp256k1 public key, serialized in compressed form
+    pub ephemeral_key: Vec<u8>,
+    /// Identity secp256k1 public key of the node, serialized in compressed form
+    pub identity_key: Vec<u8>,
+    /// Signature of the SHA-256 of the ephemeral key by the identity key, serialized in DER
+    pub signature: Vec<u8>,
+}
+
+impl EncryptionKey {
+    /// Whether the peer offers to encrypt the session
+    pub fn is_empty(&self) -> bool {
+        self.ephemeral_key.is_empty()
+    }
 }
 
 ///////////////////////////////////////////////////////////
@@ -122,6 +164,69 @@ pub struct LastBeacon {
     pub highest_block_checkpoint: CheckpointBeacon,
 }
 
+///////////////////////////////////////////////////////////
+// SYNCHRONIZATION MESSAGES
+///////////////////////////////////////////////////////////
+#[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::GetHeaders")]
+pub struct GetHeaders {
+    pub highest_block_checkpoint: CheckpointBeacon,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::Headers")]
+pub struct Headers {
+    pub headers: Vec<SignedBlockHeader>,
+}
+
+///////////////////////////////////////////////////////////
+// COMPACT BLOCK MESSAGES
+///////////////////////////////////////////////////////////
+/// Block relayed as its header along with short identifiers of its transactions, which the
+/// receiver is expected to find in its transactions pool
+#[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::CompactBlock")]
+pub struct CompactBlock {
+    pub block_header: BlockHeader,
+    pub proof: LeadershipProof,
+    /// Nonce used to compute the short identifiers
+    pub nonce: u64,
+    /// Short identifiers of the transactions which are not prefilled, in block order
+    pub short_ids: Vec<u64>,
+    /// Transactions sent in full, sorted by their index in the block
+    pub prefilled_txns: Vec<PrefilledTransaction>,
+}
+
+impl Hashable for CompactBlock {
+    fn hash(&self) -> Hash {
+        calculate_sha256(&self.block_header.to_pb_bytes().unwrap()).into()
+    }
+}
+
+/// Transaction of a compact block sent in full, along with its index in the block
+#[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::CompactBlock_PrefilledTransaction")]
+pub struct PrefilledTransaction {
+    pub index: u32,
+    pub transaction: Transaction,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::GetBlockTransactions")]
+pub struct GetBlockTransactions {
+    pub block_hash: Hash,
+    /// Indexes in the block of the requested transactions
+    pub indexes: Vec<u32>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::BlockTransactions")]
+pub struct BlockTransactions {
+    pub block_hash: Hash,
+    /// Requested transactions, in the order of the request
+    pub transactions: Vec<Transaction>,
+}
+
 ///////////////////////////////////////////////////////////
 // AUX TYPES
 ///////////////////////////////////////////////////////////
@@ -18,6 +18,89 @@ fn builders_build_last_beacon() {
     );
 }
 
+#[test]
+fn builders_build_get_headers() {
+    let highest_block_checkpoint = CheckpointBeacon::default();
+    let msg = Message {
+        kind: Command::GetHeaders(GetHeaders {
+            highest_block_checkpoint,
+        }),
+        magic: 0xABCD,
+    };
+    assert_eq!(
+        msg,
+        Message::build_get_headers(0xABCD, highest_block_checkpoint)
+    );
+}
+
+#[test]
+fn builders_build_headers() {
+    let headers = vec![SignedBlockHeader::default(), SignedBlockHeader::default()];
+    let msg = Message {
+        kind: Command::Headers(Headers {
+            headers: headers.clone(),
+        }),
+        magic: 0xABCD,
+    };
+    assert_eq!(msg, Message::build_headers(0xABCD, headers));
+}
+
+#[test]
+fn builders_build_compact_block() {
+    let block = Block {
+        block_header: BlockHeader::default(),
+        proof: LeadershipProof::default(),
+        txns: vec![transaction_example()],
+    };
+
+    // The nonce is random, so only the rest of the fields are checked
+    match Message::build_compact_block(0xABCD, &block) {
+        Message {
+            kind: Command::CompactBlock(compact_block),
+            magic: 0xABCD,
+        } => {
+            assert_eq!(
+                compact_block,
+                CompactBlock::new(&block, compact_block.nonce)
+            );
+        }
+        msg => panic!("Unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn builders_build_get_block_transactions() {
+    let block_hash = Hash::default();
+    let msg = Message {
+        kind: Command::GetBlockTransactions(GetBlockTransactions {
+            block_hash,
+            indexes: vec![1, 3],
+        }),
+        magic: 0xABCD,
+    };
+    assert_eq!(
+        msg,
+        Message::build_get_block_transactions(0xABCD, block_hash, vec![1, 3])
+    );
+}
+
+#[test]
+fn builders_build_block_transactions() {
+    let block_hash = Hash::default();
+    let transactions = vec![transaction_example()];
+    let msg = Message {
+        kind: Command::BlockTransactions(BlockTransactions {
+            block_hash,
+            transactions: transactions.clone(),
+        }),
+        magic: 0xABCD,
+    };
+    assert_eq!(
+        msg,
+        Message::build_block_transactions(0xABCD, block_hash, transactions)
+    );
+}
+
 #[test]
 fn builders_build_block() {
     // Prepare block header
@@ -125,7 +208,7 @@ fn builders_build_pong() {
 
 #[test]
 fn builders_build_version() {
-    // Expected message (except nonce which is random and timestamp which is the current one)
+    // Expected message (except timestamp which is the current one)
     let hardcoded_last_epoch = 1234;
     let sender_addr = Address {
         ip: IpAddress::Ipv4 { ip: 3_232_235_777 },
@@ -144,6 +227,7 @@ fn builders_build_version() {
         user_agent: USER_AGENT.to_string(),
         last_epoch: hardcoded_last_epoch,
         nonce: 1234,
+        encryption_key: EncryptionKey::default(),
     });
     let msg = Message {
         kind: version_cmd,
@@ -158,6 +242,8 @@ fn builders_build_version() {
         sender_sock_addr,
         receiver_sock_addr,
         hardcoded_last_epoch,
+        1234,
+        EncryptionKey::default(),
     );
 
     // Check that the build_version function builds the expected message
@@ -170,6 +256,8 @@ fn builders_build_version() {
             receiver_address,
             user_agent,
             last_epoch,
+            nonce,
+            encryption_key,
             ..
         }) => assert!(
             *version == PROTOCOL_VERSION
@@ -178,6 +266,8 @@ fn builders_build_version() {
                 && *receiver_address == receiver_addr
                 && user_agent == USER_AGENT
                 && *last_epoch == hardcoded_last_epoch
+                && *nonce == 1234
+                && encryption_key.is_empty()
         ),
         _ => panic!("Some field/s do not match the expected value"),
     };
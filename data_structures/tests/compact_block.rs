@@ -0,0 +1,141 @@
+use witnet_crypto::{hash::Sha256, merkle::merkle_tree_root};
+use witnet_data_structures::{
+    chain::*,
+    compact_block::{short_transaction_id, PartialBlock},
+    error::CompactBlockError,
+    types::*,
+};
+
+fn transaction(value: u64) -> Transaction {
+    let output = Output::ValueTransfer(ValueTransferOutput {
+        value,
+        ..ValueTransferOutput::default()
+    });
+
+    Transaction::new(TransactionBody::new(0, vec![], vec![output]), vec![])
+}
+
+/// Block with `n` transactions and a valid merkle root
+fn block(n: u64) -> Block {
+    let txns: Vec<Transaction> = (0..n).map(transaction).collect();
+    let hashes: Vec<Sha256> = txns
+        .iter()
+        .map(|tx| match tx.hash() {
+            Hash::SHA256(x) => Sha256(x),
+        })
+        .collect();
+    let block_header = BlockHeader {
+        hash_merkle_root: merkle_tree_root(&hashes).into(),
+        ..BlockHeader::default()
+    };
+
+    Block {
+        block_header,
+        proof: LeadershipProof::default(),
+        txns,
+    }
+}
+
+#[test]
+fn compact_block_prefills_mint_transaction() {
+    let block = block(3);
+    let compact_block = CompactBlock::new(&block, 7);
+
+    assert_eq!(compact_block.hash(), block.hash());
+    assert_eq!(
+        compact_block.prefilled_txns,
+        vec![PrefilledTransaction {
+            index: 0,
+            transaction: block.txns[0].clone(),
+        }]
+    );
+    let expected_ids: Vec<u64> = block.txns[1..]
+        .iter()
+        .map(|tx| short_transaction_id(block.hash(), 7, tx.hash()))
+        .collect();
+    assert_eq!(compact_block.short_ids, expected_ids);
+
+    // The short ids depend on the nonce
+    assert_ne!(CompactBlock::new(&block, 8).short_ids, expected_ids);
+}
+
+#[test]
+fn partial_block_rebuilt_from_mempool() {
+    let block = block(4);
+    let mut partial = PartialBlock::new(CompactBlock::new(&block, 7)).unwrap();
+    assert_eq!(partial.hash(), block.hash());
+    assert_eq!(partial.missing_indexes(), vec![1, 2, 3]);
+
+    // The mempool contains some unrelated transactions and lacks the third one
+    let mempool = vec![
+        block.txns[1].clone(),
+        transaction(10),
+        block.txns[3].clone(),
+    ];
+    partial.fill_from_mempool(mempool.iter());
+    assert_eq!(partial.missing_indexes(), vec![2]);
+    assert_eq!(
+        partial.try_build(),
+        Err(CompactBlockError::MissingTransactions { missing: 1 })
+    );
+
+    // The peer must send exactly the missing transactions
+    assert_eq!(
+        partial.add_transactions(vec![]),
+        Err(CompactBlockError::UnexpectedTransactions {
+            received: 0,
+            missing: 1,
+        })
+    );
+    partial
+        .add_transactions(vec![block.txns[2].clone()])
+        .unwrap();
+    assert!(partial.missing_indexes().is_empty());
+    assert_eq!(partial.try_build(), Ok(block));
+}
+
+#[test]
+fn partial_block_rejects_invalid_prefilled_index() {
+    let block = block(2);
+    let mut compact_block = CompactBlock::new(&block, 7);
+    compact_block.prefilled_txns[0].index = 2;
+
+    assert_eq!(
+        PartialBlock::new(compact_block).unwrap_err(),
+        CompactBlockError::InvalidPrefilledIndex {
+            index: 2,
+            txns_len: 2,
+        }
+    );
+}
+
+#[test]
+fn partial_block_recovers_from_short_id_collision() {
+    let block = block(2);
+    let wrong_transaction = transaction(10);
+
+    // Simulate a collision: the short id of the second transaction matches another transaction
+    let mut compact_block = CompactBlock::new(&block, 7);
+    compact_block.short_ids = vec![short_transaction_id(
+        block.hash(),
+        7,
+        wrong_transaction.hash(),
+    )];
+    let mut partial = PartialBlock::new(compact_block).unwrap();
+
+    partial.fill_from_mempool(vec![wrong_transaction].iter());
+    assert!(partial.missing_indexes().is_empty());
+    assert_eq!(
+        partial.try_build(),
+        Err(CompactBlockError::MerkleRootMismatch)
+    );
+
+    // The transactions taken from the mempool are requested to the peer instead
+    assert!(partial.reset_mempool_transactions());
+    assert_eq!(partial.missing_indexes(), vec![1]);
+    assert!(!partial.reset_mempool_transactions());
+    partial
+        .add_transactions(vec![block.txns[1].clone()])
+        .unwrap();
+    assert_eq!(partial.try_build(), Ok(block));
+}
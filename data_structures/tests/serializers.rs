@@ -299,13 +299,14 @@ fn message_version_to_bytes() {
             user_agent: "asdf".to_string(),
             last_epoch: 8,
             nonce: 1,
+            encryption_key: EncryptionKey::default(),
         }),
         magic: 1,
     };
     let expected_buf: Vec<u8> = [
-        8, 1, 18, 55, 10, 53, 8, 2, 16, 123, 25, 4, 0, 0, 0, 0, 0, 0, 0, 34, 8, 10, 6, 192, 168, 1,
+        8, 1, 18, 57, 10, 55, 8, 2, 16, 123, 25, 4, 0, 0, 0, 0, 0, 0, 0, 34, 8, 10, 6, 192, 168, 1,
         1, 31, 64, 42, 8, 10, 6, 192, 168, 1, 2, 31, 65, 50, 4, 97, 115, 100, 102, 61, 8, 0, 0, 0,
-        65, 1, 0, 0, 0, 0, 0, 0, 0,
+        65, 1, 0, 0, 0, 0, 0, 0, 0, 74, 0,
     ]
     .to_vec();
     let result: Vec<u8> = msg.to_pb_bytes().unwrap();
@@ -333,10 +334,12 @@ fn message_version_from_bytes() {
             user_agent: "asdf".to_string(),
             last_epoch: 8,
             nonce: 1,
+            encryption_key: EncryptionKey::default(),
         }),
         magic: 1,
     };
 
+    // Peers which do not support encrypted sessions do not send the encryption key
     let buf: Vec<u8> = [
         8, 1, 18, 55, 10, 53, 8, 2, 16, 123, 25, 4, 0, 0, 0, 0, 0, 0, 0, 34, 8, 10, 6, 192, 168, 1,
         1, 31, 64, 42, 8, 10, 6, 192, 168, 1, 2, 31, 65, 50, 4, 97, 115, 100, 102, 61, 8, 0, 0, 0,
@@ -367,6 +370,7 @@ fn message_version_encode_decode() {
             user_agent: "asdf".to_string(),
             last_epoch: 8,
             nonce: 1,
+            encryption_key: EncryptionKey::default(),
         }),
         magic: 1,
     };
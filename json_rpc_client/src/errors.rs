@@ -0,0 +1,172 @@
+//! Errors of the JSON-RPC client, and error codes of the JSON-RPC interface of the node
+//!
+//! Besides the standard JSON-RPC errors, the methods of the node return these codes:
+//!
+//! | Code     | Error                          |
+//! |----------|--------------------------------|
+//! | `-32000` | Rate limit exceeded            |
+//! | `-32001` | Unauthorized                   |
+//! | `-32010` | `ChainManagerError`            |
+//! | `-32020` | `InventoryManagerError`        |
+//! | `-32030` | `TransactionError`             |
+//! | `-32040` | `BlockError`                   |
+//! | `-32050` | `RadError`                     |
+//! | `-32060` | `MasterKeyError`               |
+//!
+//! The errors coming from the node include an [`ErrorData`](ErrorData) as their `data`.
+//! These codes are part of the interface, so they must not change.
+
+use std::{fmt, io};
+
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The client sent too many requests
+pub const RATE_LIMIT_EXCEEDED: i64 = -32000;
+/// The client is not authenticated
+pub const UNAUTHORIZED: i64 = -32001;
+/// Error returned by the `ChainManager`
+pub const CHAIN_MANAGER_ERROR: i64 = -32010;
+/// Error returned by the `InventoryManager`
+pub const INVENTORY_MANAGER_ERROR: i64 = -32020;
+/// Invalid transaction
+pub const TRANSACTION_ERROR: i64 = -32030;
+/// Invalid block
+pub const BLOCK_ERROR: i64 = -32040;
+/// Error when resolving a RAD request
+pub const RAD_ERROR: i64 = -32050;
+/// Error when importing an encrypted master key
+pub const MASTER_KEY_ERROR: i64 = -32060;
+
+/// Machine-readable description of an error, sent as the `data` of the JSON-RPC error
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorData {
+    /// Name of the error type, for example `TransactionError`
+    pub error: String,
+    /// Name of the variant of the error, for example `InvalidFee`
+    pub kind: String,
+    /// Fields of the variant, if any, for example `{"fee": 1, "expected_fee": 2}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl ErrorData {
+    /// Describe an error which implements `Serialize`
+    pub fn from_serialize<T: Serialize>(error: &'static str, e: &T) -> Self {
+        // Enums are serialized as `"Variant"`, or as `{"Variant": fields}` when they have fields
+        let (kind, details) = match serde_json::to_value(e) {
+            Ok(Value::String(kind)) => (kind, None),
+            Ok(Value::Object(map)) => match map.into_iter().next() {
+                Some((kind, details)) => (kind, Some(details)),
+                None => (String::new(), None),
+            },
+            _ => (String::new(), None),
+        };
+
+        Self {
+            error: error.to_string(),
+            kind,
+            details,
+        }
+    }
+
+    /// Describe an error using its `Debug` representation, which starts with the variant name
+    pub fn from_debug<T: fmt::Debug>(error: &'static str, e: &T) -> Self {
+        let kind = format!("{:?}", e)
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+
+        Self {
+            error: error.to_string(),
+            kind,
+            details: None,
+        }
+    }
+}
+
+/// Errors of the JSON-RPC client
+#[derive(Debug, Fail)]
+pub enum ClientError {
+    /// Failed to read from or write to the connection
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+    /// The node closed the connection
+    #[fail(display = "Connection closed by the node")]
+    ConnectionClosed,
+    /// Failed to serialize a request or to deserialize a response
+    #[fail(display = "Invalid JSON-RPC message: {}", _0)]
+    Json(#[cause] serde_json::Error),
+    /// The node uses another version of JSON-RPC
+    #[fail(display = "Incompatible JSON-RPC version used by server: {}", _0)]
+    Version(String),
+    /// The id of the response does not match the id of the request
+    #[fail(display = "Expected response with id {}, got {}", expected, received)]
+    UnexpectedId {
+        /// Id of the request
+        expected: u64,
+        /// Id of the response
+        received: Value,
+    },
+    /// The node returned an error
+    #[fail(display = "{}", _0)]
+    Server(#[cause] ServerError),
+}
+
+impl ClientError {
+    /// Whether the error is a read timeout, after which the client can still be used
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            ClientError::Io(e) => {
+                e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut
+            }
+            _ => false,
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(e: serde_json::Error) -> Self {
+        ClientError::Json(e)
+    }
+}
+
+/// Error returned by the node, with code and message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Fail)]
+pub struct ServerError {
+    /// JSON-RPC error code, see [`errors`](self)
+    pub code: i64,
+    /// Description of the error
+    pub message: String,
+    /// Description of the errors coming from the node
+    #[serde(default)]
+    pub data: Option<ErrorData>,
+}
+
+// Required for Fail derive
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            RATE_LIMIT_EXCEEDED => write!(f, "Too many requests to the node, try again later"),
+            UNAUTHORIZED => write!(
+                f,
+                "The node requires authentication, check the auth_token or \
+                 auth_cookie_path params of the [jsonrpc] configuration"
+            ),
+            CHAIN_MANAGER_ERROR => write!(f, "Chain error: {}", self.message),
+            INVENTORY_MANAGER_ERROR => write!(f, "Inventory error: {}", self.message),
+            TRANSACTION_ERROR => write!(f, "Invalid transaction: {}", self.message),
+            BLOCK_ERROR => write!(f, "Invalid block: {}", self.message),
+            RAD_ERROR => write!(f, "Data request error: {}", self.message),
+            MASTER_KEY_ERROR => write!(f, "Master key error: {}", self.message),
+            code => write!(f, "{} (JSON-RPC error {})", self.message, code),
+        }
+    }
+}
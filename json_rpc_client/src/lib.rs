@@ -0,0 +1,464 @@
+//! # JSON-RPC client
+//!
+//! Blocking client of the JSON-RPC interface of the node, which uses the TCP transport, where
+//! each message is a line of JSON. The notifications of the subscriptions can arrive while
+//! waiting for the response to a request, so they are queued until they are read with
+//! [`Client::next_notification`].
+//!
+//! Besides the untyped [`Client::call`], the client has a typed method for each JSON-RPC
+//! method of the node whose params and result do not depend on the node, using the types in
+//! [`types`](types), so that any component can use them without depending on the node. The
+//! node extends it with the rest of its JSON-RPC methods.
+
+#![deny(rust_2018_idioms)]
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(missing_docs)]
+
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    mem,
+    net::{IpAddr, SocketAddr, TcpStream},
+    time::Duration,
+};
+
+use log::{debug, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use witnet_config::config::JsonRPC as JsonRpcConfig;
+use witnet_data_structures::chain::{Block, Epoch, Hash, Output, OutputPointer, Transaction};
+
+pub mod errors;
+pub mod types;
+
+pub use self::errors::{ClientError, ServerError};
+use self::types::{GetBlockChainParams, InventoryItem, TransactionInfo};
+
+/// Name of the method used to authenticate TCP connections
+pub const AUTHENTICATE_METHOD: &str = "authenticate";
+
+/// Method used by the node to send the notifications of the subscriptions
+const SUBSCRIPTION_NOTIFICATION: &str = "witnet_subscription";
+
+/// Get the token which a client must send to the node, reading the cookie file if needed.
+///
+/// Returns `None` when authentication is disabled.
+pub fn client_token(config: &JsonRpcConfig) -> io::Result<Option<String>> {
+    match (&config.auth_token, &config.auth_cookie_path) {
+        (Some(token), _) => Ok(Some(token.clone())),
+        (None, Some(cookie_path)) => Ok(Some(fs::read_to_string(cookie_path)?.trim().to_string())),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Notification of a subscription
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    /// Id of the subscription, as returned by [`Client::subscribe`]
+    pub subscription: String,
+    /// Payload of the notification, whose type depends on the subscription
+    pub result: Value,
+}
+
+impl Notification {
+    /// Deserialize the payload of the notification
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T, ClientError> {
+        serde_json::from_value(self.result.clone()).map_err(ClientError::Json)
+    }
+}
+
+/// Request sent to the node
+#[derive(Debug, Serialize)]
+struct Request<'a> {
+    jsonrpc: &'static str,
+    method: &'a str,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    params: Value,
+    id: u64,
+}
+
+/// Any message sent by the node: a response or a notification
+#[derive(Debug, Deserialize)]
+struct Message {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Value,
+    #[serde(default)]
+    result: Value,
+    #[serde(default)]
+    error: Option<ServerError>,
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Blocking JSON-RPC client of a node
+#[derive(Debug)]
+pub struct Client<S = TcpStream> {
+    stream: BufReader<S>,
+    next_id: u64,
+    notifications: VecDeque<Notification>,
+    /// Part of a line read before a read timeout expired
+    partial_line: String,
+}
+
+impl Client<TcpStream> {
+    /// Connect to the TCP JSON-RPC server of a node
+    pub fn connect(addr: SocketAddr) -> Result<Self, ClientError> {
+        debug!("Connecting to JSON-RPC server at {}", addr);
+
+        Ok(Self::new(TcpStream::connect(addr)?))
+    }
+
+    /// Set the maximum time to wait for a message of the node, or `None` to wait forever.
+    ///
+    /// When it expires, the pending call fails with an error whose
+    /// [`is_timeout`](ClientError::is_timeout) is true.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), ClientError> {
+        self.stream.get_ref().set_read_timeout(timeout)?;
+
+        Ok(())
+    }
+}
+
+impl<S: Read + Write> Client<S> {
+    /// Use an already open connection to a node
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+            next_id: 1,
+            notifications: VecDeque::new(),
+            partial_line: String::new(),
+        }
+    }
+
+    /// Close the client, returning its connection
+    pub fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    /// Send a request as is, and return the next line sent by the node, which is usually the
+    /// response.
+    ///
+    /// The request must be a single line of JSON, the newline is added if missing.
+    pub fn call_raw(&mut self, request: &str) -> Result<String, ClientError> {
+        let request = request.trim_end_matches('\n');
+        if request.contains('\n') {
+            warn!("The request contains more than one newline, only the first response will be returned");
+        }
+        self.write_line(request)?;
+
+        self.read_line()
+    }
+
+    /// Call a method, and wait for its response.
+    ///
+    /// The params are omitted when they serialize to `null`.
+    pub fn call<P, T>(&mut self, method: &str, params: P) -> Result<T, ClientError>
+    where
+        P: Serialize,
+        T: DeserializeOwned,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = Request {
+            jsonrpc: "2.0",
+            method,
+            params: serde_json::to_value(params)?,
+            id,
+        };
+        self.write_line(&serde_json::to_string(&request)?)?;
+
+        loop {
+            let message: Message = serde_json::from_str(&self.read_line()?)?;
+            if message.jsonrpc != "2.0" {
+                return Err(ClientError::Version(message.jsonrpc));
+            }
+
+            if message.method.as_ref().map(String::as_str) == Some(SUBSCRIPTION_NOTIFICATION) {
+                self.notifications
+                    .push_back(serde_json::from_value(message.params)?);
+                continue;
+            }
+
+            // The node answers with a null id when it cannot read the id of the request, for
+            // example when it exceeds the rate limit
+            if message.id.is_null() {
+                if let Some(error) = message.error {
+                    return Err(ClientError::Server(error));
+                }
+            }
+
+            // Only one request is sent at a time, so the response must have the same id
+            if message.id != Value::from(id) {
+                return Err(ClientError::UnexpectedId {
+                    expected: id,
+                    received: message.id,
+                });
+            }
+
+            return match message.error {
+                Some(error) => {
+                    if let Some(data) = &error.data {
+                        debug!("Server error: {}::{}", data.error, data.kind);
+                    }
+                    Err(ClientError::Server(error))
+                }
+                None => serde_json::from_value(message.result).map_err(ClientError::Json),
+            };
+        }
+    }
+
+    /// Prove to the node that this client knows the authentication token
+    pub fn authenticate(&mut self, token: &str) -> Result<bool, ClientError> {
+        self.call(AUTHENTICATE_METHOD, (token,))
+    }
+
+    /// Subscribe to the notifications of `method`, returning the id of the subscription.
+    ///
+    /// The filter is omitted when it is `null`.
+    pub fn subscribe(&mut self, method: &str, filter: Value) -> Result<String, ClientError> {
+        if filter.is_null() {
+            self.call("witnet_subscribe", (method,))
+        } else {
+            self.call("witnet_subscribe", (method, filter))
+        }
+    }
+
+    /// Cancel a subscription, returning whether it existed
+    pub fn unsubscribe(&mut self, subscription_id: &str) -> Result<bool, ClientError> {
+        self.call("witnet_unsubscribe", (subscription_id,))
+    }
+
+    /// Wait for the next notification of any of the subscriptions
+    pub fn next_notification(&mut self) -> Result<Notification, ClientError> {
+        if let Some(notification) = self.notifications.pop_front() {
+            return Ok(notification);
+        }
+
+        loop {
+            let message: Message = serde_json::from_str(&self.read_line()?)?;
+            if message.method.as_ref().map(String::as_str) == Some(SUBSCRIPTION_NOTIFICATION) {
+                return serde_json::from_value(message.params).map_err(ClientError::Json);
+            }
+
+            warn!("Ignoring unexpected JSON-RPC message: {:?}", message);
+        }
+    }
+
+    /// Make the node process, validate and potentially broadcast a new inventory item
+    pub fn inventory(&mut self, item: &InventoryItem) -> Result<bool, ClientError> {
+        self.call("inventory", item)
+    }
+
+    /// Submit a transaction and wait until the node validates it, returning its hash
+    pub fn send_transaction(&mut self, transaction: &Transaction) -> Result<Hash, ClientError> {
+        self.call("sendTransaction", (transaction,))
+    }
+
+    /// Get the hashes of the blocks of the chain, starting from an epoch.
+    ///
+    /// A negative epoch counts from the current epoch, and a limit of 0 means no limit.
+    pub fn get_block_chain(
+        &mut self,
+        epoch: i64,
+        limit: u32,
+    ) -> Result<Vec<(Epoch, Hash)>, ClientError> {
+        self.call("getBlockChain", GetBlockChainParams { epoch, limit })
+    }
+
+    /// Get a block by its hash
+    pub fn get_block(&mut self, hash: Hash) -> Result<Block, ClientError> {
+        self.call("getBlock", (hash.to_string(),))
+    }
+
+    /// Get an unspent output by its output pointer
+    pub fn get_output(&mut self, output_pointer: &OutputPointer) -> Result<Output, ClientError> {
+        self.call("getOutput", (output_pointer,))
+    }
+
+    /// Get a transaction by its hash, along with the block which includes it
+    pub fn get_transaction(&mut self, hash: Hash) -> Result<TransactionInfo, ClientError> {
+        self.call("getTransaction", (hash.to_string(),))
+    }
+
+    /// Get the unspent outputs which can be spent by an address
+    pub fn get_utxos(
+        &mut self,
+        address: &str,
+    ) -> Result<Vec<(OutputPointer, Output)>, ClientError> {
+        self.call("getUtxos", (address,))
+    }
+
+    /// Get the addresses of the peers known by the node
+    pub fn get_known_peers(&mut self) -> Result<Vec<SocketAddr>, ClientError> {
+        self.call("getKnownPeers", ())
+    }
+
+    /// Lift the ban of an IP address, returning whether it was banned
+    pub fn unban_peer(&mut self, ip: IpAddr) -> Result<bool, ClientError> {
+        self.call("unbanPeer", (ip,))
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), ClientError> {
+        let stream = self.stream.get_mut();
+        stream.write_all(line.as_bytes())?;
+        stream.write_all(b"\n")?;
+        stream.flush()?;
+
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> Result<String, ClientError> {
+        // When the read timeout expires in the middle of a line, the part already read is kept
+        // in `partial_line`, so that the next read completes it
+        if self.stream.read_line(&mut self.partial_line)? == 0 {
+            return Err(ClientError::ConnectionClosed);
+        }
+
+        Ok(mem::replace(&mut self.partial_line, String::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Connection which replies with the given lines and records the requests
+    struct MockStream {
+        responses: io::Cursor<Vec<u8>>,
+        requests: Vec<u8>,
+    }
+
+    impl MockStream {
+        fn new(responses: &[&str]) -> Self {
+            let mut bytes = vec![];
+            for response in responses {
+                bytes.extend_from_slice(response.as_bytes());
+                bytes.push(b'\n');
+            }
+
+            Self {
+                responses: io::Cursor::new(bytes),
+                requests: vec![],
+            }
+        }
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.responses.read(buf)
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.requests.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn requests(client: Client<MockStream>) -> String {
+        String::from_utf8(client.into_inner().requests).unwrap()
+    }
+
+    #[test]
+    fn server_error() {
+        let mut client = Client::new(MockStream::new(&[
+            r#"{"jsonrpc":"2.0","error":{"code":-32030,"message":"Invalid fee found: 1. Expected fee: 2","data":{"error":"TransactionError","kind":"InvalidFee","details":{"fee":1,"expected_fee":2}}},"id":1}"#,
+            r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":2}"#,
+        ]));
+
+        let error = client.call::<_, Value>("getNodeStatus", ()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid transaction: Invalid fee found: 1. Expected fee: 2"
+        );
+        match error {
+            ClientError::Server(error) => assert_eq!(error.data.unwrap().kind, "InvalidFee"),
+            error => panic!("Unexpected error: {:?}", error),
+        }
+
+        let error = client.call::<_, Value>("getNodeStatus", ()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Method not found (JSON-RPC error -32601)"
+        );
+    }
+
+    #[test]
+    fn server_error_with_null_id() {
+        let mut client = Client::new(MockStream::new(&[
+            r#"{"jsonrpc":"2.0","error":{"code":-32000,"message":"Rate limit exceeded"},"id":null}"#,
+        ]));
+
+        match client.call::<_, Value>("getNodeStatus", ()) {
+            Err(ClientError::Server(error)) => assert_eq!(error.code, errors::RATE_LIMIT_EXCEEDED),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn notifications_are_queued() {
+        let mut client = Client::new(MockStream::new(&[
+            r#"{"jsonrpc":"2.0","result":"1","id":1}"#,
+            r#"{"jsonrpc":"2.0","method":"witnet_subscription","params":{"result":7,"subscription":"1"}}"#,
+            r#"{"jsonrpc":"2.0","result":true,"id":2}"#,
+            r#"{"jsonrpc":"2.0","method":"witnet_subscription","params":{"result":8,"subscription":"1"}}"#,
+        ]));
+
+        let id = client.subscribe("newEpoch", Value::Null).unwrap();
+        assert_eq!(id, "1");
+        assert!(client.unsubscribe(&id).unwrap());
+
+        let epochs: Vec<u32> = (0..2)
+            .map(|_| client.next_notification().unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(epochs, vec![7, 8]);
+        assert!(client.next_notification().is_err());
+        assert_eq!(
+            requests(client),
+            "{\"jsonrpc\":\"2.0\",\"method\":\"witnet_subscribe\",\"params\":[\"newEpoch\"],\"id\":1}\n\
+             {\"jsonrpc\":\"2.0\",\"method\":\"witnet_unsubscribe\",\"params\":[\"1\"],\"id\":2}\n"
+        );
+    }
+
+    #[test]
+    fn unexpected_response() {
+        let mut client = Client::new(MockStream::new(&[
+            r#"{"jsonrpc":"1.0","result":true,"id":1}"#,
+            r#"{"jsonrpc":"2.0","result":true,"id":1}"#,
+        ]));
+        match client.authenticate("token") {
+            Err(ClientError::Version(version)) => assert_eq!(version, "1.0"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        match client.authenticate("token") {
+            Err(ClientError::UnexpectedId { expected: 2, .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn get_block_chain_hashes() {
+        let mut client = Client::new(MockStream::new(&[
+            r#"{"jsonrpc":"2.0","result":[[0,"ed28899af8c3148a4162736af942bc68c4466da93c5124dabfaa7c582af49e30"]],"id":1}"#,
+        ]));
+        let block_chain = client.get_block_chain(0, 0).unwrap();
+        assert_eq!(block_chain.len(), 1);
+        assert_eq!(
+            block_chain[0].1.to_string(),
+            "ed28899af8c3148a4162736af942bc68c4466da93c5124dabfaa7c582af49e30"
+        );
+    }
+}
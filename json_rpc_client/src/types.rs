@@ -0,0 +1,55 @@
+//! Params and results of the JSON-RPC methods of the node
+//!
+//! The node uses these same types to implement its methods, so a change in its interface is
+//! also a change in the interface of the clients. Only the types which do not depend on the
+//! node are defined here, the rest are defined by the node itself.
+
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
+use witnet_data_structures::chain::{Block, Transaction, TransactionPointer};
+
+/// Inventory element: block, transaction, etc
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub enum InventoryItem {
+    /// Error
+    #[serde(rename = "error")]
+    Error,
+    /// Transaction
+    #[serde(rename = "transaction")]
+    Transaction(Transaction),
+    /// Block
+    #[serde(rename = "block")]
+    Block(Block),
+}
+
+/// Params of getBlockChain method
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct GetBlockChainParams {
+    /// First epoch of the chain to return. A negative epoch counts from the current epoch
+    #[serde(default)] // default to 0
+    pub epoch: i64,
+    /// Maximum number of blocks to return, 0 means no limit
+    #[serde(default)] // default to 0
+    pub limit: u32,
+}
+
+/// Transaction along with the location of the block which includes it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransactionInfo {
+    /// Transaction
+    pub transaction: Transaction,
+    /// Block which includes the transaction, or `None` if it is still pending in the mempool
+    pub block: Option<TransactionPointer>,
+}
+
+/// Params of banPeer method
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BanPeerParams {
+    /// IP address of the peer
+    pub ip: IpAddr,
+    /// Duration of the ban in seconds, if not present the one from the configuration is used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_seconds: Option<u64>,
+}
@@ -47,6 +47,7 @@ impl ChainManager {
             let consensus_constants = (&config.consensus_constants).clone();
 
             act.max_block_weight = consensus_constants.max_block_weight;
+            act.blocks_timeout = config.connections.blocks_timeout;
 
             storage_mngr::get::<_, ChainState>(&CHAIN_STATE_KEY)
                 .into_actor(act)
@@ -120,6 +121,7 @@ impl ChainManager {
 
             // Do not start the MiningManager if the configuration disables it
             act.mining_enabled = config.mining.enabled;
+            act.block_delay = config.mining.block_delay;
 
             if act.mining_enabled {
                 debug!("Mining enabled!");
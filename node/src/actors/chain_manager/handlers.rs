@@ -1,25 +1,44 @@
-use actix::{Actor, Context, Handler, Message, SystemService};
+use actix::{
+    Actor, AsyncContext, Context, Handler, Message, MessageResult, ResponseFuture, SystemService,
+};
+use futures::future::{self, Future};
 use log::{debug, error, warn};
 
 use witnet_data_structures::{
-    chain::{CheckpointBeacon, Epoch, Hashable, InventoryEntry, InventoryItem},
-    error::ChainInfoError,
+    chain::{
+        Block, CheckpointBeacon, DataRequestReport, Epoch, Hashable, InventoryEntry, InventoryItem,
+        Output, Transaction, TransactionPointer,
+    },
+    error::{BlockError, ChainInfoError},
+};
+use witnet_p2p::bans::Misbehaviour;
+use witnet_util::timestamp::get_timestamp;
+use witnet_validations::validations::{
+    validate_block, validate_header, validate_transaction, UtxoDiff,
 };
-use witnet_validations::validations::{validate_block, validate_transaction, UtxoDiff};
 
-use super::{ChainManager, ChainManagerError, StateMachine};
+use super::{
+    active_data_request_status, resolved_data_request_status, transaction_pointer_key,
+    ChainManager, ChainManagerError, StateMachine,
+};
 use crate::{
     actors::{
+        inventory_manager::InventoryManager,
+        json_rpc::JsonRpcServer,
         messages::{
-            AddBlocks, AddCandidates, AddTransaction, Anycast, Broadcast, EpochNotification,
-            GetBlocksEpochRange, GetHighestCheckpointBeacon, PeersBeacons, SendLastBeacon,
-            SessionUnitResult,
+            ActiveDataRequest, AddBlocks, AddCandidates, AddHeaders, AddTransaction, Balance,
+            Broadcast, DataRequestStatus, EpochNotification, FillPartialBlock,
+            GetActiveDataRequests, GetBalance, GetBlock, GetBlocksAfterBeacon, GetBlocksEpochRange,
+            GetDataRequest, GetHighestCheckpointBeacon, GetItem, GetNodeStatus, GetOutput,
+            GetTransaction, GetUtxos, NewTransaction, NodeStatus, PeersBeacons,
+            SelectTransactionsToRequest, SendLastBeacon, SessionUnitResult,
         },
         sessions_manager::SessionsManager,
     },
+    storage_mngr,
     utils::mode_consensus,
 };
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Bound};
 
 ////////////////////////////////////////////////////////////////////////////////////////
 // ACTOR MESSAGE HANDLERS
@@ -66,6 +85,7 @@ impl Handler<EpochNotification<EveryEpochPayload>> for ChainManager {
     fn handle(&mut self, msg: EpochNotification<EveryEpochPayload>, ctx: &mut Context<Self>) {
         debug!("Periodic epoch notification received {:?}", msg.checkpoint);
         self.current_epoch = Some(msg.checkpoint);
+        self.forget_expired_transaction_requests(get_timestamp());
 
         debug!(
             "EpochNotification received while StateMachine is in state {:?}",
@@ -189,50 +209,113 @@ impl Handler<AddBlocks> for ChainManager {
         match self.sm_state {
             StateMachine::WaitingConsensus => {}
             StateMachine::Synchronizing => {
-                if let Some(target_beacon) = self.target_beacon {
-                    let mut batch_succeeded = true;
-                    for block in msg.blocks.iter() {
-                        if let Err(e) = self.process_requested_block(ctx, block) {
-                            log::error!("Error processing block: {}", e);
-                            self.initialize_from_storage(ctx);
-                            log::info!("Restored chain state from storage");
-                            batch_succeeded = false;
-                            break;
-                        }
+                // Empty messages are sent by the sessions when the blocks do not arrive in time,
+                // but the stalled ranges are reassigned by the ChainManager itself
+                let sender = match msg.sender {
+                    Some(sender) if !msg.blocks.is_empty() => sender,
+                    _ => return,
+                };
+
+                let requested = match self.sync.as_mut() {
+                    Some(sync) => sync.add_blocks(sender, msg.blocks),
+                    None => false,
+                };
+
+                if requested {
+                    self.process_downloaded_blocks(ctx);
+                } else {
+                    debug!("Ignoring blocks from {} which were not requested", sender);
+                }
+            }
+            StateMachine::Synced => {}
+        };
+    }
+}
 
-                        if self.get_chain_beacon() == target_beacon {
-                            break;
-                        }
-                    }
+/// Handler for AddHeaders message
+impl Handler<AddHeaders> for ChainManager {
+    type Result = SessionUnitResult;
 
-                    if batch_succeeded {
-                        self.persist_blocks_batch(ctx, msg.blocks, target_beacon);
-                        let to_be_stored =
-                            self.chain_state.data_request_pool.finished_data_requests();
-                        to_be_stored.into_iter().for_each(|dr| {
-                            self.persist_data_request(ctx, &dr);
-                        });
-                        self.persist_chain_state(ctx);
-                    }
+    fn handle(&mut self, msg: AddHeaders, ctx: &mut Context<Self>) {
+        debug!(
+            "AddHeaders received while StateMachine is in state {:?}",
+            self.sm_state
+        );
+        // Headers are only needed while synchronizing
+        if self.sm_state != StateMachine::Synchronizing {
+            return;
+        }
 
-                    let beacon = self.get_chain_beacon();
+        let genesis_block_hash = self.genesis_block_hash;
+        let (current_epoch, sync) = match (self.current_epoch, self.sync.as_mut()) {
+            (Some(current_epoch), Some(sync)) => (current_epoch, sync),
+            _ => return,
+        };
+        if sync.headers_complete() {
+            return;
+        }
 
-                    if beacon == target_beacon {
-                        // Target achived, go back to state 1
-                        self.sm_state = StateMachine::WaitingConsensus;
-                    } else {
-                        // Try again, send Anycast<SendLastBeacon> to a "safu" peer, i.e. their last beacon matches our target beacon.
-                        SessionsManager::from_registry().do_send(Anycast {
-                            command: SendLastBeacon { beacon },
-                            safu: true,
-                        });
-                    }
-                } else {
-                    warn!("Target Beacon is None");
-                }
+        // Only the peer which the headers were requested to is listened to
+        let sender = match msg.sender {
+            Some(sender) if sync.headers_peer() == Some(sender) => sender,
+            _ => {
+                debug!(
+                    "Ignoring headers from {:?} which were not requested",
+                    msg.sender
+                );
+                return;
             }
-            StateMachine::Synced => {}
         };
+
+        // The peer agreed on the target beacon, so it must know the headers which follow ours
+        let mut failed = msg.headers.is_empty();
+        if failed {
+            warn!("Peer {} did not send any header", sender);
+        }
+
+        let mut misbehaviour = false;
+        for header in msg.headers.iter() {
+            if sync.headers_complete() {
+                break;
+            }
+
+            if let Err(e) =
+                validate_header(header, current_epoch, sync.headers_tip, genesis_block_hash)
+            {
+                warn!("Invalid header: {}", e);
+                // Headers which do not follow ours may belong to a fork, so they are not
+                // penalized
+                misbehaviour = match e.downcast_ref::<BlockError>() {
+                    Some(BlockError::PreviousHashNotKnown { .. }) => false,
+                    _ => true,
+                };
+                failed = true;
+                break;
+            }
+
+            if !sync.add_header(header.hash(), header.block_header.beacon.checkpoint) {
+                warn!("Received headers which do not lead to the target beacon");
+                failed = true;
+                break;
+            }
+        }
+
+        let headers_complete = sync.headers_complete();
+
+        if misbehaviour {
+            self.report_misbehaviour(sender, Misbehaviour::InvalidBlock);
+        }
+
+        if failed {
+            // Start again from our chain beacon with another peer
+            self.retry_headers(ctx);
+        } else if headers_complete {
+            debug!("All the headers up to the target beacon have been received");
+            self.request_blocks(ctx);
+        } else {
+            // Ask for the next headers
+            self.request_headers(ctx);
+        }
     }
 }
 
@@ -243,16 +326,16 @@ impl Handler<AddCandidates> for ChainManager {
     fn handle(&mut self, msg: AddCandidates, _ctx: &mut Context<Self>) {
         // AddCandidates is needed in all states
         for block in msg.blocks {
-            self.process_candidate(block)
+            self.process_candidate(block, msg.sender)
         }
     }
 }
 
 /// Handler for AddTransaction message
 impl Handler<AddTransaction> for ChainManager {
-    type Result = SessionUnitResult;
+    type Result = <AddTransaction as Message>::Result;
 
-    fn handle(&mut self, msg: AddTransaction, _ctx: &mut Context<Self>) {
+    fn handle(&mut self, msg: AddTransaction, _ctx: &mut Context<Self>) -> Self::Result {
         debug!(
             "AddTransaction received while StateMachine is in state {:?}",
             self.sm_state
@@ -260,18 +343,19 @@ impl Handler<AddTransaction> for ChainManager {
         // Ignore AddTransaction when not in Synced state
         match self.sm_state {
             StateMachine::WaitingConsensus => {
-                return;
+                return Err(ChainManagerError::ChainNotReady.into());
             }
             StateMachine::Synchronizing => {
-                return;
+                return Err(ChainManagerError::ChainNotReady.into());
             }
             StateMachine::Synced => {}
         };
 
-        let transaction_hash = &msg.transaction.hash();
-        if self.transactions_pool.contains(transaction_hash) {
+        let transaction_hash = msg.transaction.hash();
+        self.requested_transactions.remove(&transaction_hash);
+        if self.transactions_pool.contains(&transaction_hash) {
             debug!("Transaction is already in the pool: {}", transaction_hash);
-            return;
+            Ok(transaction_hash)
         } else {
             let utxo_diff = UtxoDiff::new(&self.chain_state.unspent_outputs_pool);
             match validate_transaction(
@@ -285,12 +369,22 @@ impl Handler<AddTransaction> for ChainManager {
                     // Broadcast valid transaction
                     self.broadcast_item(InventoryItem::Transaction(msg.transaction.clone()));
 
+                    // Send notification to JsonRpcServer
+                    JsonRpcServer::from_registry().do_send(NewTransaction {
+                        transaction: msg.transaction.clone(),
+                    });
+
                     // Add valid transaction to transactions_pool
                     self.transactions_pool
-                        .insert(*transaction_hash, msg.transaction);
+                        .insert(transaction_hash, msg.transaction);
+
+                    Ok(transaction_hash)
                 }
 
-                Err(e) => warn!("{}", e),
+                Err(e) => {
+                    warn!("{}", e);
+                    Err(e)
+                }
             }
         }
     }
@@ -327,6 +421,248 @@ impl Handler<GetBlocksEpochRange> for ChainManager {
     }
 }
 
+/// Handler for GetBlocksAfterBeacon
+impl Handler<GetBlocksAfterBeacon> for ChainManager {
+    type Result = Result<Vec<(Epoch, InventoryEntry)>, ChainManagerError>;
+
+    fn handle(
+        &mut self,
+        GetBlocksAfterBeacon { beacon, limit }: GetBlocksAfterBeacon,
+        _ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        debug!("GetBlocksAfterBeacon received {:?}", beacon);
+
+        // The beacon of an empty chain is followed by all the blocks
+        let start = if beacon.hash_prev_block == self.genesis_block_hash {
+            Bound::Unbounded
+        } else if self.chain_state.block_chain.get(&beacon.checkpoint)
+            == Some(&beacon.hash_prev_block)
+        {
+            Bound::Excluded(beacon.checkpoint)
+        } else {
+            return Err(ChainManagerError::BlockDoesNotExist);
+        };
+
+        let mut hashes: Vec<(Epoch, InventoryEntry)> = self
+            .chain_state
+            .block_chain
+            .range((start, Bound::Unbounded))
+            .map(|(k, v)| (*k, InventoryEntry::Block(*v)))
+            .collect();
+
+        if limit != 0 {
+            hashes.truncate(limit);
+        }
+
+        Ok(hashes)
+    }
+}
+
+/// Handler for GetBlock
+impl Handler<GetBlock> for ChainManager {
+    type Result = ResponseFuture<Block, ChainManagerError>;
+
+    fn handle(&mut self, GetBlock { hash }: GetBlock, _ctx: &mut Context<Self>) -> Self::Result {
+        // Candidates of the current epoch are not persisted yet
+        if let Some(block) = self.candidates.get(&hash) {
+            return Box::new(future::ok(block.clone()));
+        }
+
+        let fut = InventoryManager::from_registry()
+            .send(GetItem { hash })
+            .then(|res| match res {
+                Ok(Ok(InventoryItem::Block(block))) => Ok(block),
+                Ok(Ok(InventoryItem::Transaction(_))) | Ok(Err(_)) => {
+                    Err(ChainManagerError::BlockDoesNotExist)
+                }
+                Err(e) => Err(ChainManagerError::StorageError {
+                    reason: e.to_string(),
+                }),
+            });
+
+        Box::new(fut)
+    }
+}
+
+/// Handler for FillPartialBlock
+impl Handler<FillPartialBlock> for ChainManager {
+    type Result = MessageResult<FillPartialBlock>;
+
+    fn handle(
+        &mut self,
+        FillPartialBlock { mut partial }: FillPartialBlock,
+        _ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        partial.fill_from_mempool(self.transactions_pool.iter());
+
+        MessageResult(partial)
+    }
+}
+
+/// Handler for SelectTransactionsToRequest
+impl Handler<SelectTransactionsToRequest> for ChainManager {
+    type Result = MessageResult<SelectTransactionsToRequest>;
+
+    fn handle(
+        &mut self,
+        SelectTransactionsToRequest { hashes }: SelectTransactionsToRequest,
+        _ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        // Transactions are only accepted in Synced state, so there is no need to request them
+        if self.sm_state != StateMachine::Synced {
+            return MessageResult(vec![]);
+        }
+
+        MessageResult(self.select_transactions_to_request(hashes, get_timestamp()))
+    }
+}
+
+/// Handler for GetNodeStatus
+impl Handler<GetNodeStatus> for ChainManager {
+    type Result = <GetNodeStatus as Message>::Result;
+
+    fn handle(&mut self, _msg: GetNodeStatus, _ctx: &mut Context<Self>) -> Self::Result {
+        Ok(NodeStatus {
+            current_epoch: self.current_epoch,
+            chain_beacon: self
+                .chain_state
+                .chain_info
+                .as_ref()
+                .map(|chain_info| chain_info.highest_block_checkpoint),
+            state: self.sm_state,
+            target_beacon: self.target_beacon,
+            mempool_size: self.transactions_pool.len(),
+            mining_enabled: self.mining_enabled,
+        })
+    }
+}
+
+/// Handler for GetOutput
+impl Handler<GetOutput> for ChainManager {
+    type Result = <GetOutput as Message>::Result;
+
+    fn handle(
+        &mut self,
+        GetOutput { output_pointer }: GetOutput,
+        _ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        self.chain_state
+            .unspent_outputs_pool
+            .get(&output_pointer)
+            .cloned()
+            .ok_or(ChainManagerError::OutputNotFound { output_pointer })
+    }
+}
+
+/// Handler for GetTransaction
+impl Handler<GetTransaction> for ChainManager {
+    type Result = ResponseFuture<(Transaction, Option<TransactionPointer>), ChainManagerError>;
+
+    fn handle(
+        &mut self,
+        GetTransaction { hash }: GetTransaction,
+        _ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        // Pending transactions are still in the mempool
+        if let Some(transaction) = self.transactions_pool.get(&hash) {
+            return Box::new(future::ok((transaction.clone(), None)));
+        }
+
+        // Consolidated transactions are read from the block which includes them
+        let fut = storage_mngr::get::<_, TransactionPointer>(&transaction_pointer_key(hash))
+            .map_err(|e| ChainManagerError::StorageError {
+                reason: e.to_string(),
+            })
+            .and_then(move |pointer| pointer.ok_or(ChainManagerError::TransactionNotFound { hash }))
+            .and_then(move |pointer| {
+                InventoryManager::from_registry()
+                    .send(GetItem {
+                        hash: pointer.block_hash,
+                    })
+                    .then(move |res| match res {
+                        Ok(Ok(InventoryItem::Block(block))) => block
+                            .txns
+                            .get(pointer.transaction_index as usize)
+                            .cloned()
+                            .map(|transaction| (transaction, Some(pointer)))
+                            .ok_or(ChainManagerError::TransactionNotFound { hash }),
+                        Ok(Ok(InventoryItem::Transaction(_))) => {
+                            Err(ChainManagerError::StorageError {
+                                reason: format!("{} is not a block", pointer.block_hash),
+                            })
+                        }
+                        Ok(Err(e)) => Err(ChainManagerError::StorageError {
+                            reason: e.to_string(),
+                        }),
+                        Err(e) => Err(ChainManagerError::StorageError {
+                            reason: e.to_string(),
+                        }),
+                    })
+            });
+
+        Box::new(fut)
+    }
+}
+
+/// Handler for GetUtxos
+impl Handler<GetUtxos> for ChainManager {
+    type Result = <GetUtxos as Message>::Result;
+
+    fn handle(&mut self, GetUtxos { pkh }: GetUtxos, _ctx: &mut Context<Self>) -> Self::Result {
+        let mut utxos: Vec<_> = self
+            .chain_state
+            .unspent_outputs_pool
+            .iter()
+            .filter(|(_, output)| output.pkh() == Some(pkh))
+            .map(|(output_pointer, output)| (output_pointer.clone(), output.clone()))
+            .collect();
+        // The UTXO set has no order, sort them to always return the same list
+        utxos.sort_by_key(|(output_pointer, _)| output_pointer.to_string());
+
+        Ok(utxos)
+    }
+}
+
+/// Handler for GetBalance
+impl Handler<GetBalance> for ChainManager {
+    type Result = <GetBalance as Message>::Result;
+
+    fn handle(&mut self, GetBalance { pkh }: GetBalance, _ctx: &mut Context<Self>) -> Self::Result {
+        let utxos = &self.chain_state.unspent_outputs_pool;
+        let confirmed = utxos
+            .values()
+            .filter(|output| output.pkh() == Some(pkh))
+            .map(|output| output.value())
+            .sum();
+
+        // Outputs spent and received by the transactions waiting in the mempool
+        let mut spent = 0;
+        let mut received = 0;
+        for transaction in self.transactions_pool.iter() {
+            spent += transaction
+                .body
+                .inputs
+                .iter()
+                .filter_map(|input| utxos.get(&input.output_pointer()))
+                .filter(|output| output.pkh() == Some(pkh))
+                .map(|output| output.value())
+                .sum::<u64>();
+            received += transaction
+                .body
+                .outputs
+                .iter()
+                .filter(|output| output.pkh() == Some(pkh))
+                .map(|output| output.value())
+                .sum::<u64>();
+        }
+
+        Ok(Balance {
+            confirmed,
+            pending: (confirmed + received).saturating_sub(spent),
+        })
+    }
+}
+
 impl Handler<PeersBeacons> for ChainManager {
     type Result = <PeersBeacons as Message>::Result;
 
@@ -347,6 +683,11 @@ impl Handler<PeersBeacons> for ChainManager {
                 // Run the consensus on the beacons, will return the most common beacon
                 // In case of tie returns None
                 if let Some(beacon) = mode_consensus(pb.iter().map(|(_p, b)| b)).cloned() {
+                    // The blocks will be downloaded from the peers in consensus
+                    let peers_in_consensus: Vec<_> = pb
+                        .iter()
+                        .filter_map(|(p, b)| if *b == beacon { Some(*p) } else { None })
+                        .collect();
                     // Consensus: unregister peers which have a different beacon
                     let peers_out_of_consensus = pb
                         .into_iter()
@@ -362,7 +703,7 @@ impl Handler<PeersBeacons> for ChainManager {
                         .highest_block_checkpoint;
 
                     // Check if we are already synchronized
-                    self.sm_state = if our_beacon == beacon {
+                    let next_state = if our_beacon == beacon {
                         StateMachine::Synced
                     } else {
                         // Review candidates
@@ -378,25 +719,20 @@ impl Handler<PeersBeacons> for ChainManager {
                                 Err(e) => {
                                     debug!("Failed to consolidate consensus candidate: {}", e);
 
-                                    // Send Anycast<SendLastBeacon> to a safu peer in order to begin the synchronization
-                                    SessionsManager::from_registry().do_send(Anycast {
-                                        command: SendLastBeacon { beacon: our_beacon },
-                                        safu: true,
-                                    });
+                                    // Request the headers to a safu peer in order to begin the synchronization
+                                    self.start_sync(ctx, beacon, peers_in_consensus);
 
                                     StateMachine::Synchronizing
                                 }
                             }
                         } else {
-                            // Send Anycast<SendLastBeacon> to a safu peer in order to begin the synchronization
-                            SessionsManager::from_registry().do_send(Anycast {
-                                command: SendLastBeacon { beacon: our_beacon },
-                                safu: true,
-                            });
+                            // Request the headers to a safu peer in order to begin the synchronization
+                            self.start_sync(ctx, beacon, peers_in_consensus);
 
                             StateMachine::Synchronizing
                         }
                     };
+                    self.update_state_machine(next_state);
 
                     Ok(peers_out_of_consensus)
                 } else {
@@ -406,7 +742,24 @@ impl Handler<PeersBeacons> for ChainManager {
                 }
             }
             StateMachine::Synchronizing => {
-                // We are synchronizing, so ignore all the new beacons until we reach the target beacon
+                // We are synchronizing, so ignore all the new beacons until we reach the target
+                // beacon, but stop downloading blocks from the peers which have disconnected
+                let connected: Vec<_> = pb.into_iter().map(|(p, _b)| p).collect();
+                let no_peers_left = match self.sync.as_mut() {
+                    Some(sync) => {
+                        sync.retain_peers(&connected);
+
+                        sync.headers_complete() && !sync.has_peers()
+                    }
+                    None => false,
+                };
+
+                if no_peers_left {
+                    warn!("There are no peers left to download the blocks from");
+                    self.abort_sync(ctx);
+                } else {
+                    self.request_blocks(ctx);
+                }
 
                 Ok(vec![])
             }
@@ -472,3 +825,84 @@ impl Handler<PeersBeacons> for ChainManager {
         }
     }
 }
+
+/// Handler for GetDataRequest
+impl Handler<GetDataRequest> for ChainManager {
+    type Result = ResponseFuture<DataRequestStatus, ChainManagerError>;
+
+    fn handle(
+        &mut self,
+        GetDataRequest { dr_pointer }: GetDataRequest,
+        ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        // Data requests in progress are kept in memory
+        if let Some(dr_state) = self
+            .chain_state
+            .data_request_pool
+            .data_request_pool
+            .get(&dr_pointer)
+        {
+            return Box::new(future::ok(active_data_request_status(dr_state)));
+        }
+
+        // Resolved data requests are persisted using their output pointer as key
+        let chain_manager = ctx.address();
+        let fut = storage_mngr::get::<_, DataRequestReport>(&dr_pointer)
+            .map_err(|e| ChainManagerError::StorageError {
+                reason: e.to_string(),
+            })
+            .and_then(move |report| {
+                report.ok_or(ChainManagerError::DataRequestNotFound { dr_pointer })
+            })
+            .and_then(move |report| {
+                // The tally output may have been spent already, so it is read from the tally
+                // transaction instead of the UTXO set
+                chain_manager
+                    .send(GetTransaction {
+                        hash: report.tally.transaction_id,
+                    })
+                    .then(move |res| -> Result<_, ChainManagerError> {
+                        let (transaction, _) =
+                            res.map_err(|e| ChainManagerError::StorageError {
+                                reason: e.to_string(),
+                            })??;
+                        let tally_output = match transaction
+                            .body
+                            .outputs
+                            .get(report.tally.output_index as usize)
+                        {
+                            Some(Output::Tally(tally_output)) => Some(tally_output),
+                            _ => None,
+                        };
+
+                        Ok(resolved_data_request_status(report, tally_output))
+                    })
+            });
+
+        Box::new(fut)
+    }
+}
+
+/// Handler for GetActiveDataRequests
+impl Handler<GetActiveDataRequests> for ChainManager {
+    type Result = <GetActiveDataRequests as Message>::Result;
+
+    fn handle(&mut self, _msg: GetActiveDataRequests, _ctx: &mut Context<Self>) -> Self::Result {
+        let mut data_requests: Vec<_> = self
+            .chain_state
+            .data_request_pool
+            .data_request_pool
+            .iter()
+            .map(|(dr_pointer, dr_state)| ActiveDataRequest {
+                dr_pointer: dr_pointer.clone(),
+                data_request: dr_state.data_request.clone(),
+                epoch: dr_state.epoch,
+                status: active_data_request_status(dr_state),
+            })
+            .collect();
+        // Oldest data requests first
+        data_requests.sort_by_key(|dr| (dr.epoch, dr.dr_pointer.to_string()));
+
+        Ok(data_requests)
+    }
+}
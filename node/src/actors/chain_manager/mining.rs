@@ -6,7 +6,7 @@ use ansi_term::Color::{White, Yellow};
 use log::{debug, error, info, warn};
 
 use futures::future::{join_all, Future};
-use std::{collections::HashMap, time::Duration};
+use std::collections::HashMap;
 
 use super::ChainManager;
 use crate::actors::{
@@ -16,7 +16,7 @@ use crate::actors::{
     rad_manager::RadManager,
 };
 
-use crate::signature_mngr;
+use crate::signature_mngr::{self, KeyRole};
 use rand::Rng;
 use witnet_data_structures::{
     chain::{
@@ -74,8 +74,8 @@ impl ChainManager {
             // FIXME (tmpolaczyk): block creation must happen after data request mining
             // (we must wait for all the potential nodes to sent their transactions)
             // The best way would be to start mining a few seconds _before_ the epoch
-            // checkpoint, but for simplicity we just wait for 5 seconds after the checkpoint
-            ctx.run_later(Duration::from_secs(5), move |act, ctx| {
+            // checkpoint, but for simplicity we just wait for `block_delay` after the checkpoint
+            ctx.run_later(self.block_delay, move |act, ctx| {
                 info!(
                     "{} Discovered eligibility for mining a block for epoch #{}",
                     Yellow.bold().paint("[Mining]"),
@@ -86,7 +86,7 @@ impl ChainManager {
 
                 act.create_tally_transactions()
                     .join(
-                        signature_mngr::sign(&beacon)
+                        signature_mngr::sign_with(KeyRole::Block.path(), &beacon)
                             .map_err(|e| error!("Couldn't sign beacon: {}", e)),
                     )
                     .into_actor(act)
@@ -119,6 +119,7 @@ impl ChainManager {
                                 act.handle(
                                     AddCandidates {
                                         blocks: vec![block],
+                                        sender: None,
                                     },
                                     ctx,
                                 );
@@ -182,7 +183,7 @@ impl ChainManager {
                     .and_then(move |reveal_value, act, _ctx| {
                         // Create commitment transaction
                         let commit_body = create_commit_body(&dr_output_pointer, &data_request_output, reveal_value.clone());
-                        signature_mngr::sign(&commit_body)
+                        signature_mngr::sign_with(KeyRole::Commit.path(), &commit_body)
                             .map_err(|e| log::error!("Couldn't sign commit body: {}", e))
                             .into_actor(act)
                             .and_then(move |sig, act, _ctx| {
@@ -193,7 +194,7 @@ impl ChainManager {
                                 };
                                 let reveal_body = create_reveal_body(commit_pointer,  &data_request_output, reveal_value);
 
-                                signature_mngr::sign(&reveal_body)
+                                signature_mngr::sign_with(KeyRole::Reveal.path(), &reveal_body)
                                     .map_err(|e| log::error!("Couldn't sign reveal body: {}", e))
                                     .into_actor(act)
                                     .and_then(move |sig, act, ctx| {
@@ -260,7 +261,7 @@ impl ChainManager {
                     let tally_body =
                         create_tally_body(&dr_output, inputs, outputs, consensus.clone());
 
-                    signature_mngr::sign(&tally_body)
+                    signature_mngr::sign_with(KeyRole::Tally.path(), &tally_body)
                         .map_err(|e| log::error!("Couldn't sign tally body: {}", e))
                         .and_then(move |sig| {
                             let tally_transaction = Transaction::new(tally_body, vec![sig]);
@@ -372,7 +373,7 @@ fn build_block(
     // Include Mint Transaction by miner
     // TODO: Include Witnet's node PKH (keyed signature is not needed as there is no input)
     let mut pkh = PublicKeyHash::default();
-    pkh[0] = rand::thread_rng().gen();
+    pkh.hash[0] = rand::thread_rng().gen();
 
     let epoch = beacon.checkpoint;
     let reward = block_reward(epoch) + transaction_fees;
@@ -25,7 +25,7 @@
 //! * Updating the UTXO set with valid transactions that have already been anchored into a valid block. This includes:
 //!     - Removing the UTXOs that the transaction spends as inputs.
 //!     - Adding a new UTXO for every output in the transaction.
-use std::collections::HashMap;
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
 
 use actix::prelude::*;
 use actix::{
@@ -35,36 +35,59 @@ use actix::{
 use ansi_term::Color::{Purple, White, Yellow};
 use failure::Fail;
 use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
 
 use crate::actors::{
     inventory_manager::InventoryManager,
     json_rpc::JsonRpcServer,
-    messages::{AddItem, AddTransaction, Broadcast, NewBlock, SendInventoryItem},
+    messages::{
+        AddItem, AddTransaction, Broadcast, DataRequestResolved, DataRequestStatus, NewBlock,
+        ReportMisbehaviour, RequestBlocks, SendGetHeaders, SendInventoryItem, SyncStatus, Unicast,
+    },
     sessions_manager::SessionsManager,
-    storage_keys::CHAIN_STATE_KEY,
+    storage_keys::{CHAIN_STATE_KEY, TRANSACTION_POINTER_PREFIX},
 };
 use crate::storage_mngr;
 use witnet_data_structures::{
     chain::{
-        Block, ChainState, CheckpointBeacon, DataRequestReport, Epoch, Hash, Hashable,
-        InventoryItem, Output, OutputPointer, TransactionsPool, UnspentOutputsPool,
+        Block, ChainState, CheckpointBeacon, DataRequestReport, DataRequestState, Epoch, Hash,
+        Hashable, InventoryItem, Output, OutputPointer, TallyOutput, TransactionPointer,
+        TransactionsPool, UnspentOutputsPool,
     },
     data_request::DataRequestPool,
+    error::BlockError,
     serializers::decoders::TryFrom,
 };
+use witnet_p2p::bans::Misbehaviour;
 use witnet_rad::types::RadonTypes;
+use witnet_util::timestamp::get_timestamp;
 
 use witnet_validations::validations::{validate_block, validate_candidate, Diff};
 
+use self::sync::HeadersSync;
+
 mod actor;
 mod handlers;
 mod mining;
+mod sync;
 
 /// Maximum blocks number to be sent during synchronization process
 pub const MAX_BLOCKS_SYNC: usize = 500;
 
+/// Maximum number of block headers to be sent in a single Headers message
+pub const MAX_HEADERS_SYNC: usize = 2000;
+
+/// Number of blocks requested to a single peer at once during the synchronization
+pub const BLOCKS_PER_RANGE: usize = 50;
+
+/// Number of times the headers can be discarded during a synchronization before giving up
+pub const MAX_HEADERS_RETRIES: usize = 3;
+
+/// Seconds to wait for a requested transaction before requesting it to another peer
+pub const TX_REQUEST_TIMEOUT: i64 = 10;
+
 /// Possible errors when interacting with ChainManager
-#[derive(Debug, PartialEq, Fail)]
+#[derive(Debug, PartialEq, Fail, Serialize)]
 pub enum ChainManagerError {
     /// A block being processed was already known to this node
     #[fail(display = "A block being processed was already known to this node")]
@@ -75,10 +98,22 @@ pub enum ChainManagerError {
     /// StorageError
     #[fail(display = "ChainManager is not ready yet")]
     ChainNotReady,
+    /// An output is not in the UTXO set
+    #[fail(display = "Output {} not found in the UTXO set", output_pointer)]
+    OutputNotFound { output_pointer: OutputPointer },
+    /// A transaction is neither in the mempool nor in a consolidated block
+    #[fail(display = "Transaction {} not found", hash)]
+    TransactionNotFound { hash: Hash },
+    /// A data request is neither active nor resolved
+    #[fail(display = "Data request {} not found", dr_pointer)]
+    DataRequestNotFound { dr_pointer: OutputPointer },
+    /// Error when reading the storage or communicating with other actors
+    #[fail(display = "Storage error: {}", reason)]
+    StorageError { reason: String },
 }
 
 /// State Machine
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum StateMachine {
     /// First state, ChainManager is waiting to consensus between its peers
     WaitingConsensus,
@@ -110,6 +145,8 @@ pub struct ChainManager {
     max_block_weight: u32,
     /// Mining enabled
     mining_enabled: bool,
+    /// Time to wait after each checkpoint before mining a block
+    block_delay: Duration,
     /// Hash of the genesis block
     genesis_block_hash: Hash,
     /// state of the state machine
@@ -118,6 +155,13 @@ pub struct ChainManager {
     target_beacon: Option<CheckpointBeacon>,
     /// Map that stores candidate blocks for further validation and consolidation as tip of the blockchain
     candidates: HashMap<Hash, Block>,
+    /// State of the headers-first synchronization, while in the Synchronizing state
+    sync: Option<HeadersSync>,
+    /// Seconds to wait for the blocks requested to a peer during the synchronization
+    blocks_timeout: i64,
+    /// Announced transactions which have been requested to a peer, along with the timestamp of
+    /// the request
+    requested_transactions: HashMap<Hash, i64>,
 }
 
 /// Required trait for being able to retrieve ChainManager address from registry
@@ -194,6 +238,37 @@ impl ChainManager {
             .wait(ctx);
     }
 
+    /// Method to persist the location of the transactions of a block into the storage, so
+    /// they can be retrieved by their hash
+    fn persist_transaction_pointers(&self, ctx: &mut Context<Self>, block: &Block) {
+        let block_hash = block.hash();
+        let epoch = block.block_header.beacon.checkpoint;
+
+        let futs: Vec<_> = block
+            .txns
+            .iter()
+            .enumerate()
+            .map(|(transaction_index, transaction)| {
+                let pointer = TransactionPointer {
+                    block_hash,
+                    epoch,
+                    transaction_index: transaction_index as u32,
+                };
+
+                storage_mngr::put(&transaction_pointer_key(transaction.hash()), &pointer)
+            })
+            .collect();
+
+        futures::future::join_all(futs)
+            .into_actor(self)
+            .map_err(|e, _, _| error!("Failed to persist transaction pointers into storage: {}", e))
+            .and_then(|_, _, _| {
+                debug!("Successfully persisted transaction pointers into storage");
+                fut::ok(())
+            })
+            .wait(ctx);
+    }
+
     fn broadcast_item(&self, item: InventoryItem) {
         // Get SessionsManager address
         let sessions_manager_addr = System::current().registry().get::<SessionsManager>();
@@ -204,6 +279,16 @@ impl ChainManager {
         });
     }
 
+    fn report_misbehaviour(&self, address: SocketAddr, misbehaviour: Misbehaviour) {
+        // Get SessionsManager address
+        let sessions_manager_addr = System::current().registry().get::<SessionsManager>();
+
+        sessions_manager_addr.do_send(ReportMisbehaviour {
+            address,
+            misbehaviour,
+        });
+    }
+
     fn process_requested_block(
         &mut self,
         ctx: &mut Context<Self>,
@@ -235,7 +320,7 @@ impl ChainManager {
         }
     }
 
-    fn process_candidate(&mut self, block: Block) {
+    fn process_candidate(&mut self, block: Block, sender: Option<SocketAddr>) {
         if let Some(current_epoch) = self.current_epoch {
             let hash_block = block.hash();
 
@@ -245,7 +330,12 @@ impl ChainManager {
                         self.candidates.insert(hash_block, block.clone());
                         self.broadcast_item(InventoryItem::Block(block));
                     }
-                    Err(e) => warn!("{}", e),
+                    Err(e) => {
+                        warn!("{}", e);
+                        if let (Some(sender), false) = (sender, is_benign_candidate_error(&e)) {
+                            self.report_misbehaviour(sender, Misbehaviour::InvalidCandidate);
+                        }
+                    }
                 }
             }
         } else {
@@ -261,6 +351,7 @@ impl ChainManager {
     ) {
         for block in blocks {
             let block_hash = block.hash();
+            self.persist_transaction_pointers(ctx, &block);
             self.persist_item(ctx, InventoryItem::Block(block));
 
             if block_hash == target_beacon.hash_prev_block {
@@ -299,7 +390,19 @@ impl ChainManager {
                     let to_be_stored = self.chain_state.data_request_pool.finished_data_requests();
                     to_be_stored.into_iter().for_each(|dr| {
                         self.persist_data_request(ctx, &dr);
-                        show_info_tally(&self.chain_state.unspent_outputs_pool, dr, block_epoch);
+
+                        let (dr_pointer, report) = dr;
+                        let tally_output =
+                            match self.chain_state.unspent_outputs_pool.get(&report.tally) {
+                                Some(Output::Tally(tally_output)) => Some(tally_output),
+                                _ => None,
+                            };
+                        let status = resolved_data_request_status(report, tally_output);
+                        show_info_tally(&dr_pointer, &status, block_epoch);
+
+                        // Send notification to JsonRpcServer
+                        JsonRpcServer::from_registry()
+                            .do_send(DataRequestResolved { dr_pointer, status });
                     });
 
                     show_info_dr(&self.chain_state.data_request_pool, &block);
@@ -319,6 +422,7 @@ impl ChainManager {
                             transaction: reveal,
                         })
                     }
+                    self.persist_transaction_pointers(ctx, block);
                     self.persist_item(ctx, InventoryItem::Block(block.clone()));
 
                     // Persist chain_info into storage
@@ -336,6 +440,215 @@ impl ChainManager {
         }
     }
 
+    /// Move the state machine to a new state, notifying the change to the JsonRpcServer
+    fn update_state_machine(&mut self, next_state: StateMachine) {
+        if self.sm_state != next_state {
+            debug!(
+                "StateMachine transition from {:?} to {:?}",
+                self.sm_state, next_state
+            );
+
+            JsonRpcServer::from_registry().do_send(SyncStatus {
+                previous: self.sm_state,
+                current: next_state,
+            });
+
+            self.sm_state = next_state;
+        }
+    }
+
+    /// Start a headers-first synchronization towards `target`, downloading the blocks from
+    /// `peers`
+    fn start_sync(
+        &mut self,
+        ctx: &mut Context<Self>,
+        target: CheckpointBeacon,
+        peers: Vec<SocketAddr>,
+    ) {
+        self.sync = Some(HeadersSync::new(target, self.get_chain_beacon(), peers));
+        self.request_headers(ctx);
+    }
+
+    /// Stop the synchronization and restore the chain state from storage
+    fn abort_sync(&mut self, ctx: &mut Context<Self>) {
+        self.sync = None;
+        self.initialize_from_storage(ctx);
+        info!("Restored chain state from storage");
+        self.update_state_machine(StateMachine::WaitingConsensus);
+    }
+
+    /// Request the headers which follow the last validated header to one of the peers whose
+    /// last beacon matches our target beacon
+    fn request_headers(&mut self, ctx: &mut Context<Self>) {
+        let (beacon, peer) = match self.sync.as_mut() {
+            Some(sync) => (sync.headers_tip, sync.select_headers_peer()),
+            None => return,
+        };
+        let peer = match peer {
+            Some(peer) => peer,
+            None => {
+                warn!("There are no peers left to download the headers from");
+                self.abort_sync(ctx);
+
+                return;
+            }
+        };
+
+        SessionsManager::from_registry().do_send(Unicast {
+            address: peer,
+            command: SendGetHeaders { beacon },
+        });
+
+        // Request the headers to another peer if they are not received in time
+        ctx.run_later(self.blocks_timeout_duration(), move |act, ctx| {
+            let stalled = act
+                .sync
+                .as_ref()
+                .map(|sync| sync.headers_tip == beacon && sync.headers_peer() == Some(peer))
+                .unwrap_or(false);
+            if stalled {
+                warn!("Timeout for waiting headers from {} achieved", peer);
+                act.retry_headers(ctx);
+            }
+        });
+    }
+
+    /// Discard the headers received so far and request them to another peer, giving up after
+    /// `MAX_HEADERS_RETRIES` attempts
+    fn retry_headers(&mut self, ctx: &mut Context<Self>) {
+        let failures = match self.sync.as_mut() {
+            Some(sync) => sync.reset_headers(),
+            None => return,
+        };
+
+        if failures > MAX_HEADERS_RETRIES {
+            warn!("Could not get valid headers after {} attempts", failures);
+            self.abort_sync(ctx);
+        } else {
+            self.request_headers(ctx);
+        }
+    }
+
+    /// Assign the pending block ranges to the idle peers and request them
+    fn request_blocks(&mut self, ctx: &mut Context<Self>) {
+        let requests = match self.sync.as_mut() {
+            Some(sync) => sync.assign(get_timestamp()),
+            None => return,
+        };
+
+        if requests.is_empty() {
+            return;
+        }
+
+        let sessions_manager_addr = SessionsManager::from_registry();
+        for (address, hashes) in requests {
+            debug!("Requesting {} blocks to {}", hashes.len(), address);
+            sessions_manager_addr.do_send(Unicast {
+                address,
+                command: RequestBlocks { hashes },
+            });
+        }
+
+        // Reassign the ranges which are not received in time
+        ctx.run_later(self.blocks_timeout_duration(), |act, ctx| {
+            act.check_stalled_ranges(ctx)
+        });
+    }
+
+    /// Release the ranges which have not been received in time and assign them to other peers
+    fn check_stalled_ranges(&mut self, ctx: &mut Context<Self>) {
+        let now = get_timestamp();
+        let blocks_timeout = self.blocks_timeout;
+        let no_peers_left = match self.sync.as_mut() {
+            Some(sync) => {
+                for peer in sync.expire(now, blocks_timeout) {
+                    warn!("Timeout for waiting blocks from {} achieved", peer);
+                }
+
+                !sync.has_peers()
+            }
+            None => return,
+        };
+
+        if no_peers_left {
+            warn!("There are no peers left to download the blocks from");
+            self.abort_sync(ctx);
+        } else {
+            self.request_blocks(ctx);
+        }
+    }
+
+    /// Consolidate, in order, the block ranges which have already been received
+    fn process_downloaded_blocks(&mut self, ctx: &mut Context<Self>) {
+        while let Some((sender, blocks)) = self.sync.as_mut().and_then(HeadersSync::next_ready) {
+            for block in blocks.iter() {
+                if let Err(e) = self.process_requested_block(ctx, block) {
+                    error!("Error processing block: {}", e);
+                    // Errors of the ChainManager are not caused by the block
+                    if e.downcast_ref::<ChainManagerError>().is_none() {
+                        self.report_misbehaviour(sender, Misbehaviour::InvalidBlock);
+                    }
+                    self.abort_sync(ctx);
+
+                    return;
+                }
+            }
+
+            let target_beacon = self.get_chain_beacon();
+            self.persist_blocks_batch(ctx, blocks, target_beacon);
+            let to_be_stored = self.chain_state.data_request_pool.finished_data_requests();
+            to_be_stored.into_iter().for_each(|dr| {
+                self.persist_data_request(ctx, &dr);
+            });
+            self.persist_chain_state(ctx);
+        }
+
+        let finished = self
+            .sync
+            .as_ref()
+            .map(HeadersSync::is_finished)
+            .unwrap_or(false);
+        if finished {
+            // Target achieved, go back to state 1
+            self.sync = None;
+            self.update_state_machine(StateMachine::WaitingConsensus);
+        } else {
+            self.request_blocks(ctx);
+        }
+    }
+
+    /// Select the announced transactions which should be requested to the peer: the ones which
+    /// are not in the transactions pool and have not been requested to another peer in the last
+    /// `TX_REQUEST_TIMEOUT` seconds
+    fn select_transactions_to_request(&mut self, hashes: Vec<Hash>, now: i64) -> Vec<Hash> {
+        let mut selected = vec![];
+        for hash in hashes {
+            if self.transactions_pool.contains(&hash) {
+                continue;
+            }
+
+            match self.requested_transactions.get(&hash) {
+                Some(timestamp) if now < timestamp + TX_REQUEST_TIMEOUT => {}
+                _ => {
+                    self.requested_transactions.insert(hash, now);
+                    selected.push(hash);
+                }
+            }
+        }
+
+        selected
+    }
+
+    /// Forget the requests of the transactions which have not been received in time
+    fn forget_expired_transaction_requests(&mut self, now: i64) {
+        self.requested_transactions
+            .retain(|_, timestamp| now < *timestamp + TX_REQUEST_TIMEOUT);
+    }
+
+    fn blocks_timeout_duration(&self) -> Duration {
+        Duration::from_secs(self.blocks_timeout as u64)
+    }
+
     fn get_chain_beacon(&self) -> CheckpointBeacon {
         self.chain_state
             .chain_info
@@ -346,6 +659,26 @@ impl ChainManager {
 }
 
 // Helper methods
+/// Whether a candidate may have been rejected without any fault of the peer which sent it.
+///
+/// Candidates from other epochs are sent by honest peers when the epoch changes, and the
+/// eligibility of a candidate depends on the chain state, so a peer on a fork may send
+/// candidates which are valid in its chain. Errors which are not block errors are internal
+/// errors of this node.
+fn is_benign_candidate_error(e: &failure::Error) -> bool {
+    match e.downcast_ref::<BlockError>() {
+        Some(BlockError::CandidateFromDifferentEpoch { .. })
+        | Some(BlockError::NotValidPoe)
+        | None => true,
+        Some(_) => false,
+    }
+}
+
+/// Storage key of the pointer to the block which includes a transaction
+fn transaction_pointer_key(hash: Hash) -> (&'static [u8], Hash) {
+    (TRANSACTION_POINTER_PREFIX, hash)
+}
+
 fn update_pools(
     block: &Block,
     unspent_outputs_pool: &mut UnspentOutputsPool,
@@ -365,23 +698,57 @@ fn update_pools(
     utxo_diff.apply(unspent_outputs_pool);
 }
 
-fn show_info_tally(
-    unspent_outputs_pool: &UnspentOutputsPool,
-    dr: (OutputPointer, DataRequestReport),
-    block_epoch: Epoch,
-) {
-    let tally_output_pointer = dr.1.tally;
-    let tr = unspent_outputs_pool.get(&tally_output_pointer);
-    if let Some(Output::Tally(tally_output)) = tr {
-        let result = RadonTypes::try_from(tally_output.result.as_slice())
-            .map(|x| x.to_string())
-            .unwrap_or_else(|_| "RADError".to_string());
+/// Decode the result of a tally output
+fn tally_result(tally_output: &TallyOutput) -> String {
+    RadonTypes::try_from(tally_output.result.as_slice())
+        .map(|x| x.to_string())
+        .unwrap_or_else(|_| "RADError".to_string())
+}
+
+/// Sort a list of output pointers, which come from a `HashSet` and have no order
+fn sorted_output_pointers<'a, I>(output_pointers: I) -> Vec<OutputPointer>
+where
+    I: IntoIterator<Item = &'a OutputPointer>,
+{
+    let mut output_pointers: Vec<_> = output_pointers.into_iter().cloned().collect();
+    output_pointers.sort_by_key(ToString::to_string);
+
+    output_pointers
+}
+
+/// Progress of a data request which has not been resolved yet
+fn active_data_request_status(dr_state: &DataRequestState) -> DataRequestStatus {
+    DataRequestStatus {
+        stage: Some(dr_state.stage),
+        commits: sorted_output_pointers(&dr_state.info.commits),
+        reveals: sorted_output_pointers(&dr_state.info.reveals),
+        tally: None,
+        result: None,
+    }
+}
+
+/// Progress of a resolved data request, decoding the result from its tally output
+fn resolved_data_request_status(
+    report: DataRequestReport,
+    tally_output: Option<&TallyOutput>,
+) -> DataRequestStatus {
+    DataRequestStatus {
+        stage: None,
+        commits: sorted_output_pointers(&report.commits),
+        reveals: sorted_output_pointers(&report.reveals),
+        tally: Some(report.tally),
+        result: tally_output.map(tally_result),
+    }
+}
+
+fn show_info_tally(dr_pointer: &OutputPointer, status: &DataRequestStatus, block_epoch: Epoch) {
+    if let Some(result) = &status.result {
         info!(
             "{} {} completed at epoch #{} with result: {}",
             Yellow.bold().paint("[Data Request]"),
-            Yellow.bold().paint(&dr.0.to_string()),
+            Yellow.bold().paint(dr_pointer.to_string()),
             Yellow.bold().paint(block_epoch.to_string()),
-            Yellow.bold().paint(result),
+            Yellow.bold().paint(result.as_str()),
         );
     }
 }
@@ -0,0 +1,384 @@
+//! Headers-first synchronization
+//!
+//! The headers of the blocks between the beacon of our chain and the target beacon are
+//! downloaded from a single peer and validated first. If that peer sends headers which are not
+//! valid or do not lead to the target beacon, the headers are discarded and requested to another
+//! peer. Once all of them are known, the blocks are split in ranges of
+//! `BLOCKS_PER_RANGE` blocks, which are requested in parallel to the peers that agreed on the
+//! target beacon. The ranges are consolidated strictly in order, and the ranges which are not
+//! received in time are assigned to a different peer.
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    net::SocketAddr,
+};
+
+use witnet_data_structures::chain::{Block, CheckpointBeacon, Epoch, Hash, Hashable};
+
+use super::BLOCKS_PER_RANGE;
+
+/// State of a headers-first synchronization
+#[derive(Debug)]
+pub struct HeadersSync {
+    /// Beacon which the node is trying to reach
+    pub target: CheckpointBeacon,
+    /// Beacon of our chain when the synchronization started
+    chain_beacon: CheckpointBeacon,
+    /// Beacon of the last validated header
+    pub headers_tip: CheckpointBeacon,
+    /// Peer which the headers are being requested to
+    headers_peer: Option<SocketAddr>,
+    /// Number of times the headers have been discarded
+    headers_failures: usize,
+    /// Hashes of the validated headers, in chain order
+    headers: Vec<Hash>,
+    /// Peers which can be asked for blocks
+    peers: Vec<SocketAddr>,
+    /// Index of the first header of the ranges which are not assigned to any peer
+    unassigned: BTreeSet<usize>,
+    /// Range assigned to each peer, along with the timestamp of the request
+    assigned: HashMap<SocketAddr, (usize, i64)>,
+    /// Ranges which have been received but not consolidated yet, along with the peer which sent
+    /// them
+    downloaded: BTreeMap<usize, (SocketAddr, Vec<Block>)>,
+    /// Index of the first header of the next range to be consolidated
+    next_range: usize,
+}
+
+impl HeadersSync {
+    /// Start a synchronization from `chain_beacon` to `target`, downloading the blocks from
+    /// `peers`
+    pub fn new(
+        target: CheckpointBeacon,
+        chain_beacon: CheckpointBeacon,
+        peers: Vec<SocketAddr>,
+    ) -> Self {
+        Self {
+            target,
+            chain_beacon,
+            headers_tip: chain_beacon,
+            headers_peer: None,
+            headers_failures: 0,
+            headers: vec![],
+            peers,
+            unassigned: BTreeSet::new(),
+            assigned: HashMap::new(),
+            downloaded: BTreeMap::new(),
+            next_range: 0,
+        }
+    }
+
+    /// Append a header which has already been validated against `headers_tip`
+    ///
+    /// Returns false if the header does not lead to the target beacon, in which case it is
+    /// not appended.
+    pub fn add_header(&mut self, hash: Hash, checkpoint: Epoch) -> bool {
+        if self.headers_complete()
+            || checkpoint > self.target.checkpoint
+            || (checkpoint == self.target.checkpoint && hash != self.target.hash_prev_block)
+        {
+            return false;
+        }
+
+        self.headers.push(hash);
+        self.headers_tip = CheckpointBeacon {
+            checkpoint,
+            hash_prev_block: hash,
+        };
+
+        if self.headers_complete() {
+            self.unassigned = (0..self.headers.len()).step_by(BLOCKS_PER_RANGE).collect();
+        }
+
+        true
+    }
+
+    /// Select the peer to request the headers to, which is kept until it fails
+    pub fn select_headers_peer(&mut self) -> Option<SocketAddr> {
+        self.headers_peer = self.peers.first().cloned();
+
+        self.headers_peer
+    }
+
+    /// Peer which the headers are being requested to
+    pub fn headers_peer(&self) -> Option<SocketAddr> {
+        self.headers_peer
+    }
+
+    /// Discard the headers received so far and stop using the peer which sent them, because
+    /// they were not valid, did not lead to the target beacon or were not received in time
+    ///
+    /// Returns the number of times the headers have been discarded.
+    pub fn reset_headers(&mut self) -> usize {
+        if let Some(peer) = self.headers_peer.take() {
+            self.remove_peer(peer);
+        }
+        self.headers.clear();
+        self.headers_tip = self.chain_beacon;
+        self.unassigned.clear();
+        self.headers_failures += 1;
+
+        self.headers_failures
+    }
+
+    /// Whether all the headers up to the target beacon have been received
+    pub fn headers_complete(&self) -> bool {
+        self.headers_tip == self.target
+    }
+
+    /// Whether all the blocks have been received and consolidated
+    pub fn is_finished(&self) -> bool {
+        self.headers_complete() && self.next_range == self.headers.len()
+    }
+
+    /// Whether there are peers left to download the blocks from
+    pub fn has_peers(&self) -> bool {
+        !self.peers.is_empty()
+    }
+
+    /// Assign the pending ranges to the idle peers
+    ///
+    /// Returns the hashes of the blocks to be requested to each peer.
+    pub fn assign(&mut self, now: i64) -> Vec<(SocketAddr, Vec<Hash>)> {
+        let mut requests = vec![];
+
+        if !self.headers_complete() {
+            return requests;
+        }
+
+        for peer in self.peers.iter() {
+            if self.assigned.contains_key(peer) {
+                continue;
+            }
+
+            let start = match self.unassigned.iter().next() {
+                Some(start) => *start,
+                None => break,
+            };
+            self.unassigned.remove(&start);
+            self.assigned.insert(*peer, (start, now));
+            requests.push((*peer, self.range_hashes(start).to_vec()));
+        }
+
+        requests
+    }
+
+    /// Store the blocks received from `sender`
+    ///
+    /// Returns false if the blocks are not the range assigned to that peer, in which case they
+    /// are ignored.
+    pub fn add_blocks(&mut self, sender: SocketAddr, blocks: Vec<Block>) -> bool {
+        let start = match self.assigned.get(&sender) {
+            Some((start, _)) => *start,
+            None => return false,
+        };
+
+        let hashes = self.range_hashes(start);
+        if blocks.len() != hashes.len()
+            || blocks
+                .iter()
+                .zip(hashes)
+                .any(|(block, hash)| block.hash() != *hash)
+        {
+            return false;
+        }
+
+        self.assigned.remove(&sender);
+        self.downloaded.insert(start, (sender, blocks));
+
+        true
+    }
+
+    /// Take the next range to be consolidated, if it has already been received
+    pub fn next_ready(&mut self) -> Option<(SocketAddr, Vec<Block>)> {
+        let ready = self.downloaded.remove(&self.next_range);
+        if let Some((_, blocks)) = &ready {
+            self.next_range += blocks.len();
+        }
+
+        ready
+    }
+
+    /// Release the ranges which have been assigned for at least `timeout` seconds
+    ///
+    /// The peers which did not send their range in time are not asked for blocks again, and are
+    /// returned.
+    pub fn expire(&mut self, now: i64, timeout: i64) -> Vec<SocketAddr> {
+        let stalled: Vec<SocketAddr> = self
+            .assigned
+            .iter()
+            .filter(|(_, (_, requested_at))| now - requested_at >= timeout)
+            .map(|(peer, _)| *peer)
+            .collect();
+
+        for peer in stalled.iter() {
+            self.remove_peer(*peer);
+        }
+
+        stalled
+    }
+
+    /// Keep only the peers which are still connected, releasing the ranges assigned to the
+    /// other ones
+    pub fn retain_peers(&mut self, connected: &[SocketAddr]) {
+        let disconnected: Vec<SocketAddr> = self
+            .peers
+            .iter()
+            .filter(|peer| !connected.contains(peer))
+            .cloned()
+            .collect();
+
+        for peer in disconnected {
+            self.remove_peer(peer);
+        }
+    }
+
+    /// Stop using a peer, releasing the range assigned to it
+    fn remove_peer(&mut self, peer: SocketAddr) {
+        self.peers.retain(|p| *p != peer);
+        if let Some((start, _)) = self.assigned.remove(&peer) {
+            self.unassigned.insert(start);
+        }
+    }
+
+    /// Hashes of the blocks of the range starting at `start`
+    fn range_hashes(&self, start: usize) -> &[Hash] {
+        let end = std::cmp::min(start + BLOCKS_PER_RANGE, self.headers.len());
+
+        &self.headers[start..end]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use witnet_data_structures::chain::{BlockHeader, LeadershipProof};
+
+    fn peer(port: u16) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port)
+    }
+
+    fn block(checkpoint: Epoch) -> Block {
+        let mut block_header = BlockHeader::default();
+        block_header.beacon.checkpoint = checkpoint;
+
+        Block {
+            block_header,
+            proof: LeadershipProof::default(),
+            txns: vec![],
+        }
+    }
+
+    /// Synchronization whose headers are the blocks of epochs 1 to `n`
+    fn sync_with_headers(n: Epoch, peers: Vec<SocketAddr>) -> (HeadersSync, Vec<Block>) {
+        let blocks: Vec<Block> = (1..=n).map(block).collect();
+        let target = CheckpointBeacon {
+            checkpoint: n,
+            hash_prev_block: blocks.last().unwrap().hash(),
+        };
+        let mut sync = HeadersSync::new(target, CheckpointBeacon::default(), peers);
+        for block in blocks.iter() {
+            assert!(sync.add_header(block.hash(), block.block_header.beacon.checkpoint));
+        }
+
+        (sync, blocks)
+    }
+
+    #[test]
+    fn headers_must_lead_to_target() {
+        let target = CheckpointBeacon {
+            checkpoint: 2,
+            hash_prev_block: block(2).hash(),
+        };
+        let mut sync = HeadersSync::new(target, CheckpointBeacon::default(), vec![peer(1)]);
+
+        // A header beyond the target is rejected
+        assert!(!sync.add_header(block(3).hash(), 3));
+        // A different header at the epoch of the target is rejected
+        assert!(!sync.add_header(block(1).hash(), 2));
+
+        assert!(sync.add_header(block(1).hash(), 1));
+        assert!(!sync.headers_complete());
+        assert!(sync.add_header(block(2).hash(), 2));
+        assert!(sync.headers_complete());
+
+        // No more headers are accepted after the target
+        assert!(!sync.add_header(block(3).hash(), 3));
+        assert_eq!(sync.headers_tip, target);
+    }
+
+    #[test]
+    fn headers_are_discarded_with_their_peer() {
+        let target = CheckpointBeacon {
+            checkpoint: 2,
+            hash_prev_block: block(2).hash(),
+        };
+        let chain_beacon = CheckpointBeacon::default();
+        let mut sync = HeadersSync::new(target, chain_beacon, vec![peer(1), peer(2)]);
+
+        assert_eq!(sync.select_headers_peer(), Some(peer(1)));
+        assert!(sync.add_header(block(1).hash(), 1));
+
+        // The headers are discarded and the peer which sent them is no longer used
+        assert_eq!(sync.reset_headers(), 1);
+        assert_eq!(sync.headers_tip, chain_beacon);
+        assert_eq!(sync.headers_peer(), None);
+        assert_eq!(sync.select_headers_peer(), Some(peer(2)));
+
+        assert_eq!(sync.reset_headers(), 2);
+        assert_eq!(sync.select_headers_peer(), None);
+        assert!(!sync.has_peers());
+    }
+
+    #[test]
+    fn ranges_are_downloaded_in_parallel_and_consolidated_in_order() {
+        let n = BLOCKS_PER_RANGE as Epoch + 1;
+        let (mut sync, blocks) = sync_with_headers(n, vec![peer(1), peer(2), peer(3)]);
+
+        // There are only two ranges, so the third peer stays idle
+        let requests = sync.assign(0);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].0, peer(1));
+        assert_eq!(requests[0].1.len(), BLOCKS_PER_RANGE);
+        assert_eq!(requests[1].0, peer(2));
+        assert_eq!(requests[1].1, vec![blocks[BLOCKS_PER_RANGE].hash()]);
+
+        // Blocks which were not requested to the sender are ignored
+        assert!(!sync.add_blocks(peer(3), blocks[BLOCKS_PER_RANGE..].to_vec()));
+        assert!(!sync.add_blocks(peer(2), blocks[..1].to_vec()));
+
+        // The second range cannot be consolidated before the first one
+        assert!(sync.add_blocks(peer(2), blocks[BLOCKS_PER_RANGE..].to_vec()));
+        assert!(sync.next_ready().is_none());
+
+        assert!(sync.add_blocks(peer(1), blocks[..BLOCKS_PER_RANGE].to_vec()));
+        assert_eq!(sync.next_ready().unwrap().0, peer(1));
+        assert!(!sync.is_finished());
+        assert_eq!(sync.next_ready().unwrap().0, peer(2));
+        assert!(sync.is_finished());
+    }
+
+    #[test]
+    fn stalled_ranges_are_reassigned() {
+        let (mut sync, _blocks) = sync_with_headers(1, vec![peer(1), peer(2)]);
+
+        let requests = sync.assign(100);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].0, peer(1));
+
+        // The range is not expired before the timeout
+        assert!(sync.expire(109, 10).is_empty());
+        assert!(sync.assign(109).is_empty());
+
+        // Once expired, the range is assigned to the other peer
+        assert_eq!(sync.expire(110, 10), vec![peer(1)]);
+        let requests = sync.assign(110);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].0, peer(2));
+
+        // Disconnected peers release their range too
+        sync.retain_peers(&[peer(1)]);
+        assert!(!sync.has_peers());
+        assert!(sync.assign(110).is_empty());
+    }
+}
@@ -29,6 +29,8 @@ pub type BytesMut = bytes::BytesMut;
 /// Message: [u8; Message size]
 /// ```
 ///
+/// The codec does not encrypt the messages: encrypted sessions encrypt them before framing them.
+///
 /// The message format is described in the file [schemas/protocol.fbs][protocol]
 ///
 /// [protocol]: https://github.com/witnet/witnet-rust/blob/master/schemas/protocol.fbs
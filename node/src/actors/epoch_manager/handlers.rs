@@ -5,7 +5,7 @@ use log::{debug, error};
 use witnet_data_structures::chain::Epoch;
 
 use super::EpochManager;
-use crate::actors::messages::{EpochResult, GetEpoch, SubscribeAll, SubscribeEpoch};
+use crate::actors::messages::{EpochResult, GetEpoch, SetTimestamp, SubscribeAll, SubscribeEpoch};
 
 ////////////////////////////////////////////////////////////////////////////////////////
 // ACTOR MESSAGE HANDLERS
@@ -56,3 +56,25 @@ impl Handler<SubscribeAll> for EpochManager {
         self.subscriptions_all.push(msg.notification);
     }
 }
+
+impl Handler<SetTimestamp> for EpochManager {
+    type Result = ();
+
+    /// Method to handle SetTimestamp messages
+    fn handle(&mut self, msg: SetTimestamp, _ctx: &mut Self::Context) {
+        debug!("Clock set to timestamp {}", msg.timestamp);
+        self.set_timestamp(msg.timestamp);
+
+        // Notify the subscribers if the new timestamp is in a new epoch
+        match self.current_epoch() {
+            Ok(epoch) if self.last_checked_epoch.map_or(true, |last| epoch > last) => {
+                self.process_checkpoint(epoch)
+            }
+            Ok(_) => {}
+            Err(error) => error!(
+                "Failed to retrieve epoch after setting the clock. Error was: {:?}",
+                error
+            ),
+        }
+    }
+}
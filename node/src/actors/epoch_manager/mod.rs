@@ -5,10 +5,10 @@ use ansi_term::Color::Purple;
 
 use log::{debug, error, info, warn};
 
-use std::{collections::BTreeMap, time::Duration};
+use std::{collections::BTreeMap, fmt, time::Duration};
 
 use witnet_data_structures::chain::Epoch;
-use witnet_util::timestamp::{get_timestamp, get_timestamp_nanos};
+use witnet_util::timestamp::get_timestamp_nanos;
 
 use crate::actors::messages::{EpochNotification, EpochResult};
 use crate::config_mngr;
@@ -32,6 +32,12 @@ pub enum EpochManagerError {
     Overflow,
 }
 
+impl fmt::Display for EpochManagerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EpochManagerError::{:?}", self)
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////
 // ACTOR BASIC STRUCTURE
 ////////////////////////////////////////////////////////////////////////////////////////
@@ -52,6 +58,9 @@ pub struct EpochManager {
 
     /// Last epoch that was checked by the epoch monitor process
     last_checked_epoch: Option<Epoch>,
+
+    /// Timestamp set with a `SetTimestamp` message, which replaces the system clock
+    manual_timestamp: Option<i64>,
 }
 
 /// Required trait for being able to retrieve EpochManager address from system registry
@@ -90,9 +99,21 @@ impl EpochManager {
             (_, None) => Err(EpochManagerError::UnknownCheckpointPeriod),
         }
     }
+    /// Current timestamp, in seconds and nanoseconds, taken from the system clock unless it has
+    /// been replaced with `set_timestamp`
+    fn now(&self) -> (i64, u32) {
+        match self.manual_timestamp {
+            Some(timestamp) => (timestamp, 0),
+            None => get_timestamp_nanos(),
+        }
+    }
+    /// Replace the system clock with the given timestamp
+    pub fn set_timestamp(&mut self, timestamp: i64) {
+        self.manual_timestamp = Some(timestamp);
+    }
     /// Calculate the last checkpoint (current epoch)
     pub fn current_epoch(&self) -> EpochResult<Epoch> {
-        let now = get_timestamp();
+        let (now, _) = self.now();
         self.epoch_at(now)
     }
     /// Calculate the timestamp for a checkpoint (the start of an epoch)
@@ -135,7 +156,7 @@ impl EpochManager {
     /// Method to compute time remaining to next checkpoint
     fn time_to_next_checkpoint(&self) -> EpochResult<Duration> {
         // Get current timestamp and epoch
-        let (now_secs, now_nanos) = get_timestamp_nanos();
+        let (now_secs, now_nanos) = self.now();
         let current_epoch = self.epoch_at(now_secs)?;
 
         // Get timestamp for the start of next checkpoint
@@ -164,57 +185,67 @@ impl EpochManager {
                 Duration::from_secs(u64::from(self.checkpoints_period.unwrap()))
             }),
             move |act, ctx| {
+                // Epochs are advanced by `SetTimestamp` messages when the system clock has been
+                // replaced
+                if act.manual_timestamp.is_some() {
+                    return;
+                }
+
                 // Get current epoch
                 let current_epoch = match act.current_epoch() {
                     Ok(epoch) => epoch,
                     Err(_) => return,
                 };
 
-                // Send message to actors which subscribed to all epochs
-                for subscription in &mut act.subscriptions_all {
-                    subscription.send_notification(current_epoch);
-                }
-
-                // Get all the checkpoints that had some subscription but were skipped for some
-                // reason (process sent to background, checkpoint monitor process had no
-                // resources to execute in time...)
-                let epoch_checkpoints: Vec<_> = act
-                    .subscriptions_epoch
-                    .range(act.last_checked_epoch.unwrap_or(0)..=current_epoch)
-                    .map(|(k, _v)| *k)
-                    .collect();
-
-                // Send notifications for skipped checkpoints for subscriptions to a particular
-                // epoch
-                // Notifications for skipped checkpoints are not sent for subscriptions to all
-                // epochs
-                for checkpoint in epoch_checkpoints {
-                    // Get the subscriptions to the skipped checkpoint
-                    if let Some(subscriptions) = act.subscriptions_epoch.remove(&checkpoint) {
-                        // Send notifications to subscribers for skipped checkpoints
-                        for mut subscription in subscriptions {
-                            // TODO: should send messages or just drop?
-                            // TODO: send notifications also for subscriptions to all epochs?
-                            subscription.send_notification(checkpoint);
-                        }
-                    }
-                }
-
-                // Update last checked epoch
-                act.last_checked_epoch = Some(current_epoch);
-
-                debug!("Updated epoch in ChainManager state to #{}", current_epoch);
-                info!(
-                    "{} We are now in epoch #{}",
-                    Purple.bold().paint("[Checkpoints]"),
-                    Purple.bold().paint(current_epoch.to_string())
-                );
+                act.process_checkpoint(current_epoch);
 
                 // Reschedule checkpoint monitor process
                 act.checkpoint_monitor(ctx);
             },
         );
     }
+    /// Method to notify the subscribers of the start of an epoch
+    fn process_checkpoint(&mut self, current_epoch: Epoch) {
+        // Send message to actors which subscribed to all epochs
+        for subscription in &mut self.subscriptions_all {
+            subscription.send_notification(current_epoch);
+        }
+
+        // Get all the checkpoints that had some subscription but were skipped for some
+        // reason (process sent to background, checkpoint monitor process had no
+        // resources to execute in time...)
+        let epoch_checkpoints: Vec<_> = self
+            .subscriptions_epoch
+            .range(self.last_checked_epoch.unwrap_or(0)..=current_epoch)
+            .map(|(k, _v)| *k)
+            .collect();
+
+        // Send notifications for skipped checkpoints for subscriptions to a particular
+        // epoch
+        // Notifications for skipped checkpoints are not sent for subscriptions to all
+        // epochs
+        for checkpoint in epoch_checkpoints {
+            // Get the subscriptions to the skipped checkpoint
+            if let Some(subscriptions) = self.subscriptions_epoch.remove(&checkpoint) {
+                // Send notifications to subscribers for skipped checkpoints
+                for mut subscription in subscriptions {
+                    // TODO: should send messages or just drop?
+                    // TODO: send notifications also for subscriptions to all epochs?
+                    subscription.send_notification(checkpoint);
+                }
+            }
+        }
+
+        // Update last checked epoch
+        self.last_checked_epoch = Some(current_epoch);
+
+        debug!("Updated epoch in ChainManager state to #{}", current_epoch);
+        info!(
+            "{} We are now in epoch #{}",
+            Purple.bold().paint("[Checkpoints]"),
+            Purple.bold().paint(current_epoch.to_string())
+        );
+    }
 }
 
 /// Trait that must follow all notifications that will be sent back to subscriber actors
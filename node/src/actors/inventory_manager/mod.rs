@@ -2,7 +2,8 @@
 //! InventoryManager is the actor in charge of managing the entire life cycle of all inventory items (i.e. transactions and blocks).
 //! It acts as a single entry point for getting and putting inventory items from and into StorageManager. This creates one more degree of abstraction between how storage works and the node business logic of the app.mod actor;
 
-use std::fmt;
+use failure::Fail;
+use serde::Serialize;
 
 mod actor;
 mod handlers;
@@ -12,18 +13,15 @@ mod handlers;
 pub struct InventoryManager;
 
 /// Possible errors when interacting with InventoryManager
-#[derive(Debug)]
+#[derive(Debug, Fail, Serialize)]
 pub enum InventoryManagerError {
     /// An item being processed was already known to this node
+    #[fail(display = "An item being processed was already known to this node")]
     ItemAlreadyExists,
     /// An item does not exist
+    #[fail(display = "The item does not exist")]
     ItemDoesNotExist,
     /// MailBoxError
+    #[fail(display = "Failed to access the storage")]
     MailBoxError,
 }
-
-impl fmt::Display for InventoryManagerError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "InventoryManagerError::{:?}", self)
-    }
-}
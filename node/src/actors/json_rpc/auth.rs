@@ -0,0 +1,201 @@
+//! Authentication and access control of the JSON-RPC server
+//!
+//! When authentication is enabled, the clients must prove that they know a secret token before
+//! calling any method. The token is either set in the configuration, or randomly generated by the
+//! node at start and written to a cookie file, which the local clients can read.
+//!
+//! Over TCP the token is sent using the `authenticate` method, and over HTTP and WebSockets it is
+//! sent in the `Authorization: Bearer <token>` header.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use rand::Rng;
+use witnet_config::config::JsonRPC as JsonRpcConfig;
+use witnet_util::parser::to_hex;
+
+use super::errors;
+
+pub use witnet_json_rpc_client::{client_token, AUTHENTICATE_METHOD};
+
+/// Groups of JSON-RPC methods which can be enabled separately in the configuration
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MethodGroup {
+    /// Methods which read the state of the node, and subscriptions
+    ReadOnly,
+    /// Methods which submit new blocks and transactions
+    Submit,
+    /// Methods which manage the node, such as importing or exporting its master key
+    Admin,
+}
+
+/// Method groups enabled in a JSON-RPC server
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EnabledMethods {
+    /// Enable the `ReadOnly` group
+    pub read_only: bool,
+    /// Enable the `Submit` group
+    pub submit: bool,
+    /// Enable the `Admin` group
+    pub admin: bool,
+}
+
+impl EnabledMethods {
+    /// Enable all the method groups
+    pub fn all() -> Self {
+        Self {
+            read_only: true,
+            submit: true,
+            admin: true,
+        }
+    }
+
+    /// Read the enabled method groups from the configuration
+    pub fn from_config(config: &JsonRpcConfig) -> Self {
+        Self {
+            read_only: config.read_only_methods,
+            submit: config.submit_methods,
+            admin: config.admin_methods,
+        }
+    }
+
+    /// Check whether a method group is enabled
+    pub fn contains(self, group: MethodGroup) -> bool {
+        match group {
+            MethodGroup::ReadOnly => self.read_only,
+            MethodGroup::Submit => self.submit,
+            MethodGroup::Admin => self.admin,
+        }
+    }
+}
+
+/// Error returned to the clients which are not authenticated
+pub fn unauthorized_error() -> jsonrpc_core::Error {
+    jsonrpc_core::Error {
+        code: jsonrpc_core::ErrorCode::ServerError(errors::UNAUTHORIZED),
+        message: "Unauthorized".to_string(),
+        data: None,
+    }
+}
+
+/// Error returned when calling a method whose group is disabled in the configuration
+pub fn method_disabled_error(method: &str) -> jsonrpc_core::Error {
+    jsonrpc_core::Error {
+        code: jsonrpc_core::ErrorCode::MethodNotFound,
+        message: format!("Method {} is disabled by configuration", method),
+        data: None,
+    }
+}
+
+/// Get the token which the clients of this node must send, writing the cookie file if needed.
+///
+/// Returns `None` when authentication is disabled.
+pub fn server_token(config: &JsonRpcConfig) -> io::Result<Option<String>> {
+    match (&config.auth_token, &config.auth_cookie_path) {
+        (Some(token), _) => Ok(Some(token.clone())),
+        (None, Some(cookie_path)) => {
+            let token = to_hex(&rand::thread_rng().gen::<[u8; 32]>());
+            write_cookie(cookie_path, &token)?;
+
+            Ok(Some(token))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+/// Write the token to the cookie file, which is only readable by the current user
+///
+/// Any existing file is removed first, so that the token is never written to a file with other
+/// permissions, and the new file is created with the right permissions instead of changing them
+/// after writing the token.
+fn write_cookie(path: &Path, token: &str) -> io::Result<()> {
+    if let Err(e) = fs::remove_file(path) {
+        if e.kind() != io::ErrorKind::NotFound {
+            return Err(e);
+        }
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path)?.write_all(token.as_bytes())
+}
+
+/// Compare two tokens in constant time, so that the time to reject a token does not reveal how
+/// many of its bytes are correct
+pub fn tokens_match(expected: &str, received: &str) -> bool {
+    expected.len() == received.len()
+        && expected
+            .bytes()
+            .zip(received.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Extract the token from the value of an `Authorization: Bearer <token>` header
+pub fn bearer_token(header: &str) -> Option<&str> {
+    let mut parts = header.trim().splitn(2, ' ');
+    match (parts.next(), parts.next()) {
+        (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("Bearer") => Some(token.trim()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_match_same_length() {
+        assert!(tokens_match("abcd", "abcd"));
+        assert!(!tokens_match("abcd", "abce"));
+        assert!(!tokens_match("abcd", "abc"));
+        assert!(!tokens_match("abcd", ""));
+    }
+
+    #[test]
+    fn parse_bearer_token() {
+        assert_eq!(bearer_token("Bearer abcd"), Some("abcd"));
+        assert_eq!(bearer_token("bearer  abcd "), Some("abcd"));
+        assert_eq!(bearer_token("Basic abcd"), None);
+        assert_eq!(bearer_token("abcd"), None);
+    }
+
+    #[test]
+    fn enabled_method_groups() {
+        let enabled = EnabledMethods {
+            read_only: true,
+            submit: false,
+            admin: false,
+        };
+        assert!(enabled.contains(MethodGroup::ReadOnly));
+        assert!(!enabled.contains(MethodGroup::Submit));
+        assert!(EnabledMethods::all().contains(MethodGroup::Admin));
+    }
+
+    #[test]
+    fn write_cookie_replaces_existing_file() {
+        let path = std::env::temp_dir().join(format!("witnet_cookie_{}", std::process::id()));
+        fs::write(&path, "old token, which is longer than the new one").unwrap();
+
+        write_cookie(&path, "abcd").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "abcd");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+}
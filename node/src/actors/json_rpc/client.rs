@@ -0,0 +1,347 @@
+//! Typed client of the JSON-RPC interface of the node
+//!
+//! The requests and responses use the same types as the methods in
+//! [`json_rpc_methods`](super::json_rpc_methods), so a change in the interface of the node is
+//! also a change in the interface of the client.
+//!
+//! It extends the client of `witnet_json_rpc_client`, which has the typed methods that the
+//! components which do not depend on the node can use, with the methods whose types are
+//! defined by the node.
+
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    ops::{Deref, DerefMut},
+};
+
+use serde_json::Value;
+
+use witnet_data_structures::chain::{Epoch, OutputPointer, PublicKeyHash};
+pub use witnet_json_rpc_client::{ClientError, Notification, ServerError};
+
+use crate::{
+    actors::{
+        chain_manager::StateMachine,
+        messages::{
+            ActiveDataRequest, Balance, BannedPeer, DataRequestStatus, NodeStatus, PeerInfo,
+        },
+    },
+    signature_mngr::KeyRole,
+};
+
+use super::json_rpc_methods::{
+    BanPeerParams, ExportMasterKeyParams, GetPublicKeyParams, ImportMasterKeyParams, NodePublicKey,
+};
+
+/// Subscriptions of the JSON-RPC interface, along with their filters
+#[derive(Debug, Clone, PartialEq)]
+pub enum Subscription {
+    /// Consolidated blocks, notified as [`Block`](witnet_data_structures::chain::Block)
+    NewBlocks,
+    /// Transactions admitted into the mempool, notified as
+    /// [`Transaction`](witnet_data_structures::chain::Transaction)
+    NewTransactions {
+        /// Only notify the transactions with an output to this address
+        address: Option<PublicKeyHash>,
+    },
+    /// Start of a new epoch, notified as an [`Epoch`](witnet_data_structures::chain::Epoch)
+    NewEpoch {
+        /// Only notify the epochs which are a multiple of this number
+        every: Option<Epoch>,
+    },
+    /// Transitions of the state of the `ChainManager`, notified as
+    /// [`SyncStatus`](crate::actors::messages::SyncStatus)
+    SyncStatus {
+        /// Only notify the transitions into one of these states
+        states: Option<Vec<StateMachine>>,
+    },
+    /// Resolved data requests, notified as
+    /// [`DataRequestResolved`](crate::actors::messages::DataRequestResolved)
+    DataRequestResolved,
+}
+
+impl Subscription {
+    /// Name of the subscription method
+    pub fn method(&self) -> &'static str {
+        match self {
+            Subscription::NewBlocks => "newBlocks",
+            Subscription::NewTransactions { .. } => "newTransactions",
+            Subscription::NewEpoch { .. } => "newEpoch",
+            Subscription::SyncStatus { .. } => "syncStatus",
+            Subscription::DataRequestResolved => "dataRequestResolved",
+        }
+    }
+
+    /// Filter sent with the `witnet_subscribe` request, `null` if there is none
+    fn filter(&self) -> Value {
+        match self {
+            // Encoded as the bech32 address of the configured environment, like the addresses
+            // of the rest of the methods
+            Subscription::NewTransactions {
+                address: Some(address),
+            } => serde_json::json!({ "address": address }),
+            Subscription::NewEpoch { every: Some(every) } => {
+                serde_json::json!({ "every": every })
+            }
+            Subscription::SyncStatus {
+                states: Some(states),
+            } => serde_json::json!({ "states": states }),
+            _ => Value::Null,
+        }
+    }
+}
+
+/// Blocking JSON-RPC client of a node, with a method for each JSON-RPC method of the node.
+///
+/// The methods which do not depend on the types of the node are those of
+/// [`witnet_json_rpc_client::Client`], which this client dereferences to.
+pub struct Client<S = TcpStream> {
+    inner: witnet_json_rpc_client::Client<S>,
+}
+
+impl Client<TcpStream> {
+    /// Connect to the TCP JSON-RPC server of a node
+    pub fn connect(addr: SocketAddr) -> Result<Self, ClientError> {
+        witnet_json_rpc_client::Client::connect(addr).map(|inner| Self { inner })
+    }
+}
+
+impl<S> Deref for Client<S> {
+    type Target = witnet_json_rpc_client::Client<S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<S> DerefMut for Client<S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<S: Read + Write> Client<S> {
+    /// Use an already open connection to a node
+    pub fn new(stream: S) -> Self {
+        Self {
+            inner: witnet_json_rpc_client::Client::new(stream),
+        }
+    }
+
+    /// Subscribe to the notifications of the node, returning the id of the subscription
+    pub fn subscribe(&mut self, subscription: &Subscription) -> Result<String, ClientError> {
+        self.inner
+            .subscribe(subscription.method(), subscription.filter())
+    }
+
+    /// Get the balance of an address
+    pub fn get_balance(&mut self, address: &str) -> Result<Balance, ClientError> {
+        self.call("getBalance", (address,))
+    }
+
+    /// Get the progress of a data request by its output pointer
+    pub fn get_data_request(
+        &mut self,
+        dr_pointer: &OutputPointer,
+    ) -> Result<DataRequestStatus, ClientError> {
+        self.call("getDataRequest", (dr_pointer,))
+    }
+
+    /// Get the data requests which have not been resolved yet
+    pub fn get_active_data_requests(&mut self) -> Result<Vec<ActiveDataRequest>, ClientError> {
+        self.call("getActiveDataRequests", ())
+    }
+
+    /// Get the status of the node
+    pub fn get_node_status(&mut self) -> Result<NodeStatus, ClientError> {
+        self.call("getNodeStatus", ())
+    }
+
+    /// Get the consolidated sessions of the node
+    pub fn get_peers(&mut self) -> Result<Vec<PeerInfo>, ClientError> {
+        self.call("getPeers", ())
+    }
+
+    /// Get the IP addresses banned by the node
+    pub fn get_banned_peers(&mut self) -> Result<Vec<BannedPeer>, ClientError> {
+        self.call("getBannedPeers", ())
+    }
+
+    /// Ban an IP address, for the given number of seconds or the configured ban duration
+    pub fn ban_peer(&mut self, params: &BanPeerParams) -> Result<BannedPeer, ClientError> {
+        self.call("banPeer", params)
+    }
+
+    /// Get the master public key of the node, or the key derived for a role
+    pub fn get_public_key(&mut self, role: Option<KeyRole>) -> Result<NodePublicKey, ClientError> {
+        self.call("getPublicKey", role.map(|role| GetPublicKeyParams { role }))
+    }
+
+    /// Export the master key of the node
+    pub fn export_master_key(
+        &mut self,
+        params: &ExportMasterKeyParams,
+    ) -> Result<String, ClientError> {
+        self.call("exportMasterKey", params)
+    }
+
+    /// Replace the master key of the node, returning its new public key
+    pub fn import_master_key(
+        &mut self,
+        params: &ImportMasterKeyParams,
+    ) -> Result<NodePublicKey, ClientError> {
+        self.call("importMasterKey", params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use witnet_data_structures::chain::Environment;
+
+    use super::*;
+
+    /// Connection which replies with the given lines and records the requests
+    struct MockStream {
+        responses: io::Cursor<Vec<u8>>,
+        requests: Vec<u8>,
+    }
+
+    impl MockStream {
+        fn new(responses: &[&str]) -> Self {
+            let mut bytes = vec![];
+            for response in responses {
+                bytes.extend_from_slice(response.as_bytes());
+                bytes.push(b'\n');
+            }
+
+            Self {
+                responses: io::Cursor::new(bytes),
+                requests: vec![],
+            }
+        }
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.responses.read(buf)
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.requests.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn requests(client: Client<MockStream>) -> String {
+        String::from_utf8(client.inner.into_inner().requests).unwrap()
+    }
+
+    #[test]
+    fn call_without_params() {
+        let mut client = Client::new(MockStream::new(&[
+            r#"{"jsonrpc":"2.0","result":["127.0.0.1:21337"],"id":1}"#,
+        ]));
+        let peers = client.get_known_peers().unwrap();
+        assert_eq!(peers, vec!["127.0.0.1:21337".parse().unwrap()]);
+        assert_eq!(
+            requests(client),
+            "{\"jsonrpc\":\"2.0\",\"method\":\"getKnownPeers\",\"id\":1}\n"
+        );
+    }
+
+    #[test]
+    fn call_with_params() {
+        let mut client = Client::new(MockStream::new(&[
+            r#"{"jsonrpc":"2.0","result":{"confirmed":1,"pending":2},"id":1}"#,
+        ]));
+        let balance = client.get_balance("twit1abcd").unwrap();
+        assert_eq!(
+            balance,
+            Balance {
+                confirmed: 1,
+                pending: 2
+            }
+        );
+        assert_eq!(
+            requests(client),
+            "{\"jsonrpc\":\"2.0\",\"method\":\"getBalance\",\"params\":[\"twit1abcd\"],\"id\":1}\n"
+        );
+    }
+
+    #[test]
+    fn get_public_key() {
+        let mut client = Client::new(MockStream::new(&[
+            r#"{"jsonrpc":"2.0","result":{"public_key":"02ab","address":"twit1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqkz4ay"},"id":1}"#,
+        ]));
+        let public_key = client.get_public_key(Some(KeyRole::Commit)).unwrap();
+        assert_eq!(public_key.public_key, "02ab");
+        assert_eq!(
+            public_key.address,
+            "twit1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqkz4ay"
+        );
+        assert_eq!(
+            requests(client),
+            "{\"jsonrpc\":\"2.0\",\"method\":\"getPublicKey\",\"params\":{\"role\":\"commit\"},\"id\":1}\n"
+        );
+    }
+
+    #[test]
+    fn get_peers() {
+        let mut client = Client::new(MockStream::new(&[
+            r#"{"jsonrpc":"2.0","result":[{"address":"127.0.0.1:21337","session_type":"Outbound","last_beacon":null}],"id":1}"#,
+        ]));
+        let peers = client.get_peers().unwrap();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].address, "127.0.0.1:21337".parse().unwrap());
+        assert_eq!(peers[0].last_beacon, None);
+    }
+
+    #[test]
+    fn notifications_are_queued() {
+        let mut client = Client::new(MockStream::new(&[
+            r#"{"jsonrpc":"2.0","result":"1","id":1}"#,
+            r#"{"jsonrpc":"2.0","method":"witnet_subscription","params":{"result":7,"subscription":"1"}}"#,
+            r#"{"jsonrpc":"2.0","result":true,"id":2}"#,
+            r#"{"jsonrpc":"2.0","method":"witnet_subscription","params":{"result":8,"subscription":"1"}}"#,
+        ]));
+
+        let id = client
+            .subscribe(&Subscription::NewEpoch { every: None })
+            .unwrap();
+        assert_eq!(id, "1");
+        assert!(client.unsubscribe(&id).unwrap());
+
+        let epochs: Vec<Epoch> = (0..2)
+            .map(|_| client.next_notification().unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(epochs, vec![7, 8]);
+        assert!(client.next_notification().is_err());
+    }
+
+    #[test]
+    fn subscription_filter() {
+        assert_eq!(Subscription::NewBlocks.filter(), Value::Null);
+        assert_eq!(
+            Subscription::SyncStatus {
+                states: Some(vec![StateMachine::Synced])
+            }
+            .filter(),
+            serde_json::json!({ "states": ["Synced"] })
+        );
+
+        let address = PublicKeyHash::from_bytes(&[0xab; 20]).unwrap();
+        assert_eq!(
+            Subscription::NewTransactions {
+                address: Some(address)
+            }
+            .filter(),
+            serde_json::json!({ "address": address.bech32(&Environment::Testnet1) })
+        );
+    }
+}
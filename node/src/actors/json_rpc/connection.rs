@@ -1,21 +1,102 @@
 use actix::{
-    io::FramedWrite, io::WriteHandler, Actor, ActorFuture, Addr, AsyncContext, Context,
-    ContextFutureSpawner, Running, StreamHandler, WrapFuture,
+    io::FramedWrite, io::WriteHandler, Actor, ActorContext, ActorFuture, Addr, AsyncContext,
+    Context, ContextFutureSpawner, Running, StreamHandler, WrapFuture,
 };
 use tokio::{io::WriteHalf, net::TcpStream};
 
 use bytes;
 use bytes::BytesMut;
+use jsonrpc_core::{
+    Error, ErrorCode, Failure, Id, MethodCall, Output, Params, Success, Value, Version,
+};
 use log::*;
-use std::{io, rc::Rc};
+use std::{
+    io,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use super::{
+    auth::{tokens_match, unauthorized_error, AUTHENTICATE_METHOD},
+    errors,
     newline_codec::NewLineCodec,
     server::{JsonRpcServer, Unregister},
 };
 use jsonrpc_pubsub::{PubSubHandler, Session};
 use std::sync::Arc;
 
+/// Limits applied to the JSON-RPC connections, to prevent a misbehaving client from
+/// stalling the actor system
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConnectionLimits {
+    /// Maximum size of a request, in bytes
+    pub max_request_size: usize,
+    /// Connections without activity during this time are closed
+    pub idle_timeout: Duration,
+    /// Maximum number of concurrent connections
+    pub max_connections: usize,
+    /// Maximum number of requests per second of a single connection
+    pub max_requests_per_second: u32,
+}
+
+/// Result of counting a request against a rate limit
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RateLimit {
+    /// The request must be handled
+    Allowed,
+    /// First request over the limit in the current window, which is answered with an error
+    Exceeded,
+    /// Any other request over the limit in the current window, which is dropped without an
+    /// answer, so that a flooding client cannot fill the output buffer with error responses
+    Dropped,
+}
+
+/// Rate limiter which allows up to `max_requests_per_second` requests in every window of one
+/// second
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    /// Maximum number of requests of each window
+    max_requests_per_second: u32,
+    /// Start of the current rate limiting window, which lasts one second
+    window_start: Instant,
+    /// Number of requests received during the current rate limiting window
+    window_requests: u32,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter whose first window starts now
+    pub fn new(max_requests_per_second: u32) -> Self {
+        RateLimiter {
+            max_requests_per_second,
+            window_start: Instant::now(),
+            window_requests: 0,
+        }
+    }
+
+    /// Count a new request
+    pub fn check(&mut self) -> RateLimit {
+        let now = Instant::now();
+        if now.duration_since(self.window_start) >= Duration::from_secs(1) {
+            self.window_start = now;
+            self.window_requests = 0;
+        }
+        self.window_requests = self.window_requests.saturating_add(1);
+
+        if self.window_requests <= self.max_requests_per_second {
+            RateLimit::Allowed
+        } else if self.window_requests == self.max_requests_per_second.saturating_add(1) {
+            RateLimit::Exceeded
+        } else {
+            RateLimit::Dropped
+        }
+    }
+
+    /// Whether the current window has ended, so that the next request starts a new one
+    pub fn window_expired(&self) -> bool {
+        self.window_start.elapsed() >= Duration::from_secs(1)
+    }
+}
+
 /// A single JSON-RPC connection
 pub struct JsonRpc {
     /// Stream
@@ -27,11 +108,119 @@ pub struct JsonRpc {
     pub jsonrpc_io: Rc<PubSubHandler<Arc<Session>>>,
     /// Sender
     pub session: Arc<Session>,
+    /// Limits of this connection
+    pub limits: ConnectionLimits,
+    /// Instant of the last request or notification
+    pub last_activity: Instant,
+    /// Rate limit of the requests of this connection
+    pub rate_limiter: RateLimiter,
+    /// Token which the client must send with the `authenticate` method before calling any other
+    /// method. When `None`, authentication is disabled
+    pub auth_token: Option<String>,
+    /// Whether the client has already sent the right token
+    pub authenticated: bool,
+}
+
+impl JsonRpc {
+    /// Close the connection if it has been idle for longer than the idle timeout, otherwise
+    /// check again when the timeout would expire
+    fn check_idle(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let idle_time = self.last_activity.elapsed();
+
+        if idle_time >= self.limits.idle_timeout {
+            debug!("Closing idle JSON-RPC connection");
+            ctx.stop();
+        } else {
+            ctx.run_later(self.limits.idle_timeout - idle_time, |act, ctx| {
+                act.check_idle(ctx)
+            });
+        }
+    }
+
+    /// Handle the `authenticate` method, and reject the requests of the clients which are not
+    /// authenticated yet.
+    ///
+    /// Returns the response to send when the request must not reach the JSON-RPC handler.
+    fn check_auth(&mut self, msg: &str) -> Option<String> {
+        let authorized = self.authenticated || self.auth_token.is_none();
+
+        match serde_json::from_str::<MethodCall>(msg) {
+            Ok(call) => {
+                if call.method == AUTHENTICATE_METHOD {
+                    let output = self.authenticate(call.params);
+                    Some(response(call.id, output))
+                } else if authorized {
+                    None
+                } else {
+                    Some(response(call.id, Err(unauthorized_error())))
+                }
+            }
+            // Let the JSON-RPC handler answer invalid requests and batches
+            Err(_) if authorized => None,
+            Err(_) => Some(response(Id::Null, Err(unauthorized_error()))),
+        }
+    }
+
+    /// Check the token sent with the `authenticate` method
+    fn authenticate(&mut self, params: Params) -> Result<Value, Error> {
+        let (received,): (String,) = params.parse()?;
+
+        match &self.auth_token {
+            // Authentication is disabled, so every client is authenticated
+            None => Ok(Value::Bool(true)),
+            Some(token) if tokens_match(token, &received) => {
+                self.authenticated = true;
+                Ok(Value::Bool(true))
+            }
+            Some(_) => {
+                warn!("JSON-RPC client sent a wrong authentication token");
+                Err(unauthorized_error())
+            }
+        }
+    }
+}
+
+/// Serialize a JSON-RPC response
+fn response(id: Id, result: Result<Value, Error>) -> String {
+    let output = match result {
+        Ok(result) => Output::Success(Success {
+            jsonrpc: Some(Version::V2),
+            result,
+            id,
+        }),
+        Err(error) => Output::Failure(Failure {
+            jsonrpc: Some(Version::V2),
+            error,
+            id,
+        }),
+    };
+
+    serde_json::to_string(&output).unwrap()
+}
+
+/// Error returned to the requests rejected because of the rate limit
+pub fn rate_limit_error() -> Error {
+    Error {
+        code: ErrorCode::ServerError(errors::RATE_LIMIT_EXCEEDED),
+        message: "Rate limit exceeded".to_string(),
+        data: None,
+    }
+}
+
+/// JSON-RPC response for the requests rejected because of the rate limit
+// The request is not parsed, so the response has a null id
+fn rate_limit_response() -> String {
+    response(Id::Null, Err(rate_limit_error()))
 }
 
 impl Actor for JsonRpc {
     type Context = Context<Self>;
 
+    /// Method to be executed when the actor is started
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.check_idle(ctx);
+    }
+
     /// Method to be executed when the actor is stopping
     fn stopping(&mut self, ctx: &mut Self::Context) -> Running {
         // Unregister session from JsonRpcServer
@@ -50,6 +239,20 @@ impl StreamHandler<BytesMut, io::Error> for JsonRpc {
     /// This is main event loop for client requests
     fn handle(&mut self, bytes: BytesMut, ctx: &mut Self::Context) {
         debug!("Got JSON-RPC message");
+        self.last_activity = Instant::now();
+
+        match self.rate_limiter.check() {
+            RateLimit::Allowed => {}
+            RateLimit::Exceeded => {
+                // Answer without handling the request, so that a client sending many requests
+                // cannot keep the actor system busy
+                warn!("JSON-RPC connection exceeded the rate limit");
+                self.framed.write(BytesMut::from(rate_limit_response()));
+                return;
+            }
+            RateLimit::Dropped => return,
+        }
+
         let msg = match String::from_utf8(bytes.to_vec()) {
             Ok(msg) => {
                 // A valid utf8 string is forwarded to the JSON-RPC parser
@@ -71,6 +274,11 @@ impl StreamHandler<BytesMut, io::Error> for JsonRpc {
             }
         };
 
+        if let Some(response) = self.check_auth(&msg) {
+            self.framed.write(BytesMut::from(response));
+            return;
+        }
+
         let session = Arc::clone(&self.session);
 
         // Handle response asynchronously
@@ -86,10 +294,47 @@ impl StreamHandler<BytesMut, io::Error> for JsonRpc {
             })
             .wait(ctx);
     }
+
+    /// Close the connection when the request cannot be decoded, for example when it is
+    /// longer than the maximum request size
+    fn error(&mut self, err: io::Error, _ctx: &mut Self::Context) -> Running {
+        warn!("Closing JSON-RPC connection: {}", err);
+
+        Running::Stop
+    }
 }
 
 impl StreamHandler<String, ()> for JsonRpc {
     fn handle(&mut self, item: String, _ctx: &mut Self::Context) {
+        // Sending notifications keeps the subscriptions alive
+        self.last_activity = Instant::now();
         self.framed.write(BytesMut::from(item));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_answers_only_the_first_request_over_the_limit() {
+        let mut rate_limiter = RateLimiter::new(2);
+
+        assert_eq!(rate_limiter.check(), RateLimit::Allowed);
+        assert_eq!(rate_limiter.check(), RateLimit::Allowed);
+        assert_eq!(rate_limiter.check(), RateLimit::Exceeded);
+        assert_eq!(rate_limiter.check(), RateLimit::Dropped);
+        assert_eq!(rate_limiter.check(), RateLimit::Dropped);
+    }
+
+    #[test]
+    fn rate_limiter_starts_a_new_window_every_second() {
+        let mut rate_limiter = RateLimiter::new(1);
+
+        assert_eq!(rate_limiter.check(), RateLimit::Allowed);
+        assert_eq!(rate_limiter.check(), RateLimit::Exceeded);
+
+        rate_limiter.window_start -= Duration::from_secs(1);
+        assert_eq!(rate_limiter.check(), RateLimit::Allowed);
+    }
+}
@@ -0,0 +1,201 @@
+//! Error codes of the JSON-RPC interface
+//!
+//! Besides the standard JSON-RPC errors, the methods return the codes listed in
+//! [`witnet_json_rpc_client::errors`](witnet_json_rpc_client::errors), where they are defined
+//! so that the clients can use them without depending on the node. This module maps the errors
+//! of the node to those codes.
+
+use std::fmt;
+
+use witnet_data_structures::error::{BlockError, TransactionError};
+pub use witnet_json_rpc_client::errors::{
+    ErrorData, BLOCK_ERROR, CHAIN_MANAGER_ERROR, INVENTORY_MANAGER_ERROR, MASTER_KEY_ERROR,
+    RAD_ERROR, RATE_LIMIT_EXCEEDED, TRANSACTION_ERROR, UNAUTHORIZED,
+};
+use witnet_rad::error::RadError;
+
+use crate::{
+    actors::{chain_manager::ChainManagerError, inventory_manager::InventoryManagerError},
+    signature_mngr::MasterKeyError,
+};
+
+/// Errors of the node which have their own code in the JSON-RPC interface
+pub trait RpcError: fmt::Display {
+    /// JSON-RPC error code
+    const CODE: i64;
+
+    /// Machine-readable description of the error
+    fn data(&self) -> ErrorData;
+
+    /// Convert into a JSON-RPC error, whose message is the `Display` of the error
+    fn to_rpc_error(&self) -> jsonrpc_core::Error {
+        jsonrpc_core::Error {
+            code: jsonrpc_core::ErrorCode::ServerError(Self::CODE),
+            message: self.to_string(),
+            data: serde_json::to_value(self.data()).ok(),
+        }
+    }
+}
+
+impl RpcError for ChainManagerError {
+    const CODE: i64 = CHAIN_MANAGER_ERROR;
+
+    fn data(&self) -> ErrorData {
+        ErrorData::from_serialize("ChainManagerError", self)
+    }
+}
+
+impl RpcError for InventoryManagerError {
+    const CODE: i64 = INVENTORY_MANAGER_ERROR;
+
+    fn data(&self) -> ErrorData {
+        ErrorData::from_serialize("InventoryManagerError", self)
+    }
+}
+
+impl RpcError for TransactionError {
+    const CODE: i64 = TRANSACTION_ERROR;
+
+    fn data(&self) -> ErrorData {
+        ErrorData::from_serialize("TransactionError", self)
+    }
+}
+
+impl RpcError for BlockError {
+    const CODE: i64 = BLOCK_ERROR;
+
+    fn data(&self) -> ErrorData {
+        ErrorData::from_serialize("BlockError", self)
+    }
+}
+
+impl RpcError for RadError {
+    const CODE: i64 = RAD_ERROR;
+
+    fn data(&self) -> ErrorData {
+        // Some variants contain MessagePack values, which cannot be serialized
+        ErrorData::from_debug("RadError", self)
+    }
+}
+
+impl RpcError for MasterKeyError {
+    const CODE: i64 = MASTER_KEY_ERROR;
+
+    fn data(&self) -> ErrorData {
+        ErrorData::from_serialize("MasterKeyError", self)
+    }
+}
+
+/// Convert an error into a JSON-RPC error, using the code of its type when it is known.
+///
+/// Unknown errors are converted into internal errors.
+pub fn from_failure(e: &failure::Error) -> jsonrpc_core::Error {
+    if let Some(e) = e.downcast_ref::<ChainManagerError>() {
+        e.to_rpc_error()
+    } else if let Some(e) = e.downcast_ref::<InventoryManagerError>() {
+        e.to_rpc_error()
+    } else if let Some(e) = e.downcast_ref::<TransactionError>() {
+        e.to_rpc_error()
+    } else if let Some(e) = e.downcast_ref::<BlockError>() {
+        e.to_rpc_error()
+    } else if let Some(e) = e.downcast_ref::<RadError>() {
+        e.to_rpc_error()
+    } else if let Some(e) = e.downcast_ref::<MasterKeyError>() {
+        e.to_rpc_error()
+    } else {
+        internal_error(e)
+    }
+}
+
+/// Error which does not come from the node, for example a failure to communicate with an actor
+pub fn internal_error<T: fmt::Display>(e: T) -> jsonrpc_core::Error {
+    jsonrpc_core::Error {
+        code: jsonrpc_core::ErrorCode::InternalError,
+        message: e.to_string(),
+        data: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_data_unit_variant() {
+        let error = ChainManagerError::ChainNotReady.to_rpc_error();
+        assert_eq!(
+            error.code,
+            jsonrpc_core::ErrorCode::ServerError(CHAIN_MANAGER_ERROR)
+        );
+        assert_eq!(error.message, "ChainManager is not ready yet");
+        assert_eq!(
+            error.data,
+            Some(serde_json::json!({ "error": "ChainManagerError", "kind": "ChainNotReady" }))
+        );
+    }
+
+    #[test]
+    fn error_data_struct_variant() {
+        let error = TransactionError::InvalidFee {
+            fee: 1,
+            expected_fee: 2,
+        }
+        .to_rpc_error();
+        assert_eq!(
+            error.code,
+            jsonrpc_core::ErrorCode::ServerError(TRANSACTION_ERROR)
+        );
+        assert_eq!(
+            error.data,
+            Some(serde_json::json!({
+                "error": "TransactionError",
+                "kind": "InvalidFee",
+                "details": { "fee": 1, "expected_fee": 2 },
+            }))
+        );
+    }
+
+    #[test]
+    fn error_data_from_debug() {
+        let error = RadError::MapKeyNotFound {
+            key: "price".to_string(),
+        };
+        assert_eq!(
+            error.data(),
+            ErrorData {
+                error: "RadError".to_string(),
+                kind: "MapKeyNotFound".to_string(),
+                details: None,
+            }
+        );
+    }
+
+    #[test]
+    fn from_failure_downcasts() {
+        let error = from_failure(&BlockError::NotValidPoe.into());
+        assert_eq!(
+            error.code,
+            jsonrpc_core::ErrorCode::ServerError(BLOCK_ERROR)
+        );
+
+        let error = from_failure(&InventoryManagerError::ItemDoesNotExist.into());
+        assert_eq!(
+            error.code,
+            jsonrpc_core::ErrorCode::ServerError(INVENTORY_MANAGER_ERROR)
+        );
+
+        let error = from_failure(&MasterKeyError::WrongPassword.into());
+        assert_eq!(
+            error.code,
+            jsonrpc_core::ErrorCode::ServerError(MASTER_KEY_ERROR)
+        );
+        assert_eq!(
+            error.data,
+            Some(serde_json::json!({ "error": "MasterKeyError", "kind": "WrongPassword" }))
+        );
+
+        let error = from_failure(&failure::err_msg("something failed"));
+        assert_eq!(error.code, jsonrpc_core::ErrorCode::InternalError);
+        assert_eq!(error.message, "something failed");
+    }
+}
@@ -1,28 +1,49 @@
 use std::{
     collections::HashMap,
+    net::IpAddr,
     sync::atomic::{AtomicUsize, Ordering},
     sync::Arc,
+    time::Duration,
 };
 
 #[cfg(not(test))]
 use actix::System;
 use actix::{MailboxError, SystemService};
-use jsonrpc_core::{futures, futures::Future, BoxFuture, MetaIoHandler, Params, Value};
+use jsonrpc_core::{
+    futures, futures::Future, BoxFuture, MetaIoHandler, Middleware, Params, RpcMethodSimple, Value,
+};
 use jsonrpc_pubsub::{PubSubHandler, Session, Subscriber, SubscriptionId};
 use log::{debug, error, info};
 use serde::{Deserialize, Serialize};
 
-use witnet_data_structures::chain::{self, Block, Hash, InventoryEntry, Transaction};
+use witnet_data_structures::chain::{
+    self, Environment, Hash, InventoryEntry, OutputPointer, PublicKey, PublicKeyHash, Transaction,
+};
+pub use witnet_json_rpc_client::types::{
+    BanPeerParams, GetBlockChainParams, InventoryItem, TransactionInfo,
+};
+use witnet_util::parser::to_hex;
 
 use crate::actors::{
     chain_manager::{ChainManager, ChainManagerError},
     epoch_manager::EpochManager,
     inventory_manager::InventoryManager,
-    messages::{AddCandidates, AddTransaction, GetBlocksEpochRange, GetEpoch, GetItem},
+    messages::{
+        AddCandidates, AddTransaction, BanPeer, GetActiveDataRequests, GetBalance, GetBannedPeers,
+        GetBlocksEpochRange, GetConsolidatedPeers, GetDataRequest, GetEpoch, GetItem,
+        GetNodeStatus, GetOutput, GetTransaction, GetUtxos, RequestPeers, UnbanPeer,
+    },
+    peers_manager::PeersManager,
+    sessions_manager::SessionsManager,
 };
+use crate::config_mngr;
+use crate::signature_mngr::{self, KeyExportFormat, KeyRole, MasterKey};
 
-//use std::str::FromStr;
-use super::Subscriptions;
+use super::{
+    auth::{method_disabled_error, EnabledMethods, MethodGroup},
+    errors::{self, internal_error, RpcError},
+    subscription_filter, NewEpochFilter, NewTransactionsFilter, Subscriptions, SyncStatusFilter,
+};
 
 #[cfg(test)]
 use self::mock_actix::System;
@@ -32,20 +53,122 @@ type JsonRpcResultAsync = Box<dyn Future<Item = Value, Error = jsonrpc_core::Err
 
 /// Define the JSON-RPC interface:
 /// All the methods available through JSON-RPC
-pub fn jsonrpc_io_handler(subscriptions: Subscriptions) -> PubSubHandler<Arc<Session>> {
-    let mut io = PubSubHandler::new(MetaIoHandler::default());
+///
+/// The subscription ids are taken from `subscription_ids`, which must be shared by all the
+/// handlers using the same `subscriptions`, so that the ids are unique across transports.
+///
+/// The methods whose group is not in `enabled_methods` always return an error.
+///
+/// The addresses in the filters of the subscriptions must belong to `environment`.
+pub fn jsonrpc_io_handler(
+    subscriptions: Subscriptions,
+    subscription_ids: Arc<AtomicUsize>,
+    enabled_methods: EnabledMethods,
+    environment: Environment,
+) -> PubSubHandler<Arc<Session>> {
+    jsonrpc_io_handler_with_middleware(
+        subscriptions,
+        subscription_ids,
+        enabled_methods,
+        environment,
+        Default::default(),
+    )
+}
 
-    io.add_method("inventory", |params: Params| inventory(params.parse()?));
-    io.add_method("getBlockChain", |params: Params| {
+/// Same as `jsonrpc_io_handler`, but every request goes through `middleware` before reaching
+/// the methods
+pub fn jsonrpc_io_handler_with_middleware<S>(
+    subscriptions: Subscriptions,
+    subscription_ids: Arc<AtomicUsize>,
+    enabled_methods: EnabledMethods,
+    environment: Environment,
+    middleware: S,
+) -> PubSubHandler<Arc<Session>, S>
+where
+    S: Middleware<Arc<Session>>,
+{
+    let mut io = PubSubHandler::new(MetaIoHandler::with_middleware(middleware));
+    let mut methods = MethodRegistry {
+        io: &mut io,
+        enabled_methods,
+    };
+
+    methods.add(MethodGroup::Submit, "inventory", |params: Params| {
+        inventory(params.parse()?)
+    });
+    methods.add(MethodGroup::Submit, "sendTransaction", |params: Params| {
+        send_transaction(params.parse())
+    });
+    methods.add(MethodGroup::ReadOnly, "getBlockChain", |params: Params| {
         get_block_chain(params.parse())
     });
-    io.add_method("getBlock", |params: Params| get_block(params.parse()));
-    //io.add_method("getOutput", |params: Params| get_output(params.parse()));
+    methods.add(MethodGroup::ReadOnly, "getBlock", |params: Params| {
+        get_block(params.parse())
+    });
+    methods.add(MethodGroup::ReadOnly, "getPublicKey", |params: Params| {
+        get_public_key(params.parse())
+    });
+    methods.add(MethodGroup::Admin, "exportMasterKey", |params: Params| {
+        export_master_key(params.parse())
+    });
+    methods.add(MethodGroup::Admin, "importMasterKey", |params: Params| {
+        import_master_key(params.parse())
+    });
+    methods.add(MethodGroup::ReadOnly, "getOutput", |params: Params| {
+        get_output(params.parse())
+    });
+    methods.add(MethodGroup::ReadOnly, "getTransaction", |params: Params| {
+        get_transaction(params.parse())
+    });
+    methods.add(MethodGroup::ReadOnly, "getUtxos", |params: Params| {
+        get_utxos(params.parse())
+    });
+    methods.add(MethodGroup::ReadOnly, "getBalance", |params: Params| {
+        get_balance(params.parse())
+    });
+    methods.add(MethodGroup::ReadOnly, "getDataRequest", |params: Params| {
+        get_data_request(params.parse())
+    });
+    methods.add(
+        MethodGroup::ReadOnly,
+        "getActiveDataRequests",
+        |_params: Params| get_active_data_requests(),
+    );
+    methods.add(MethodGroup::ReadOnly, "getNodeStatus", |_params: Params| {
+        get_node_status()
+    });
+    methods.add(MethodGroup::ReadOnly, "getPeers", |_params: Params| {
+        get_peers()
+    });
+    methods.add(MethodGroup::ReadOnly, "getKnownPeers", |_params: Params| {
+        get_known_peers()
+    });
+    methods.add(
+        MethodGroup::ReadOnly,
+        "getBannedPeers",
+        |_params: Params| get_banned_peers(),
+    );
+    methods.add(MethodGroup::Admin, "banPeer", |params: Params| {
+        ban_peer(params.parse())
+    });
+    methods.add(MethodGroup::Admin, "unbanPeer", |params: Params| {
+        unban_peer(params.parse())
+    });
+
+    // Subscriptions only read the state of the node
+    if !enabled_methods.contains(MethodGroup::ReadOnly) {
+        for name in &["witnet_subscribe", "witnet_unsubscribe"] {
+            io.add_method(name, move |_params: Params| -> JsonRpcResult {
+                Err(method_disabled_error(name))
+            });
+        }
+
+        return io;
+    }
 
     // We need two Arcs, one for subscribe and one for unsuscribe
     let ss = subscriptions.clone();
     let ssu = subscriptions.clone();
-    let atomic_counter = AtomicUsize::new(1);
     io.add_subscription(
         "witnet_subscription",
         (
@@ -77,10 +200,40 @@ pub fn jsonrpc_io_handler(subscriptions: Subscriptions) -> PubSubHandler<Arc<Ses
                 // Get params, or set to Value::Null if the "params" key does not exist
                 let method_params = params_vec.get(1).cloned().unwrap_or_default();
 
+                // Check that the method exists and that its params are a valid filter
+                let method_name = match method_name.as_str() {
+                    "newBlocks" => Ok("newBlocks"),
+                    "dataRequestResolved" => Ok("dataRequestResolved"),
+                    "newTransactions" => {
+                        NewTransactionsFilter::from_params(&method_params, &environment)
+                            .map(|_| "newTransactions")
+                    }
+                    "newEpoch" => {
+                        subscription_filter::<NewEpochFilter>(&method_params).map(|_| "newEpoch")
+                    }
+                    "syncStatus" => subscription_filter::<SyncStatusFilter>(&method_params)
+                        .map(|_| "syncStatus"),
+                    e => {
+                        debug!("Unknown subscription method: {}", e);
+                        Err(jsonrpc_core::Error::invalid_params(format!(
+                            "Unknown subscription method: {}",
+                            e
+                        )))
+                    }
+                };
+                let method_name = match method_name {
+                    Ok(method_name) => method_name,
+                    Err(e) => {
+                        // Ignore errors with `.ok()` because an error here means the connection was closed
+                        subscriber.reject(e).ok();
+                        return;
+                    }
+                };
+
                 let add_subscription = |method_name, subscriber: Subscriber| {
                     if let Ok(mut s) = ss.lock() {
                         let id = SubscriptionId::String(
-                            atomic_counter.fetch_add(1, Ordering::SeqCst).to_string(),
+                            (subscription_ids.fetch_add(1, Ordering::SeqCst) + 1).to_string(),
                         );
                         if let Ok(sink) = subscriber.assign_id(id.clone()) {
                             let v = s.entry(method_name).or_insert_with(HashMap::new);
@@ -96,23 +249,8 @@ pub fn jsonrpc_io_handler(subscriptions: Subscriptions) -> PubSubHandler<Arc<Ses
                     }
                 };
 
-                match method_name.as_str() {
-                    "newBlocks" => {
-                        debug!("New subscription to newBlocks");
-                        add_subscription("newBlocks", subscriber);
-                    }
-                    e => {
-                        debug!("Unknown subscription method: {}", e);
-                        // Ignore errors with `.ok()` because an error here means the connection was closed
-                        subscriber
-                            .reject(jsonrpc_core::Error::invalid_params(format!(
-                                "Unknown subscription method: {}",
-                                e
-                            )))
-                            .ok();
-                        return;
-                    }
-                }
+                debug!("New subscription to {}", method_name);
+                add_subscription(method_name, subscriber);
             },
         ),
         (
@@ -150,26 +288,27 @@ pub fn jsonrpc_io_handler(subscriptions: Subscriptions) -> PubSubHandler<Arc<Ses
     io
 }
 
-fn internal_error<T: std::fmt::Debug>(e: T) -> jsonrpc_core::Error {
-    jsonrpc_core::Error {
-        code: jsonrpc_core::ErrorCode::InternalError,
-        message: format!("{:?}", e),
-        data: None,
-    }
+/// Helper to add methods to a handler, replacing the methods whose group is not enabled by a
+/// method which always fails
+struct MethodRegistry<'a, S: Middleware<Arc<Session>>> {
+    io: &'a mut PubSubHandler<Arc<Session>, S>,
+    enabled_methods: EnabledMethods,
 }
 
-/// Inventory element: block, transaction, etc
-#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
-pub enum InventoryItem {
-    /// Error
-    #[serde(rename = "error")]
-    Error,
-    /// Transaction
-    #[serde(rename = "transaction")]
-    Transaction(Transaction),
-    /// Block
-    #[serde(rename = "block")]
-    Block(Block),
+impl<'a, S: Middleware<Arc<Session>>> MethodRegistry<'a, S> {
+    fn add<F>(&mut self, group: MethodGroup, name: &'static str, method: F)
+    where
+        F: RpcMethodSimple,
+    {
+        if self.enabled_methods.contains(group) {
+            self.io.add_method(name, method);
+        } else {
+            self.io
+                .add_method(name, move |_params: Params| -> JsonRpcResult {
+                    Err(method_disabled_error(name))
+                });
+        }
+    }
 }
 
 /// Make the node process, validate and potentially broadcast a new inventory entry.
@@ -191,6 +330,7 @@ pub fn inventory(inv_elem: InventoryItem) -> JsonRpcResult {
             // But it's not so we just assume success
             chain_manager_addr.do_send(AddCandidates {
                 blocks: vec![block],
+                sender: None,
             });
 
             // Returns a boolean indicating success
@@ -222,15 +362,26 @@ pub fn inventory(inv_elem: InventoryItem) -> JsonRpcResult {
     }
 }
 
-/// Params of getBlockChain method
-#[derive(Debug, Default, Deserialize, Serialize)]
-pub struct GetBlockChainParams {
-    /// TODO
-    #[serde(default)] // default to 0
-    pub epoch: i64,
-    /// TODO
-    #[serde(default)] // default to 0
-    pub limit: u32,
+/// Submit a transaction and wait until the node validates it.
+///
+/// Returns the hash of the transaction, or the reason why it was rejected. Unlike `inventory`,
+/// an invalid transaction results in an error.
+pub fn send_transaction(params: Result<(Transaction,), jsonrpc_core::Error>) -> JsonRpcResultAsync {
+    let transaction = match params {
+        Ok(x) => x.0,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    debug!("Got transaction from JSON-RPC. Sending AddTransaction message.");
+    Box::new(
+        ChainManager::from_registry()
+            .send(AddTransaction { transaction })
+            .then(|res| match res {
+                Ok(Ok(hash)) => serde_json::to_value(hash).map_err(internal_error),
+                Ok(Err(e)) => Err(errors::from_failure(&e)),
+                Err(e) => Err(internal_error(e)),
+            }),
+    )
 }
 
 /// Get the list of all the known block hashes.
@@ -269,7 +420,7 @@ pub fn get_block_chain(
                 futures::finished(value)
             }
             Ok(Err(e)) => {
-                let err = internal_error(e);
+                let err = e.to_rpc_error();
                 futures::failed(err)
             }
             Err(e) => {
@@ -353,7 +504,7 @@ pub fn get_block(hash: Result<(Hash,), jsonrpc_core::Error>) -> JsonRpcResultAsy
                     futures::failed(err)
                 }
                 Ok(Err(e)) => {
-                    let err = internal_error(e);
+                    let err = e.to_rpc_error();
                     futures::failed(err)
                 }
                 Err(e) => {
@@ -364,48 +515,325 @@ pub fn get_block(hash: Result<(Hash,), jsonrpc_core::Error>) -> JsonRpcResultAsy
     )
 }
 
-/*
-/// get output
-pub fn get_output(output_pointer: Result<(String,), jsonrpc_core::Error>) -> JsonRpcResultAsync {
-    let output_pointer = match output_pointer {
-        Ok(x) => match OutputPointer::from_str(&x.0) {
-            Ok(x) => x,
-            Err(e) => {
-                let err = internal_error(e);
-                return Box::new(futures::failed(err));
-            }
-        },
-        Err(e) => {
-            return Box::new(futures::failed(e));
+/// Public key of the node and the address derived from it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NodePublicKey {
+    /// Compressed public key encoded as hex
+    pub public_key: String,
+    /// Bech32 address of the public key hash
+    pub address: String,
+}
+
+impl NodePublicKey {
+    /// Encode the public key, using the address format of the given environment
+    pub fn new(public_key: PublicKey, environment: &Environment) -> Self {
+        let mut bytes = vec![public_key.compressed];
+        bytes.extend_from_slice(&public_key.bytes);
+        let pkh: PublicKeyHash = public_key.pkh();
+
+        NodePublicKey {
+            public_key: to_hex(&bytes),
+            address: pkh.bech32(environment),
         }
+    }
+}
+
+fn public_key_response<F>(public_key: F) -> JsonRpcResultAsync
+where
+    F: Future<Item = PublicKey, Error = failure::Error> + Send + 'static,
+{
+    Box::new(public_key.join(config_mngr::get()).then(|res| {
+        res.map_err(|e| errors::from_failure(&e))
+            .and_then(|(public_key, config)| {
+                serde_json::to_value(NodePublicKey::new(public_key, &config.environment))
+                    .map_err(internal_error)
+            })
+    }))
+}
+
+/// Params of getPublicKey method
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GetPublicKeyParams {
+    /// Get the public key derived for this role instead of the master public key
+    pub role: KeyRole,
+}
+
+/// Get the public key of the node and its address
+pub fn get_public_key(
+    params: Result<Option<GetPublicKeyParams>, jsonrpc_core::Error>,
+) -> JsonRpcResultAsync {
+    match params {
+        Ok(None) => public_key_response(signature_mngr::public_key()),
+        Ok(Some(GetPublicKeyParams { role })) => {
+            public_key_response(signature_mngr::derived_public_key(role.path()))
+        }
+        Err(e) => Box::new(futures::failed(e)),
+    }
+}
+
+/// Params of exportMasterKey method
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "lowercase")]
+pub enum ExportMasterKeyParams {
+    /// Export the BIP39 mnemonic the key was generated from
+    Mnemonic,
+    /// Export the key encrypted with a password
+    Encrypted {
+        /// Password used to encrypt the key
+        password: String,
+    },
+}
+
+/// Export the master key of the node, as a mnemonic or encrypted with a password
+pub fn export_master_key(
+    params: Result<ExportMasterKeyParams, jsonrpc_core::Error>,
+) -> JsonRpcResultAsync {
+    let format = match params {
+        Ok(ExportMasterKeyParams::Mnemonic) => KeyExportFormat::Mnemonic,
+        Ok(ExportMasterKeyParams::Encrypted { password }) => {
+            KeyExportFormat::Encrypted(password.into())
+        }
+        Err(e) => return Box::new(futures::failed(e)),
     };
-    let chain_manager_addr = ChainManager::from_registry();
+
+    Box::new(
+        signature_mngr::export_master_key(format)
+            .map(Value::String)
+            .map_err(|e| errors::from_failure(&e)),
+    )
+}
+
+/// Params of importMasterKey method
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ImportMasterKeyParams {
+    /// BIP39 mnemonic phrase
+    Mnemonic {
+        /// Mnemonic words separated by spaces
+        mnemonic: String,
+    },
+    /// Key exported with `exportMasterKey` using the encrypted format
+    Encrypted {
+        /// Encrypted key encoded as hex
+        encrypted: String,
+        /// Password used to encrypt the key
+        password: String,
+    },
+}
+
+/// Replace the master key of the node with the given one.
+///
+/// Returns the new public key of the node and its address
+pub fn import_master_key(
+    params: Result<ImportMasterKeyParams, jsonrpc_core::Error>,
+) -> JsonRpcResultAsync {
+    let master_key = match params {
+        Ok(ImportMasterKeyParams::Mnemonic { mnemonic }) => MasterKey::from_mnemonic(&mnemonic)
+            .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string())),
+        // Decryption errors, such as a wrong password, have their own error code
+        Ok(ImportMasterKeyParams::Encrypted {
+            encrypted,
+            password,
+        }) => MasterKey::from_encrypted(&encrypted, password.as_bytes())
+            .map_err(|e| errors::from_failure(&e)),
+        Err(e) => Err(e),
+    };
+    let master_key = match master_key {
+        Ok(x) => x,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    public_key_response(signature_mngr::import_master_key(master_key))
+}
+
+/// Helper function to convert the result of a ChainManager query to a JSON value, or a JSON-RPC
+/// error
+fn chain_manager_response<T: Serialize>(
+    res: Result<Result<T, ChainManagerError>, MailboxError>,
+) -> Result<Value, jsonrpc_core::Error> {
+    match res {
+        Ok(Ok(x)) => serde_json::to_value(x).map_err(internal_error),
+        Ok(Err(e)) => Err(e.to_rpc_error()),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+/// Parse an address, which must belong to the environment of this node
+fn parse_address(
+    address: String,
+) -> impl Future<Item = PublicKeyHash, Error = jsonrpc_core::Error> {
+    config_mngr::get()
+        .map_err(internal_error)
+        .and_then(move |config| {
+            PublicKeyHash::from_bech32(&config.environment, &address)
+                .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))
+        })
+}
+
+/// Get an unspent output by its output pointer
+/* test
+{"jsonrpc":"2.0","id":1,"method":"getOutput","params":["1234567890abcdef111111111111111111111111111111111111111111111111:1"]}
+*/
+pub fn get_output(params: Result<(OutputPointer,), jsonrpc_core::Error>) -> JsonRpcResultAsync {
+    let output_pointer = match params {
+        Ok(x) => x.0,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
     Box::new(
-        chain_manager_addr
+        ChainManager::from_registry()
             .send(GetOutput { output_pointer })
+            .then(chain_manager_response),
+    )
+}
+
+/// Get a transaction by its hash
+pub fn get_transaction(params: Result<(Hash,), jsonrpc_core::Error>) -> JsonRpcResultAsync {
+    let hash = match params {
+        Ok(x) => x.0,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    Box::new(
+        ChainManager::from_registry()
+            .send(GetTransaction { hash })
+            .then(|res| {
+                chain_manager_response(res.map(|res| {
+                    res.map(|(transaction, block)| TransactionInfo { transaction, block })
+                }))
+            }),
+    )
+}
+
+/// Get the unspent outputs which can be spent by an address
+pub fn get_utxos(params: Result<(String,), jsonrpc_core::Error>) -> JsonRpcResultAsync {
+    let address = match params {
+        Ok(x) => x.0,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    Box::new(parse_address(address).and_then(|pkh| {
+        ChainManager::from_registry()
+            .send(GetUtxos { pkh })
+            .then(chain_manager_response)
+    }))
+}
+
+/// Get the balance of an address
+pub fn get_balance(params: Result<(String,), jsonrpc_core::Error>) -> JsonRpcResultAsync {
+    let address = match params {
+        Ok(x) => x.0,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    Box::new(parse_address(address).and_then(|pkh| {
+        ChainManager::from_registry()
+            .send(GetBalance { pkh })
+            .then(chain_manager_response)
+    }))
+}
+
+/// Get the progress of a data request by its output pointer
+pub fn get_data_request(
+    params: Result<(OutputPointer,), jsonrpc_core::Error>,
+) -> JsonRpcResultAsync {
+    let dr_pointer = match params {
+        Ok(x) => x.0,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    Box::new(
+        ChainManager::from_registry()
+            .send(GetDataRequest { dr_pointer })
+            .then(chain_manager_response),
+    )
+}
+
+/// Get the data requests which have not been resolved yet
+pub fn get_active_data_requests() -> JsonRpcResultAsync {
+    Box::new(
+        ChainManager::from_registry()
+            .send(GetActiveDataRequests)
+            .then(chain_manager_response),
+    )
+}
+
+/// Get the status of the node
+pub fn get_node_status() -> JsonRpcResultAsync {
+    Box::new(
+        ChainManager::from_registry()
+            .send(GetNodeStatus)
+            .then(chain_manager_response),
+    )
+}
+
+/// Get the consolidated sessions of the node and the last beacon received from each peer
+pub fn get_peers() -> JsonRpcResultAsync {
+    Box::new(
+        SessionsManager::from_registry()
+            .send(GetConsolidatedPeers)
+            .map_err(internal_error)
+            .and_then(|peers| serde_json::to_value(peers).map_err(internal_error)),
+    )
+}
+
+/// Get the addresses of the peers known by the node
+pub fn get_known_peers() -> JsonRpcResultAsync {
+    Box::new(
+        PeersManager::from_registry()
+            .send(RequestPeers)
             .then(|res| match res {
-                Ok(Ok(output)) => {
-                    let value = match serde_json::to_value(output) {
-                        Ok(x) => x,
-                        Err(e) => {
-                            let err = internal_error(e);
-                            return futures::failed(err);
-                        }
-                    };
-                    futures::finished(value)
-                }
-                Ok(Err(e)) => {
-                    let err = internal_error(e);
-                    futures::failed(err)
-                }
-                Err(e) => {
-                    let err = internal_error(e);
-                    futures::failed(err)
-                }
+                Ok(Ok(peers)) => serde_json::to_value(peers).map_err(internal_error),
+                Ok(Err(e)) => Err(errors::from_failure(&e)),
+                Err(e) => Err(internal_error(e)),
             }),
     )
 }
-*/
+
+/// Get the IP addresses banned by the node and the timestamp when their ban expires
+pub fn get_banned_peers() -> JsonRpcResultAsync {
+    Box::new(
+        SessionsManager::from_registry()
+            .send(GetBannedPeers)
+            .map_err(internal_error)
+            .and_then(|peers| serde_json::to_value(peers).map_err(internal_error)),
+    )
+}
+
+/// Ban an IP address, closing all the sessions with it
+pub fn ban_peer(params: Result<BanPeerParams, jsonrpc_core::Error>) -> JsonRpcResultAsync {
+    let params = match params {
+        Ok(x) => x,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    Box::new(
+        SessionsManager::from_registry()
+            .send(BanPeer {
+                ip: params.ip,
+                duration: params.duration_seconds.map(Duration::from_secs),
+            })
+            .map_err(internal_error)
+            .and_then(|banned_peer| serde_json::to_value(banned_peer).map_err(internal_error)),
+    )
+}
+
+/// Lift the ban of an IP address.
+///
+/// Returns whether the address was banned
+pub fn unban_peer(params: Result<(IpAddr,), jsonrpc_core::Error>) -> JsonRpcResultAsync {
+    let ip = match params {
+        Ok(x) => x.0,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    Box::new(
+        SessionsManager::from_registry()
+            .send(UnbanPeer { ip })
+            .map_err(internal_error)
+            .map(Value::Bool),
+    )
+}
 
 #[cfg(test)]
 mod mock_actix {
@@ -440,7 +868,7 @@ mod tests {
     use futures::sync::mpsc;
 
     #[cfg(test)]
-    use witnet_data_structures::chain::RADRequest;
+    use witnet_data_structures::chain::{Block, RADRequest};
 
     use super::*;
 
@@ -454,7 +882,12 @@ mod tests {
         let subscriptions = Subscriptions::default();
         let (transport_sender, _transport_receiver) = mpsc::channel(0);
         let meta = Arc::new(Session::new(transport_sender));
-        let io = jsonrpc_io_handler(subscriptions);
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
         let response = io.handle_request_sync(empty_string, meta);
         assert_eq!(response, Some(parse_error));
     }
@@ -522,7 +955,7 @@ mod tests {
             backup_witnesses: 0,
             commit_fee: 0,
             data_request: rad_request,
-            pkh: [0; 20],
+            pkh: PublicKeyHash::default(),
             reveal_fee: 0,
             tally_fee: 0,
             time_lock: 0,
@@ -534,17 +967,17 @@ mod tests {
             value: 0,
         });
         let reveal_output = Output::Reveal(RevealOutput {
-            pkh: [0; 20],
+            pkh: PublicKeyHash::default(),
             reveal: [0; 32].to_vec(),
             value: 0,
         });
         let consensus_output = Output::Tally(TallyOutput {
-            pkh: [0; 20],
+            pkh: PublicKeyHash::default(),
             result: [0; 32].to_vec(),
             value: 0,
         });
         let value_transfer_output = Output::ValueTransfer(ValueTransferOutput {
-            pkh: [0; 20],
+            pkh: PublicKeyHash::default(),
             value: 0,
         });
         let inputs = vec![reveal_input, data_request_input, commit_input];
@@ -586,7 +1019,12 @@ mod tests {
         let subscriptions = Subscriptions::default();
         let (transport_sender, _transport_receiver) = mpsc::channel(0);
         let meta = Arc::new(Session::new(transport_sender));
-        let io = jsonrpc_io_handler(subscriptions);
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
         let response = io.handle_request_sync(&msg, meta);
         assert_eq!(response, Some(expected));
     }
@@ -599,7 +1037,12 @@ mod tests {
         let subscriptions = Subscriptions::default();
         let (transport_sender, _transport_receiver) = mpsc::channel(0);
         let meta = Arc::new(Session::new(transport_sender));
-        let io = jsonrpc_io_handler(subscriptions);
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
         let response = io.handle_request_sync(&msg, meta);
         // Compare only the first N characters
         let response =
@@ -617,7 +1060,226 @@ mod tests {
         let subscriptions = Subscriptions::default();
         let (transport_sender, _transport_receiver) = mpsc::channel(0);
         let meta = Arc::new(Session::new(transport_sender));
-        let io = jsonrpc_io_handler(subscriptions);
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
+        let response = io.handle_request_sync(&msg, meta);
+        assert_eq!(response, Some(expected));
+    }
+
+    #[test]
+    fn import_master_key_invalid_mnemonic() {
+        // The mnemonic is validated before replacing the key of the node
+        let msg = r#"{"jsonrpc":"2.0","method":"importMasterKey","params":{"mnemonic":"witnet witnet witnet"},"id":1}"#;
+        let expected =
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
+        let response = io.handle_request_sync(&msg, meta);
+        // Compare only the first N characters
+        let response =
+            response.map(|s| s.chars().take(expected.chars().count()).collect::<String>());
+        assert_eq!(response, Some(expected));
+    }
+
+    #[test]
+    fn get_output_invalid_pointer() {
+        // The output pointer must have the format "<transaction_id>:<output_index>"
+        let msg = r#"{"jsonrpc":"2.0","method":"getOutput","params":["1234"],"id":1}"#;
+        let expected =
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
+        let response = io.handle_request_sync(&msg, meta);
+        // Compare only the first N characters
+        let response =
+            response.map(|s| s.chars().take(expected.chars().count()).collect::<String>());
+        assert_eq!(response, Some(expected));
+    }
+
+    #[test]
+    fn get_data_request_invalid_pointer() {
+        let msg = r#"{"jsonrpc":"2.0","method":"getDataRequest","params":["1234:0"],"id":1}"#;
+        let expected =
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
+        let response = io.handle_request_sync(&msg, meta);
+        // Compare only the first N characters
+        let response =
+            response.map(|s| s.chars().take(expected.chars().count()).collect::<String>());
+        assert_eq!(response, Some(expected));
+    }
+
+    #[test]
+    fn get_balance_missing_address() {
+        let msg = r#"{"jsonrpc":"2.0","method":"getBalance","id":1}"#;
+        let expected =
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
+        let response = io.handle_request_sync(&msg, meta);
+        // Compare only the first N characters
+        let response =
+            response.map(|s| s.chars().take(expected.chars().count()).collect::<String>());
+        assert_eq!(response, Some(expected));
+    }
+
+    #[test]
+    fn get_public_key_invalid_role() {
+        let msg = r#"{"jsonrpc":"2.0","method":"getPublicKey","params":{"role":"wallet"},"id":1}"#;
+        let expected = r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params: unknown variant `wallet`"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
+        let response = io.handle_request_sync(&msg, meta);
+        // Compare only the first N characters
+        let response =
+            response.map(|s| s.chars().take(expected.chars().count()).collect::<String>());
+        assert_eq!(response, Some(expected));
+    }
+
+    #[test]
+    fn export_master_key_invalid_format() {
+        let msg =
+            r#"{"jsonrpc":"2.0","method":"exportMasterKey","params":{"format":"plain"},"id":1}"#;
+        let expected = r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params: unknown variant `plain`"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
+        let response = io.handle_request_sync(&msg, meta);
+        // Compare only the first N characters
+        let response =
+            response.map(|s| s.chars().take(expected.chars().count()).collect::<String>());
+        assert_eq!(response, Some(expected));
+    }
+
+    #[test]
+    fn send_transaction_invalid_params() {
+        let msg =
+            r#"{"jsonrpc":"2.0","method":"sendTransaction","params":[{"signatures":[]}],"id":1}"#;
+        let expected =
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params: missing field"#
+                .to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
+        let response = io.handle_request_sync(&msg, meta);
+        // Compare only the first N characters
+        let response =
+            response.map(|s| s.chars().take(expected.chars().count()).collect::<String>());
+        assert_eq!(response, Some(expected));
+    }
+
+    #[test]
+    fn ban_peer_invalid_ip() {
+        let msg = r#"{"jsonrpc":"2.0","method":"banPeer","params":{"ip":"127.0.0"},"id":1}"#;
+        let expected =
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
+        let response = io.handle_request_sync(&msg, meta);
+        // Compare only the first N characters
+        let response =
+            response.map(|s| s.chars().take(expected.chars().count()).collect::<String>());
+        assert_eq!(response, Some(expected));
+    }
+
+    #[test]
+    fn admin_methods_disabled() {
+        let msg =
+            r#"{"jsonrpc":"2.0","method":"exportMasterKey","params":{"format":"plain"},"id":1}"#;
+        let expected = r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method exportMasterKey is disabled by configuration"},"id":1}"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let enabled_methods = EnabledMethods {
+            admin: false,
+            ..EnabledMethods::all()
+        };
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            enabled_methods,
+            Environment::Testnet1,
+        );
+        let response = io.handle_request_sync(&msg, meta);
+        assert_eq!(response, Some(expected));
+    }
+
+    #[test]
+    fn subscribe_read_only_methods_disabled() {
+        let msg = r#"{"jsonrpc":"2.0","method":"witnet_subscribe","params":["newBlocks"],"id":1}"#;
+        let expected = r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method witnet_subscribe is disabled by configuration"},"id":1}"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let enabled_methods = EnabledMethods {
+            read_only: false,
+            ..EnabledMethods::all()
+        };
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            enabled_methods,
+            Environment::Testnet1,
+        );
         let response = io.handle_request_sync(&msg, meta);
         assert_eq!(response, Some(expected));
     }
@@ -632,7 +1294,12 @@ mod tests {
         let subscriptions = Subscriptions::default();
         let (transport_sender, _transport_receiver) = mpsc::channel(0);
         let meta = Arc::new(Session::new(transport_sender));
-        let io = jsonrpc_io_handler(subscriptions);
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
         let response = io.handle_request_sync(&msg, meta);
         assert_eq!(response, Some(expected));
     }
@@ -645,11 +1312,77 @@ mod tests {
         let subscriptions = Subscriptions::default();
         let (transport_sender, _transport_receiver) = mpsc::channel(0);
         let meta = Arc::new(Session::new(transport_sender));
-        let io = jsonrpc_io_handler(subscriptions);
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
+        let response = io.handle_request_sync(&msg, meta);
+        assert_eq!(response, Some(expected));
+    }
+
+    #[test]
+    fn subscribe_data_request_resolved() {
+        let msg = r#"{"jsonrpc":"2.0","method":"witnet_subscribe","params":["dataRequestResolved"],"id":1}"#;
+        let expected = r#"{"jsonrpc":"2.0","result":"1","id":1}"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let io = jsonrpc_io_handler(
+            subscriptions.clone(),
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
+        let response = io.handle_request_sync(&msg, meta);
+        assert_eq!(response, Some(expected));
+        assert!(subscriptions
+            .lock()
+            .unwrap()
+            .contains_key("dataRequestResolved"));
+    }
+
+    #[test]
+    fn subscribe_sync_status_with_filter() {
+        let msg = r#"{"jsonrpc":"2.0","method":"witnet_subscribe","params":["syncStatus",{"states":["Synced"]}],"id":1}"#;
+        let expected = r#"{"jsonrpc":"2.0","result":"1","id":1}"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
         let response = io.handle_request_sync(&msg, meta);
         assert_eq!(response, Some(expected));
     }
 
+    #[test]
+    fn subscribe_new_epoch_invalid_filter() {
+        // The filter params are validated when subscribing
+        let msg = r#"{"jsonrpc":"2.0","method":"witnet_subscribe","params":["newEpoch",{"every":"day"}],"id":1}"#;
+        let expected =
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let io = jsonrpc_io_handler(
+            subscriptions.clone(),
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
+        let response = io.handle_request_sync(&msg, meta);
+        // Compare only the first N characters
+        let response =
+            response.map(|s| s.chars().take(expected.chars().count()).collect::<String>());
+        assert_eq!(response, Some(expected));
+        assert!(subscriptions.lock().unwrap().is_empty());
+    }
+
     #[test]
     fn unsubscribe_returns_true() {
         // Check that unsubscribe returns true
@@ -658,7 +1391,12 @@ mod tests {
         let subscriptions = Subscriptions::default();
         let (transport_sender, _transport_receiver) = mpsc::channel(0);
         let meta = Arc::new(Session::new(transport_sender));
-        let io = jsonrpc_io_handler(subscriptions);
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
         // But first, subscribe to newBlocks
         let msg1 = r#"{"jsonrpc":"2.0","method":"witnet_subscribe","params":["newBlocks"],"id":1}"#;
         let _response1 = io.handle_request_sync(&msg1, meta.clone());
@@ -674,7 +1412,12 @@ mod tests {
         let subscriptions = Subscriptions::default();
         let (transport_sender, _transport_receiver) = mpsc::channel(0);
         let meta = Arc::new(Session::new(transport_sender));
-        let io = jsonrpc_io_handler(subscriptions);
+        let io = jsonrpc_io_handler(
+            subscriptions,
+            Arc::default(),
+            EnabledMethods::all(),
+            Environment::Testnet1,
+        );
         let response = io.handle_request_sync(&msg, meta);
         assert_eq!(response, Some(expected));
     }
@@ -703,7 +1446,7 @@ mod tests {
             transaction_id: Hash::default(),
         });
         let value_transfer_output = Output::ValueTransfer(ValueTransferOutput {
-            pkh: [0; 20],
+            pkh: PublicKeyHash::default(),
             value: 0,
         });
 
@@ -744,7 +1487,7 @@ mod tests {
             backup_witnesses: 0,
             commit_fee: 0,
             data_request: rad_request,
-            pkh: [0; 20],
+            pkh: PublicKeyHash::default(),
             reveal_fee: 0,
             tally_fee: 0,
             time_lock: 0,
@@ -756,12 +1499,12 @@ mod tests {
             value: 0,
         });
         let reveal_output = Output::Reveal(RevealOutput {
-            pkh: [0; 20],
+            pkh: PublicKeyHash::default(),
             reveal: [0; 32].to_vec(),
             value: 0,
         });
         let consensus_output = Output::Tally(TallyOutput {
-            pkh: [0; 20],
+            pkh: PublicKeyHash::default(),
             result: [0; 32].to_vec(),
             value: 0,
         });
@@ -794,7 +1537,7 @@ mod tests {
         };
         let inv_elem = InventoryItem::Block(block);
         let s = serde_json::to_string(&inv_elem);
-        let expected = r#"{"block":{"block_header":{"version":1,"beacon":{"checkpoint":2,"hash_prev_block":{"SHA256":[4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4]}},"hash_merkle_root":{"SHA256":[3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3]}},"proof":{"block_sig":{"signature":{"Secp256k1":{"der":[]}},"public_key":{"compressed":0,"bytes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}}},"txns":[{"body":{"version":0,"inputs":[{"Commit":{"transaction_id":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"output_index":0,"nonce":0}},{"DataRequest":{"transaction_id":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"output_index":0,"poe":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}},{"Reveal":{"transaction_id":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"output_index":0}}],"outputs":[{"ValueTransfer":{"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"value":0}},{"DataRequest":{"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"data_request":{"not_before":0,"retrieve":[{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0]},{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0]}],"aggregate":{"script":[0]},"consensus":{"script":[0]},"deliver":[{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l2awcd/"},{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l1awcw/"}]},"value":0,"witnesses":0,"backup_witnesses":0,"commit_fee":0,"reveal_fee":0,"tally_fee":0,"time_lock":0}},{"Commit":{"commitment":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"value":0}},{"Reveal":{"reveal":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"value":0}},{"Tally":{"result":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"value":0}}],"hash":null},"signatures":[{"signature":{"Secp256k1":{"der":[]}},"public_key":{"compressed":0,"bytes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}}]}]}}"#;
+        let expected = r#"{"block":{"block_header":{"version":1,"beacon":{"checkpoint":2,"hash_prev_block":{"SHA256":[4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4]}},"hash_merkle_root":{"SHA256":[3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3]}},"proof":{"block_sig":{"signature":{"Secp256k1":{"der":[]}},"public_key":{"compressed":0,"bytes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}}},"txns":[{"body":{"version":0,"inputs":[{"Commit":{"transaction_id":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"output_index":0,"nonce":0}},{"DataRequest":{"transaction_id":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"output_index":0,"poe":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}},{"Reveal":{"transaction_id":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"output_index":0}}],"outputs":[{"ValueTransfer":{"pkh":"twit1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqkz4ay","value":0}},{"DataRequest":{"pkh":"twit1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqkz4ay","data_request":{"not_before":0,"retrieve":[{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0]},{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0]}],"aggregate":{"script":[0]},"consensus":{"script":[0]},"deliver":[{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l2awcd/"},{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l1awcw/"}]},"value":0,"witnesses":0,"backup_witnesses":0,"commit_fee":0,"reveal_fee":0,"tally_fee":0,"time_lock":0}},{"Commit":{"commitment":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"value":0}},{"Reveal":{"reveal":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"pkh":"twit1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqkz4ay","value":0}},{"Tally":{"result":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"pkh":"twit1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqkz4ay","value":0}}],"hash":null},"signatures":[{"signature":{"Secp256k1":{"der":[]}},"public_key":{"compressed":0,"bytes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}}]}]}}"#;
         assert_eq!(s.unwrap(), expected);
     }
 
@@ -839,7 +1582,7 @@ mod tests {
 
         let inv_elem = InventoryItem::Transaction(transaction);
         let s = serde_json::to_string(&inv_elem);
-        let expected = r#"{"transaction":{"body":{"version":0,"inputs":[{"ValueTransfer":{"transaction_id":{"SHA256":[9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9]},"output_index":0}}],"outputs":[{"DataRequest":{"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"data_request":{"not_before":0,"retrieve":[{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0]},{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0]}],"aggregate":{"script":[0]},"consensus":{"script":[0]},"deliver":[{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l2awcd/"},{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l1awcw/"}]},"value":0,"witnesses":0,"backup_witnesses":0,"commit_fee":0,"reveal_fee":0,"tally_fee":0,"time_lock":0}}],"hash":null},"signatures":[{"signature":{"Secp256k1":{"der":[]}},"public_key":{"compressed":0,"bytes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}}]}}"#;
+        let expected = r#"{"transaction":{"body":{"version":0,"inputs":[{"ValueTransfer":{"transaction_id":{"SHA256":[9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9]},"output_index":0}}],"outputs":[{"DataRequest":{"pkh":"twit1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqkz4ay","data_request":{"not_before":0,"retrieve":[{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0]},{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0]}],"aggregate":{"script":[0]},"consensus":{"script":[0]},"deliver":[{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l2awcd/"},{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l1awcw/"}]},"value":0,"witnesses":0,"backup_witnesses":0,"commit_fee":0,"reveal_fee":0,"tally_fee":0,"time_lock":0}}],"hash":null},"signatures":[{"signature":{"Secp256k1":{"der":[]}},"public_key":{"compressed":0,"bytes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}}]}}"#;
         assert_eq!(s.unwrap(), expected);
     }
 
@@ -860,7 +1603,7 @@ mod tests {
             backup_witnesses: 0,
             commit_fee: 0,
             data_request,
-            pkh: [0; 20],
+            pkh: PublicKeyHash::default(),
             reveal_fee: 0,
             tally_fee: 0,
             time_lock: 0,
@@ -1,14 +1,26 @@
+/// Authentication and access control
+pub mod auth;
+/// Typed JSON-RPC client
+pub mod client;
 mod connection;
+/// Error codes
+pub mod errors;
 /// JSON-RPC methods
 pub mod json_rpc_methods;
 mod newline_codec;
 mod server;
+mod transports;
 
 pub use self::server::JsonRpcServer;
 use jsonrpc_core::Value;
+use serde::{de::DeserializeOwned, Deserialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
+use witnet_data_structures::chain::{Environment, Epoch, PublicKeyHash, Transaction};
+
+use crate::actors::chain_manager::StateMachine;
+
 /// Subscriptions. Indexed by method_name, then subscription id
 pub type Subscriptions = Arc<
     Mutex<
@@ -43,3 +55,152 @@ impl From<SubscriptionResult> for jsonrpc_core::Params {
         jsonrpc_core::Params::Map(map)
     }
 }
+
+/// Parse the filter of a subscription from its params. Subscriptions without params use the
+/// default filter, which matches all the notifications.
+pub fn subscription_filter<F>(params: &Value) -> Result<F, jsonrpc_core::Error>
+where
+    F: DeserializeOwned + Default,
+{
+    serde_json::from_value::<Option<F>>(params.clone())
+        .map(Option::unwrap_or_default)
+        .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))
+}
+
+/// Filter of the `newTransactions` subscription
+#[derive(Debug, Default)]
+pub struct NewTransactionsFilter {
+    /// Only notify the transactions with an output to this address
+    pub address: Option<PublicKeyHash>,
+}
+
+/// Params of the `newTransactions` subscription, with the address not yet decoded
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NewTransactionsParams {
+    address: Option<String>,
+}
+
+impl NewTransactionsFilter {
+    /// Parse the filter from the params of a subscription. Like in the rest of the methods, the
+    /// address must be a bech32 address of the environment of the node.
+    pub fn from_params(
+        params: &Value,
+        environment: &Environment,
+    ) -> Result<Self, jsonrpc_core::Error> {
+        let params: NewTransactionsParams = subscription_filter(params)?;
+        let address = params
+            .address
+            .map(|address| {
+                PublicKeyHash::from_bech32(environment, &address)
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))
+            })
+            .transpose()?;
+
+        Ok(Self { address })
+    }
+
+    /// Check if a transaction matches this filter
+    pub fn matches(&self, transaction: &Transaction) -> bool {
+        match self.address {
+            Some(pkh) => transaction
+                .body
+                .outputs
+                .iter()
+                .any(|output| output.pkh() == Some(pkh)),
+            None => true,
+        }
+    }
+}
+
+/// Filter of the `newEpoch` subscription
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewEpochFilter {
+    /// Only notify the epochs which are a multiple of this number
+    pub every: Option<Epoch>,
+}
+
+impl NewEpochFilter {
+    /// Check if an epoch matches this filter
+    pub fn matches(&self, epoch: Epoch) -> bool {
+        match self.every {
+            Some(0) | None => true,
+            Some(every) => epoch % every == 0,
+        }
+    }
+}
+
+/// Filter of the `syncStatus` subscription
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyncStatusFilter {
+    /// Only notify the transitions into one of these states
+    pub states: Option<Vec<StateMachine>>,
+}
+
+impl SyncStatusFilter {
+    /// Check if a transition into a state matches this filter
+    pub fn matches(&self, state: StateMachine) -> bool {
+        match &self.states {
+            Some(states) => states.contains(&state),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_filters_match_everything() {
+        let transactions =
+            NewTransactionsFilter::from_params(&Value::Null, &Environment::Testnet1).unwrap();
+        assert!(transactions.matches(&Transaction::default()));
+
+        let epochs: NewEpochFilter = subscription_filter(&Value::Null).unwrap();
+        assert!(epochs.matches(7));
+
+        let sync_status: SyncStatusFilter = subscription_filter(&Value::Null).unwrap();
+        assert!(sync_status.matches(StateMachine::Synchronizing));
+    }
+
+    #[test]
+    fn new_epoch_filter() {
+        let params = serde_json::json!({ "every": 10 });
+        let filter: NewEpochFilter = subscription_filter(&params).unwrap();
+        assert!(filter.matches(20));
+        assert!(!filter.matches(21));
+    }
+
+    #[test]
+    fn sync_status_filter() {
+        let params = serde_json::json!({ "states": ["Synced"] });
+        let filter: SyncStatusFilter = subscription_filter(&params).unwrap();
+        assert!(filter.matches(StateMachine::Synced));
+        assert!(!filter.matches(StateMachine::WaitingConsensus));
+    }
+
+    #[test]
+    fn new_transactions_filter_invalid_address() {
+        let params = serde_json::json!({ "address": "twit1invalid" });
+        let filter = NewTransactionsFilter::from_params(&params, &Environment::Testnet1);
+        assert!(filter.is_err());
+    }
+
+    #[test]
+    fn new_transactions_filter_environment() {
+        let pkh = PublicKeyHash::from_bytes(&[0xab; 20]).unwrap();
+
+        let params = serde_json::json!({ "address": pkh.bech32(&Environment::Testnet1) });
+        let filter = NewTransactionsFilter::from_params(&params, &Environment::Testnet1).unwrap();
+        assert_eq!(filter.address, Some(pkh));
+
+        // Addresses of other environments and hex public key hashes are rejected
+        let params = serde_json::json!({ "address": pkh.bech32(&Environment::Mainnet) });
+        assert!(NewTransactionsFilter::from_params(&params, &Environment::Testnet1).is_err());
+        let params = serde_json::json!({ "address": "abababababababababababababababababababab" });
+        assert!(NewTransactionsFilter::from_params(&params, &Environment::Testnet1).is_err());
+    }
+}
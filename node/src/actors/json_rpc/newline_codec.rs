@@ -7,8 +7,33 @@ use tokio::codec::{Decoder, Encoder};
 ///
 /// Read until the first newline (`\n`).
 /// The newline is stripped from the returned message.
+/// Messages longer than `max_length` bytes result in an error, so a client
+/// which never sends a newline cannot make the buffer grow without limit.
 #[derive(Debug, Message, Eq, PartialEq, Clone)]
-pub struct NewLineCodec;
+pub struct NewLineCodec {
+    /// Maximum length of a message, not including the newline
+    max_length: usize,
+    /// Index of the first byte which has not been searched for a newline yet
+    // Avoids scanning the same bytes again when a long message arrives in many chunks
+    next_index: usize,
+}
+
+impl NewLineCodec {
+    /// Create a codec which rejects messages longer than `max_length` bytes
+    pub fn new_with_max_length(max_length: usize) -> Self {
+        Self {
+            max_length,
+            next_index: 0,
+        }
+    }
+}
+
+/// By default there is no limit on the length of the messages
+impl Default for NewLineCodec {
+    fn default() -> Self {
+        Self::new_with_max_length(usize::max_value())
+    }
+}
 
 /// Implement decoder trait for NewLineCodec
 impl Decoder for NewLineCodec {
@@ -17,20 +42,38 @@ impl Decoder for NewLineCodec {
 
     /// Method to decode bytes to a request
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let mut ftb: Option<Self::Item> = None;
-
-        let new_line_pos = src.iter().position(|&x| x == b'\n');
-        if let Some(new_line_pos) = new_line_pos {
-            // Split the message at the first newline
-            let mut msg = src.split_to(new_line_pos + 1);
-            // Strip that newline from the returned bytes
-            let _newline = msg.split_off(new_line_pos);
-            ftb = Some(msg);
+        // Only search up to the position where the newline of the longest valid message would be
+        let search_end = src.len().min(self.max_length.saturating_add(1));
+        let new_line_pos = src[self.next_index..search_end]
+            .iter()
+            .position(|&x| x == b'\n')
+            .map(|pos| self.next_index + pos);
+
+        match new_line_pos {
+            Some(new_line_pos) => {
+                self.next_index = 0;
+                // Split the message at the first newline
+                let mut msg = src.split_to(new_line_pos + 1);
+                // Strip that newline from the returned bytes
+                let _newline = msg.split_off(new_line_pos);
+
+                Ok(Some(msg))
+            }
+            None if src.len() > self.max_length => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "JSON-RPC message longer than the maximum of {} bytes",
+                    self.max_length
+                ),
+            )),
+            None => {
+                // If the message is incomplete, return without consuming anything.
+                // This method will be called again when more bytes arrive.
+                self.next_index = search_end;
+
+                Ok(None)
+            }
         }
-        // If the message is incomplete, return without consuming anything.
-        // This method will be called again when more bytes arrive.
-
-        Ok(ftb)
     }
 }
 
@@ -61,7 +104,7 @@ mod tests {
     #[test]
     fn empty() {
         let mut empty_buf = BytesMut::from(vec![]);
-        let mut c = NewLineCodec;
+        let mut c = NewLineCodec::default();
         assert_eq!(None, c.decode(&mut empty_buf).unwrap());
     }
 
@@ -69,7 +112,7 @@ mod tests {
     fn no_newline() {
         let mut input = BytesMut::from(b"abcd".to_vec());
         let original = input.clone();
-        let mut c = NewLineCodec;
+        let mut c = NewLineCodec::default();
         // When there is no newline, the codec returns None
         assert_eq!(None, c.decode(&mut input).unwrap());
         // And the input is left unchanged
@@ -80,7 +123,7 @@ mod tests {
     fn only_newlines() {
         let mut empty_bytes = BytesMut::from(b"\n\n\n\n".to_vec());
         let expected = BytesMut::from(vec![]);
-        let mut c = NewLineCodec;
+        let mut c = NewLineCodec::default();
         // Exactly 4 newlines
         assert_eq!(Some(expected.clone()), c.decode(&mut empty_bytes).unwrap());
         assert_eq!(Some(expected.clone()), c.decode(&mut empty_bytes).unwrap());
@@ -97,7 +140,7 @@ mod tests {
     fn newlines_and_as() {
         let mut empty_bytes = BytesMut::from(b"a\na\na\na\na".to_vec());
         let expected = BytesMut::from(b"a".to_vec());
-        let mut c = NewLineCodec;
+        let mut c = NewLineCodec::default();
         // Exactly 4 newlines
         assert_eq!(Some(expected.clone()), c.decode(&mut empty_bytes).unwrap());
         assert_eq!(Some(expected.clone()), c.decode(&mut empty_bytes).unwrap());
@@ -112,12 +155,58 @@ mod tests {
         assert_eq!(BytesMut::from(b"a".to_vec()), empty_bytes);
     }
 
+    #[test]
+    fn max_length() {
+        let mut input = BytesMut::from(b"abcd\nabcde\n".to_vec());
+        let mut c = NewLineCodec::new_with_max_length(4);
+        // A message of exactly `max_length` bytes is accepted
+        assert_eq!(
+            Some(BytesMut::from(b"abcd".to_vec())),
+            c.decode(&mut input).unwrap()
+        );
+        // But a longer one is rejected
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            c.decode(&mut input).unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn max_length_without_newline() {
+        let mut input = BytesMut::from(b"abcd".to_vec());
+        let mut c = NewLineCodec::new_with_max_length(4);
+        // The message may still be valid if the next byte is a newline
+        assert_eq!(None, c.decode(&mut input).unwrap());
+        input.extend_from_slice(b"e");
+        // Now the message is too long, even if the newline never arrives
+        assert!(c.decode(&mut input).is_err());
+    }
+
+    #[test]
+    fn message_in_chunks() {
+        let mut input = BytesMut::from(b"ab".to_vec());
+        let mut c = NewLineCodec::new_with_max_length(8);
+        assert_eq!(None, c.decode(&mut input).unwrap());
+        input.extend_from_slice(b"cd");
+        assert_eq!(None, c.decode(&mut input).unwrap());
+        input.extend_from_slice(b"\nef\n");
+        assert_eq!(
+            Some(BytesMut::from(b"abcd".to_vec())),
+            c.decode(&mut input).unwrap()
+        );
+        assert_eq!(
+            Some(BytesMut::from(b"ef".to_vec())),
+            c.decode(&mut input).unwrap()
+        );
+        assert_eq!(None, c.decode(&mut input).unwrap());
+    }
+
     #[test]
     fn isomorphic() {
         let mut input = BytesMut::from(b"A long string with some\n newlines.\n".to_vec());
         let original = input.clone();
         let mut decoded = vec![];
-        let mut c = NewLineCodec;
+        let mut c = NewLineCodec::default();
 
         // Decoding a message and encoding it again results in the original message
         while let Some(x) = c.decode(&mut input).unwrap() {
@@ -11,17 +11,37 @@ use tokio::{
 
 use futures::{sync::mpsc, Stream};
 use log::*;
-use std::{collections::HashMap, collections::HashSet, net::SocketAddr, rc::Rc, sync::Arc};
+use std::{
+    collections::HashMap,
+    collections::HashSet,
+    net::SocketAddr,
+    rc::Rc,
+    sync::{atomic::AtomicUsize, Arc},
+    time::Instant,
+};
+use tokio::runtime::Runtime;
 
 use super::{
-    connection::JsonRpc, json_rpc_methods::jsonrpc_io_handler, newline_codec::NewLineCodec,
-    SubscriptionResult, Subscriptions,
+    auth::{self, EnabledMethods},
+    connection::{ConnectionLimits, JsonRpc, RateLimiter},
+    json_rpc_methods::{jsonrpc_io_handler, jsonrpc_io_handler_with_middleware},
+    newline_codec::NewLineCodec,
+    subscription_filter, transports, NewEpochFilter, NewTransactionsFilter, SubscriptionResult,
+    Subscriptions, SyncStatusFilter,
 };
 use crate::{
-    actors::messages::{InboundTcpConnect, NewBlock},
+    actors::{
+        epoch_manager::EpochManager,
+        messages::{
+            DataRequestResolved, EpochNotification, InboundTcpConnect, NewBlock, NewTransaction,
+            Subscribe, SyncStatus,
+        },
+    },
     config_mngr,
 };
 use jsonrpc_pubsub::{PubSubHandler, Session};
+use witnet_config::config::JsonRPC as JsonRpcConfig;
+use witnet_data_structures::chain::Environment;
 
 /// JSON RPC server
 #[derive(Default)]
@@ -30,11 +50,25 @@ pub struct JsonRpcServer {
     server_addr: Option<SocketAddr>,
     /// Open connections, stored as instances of the `JsonRpc` actor
     open_connections: HashSet<Addr<JsonRpc>>,
+    /// Limits applied to the connections
+    limits: ConnectionLimits,
+    /// Token which the clients must send to authenticate, if authentication is enabled
+    auth_token: Option<String>,
+    /// Environment of the addresses in the filters of the subscriptions
+    environment: Environment,
     /// JSON-RPC methods
     // Stored as an `Rc` to avoid creating a new handler for each connection
     jsonrpc_io: Option<Rc<PubSubHandler<Arc<Session>>>>,
     /// List of subscriptions
     subscriptions: Subscriptions,
+    /// Last subscription id, shared by all the transports
+    subscription_ids: Arc<AtomicUsize>,
+    /// Runtime of the HTTP and WebSockets servers
+    runtime: Option<Runtime>,
+    /// HTTP server, which is closed when dropped
+    http_server: Option<transports::HttpServer>,
+    /// WebSockets server, which is closed when dropped
+    ws_server: Option<jsonrpc_ws_server::Server>,
 }
 
 /// Required traits for beInboundTcpConnecting able to retrieve storage manager address from registry
@@ -59,12 +93,34 @@ impl JsonRpcServer {
                 debug!("Starting JSON-RPC interface.");
                 let server_addr = config.jsonrpc.server_address;
                 act.server_addr = Some(server_addr);
+                act.environment = config.environment.clone();
+                act.limits = ConnectionLimits {
+                    max_request_size: config.jsonrpc.max_request_size,
+                    idle_timeout: config.jsonrpc.idle_timeout,
+                    max_connections: usize::from(config.jsonrpc.max_connections),
+                    max_requests_per_second: config.jsonrpc.max_requests_per_second,
+                };
+                act.auth_token = match auth::server_token(&config.jsonrpc) {
+                    Ok(auth_token) => auth_token,
+                    Err(e) => {
+                        error!("Could not write JSON-RPC authentication cookie: {:?}", e);
+                        panic!("Could not write JSON-RPC authentication cookie: {:?}", e);
+                    }
+                };
+                if act.auth_token.is_none() {
+                    info!("JSON-RPC authentication is disabled, any client can call the enabled methods");
+                }
+
                 // Create and store the JSON-RPC method handler
-                let jsonrpc_io = jsonrpc_io_handler(act.subscriptions.clone());
+                let jsonrpc_io = jsonrpc_io_handler(
+                    act.subscriptions.clone(),
+                    act.subscription_ids.clone(),
+                    EnabledMethods::from_config(&config.jsonrpc),
+                    act.environment.clone(),
+                );
                 act.jsonrpc_io = Some(Rc::new(jsonrpc_io));
 
                 // Bind TCP listener to this address
-                // FIXME(#176): running `yes | nc 127.0.0.1 1234` freezes the entire actor system
                 let listener = match TcpListener::bind(&server_addr) {
                     Ok(listener) => listener,
                     Err(e) => {
@@ -84,15 +140,107 @@ impl JsonRpcServer {
                         .map(InboundTcpConnect::new),
                 );
 
+                // Subscribe to all epochs to notify the `newEpoch` subscribers
+                EpochManager::from_registry()
+                    .do_send(Subscribe::to_all(ctx.address(), EveryEpochPayload));
+
                 debug!("JSON-RPC interface is now running at {}", server_addr);
 
+                act.start_http_ws_servers(&config.jsonrpc);
+
                 fut::ok(())
             })
             .map_err(|err, _, _| log::error!("JsonRpcServer config failed: {}", err))
             .wait(ctx);
     }
 
+    /// Start the HTTP and WebSockets servers, if they are enabled in the configuration
+    fn start_http_ws_servers(&mut self, config: &JsonRpcConfig) {
+        if config.http_server_address.is_none() && config.ws_server_address.is_none() {
+            return;
+        }
+
+        let runtime = match transports::runtime(System::current()) {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                error!("Could not start JSON-RPC runtime: {:?}", e);
+                panic!("Could not start JSON-RPC runtime: {:?}", e);
+            }
+        };
+
+        let enabled_methods = EnabledMethods::from_config(config);
+
+        if let Some(addr) = config.http_server_address {
+            match transports::start_http_server(
+                &addr,
+                jsonrpc_io_handler(
+                    self.subscriptions.clone(),
+                    self.subscription_ids.clone(),
+                    enabled_methods,
+                    self.environment.clone(),
+                ),
+                &runtime,
+                config,
+                self.limits,
+                self.auth_token.clone(),
+            ) {
+                Ok(server) => {
+                    debug!("JSON-RPC HTTP interface is now running at {}", addr);
+                    self.http_server = Some(server);
+                }
+                Err(e) => {
+                    error!("Could not start JSON-RPC HTTP server: {:?}", e);
+                    panic!("Could not start JSON-RPC HTTP server: {:?}", e);
+                }
+            }
+        }
+
+        if let Some(addr) = config.ws_server_address {
+            let subscriptions = self.subscriptions.clone();
+            let subscription_ids = self.subscription_ids.clone();
+            let environment = self.environment.clone();
+
+            match transports::start_ws_server(
+                &addr,
+                |middleware| {
+                    jsonrpc_io_handler_with_middleware(
+                        subscriptions,
+                        subscription_ids,
+                        enabled_methods,
+                        environment,
+                        middleware,
+                    )
+                },
+                &runtime,
+                System::current(),
+                config,
+                self.limits,
+                self.auth_token.clone(),
+            ) {
+                Ok(server) => {
+                    debug!("JSON-RPC WebSockets interface is now running at {}", addr);
+                    self.ws_server = Some(server);
+                }
+                Err(e) => {
+                    error!("Could not start JSON-RPC WebSockets server: {:?}", e);
+                    panic!("Could not start JSON-RPC WebSockets server: {:?}", e);
+                }
+            }
+        }
+
+        self.runtime = Some(runtime);
+    }
+
     fn add_connection(&mut self, parent: Addr<JsonRpcServer>, stream: TcpStream) {
+        if self.open_connections.len() >= self.limits.max_connections {
+            // Dropping the stream closes the connection
+            warn!(
+                "Rejecting JSON-RPC connection: limit of {} open connections reached",
+                self.limits.max_connections
+            );
+            return;
+        }
+
         debug!(
             "Add session (currently {} open connections)",
             1 + self.open_connections.len()
@@ -101,17 +249,25 @@ impl JsonRpcServer {
         // Get a reference to the JSON-RPC method handler
         let jsonrpc_io = Rc::clone(self.jsonrpc_io.as_ref().unwrap());
         let (transport_sender, transport_receiver) = mpsc::channel(16);
+        let limits = self.limits;
+        let auth_token = self.auth_token.clone();
 
         // Create a new `JsonRpc` actor which will listen to this stream
         let addr = JsonRpc::create(|ctx| {
             let (r, w) = stream.split();
-            JsonRpc::add_stream(FramedRead::new(r, NewLineCodec), ctx);
+            let codec = NewLineCodec::new_with_max_length(limits.max_request_size);
+            JsonRpc::add_stream(FramedRead::new(r, codec), ctx);
             JsonRpc::add_stream(transport_receiver, ctx);
             JsonRpc {
-                framed: io::FramedWrite::new(w, NewLineCodec, ctx),
+                framed: io::FramedWrite::new(w, NewLineCodec::default(), ctx),
                 parent,
                 jsonrpc_io,
                 session: Arc::new(Session::new(transport_sender)),
+                limits,
+                last_activity: Instant::now(),
+                rate_limiter: RateLimiter::new(limits.max_requests_per_second),
+                auth_token,
+                authenticated: false,
             }
         });
 
@@ -122,6 +278,41 @@ impl JsonRpcServer {
     fn remove_connection(&mut self, addr: &Addr<JsonRpc>) {
         self.open_connections.remove(addr);
     }
+
+    /// Send a notification to the subscribers of a method whose params match the filter
+    fn notify_subscribers<F>(
+        &mut self,
+        ctx: &mut <Self as Actor>::Context,
+        method_name: &str,
+        result: serde_json::Value,
+        filter: F,
+    ) where
+        F: Fn(&serde_json::Value) -> bool,
+    {
+        if let Ok(subs) = self.subscriptions.lock() {
+            let empty_map = HashMap::new();
+            for (subscription, (sink, subscription_params)) in
+                subs.get(method_name).unwrap_or(&empty_map)
+            {
+                if !filter(subscription_params) {
+                    continue;
+                }
+
+                debug!("Sending {} notification!", method_name);
+                let r = SubscriptionResult {
+                    result: result.clone(),
+                    subscription: subscription.clone(),
+                };
+                ctx.spawn(
+                    sink.notify(r.into())
+                        .into_actor(self)
+                        .then(|_res, _act, _ctx| actix::fut::ok(())),
+                );
+            }
+        } else {
+            error!("Failed to adquire lock in {} notification", method_name);
+        }
+    }
 }
 
 impl Actor for JsonRpcServer {
@@ -166,24 +357,67 @@ impl Handler<NewBlock> for JsonRpcServer {
     fn handle(&mut self, msg: NewBlock, ctx: &mut Self::Context) -> Self::Result {
         debug!("Got NewBlock message, sending notifications...");
         let block = serde_json::to_value(msg.block).unwrap();
-        if let Ok(subs) = self.subscriptions.lock() {
-            let empty_map = HashMap::new();
-            for (subscription, (sink, _subscription_params)) in
-                subs.get("newBlocks").unwrap_or(&empty_map)
-            {
-                debug!("Sending NewBlock notification!");
-                let r = SubscriptionResult {
-                    result: block.clone(),
-                    subscription: subscription.clone(),
-                };
-                ctx.spawn(
-                    sink.notify(r.into())
-                        .into_actor(self)
-                        .then(|_res, _act, _ctx| actix::fut::ok(())),
-                );
-            }
-        } else {
-            error!("Failed to adquire lock in NewBlock handle");
-        }
+        self.notify_subscribers(ctx, "newBlocks", block, |_| true);
+    }
+}
+
+impl Handler<DataRequestResolved> for JsonRpcServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: DataRequestResolved, ctx: &mut Self::Context) -> Self::Result {
+        debug!("Got DataRequestResolved message, sending notifications...");
+        let data_request = serde_json::to_value(msg).unwrap();
+        self.notify_subscribers(ctx, "dataRequestResolved", data_request, |_| true);
+    }
+}
+
+impl Handler<NewTransaction> for JsonRpcServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: NewTransaction, ctx: &mut Self::Context) -> Self::Result {
+        debug!("Got NewTransaction message, sending notifications...");
+        let transaction = serde_json::to_value(&msg.transaction).unwrap();
+        let environment = self.environment.clone();
+        self.notify_subscribers(ctx, "newTransactions", transaction, |params| {
+            NewTransactionsFilter::from_params(params, &environment)
+                .map(|filter| filter.matches(&msg.transaction))
+                .unwrap_or(false)
+        });
+    }
+}
+
+impl Handler<SyncStatus> for JsonRpcServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SyncStatus, ctx: &mut Self::Context) -> Self::Result {
+        debug!("Got SyncStatus message, sending notifications...");
+        let status = serde_json::to_value(&msg).unwrap();
+        self.notify_subscribers(ctx, "syncStatus", status, |params| {
+            subscription_filter::<SyncStatusFilter>(params)
+                .map(|filter| filter.matches(msg.current))
+                .unwrap_or(false)
+        });
+    }
+}
+
+/// Payload for the notification for all epochs
+#[derive(Clone, Debug)]
+pub struct EveryEpochPayload;
+
+impl Handler<EpochNotification<EveryEpochPayload>> for JsonRpcServer {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: EpochNotification<EveryEpochPayload>,
+        ctx: &mut Self::Context,
+    ) -> Self::Result {
+        debug!("Got EpochNotification message, sending notifications...");
+        let epoch = serde_json::to_value(msg.checkpoint).unwrap();
+        self.notify_subscribers(ctx, "newEpoch", epoch, |params| {
+            subscription_filter::<NewEpochFilter>(params)
+                .map(|filter| filter.matches(msg.checkpoint))
+                .unwrap_or(false)
+        });
     }
 }
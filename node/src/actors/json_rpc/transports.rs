@@ -0,0 +1,559 @@
+//! HTTP and WebSockets transports for the JSON-RPC server
+//!
+//! Unlike the TCP transport, these servers run in their own threads, outside of the actix
+//! system. The JSON-RPC methods use the current `System` to find the actors, so it must be set
+//! in every thread that runs them.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+use actix::System;
+use futures::{
+    future::Either,
+    sync::{mpsc, oneshot},
+    Future, Sink, Stream,
+};
+use jsonrpc_core::{Middleware, Output, Params, Request, Value, Version};
+use jsonrpc_http_server::{
+    cors::AccessControlAllowOrigin, hyper, DomainsValidation, Host, RequestMiddlewareAction,
+    Response,
+};
+use jsonrpc_pubsub::{PubSubHandler, Session};
+use jsonrpc_ws_server::{ws, Origin, RequestContext, SessionId};
+use log::{debug, warn};
+use tokio::{
+    io::{self, AsyncRead},
+    net::{TcpListener, TcpStream},
+    runtime::{self, Runtime},
+    timer::{Interval, Timeout},
+};
+
+use witnet_config::config::JsonRPC as JsonRpcConfig;
+
+use super::{
+    auth::{bearer_token, tokens_match},
+    connection::{rate_limit_error, ConnectionLimits, RateLimit, RateLimiter},
+};
+
+/// Period between the checks of the idle timeout of the WebSockets connections
+const IDLE_CHECK_PERIOD: Duration = Duration::from_secs(1);
+
+/// Create the runtime which polls the futures of the HTTP and WebSockets servers
+pub fn runtime(system: System) -> Result<Runtime, io::Error> {
+    // `after_start` needs a closure which can be shared between threads
+    let system = Mutex::new(system);
+
+    runtime::Builder::new()
+        .name_prefix("jsonrpc-")
+        .after_start(move || System::set_current(system.lock().unwrap().clone()))
+        .build()
+}
+
+/// Check the `Authorization` header of a request, if authentication is enabled
+fn is_authorized(auth_token: Option<&str>, authorization: Option<&[u8]>) -> bool {
+    match auth_token {
+        None => true,
+        Some(token) => authorization
+            .and_then(|header| std::str::from_utf8(header).ok())
+            .and_then(bearer_token)
+            .map(|received| tokens_match(token, received))
+            .unwrap_or(false),
+    }
+}
+
+/// Error returned by the subscription methods over HTTP
+fn subscriptions_unsupported_error(method: &str) -> jsonrpc_core::Error {
+    jsonrpc_core::Error {
+        code: jsonrpc_core::ErrorCode::MethodNotFound,
+        message: format!(
+            "Method {} is not supported over HTTP, use TCP or WebSockets instead",
+            method
+        ),
+        data: None,
+    }
+}
+
+/// Response sent to the HTTP clients which exceed the rate limit
+const TOO_MANY_REQUESTS_RESPONSE: &[u8] = b"HTTP/1.1 429 Too Many Requests\r\n\
+Content-Type: text/plain; charset=utf-8\r\n\
+Content-Length: 20\r\n\
+Connection: close\r\n\
+\r\n\
+Rate limit exceeded\n";
+
+/// Running JSON-RPC HTTP server, which is closed when dropped
+pub struct HttpServer {
+    /// Server which handles the requests, listening on a local address
+    _server: jsonrpc_http_server::Server,
+    /// Stops accepting the connections of the clients when dropped
+    _stop: oneshot::Sender<()>,
+}
+
+/// Whether a new HTTP connection is handled
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Admission {
+    /// The connection is forwarded to the server
+    Allowed,
+    /// The client exceeded the rate limit, so it is answered with a `429` status
+    RateLimited,
+    /// There are already `max_connections` connections open, so it is closed
+    TooManyConnections,
+}
+
+/// Open connections and rate limits of the clients of the HTTP server
+#[derive(Debug, Default)]
+struct HttpClients {
+    /// Number of open connections
+    connections: usize,
+    /// Rate limit of each IP address
+    rate_limiters: HashMap<IpAddr, RateLimiter>,
+}
+
+impl HttpClients {
+    /// Count a new connection from `ip`. Every connection sends a single request, because they
+    /// are closed after every response, so the rate limit of the requests applies to them
+    fn admit(&mut self, ip: IpAddr, limits: &ConnectionLimits) -> Admission {
+        if self.connections >= limits.max_connections {
+            return Admission::TooManyConnections;
+        }
+
+        // Forget the clients whose window has ended, so that the map does not keep growing
+        self.rate_limiters
+            .retain(|_ip, rate_limiter| !rate_limiter.window_expired());
+        let rate_limit = self
+            .rate_limiters
+            .entry(ip)
+            .or_insert_with(|| RateLimiter::new(limits.max_requests_per_second))
+            .check();
+        if rate_limit != RateLimit::Allowed {
+            return Admission::RateLimited;
+        }
+
+        self.connections += 1;
+
+        Admission::Allowed
+    }
+}
+
+/// Handle a connection of a client of the HTTP server, forwarding it to the server at
+/// `server_addr` if it is admitted
+fn handle_http_connection(
+    client: TcpStream,
+    server_addr: SocketAddr,
+    clients: &Arc<Mutex<HttpClients>>,
+    limits: &ConnectionLimits,
+) {
+    let ip = match client.peer_addr() {
+        Ok(peer_addr) => peer_addr.ip(),
+        Err(e) => {
+            warn!("Failed to get the address of a JSON-RPC HTTP client: {}", e);
+            return;
+        }
+    };
+
+    match clients.lock().unwrap().admit(ip, limits) {
+        Admission::Allowed => {}
+        Admission::RateLimited => {
+            // HTTP requests must always be answered, so they are never dropped
+            warn!("JSON-RPC HTTP client {} exceeded the rate limit", ip);
+            tokio::spawn(
+                io::write_all(client, TOO_MANY_REQUESTS_RESPONSE)
+                    .map(|_| ())
+                    .map_err(|e| debug!("Failed to answer JSON-RPC HTTP client: {}", e)),
+            );
+            return;
+        }
+        Admission::TooManyConnections => {
+            debug!("Rejecting JSON-RPC HTTP connection, too many connections");
+            return;
+        }
+    }
+
+    let clients = Arc::clone(clients);
+    let forward = TcpStream::connect(&server_addr).and_then(move |server| {
+        let (client_reader, client_writer) = client.split();
+        let (server_reader, server_writer) = server.split();
+        // The server closes the connection after the response, which ends the forwarding even
+        // if the client keeps its side open
+        let request = io::copy(client_reader, server_writer)
+            .then(|_| futures::future::empty::<(), io::Error>());
+        let response = io::copy(server_reader, client_writer).map(|_| ());
+
+        response.select(request).map(|_| ()).map_err(|(e, _)| e)
+    });
+
+    // Connections which do not complete their request in time are closed
+    tokio::spawn(Timeout::new(forward, limits.idle_timeout).then(move |res| {
+        if let Err(e) = res {
+            debug!("JSON-RPC HTTP connection closed: {}", e);
+        }
+        clients.lock().unwrap().connections -= 1;
+
+        Ok(())
+    }));
+}
+
+/// Start a JSON-RPC server which accepts requests as HTTP POST
+///
+/// HTTP cannot push notifications to the client, so the subscription methods always fail.
+///
+/// The server listens on a local address, and the clients connect to `addr`, where their
+/// connections are counted and rate limited by IP address before being forwarded to it.
+pub fn start_http_server(
+    addr: &SocketAddr,
+    mut jsonrpc_io: PubSubHandler<Arc<Session>>,
+    runtime: &Runtime,
+    config: &JsonRpcConfig,
+    limits: ConnectionLimits,
+    auth_token: Option<String>,
+) -> Result<HttpServer, io::Error> {
+    // Replace the subscription methods, which would otherwise register a subscription that is
+    // never notified nor removed
+    for name in &["witnet_subscribe", "witnet_unsubscribe"] {
+        jsonrpc_io.add_method(name, move |_params: Params| {
+            Err::<Value, _>(subscriptions_unsupported_error(name))
+        });
+    }
+
+    let listener = TcpListener::bind(addr)?;
+
+    // The server only allows its own address besides `allowed_hosts`, which is the local one
+    // instead of `addr`
+    let mut allowed_hosts: Vec<Host> = config
+        .allowed_hosts
+        .iter()
+        .map(|host| Host::from(host.as_str()))
+        .collect();
+    allowed_hosts.push(Host::from(addr.to_string()));
+    allowed_hosts.push(Host::from(
+        addr.to_string().replace("127.0.0.1", "localhost"),
+    ));
+
+    let server = jsonrpc_http_server::ServerBuilder::with_meta_extractor(
+        jsonrpc_io,
+        |_request: &hyper::Request<hyper::Body>| {
+            // Never used to send notifications, because the subscription methods are disabled
+            let (transport_sender, _transport_receiver) = mpsc::channel(0);
+            Arc::new(Session::new(transport_sender))
+        },
+    )
+    .cors(DomainsValidation::AllowOnly(
+        config
+            .allowed_origins
+            .iter()
+            .map(|origin| AccessControlAllowOrigin::from(origin.as_str()))
+            .collect(),
+    ))
+    .allowed_hosts(DomainsValidation::AllowOnly(allowed_hosts))
+    .request_middleware(move |request: hyper::Request<hyper::Body>| {
+        let authorization = request
+            .headers()
+            .get(hyper::header::AUTHORIZATION)
+            .map(|header| header.as_bytes());
+
+        if !is_authorized(auth_token.as_ref().map(String::as_str), authorization) {
+            warn!("Rejecting unauthorized JSON-RPC HTTP request");
+            RequestMiddlewareAction::from(Response {
+                code: hyper::StatusCode::UNAUTHORIZED,
+                content_type: hyper::header::HeaderValue::from_static("text/plain; charset=utf-8"),
+                content: "Unauthorized\n".to_string(),
+            })
+        } else {
+            RequestMiddlewareAction::from(request)
+        }
+    })
+    .event_loop_executor(runtime.executor())
+    .max_request_body_size(limits.max_request_size)
+    // Close the connection after every response, so that idle clients do not keep it open
+    .keep_alive(false)
+    .start_http(&SocketAddr::from(([127, 0, 0, 1], 0)))?;
+    let server_addr = *server.address();
+
+    let (stop, stopped) = oneshot::channel();
+    let clients = Arc::new(Mutex::new(HttpClients::default()));
+    let accept = listener
+        .incoming()
+        .then(Ok::<_, ()>)
+        .for_each(move |client| {
+            match client {
+                Ok(client) => handle_http_connection(client, server_addr, &clients, &limits),
+                Err(e) => warn!("Failed to accept JSON-RPC HTTP connection: {}", e),
+            }
+
+            Ok(())
+        });
+    runtime.executor().spawn(
+        accept
+            .select(stopped.then(|_| Ok::<_, ()>(())))
+            .then(|_| Ok(())),
+    );
+
+    Ok(HttpServer {
+        _server: server,
+        _stop: stop,
+    })
+}
+
+/// State of an open WebSockets connection
+struct WsConnection {
+    /// Id of the connection, which unlike the address of its session is never reused
+    id: SessionId,
+    /// Rate limit of the requests of this connection
+    rate_limiter: RateLimiter,
+    /// Instant of the last request or notification
+    last_activity: Instant,
+}
+
+/// Open WebSockets connections, indexed by the address of their session
+type WsConnections = Arc<Mutex<HashMap<usize, WsConnection>>>;
+
+/// Key of the connection of `session` in `WsConnections`
+fn session_key(session: &Session) -> usize {
+    session as *const Session as usize
+}
+
+/// Record activity in the connection of `key`
+fn touch(connections: &WsConnections, key: usize) {
+    if let Some(connection) = connections.lock().unwrap().get_mut(&key) {
+        connection.last_activity = Instant::now();
+    }
+}
+
+/// Middleware which applies the rate limit of each WebSockets connection to its requests
+#[derive(Clone, Default)]
+pub struct WsMiddleware {
+    connections: WsConnections,
+}
+
+type WsMiddlewareFuture = Box<dyn Future<Item = Option<jsonrpc_core::Response>, Error = ()> + Send>;
+type WsMiddlewareCallFuture = Box<dyn Future<Item = Option<Output>, Error = ()> + Send>;
+
+impl Middleware<Arc<Session>> for WsMiddleware {
+    type Future = WsMiddlewareFuture;
+    type CallFuture = WsMiddlewareCallFuture;
+
+    fn on_request<F, X>(
+        &self,
+        request: Request,
+        meta: Arc<Session>,
+        next: F,
+    ) -> Either<Self::Future, X>
+    where
+        F: Fn(Request, Arc<Session>) -> X + Send + Sync,
+        X: Future<Item = Option<jsonrpc_core::Response>, Error = ()> + Send + 'static,
+    {
+        let rate_limit = {
+            let mut connections = self.connections.lock().unwrap();
+            match connections.get_mut(&session_key(&meta)) {
+                Some(connection) => {
+                    connection.last_activity = Instant::now();
+                    connection.rate_limiter.check()
+                }
+                None => RateLimit::Allowed,
+            }
+        };
+
+        match rate_limit {
+            RateLimit::Allowed => Either::B(next(request, meta)),
+            RateLimit::Exceeded => {
+                // Answer without handling the request, so that a client sending many requests
+                // cannot keep the actor system busy
+                warn!("JSON-RPC WebSockets connection exceeded the rate limit");
+                Either::A(Box::new(futures::future::ok(Some(
+                    jsonrpc_core::Response::from(rate_limit_error(), Some(Version::V2)),
+                ))))
+            }
+            RateLimit::Dropped => Either::A(Box::new(futures::future::ok(None))),
+        }
+    }
+}
+
+/// Call `close` when the WebSockets connection `id` of `session`, stored with `key` in
+/// `connections`, has been idle for longer than
+/// `idle_timeout`, and forget the state of the connection once it is closed
+fn close_when_idle<C>(
+    session: Weak<Session>,
+    key: usize,
+    id: SessionId,
+    connections: WsConnections,
+    idle_timeout: Duration,
+    close: C,
+) -> impl Future<Item = (), Error = ()> + Send
+where
+    C: Fn() + Send + 'static,
+{
+    Interval::new(Instant::now() + IDLE_CHECK_PERIOD, IDLE_CHECK_PERIOD)
+        .map_err(|e| warn!("Failed to check the idle timeout: {}", e))
+        .take_while(move |_| {
+            let mut connections = connections.lock().unwrap();
+            // The session is dropped when the connection is closed by any of the two sides
+            let open = session.upgrade().is_some();
+            let idle = connections
+                .get(&key)
+                .filter(|connection| connection.id == id)
+                .map_or(true, |connection| {
+                    connection.last_activity.elapsed() >= idle_timeout
+                });
+
+            if open && !idle {
+                return Ok(true);
+            }
+            if open {
+                debug!("Closing idle JSON-RPC WebSockets connection");
+                close();
+            }
+            if connections.get(&key).map(|connection| connection.id) == Some(id) {
+                connections.remove(&key);
+            }
+
+            Ok(false)
+        })
+        .for_each(|_| Ok(()))
+}
+
+/// Start a JSON-RPC server which accepts WebSockets connections
+///
+/// Each connection has its own session, so subscriptions work the same as with TCP. The
+/// handler is created by `jsonrpc_io` with the middleware that applies the rate limit.
+pub fn start_ws_server<F>(
+    addr: &SocketAddr,
+    jsonrpc_io: F,
+    runtime: &Runtime,
+    system: System,
+    config: &JsonRpcConfig,
+    limits: ConnectionLimits,
+    auth_token: Option<String>,
+) -> Result<jsonrpc_ws_server::Server, jsonrpc_ws_server::Error>
+where
+    F: FnOnce(WsMiddleware) -> PubSubHandler<Arc<Session>, WsMiddleware>,
+{
+    let system = Mutex::new(system);
+    let middleware = WsMiddleware::default();
+    let connections = middleware.connections.clone();
+
+    jsonrpc_ws_server::ServerBuilder::with_meta_extractor(
+        jsonrpc_io(middleware),
+        move |context: &RequestContext| {
+            // The requests are handled in the thread of the WebSockets event loop, which is the
+            // same thread that extracts the metadata of the new connections
+            System::set_current(system.lock().unwrap().clone());
+
+            let (transport_sender, transport_receiver) = mpsc::channel(16);
+            let session = Arc::new(Session::new(transport_sender));
+            let key = session_key(&session);
+            connections.lock().unwrap().insert(
+                key,
+                WsConnection {
+                    id: context.session_id,
+                    rate_limiter: RateLimiter::new(limits.max_requests_per_second),
+                    last_activity: Instant::now(),
+                },
+            );
+
+            // Sending notifications keeps the subscriptions alive
+            let notified_connections = connections.clone();
+            context.executor.spawn(
+                transport_receiver
+                    .inspect(move |_| touch(&notified_connections, key))
+                    .forward(context.sender().sink_map_err(|_| ()))
+                    .map(|_| ()),
+            );
+            let out = context.out.clone();
+            context.executor.spawn(close_when_idle(
+                Arc::downgrade(&session),
+                key,
+                context.session_id,
+                connections.clone(),
+                limits.idle_timeout,
+                move || {
+                    let _ = out.close(ws::CloseCode::Away);
+                },
+            ));
+
+            session
+        },
+    )
+    .request_middleware(move |request: &ws::Request| {
+        let authorization = request.header("Authorization").map(Vec::as_slice);
+
+        if is_authorized(auth_token.as_ref().map(String::as_str), authorization) {
+            None
+        } else {
+            warn!("Rejecting unauthorized JSON-RPC WebSockets connection");
+            Some(ws::Response::new(401, "Unauthorized", vec![]))
+        }
+    })
+    .allowed_origins(DomainsValidation::AllowOnly(
+        config
+            .allowed_origins
+            .iter()
+            .map(|origin| Origin::from(origin.as_str()))
+            .collect(),
+    ))
+    .allowed_hosts(DomainsValidation::AllowOnly(
+        config
+            .allowed_hosts
+            .iter()
+            .map(|host| Host::from(host.as_str()))
+            .collect(),
+    ))
+    .event_loop_executor(runtime.executor())
+    .max_connections(limits.max_connections)
+    .max_payload(limits.max_request_size)
+    .start(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> ConnectionLimits {
+        ConnectionLimits {
+            max_connections: 2,
+            max_requests_per_second: 1,
+            ..ConnectionLimits::default()
+        }
+    }
+
+    #[test]
+    fn http_rate_limit_applies_to_each_ip() {
+        let mut clients = HttpClients::default();
+        let limits = limits();
+        let first_ip = IpAddr::from([10, 0, 0, 1]);
+        let second_ip = IpAddr::from([10, 0, 0, 2]);
+
+        assert_eq!(clients.admit(first_ip, &limits), Admission::Allowed);
+        assert_eq!(clients.admit(first_ip, &limits), Admission::RateLimited);
+        assert_eq!(clients.admit(second_ip, &limits), Admission::Allowed);
+    }
+
+    #[test]
+    fn http_connections_are_limited() {
+        let mut clients = HttpClients::default();
+        let limits = limits();
+
+        assert_eq!(
+            clients.admit(IpAddr::from([10, 0, 0, 1]), &limits),
+            Admission::Allowed
+        );
+        assert_eq!(
+            clients.admit(IpAddr::from([10, 0, 0, 2]), &limits),
+            Admission::Allowed
+        );
+        assert_eq!(
+            clients.admit(IpAddr::from([10, 0, 0, 3]), &limits),
+            Admission::TooManyConnections
+        );
+
+        // Closing a connection makes room for a new one
+        clients.connections -= 1;
+        assert_eq!(
+            clients.admit(IpAddr::from([10, 0, 0, 3]), &limits),
+            Admission::Allowed
+        );
+    }
+}
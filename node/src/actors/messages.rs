@@ -2,22 +2,31 @@ use std::{
     fmt,
     fmt::Debug,
     marker::Send,
-    net::SocketAddr,
+    net::{IpAddr, SocketAddr},
     ops::{Bound, RangeBounds},
+    time::Duration,
 };
 
 use actix::{actors::resolver::ResolverError, dev::ToEnvelope, Actor, Addr, Handler, Message};
+use serde::{Deserialize, Serialize};
 use tokio::net::TcpStream;
 
-use witnet_data_structures::chain::{
-    Block, CheckpointBeacon, Epoch, Hash, InventoryEntry, InventoryItem, RADConsensus, RADRequest,
-    Transaction,
+use witnet_data_structures::{
+    chain::{
+        Block, CheckpointBeacon, DataRequestOutput, DataRequestStage, Epoch, Hash, InventoryEntry,
+        InventoryItem, Output, OutputPointer, PublicKeyHash, RADConsensus, RADRequest,
+        SignedBlockHeader, Transaction, TransactionPointer,
+    },
+    compact_block::PartialBlock,
+};
+use witnet_p2p::{
+    bans::Misbehaviour,
+    sessions::{SessionStatus, SessionType},
 };
-use witnet_p2p::sessions::{SessionStatus, SessionType};
 use witnet_rad::error::RadError;
 
 use super::{
-    chain_manager::{ChainManagerError, MAX_BLOCKS_SYNC},
+    chain_manager::{ChainManagerError, StateMachine, MAX_BLOCKS_SYNC},
     epoch_manager::{
         AllEpochSubscription, EpochManagerError, SendableNotification, SingleEpochSubscription,
     },
@@ -44,6 +53,8 @@ impl Message for GetHighestCheckpointBeacon {
 pub struct AddBlocks {
     /// Blocks
     pub blocks: Vec<Block>,
+    /// Peer which sent the blocks, if any
+    pub sender: Option<SocketAddr>,
 }
 
 impl Message for AddBlocks {
@@ -54,20 +65,36 @@ impl Message for AddBlocks {
 pub struct AddCandidates {
     /// Candidates
     pub blocks: Vec<Block>,
+    /// Peer which sent the candidates, if any
+    pub sender: Option<SocketAddr>,
 }
 
 impl Message for AddCandidates {
     type Result = SessionUnitResult;
 }
 
+/// Add the block headers received during the synchronization
+pub struct AddHeaders {
+    /// Headers, in chain order
+    pub headers: Vec<SignedBlockHeader>,
+    /// Peer which sent the headers
+    pub sender: Option<SocketAddr>,
+}
+
+impl Message for AddHeaders {
+    type Result = SessionUnitResult;
+}
+
 /// Add a new transaction
+///
+/// Returns the hash of the transaction if it is valid, or the reason why it was rejected
 pub struct AddTransaction {
     /// Transaction
     pub transaction: Transaction,
 }
 
 impl Message for AddTransaction {
-    type Result = SessionUnitResult;
+    type Result = Result<Hash, failure::Error>;
 }
 
 /// Ask for a block identified by its hash
@@ -80,6 +107,27 @@ impl Message for GetBlock {
     type Result = Result<Block, ChainManagerError>;
 }
 
+/// Fill the missing transactions of a compact block with the ones of the transactions pool
+pub struct FillPartialBlock {
+    /// Block being rebuilt
+    pub partial: PartialBlock,
+}
+
+impl Message for FillPartialBlock {
+    type Result = PartialBlock;
+}
+
+/// Select which of the transactions announced by a peer should be requested to it
+pub struct SelectTransactionsToRequest {
+    /// Hashes of the announced transactions
+    pub hashes: Vec<Hash>,
+}
+
+impl Message for SelectTransactionsToRequest {
+    /// Result: the hashes of the transactions to request, which are marked as requested
+    type Result = Vec<Hash>;
+}
+
 /// Message to obtain a vector of block hashes using a range of epochs
 pub struct GetBlocksEpochRange {
     /// Range of Epochs (prefer using the new method to create a range)
@@ -126,6 +174,141 @@ impl Message for GetBlocksEpochRange {
     type Result = Result<Vec<(Epoch, InventoryEntry)>, ChainManagerError>;
 }
 
+/// Message to obtain the hashes of the blocks which follow a beacon of our chain
+///
+/// Fails with `BlockDoesNotExist` if the beacon is not part of our chain.
+pub struct GetBlocksAfterBeacon {
+    /// Beacon of a block of our chain, or the beacon of an empty chain
+    pub beacon: CheckpointBeacon,
+    /// Maximum blocks limit
+    pub limit: usize,
+}
+
+impl Message for GetBlocksAfterBeacon {
+    type Result = Result<Vec<(Epoch, InventoryEntry)>, ChainManagerError>;
+}
+
+/// Ask for an unspent output identified by its output pointer
+pub struct GetOutput {
+    /// Output pointer
+    pub output_pointer: OutputPointer,
+}
+
+impl Message for GetOutput {
+    type Result = Result<Output, ChainManagerError>;
+}
+
+/// Ask for a transaction identified by its hash, either pending in the mempool or consolidated
+/// in a block
+pub struct GetTransaction {
+    /// Transaction hash
+    pub hash: Hash,
+}
+
+impl Message for GetTransaction {
+    /// Result: the transaction and the location of the block which includes it, or `None` if
+    /// it is still pending
+    type Result = Result<(Transaction, Option<TransactionPointer>), ChainManagerError>;
+}
+
+/// Ask for the unspent outputs locked to a public key hash
+pub struct GetUtxos {
+    /// Public key hash
+    pub pkh: PublicKeyHash,
+}
+
+impl Message for GetUtxos {
+    type Result = Result<Vec<(OutputPointer, Output)>, ChainManagerError>;
+}
+
+/// Ask for the balance of a public key hash
+pub struct GetBalance {
+    /// Public key hash
+    pub pkh: PublicKeyHash,
+}
+
+/// Balance of a public key hash
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Balance {
+    /// Value of the unspent outputs in the UTXO set
+    pub confirmed: u64,
+    /// Confirmed balance updated with the transactions in the mempool
+    pub pending: u64,
+}
+
+impl Message for GetBalance {
+    type Result = Result<Balance, ChainManagerError>;
+}
+
+/// Ask for the status of the node
+pub struct GetNodeStatus;
+
+/// Status of the node
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeStatus {
+    /// Current epoch
+    pub current_epoch: Option<Epoch>,
+    /// Beacon of the last consolidated block
+    pub chain_beacon: Option<CheckpointBeacon>,
+    /// State of the ChainManager
+    pub state: StateMachine,
+    /// Beacon to which the node is trying to catch up
+    pub target_beacon: Option<CheckpointBeacon>,
+    /// Number of transactions in the mempool
+    pub mempool_size: usize,
+    /// Mining enabled
+    pub mining_enabled: bool,
+}
+
+impl Message for GetNodeStatus {
+    type Result = Result<NodeStatus, ChainManagerError>;
+}
+
+/// Ask for the progress of a data request
+pub struct GetDataRequest {
+    /// Output pointer of the data request
+    pub dr_pointer: OutputPointer,
+}
+
+/// Progress of a data request
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DataRequestStatus {
+    /// Current stage of the data request, or `None` if it has already been resolved
+    pub stage: Option<DataRequestStage>,
+    /// Output pointers of the commitments
+    pub commits: Vec<OutputPointer>,
+    /// Output pointers of the reveals
+    pub reveals: Vec<OutputPointer>,
+    /// Output pointer of the tally, once the data request has been resolved
+    pub tally: Option<OutputPointer>,
+    /// Decoded result of the tally, once the data request has been resolved
+    pub result: Option<String>,
+}
+
+impl Message for GetDataRequest {
+    type Result = Result<DataRequestStatus, ChainManagerError>;
+}
+
+/// Ask for the data requests which have not been resolved yet
+pub struct GetActiveDataRequests;
+
+/// Data request which has not been resolved yet
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActiveDataRequest {
+    /// Output pointer of the data request
+    pub dr_pointer: OutputPointer,
+    /// Data request output
+    pub data_request: DataRequestOutput,
+    /// Epoch on which the data request has been or will be unlocked
+    pub epoch: Epoch,
+    /// Progress of the data request
+    pub status: DataRequestStatus,
+}
+
+impl Message for GetActiveDataRequests {
+    type Result = Result<Vec<ActiveDataRequest>, ChainManagerError>;
+}
+
 /// A list of peers and their respective last beacon, used to establish consensus
 pub struct PeersBeacons {
     /// A list of peers and their respective last beacon
@@ -240,6 +423,16 @@ impl Subscribe {
     }
 }
 
+/// Set the current time of the EpochManager, replacing the system clock
+///
+/// From then on, epochs only advance when a new timestamp is set, so that tests can control them.
+/// The subscribers are notified as soon as the timestamp reaches a new epoch.
+#[derive(Message)]
+pub struct SetTimestamp {
+    /// Timestamp, in seconds
+    pub timestamp: i64,
+}
+
 /// Message that the EpochManager sends to subscriber actors to notify a new epoch
 #[derive(Message)]
 pub struct EpochNotification<T: Send> {
@@ -278,8 +471,6 @@ impl Message for GetItem {
 // MESSAGES FROM PEERS MANAGER
 ////////////////////////////////////////////////////////////////////////////////////////
 
-/// One peer
-pub type PeersSocketAddrResult = Result<Option<SocketAddr>, failure::Error>;
 /// One or more peer addresses
 pub type PeersSocketAddrsResult = Result<Vec<SocketAddr>, failure::Error>;
 
@@ -287,12 +478,24 @@ pub type PeersSocketAddrsResult = Result<Vec<SocketAddr>, failure::Error>;
 pub struct AddPeers {
     /// Address of the peer
     pub addresses: Vec<SocketAddr>,
+    /// Address of the peer which announced the addresses, if any
+    pub src_address: Option<SocketAddr>,
 }
 
 impl Message for AddPeers {
     type Result = PeersSocketAddrsResult;
 }
 
+/// Message to move a peer address to the tried table after a successful connection
+pub struct AddTriedPeer {
+    /// Address of the peer
+    pub address: SocketAddr,
+}
+
+impl Message for AddTriedPeer {
+    type Result = Result<(), failure::Error>;
+}
+
 /// Message to remove one or more peer addresses from the list
 pub struct RemovePeers {
     /// Address of the peer
@@ -303,11 +506,14 @@ impl Message for RemovePeers {
     type Result = PeersSocketAddrsResult;
 }
 
-/// Message to get a (random) peer address from the list
-pub struct GetRandomPeer;
+/// Message to get up to `n` different (random) peer addresses from the list
+pub struct GetRandomPeers {
+    /// Maximum number of addresses
+    pub n: usize,
+}
 
-impl Message for GetRandomPeer {
-    type Result = PeersSocketAddrResult;
+impl Message for GetRandomPeers {
+    type Result = PeersSocketAddrsResult;
 }
 
 /// Message to get all the peer addresses from the list
@@ -403,6 +609,32 @@ impl fmt::Display for SendLastBeacon {
     }
 }
 
+/// Message to request the block headers that follow a beacon
+#[derive(Clone, Debug, Message)]
+pub struct SendGetHeaders {
+    /// Beacon of the last known header
+    pub beacon: CheckpointBeacon,
+}
+
+impl fmt::Display for SendGetHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SendGetHeaders")
+    }
+}
+
+/// Message to request some blocks to the peer during the synchronization
+#[derive(Clone, Debug, Message)]
+pub struct RequestBlocks {
+    /// Hashes of the blocks, in chain order
+    pub hashes: Vec<Hash>,
+}
+
+impl fmt::Display for RequestBlocks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RequestBlocks")
+    }
+}
+
 /// Message to close an open session
 #[derive(Clone, Debug, Message)]
 pub struct CloseSession;
@@ -471,6 +703,9 @@ pub struct Consolidate {
 
     /// Session type
     pub session_type: SessionType,
+
+    /// Identity of the peer, if the session is encrypted
+    pub identity: Option<PublicKeyHash>,
 }
 
 impl Message for Consolidate {
@@ -494,6 +729,24 @@ where
     type Result = ();
 }
 
+/// Message indicating a message is to be forwarded to the consolidated outbound session with
+/// the given peer
+pub struct Unicast<T> {
+    /// Address of the peer
+    pub address: SocketAddr,
+    /// Command to be sent to the session
+    pub command: T,
+}
+
+impl<T> Message for Unicast<T>
+where
+    T: Message + Send + Debug,
+    T::Result: Send,
+    Session: Handler<T>,
+{
+    type Result = ();
+}
+
 /// Message indicating a message is to be forwarded to all the consolidated outbound sessions
 pub struct Broadcast<T> {
     /// Command to be sent to all the sessions
@@ -511,6 +764,28 @@ where
     type Result = ();
 }
 
+/// Message to get the consolidated sessions along with the last beacon received from each peer
+pub struct GetConsolidatedPeers;
+
+/// Consolidated session with a peer
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeerInfo {
+    /// Socket address which identifies the peer
+    pub address: SocketAddr,
+    /// Session type
+    pub session_type: SessionType,
+    /// Last beacon received from the peer
+    pub last_beacon: Option<CheckpointBeacon>,
+    /// Round-trip time of the last ping answered by the peer, in milliseconds
+    pub latency_ms: Option<u64>,
+    /// Identity of the peer, authenticated in the handshake if the session is encrypted
+    pub identity: Option<PublicKeyHash>,
+}
+
+impl Message for GetConsolidatedPeers {
+    type Result = Vec<PeerInfo>;
+}
+
 /// Message indicating the last beacon received from a peer
 #[derive(Clone, Debug, Message)]
 pub struct PeerBeacon {
@@ -520,6 +795,63 @@ pub struct PeerBeacon {
     pub beacon: CheckpointBeacon,
 }
 
+/// Message indicating the round-trip time of the last ping answered by a peer
+#[derive(Clone, Debug, Message)]
+pub struct PeerLatency {
+    /// Socket address which identifies the peer
+    pub address: SocketAddr,
+    /// Time between sending the ping and receiving the pong
+    pub latency: Duration,
+}
+
+/// Message reporting a protocol violation committed by a peer
+#[derive(Clone, Debug, Message)]
+pub struct ReportMisbehaviour {
+    /// Socket address which identifies the peer
+    pub address: SocketAddr,
+    /// Protocol violation committed by the peer
+    pub misbehaviour: Misbehaviour,
+}
+
+/// Banned IP address
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BannedPeer {
+    /// IP address of the peer
+    pub ip: IpAddr,
+    /// Timestamp when the ban expires
+    pub until: i64,
+}
+
+/// Message to get the IP addresses which are currently banned
+pub struct GetBannedPeers;
+
+impl Message for GetBannedPeers {
+    type Result = Vec<BannedPeer>;
+}
+
+/// Message to ban an IP address, disconnecting all the sessions with it
+pub struct BanPeer {
+    /// IP address of the peer
+    pub ip: IpAddr,
+    /// Duration of the ban, or `None` to use the one from the configuration
+    pub duration: Option<Duration>,
+}
+
+impl Message for BanPeer {
+    type Result = BannedPeer;
+}
+
+/// Message to lift the ban of an IP address
+pub struct UnbanPeer {
+    /// IP address of the peer
+    pub ip: IpAddr,
+}
+
+impl Message for UnbanPeer {
+    /// Whether the address was banned
+    type Result = bool;
+}
+
 // JsonRpcServer messages (notifications)
 
 /// New block notification
@@ -528,3 +860,29 @@ pub struct NewBlock {
     /// Block
     pub block: Block,
 }
+
+/// New transaction notification
+#[derive(Message)]
+pub struct NewTransaction {
+    /// Transaction admitted into the mempool
+    pub transaction: Transaction,
+}
+
+/// Synchronization status notification
+#[derive(Debug, Message, Serialize, Deserialize)]
+pub struct SyncStatus {
+    /// State of the ChainManager before the transition
+    pub previous: StateMachine,
+    /// State of the ChainManager after the transition
+    pub current: StateMachine,
+}
+
+/// Resolved data request notification
+#[derive(Debug, Message, Serialize, Deserialize)]
+pub struct DataRequestResolved {
+    /// Output pointer of the data request
+    pub dr_pointer: OutputPointer,
+    /// Final status of the data request
+    #[serde(flatten)]
+    pub status: DataRequestStatus,
+}
@@ -38,6 +38,17 @@ pub fn run(
         _ => (),
     }
 
+    start_actors();
+
+    // Run system
+    system.run();
+
+    Ok(())
+}
+
+/// Function to start the actors of the node in the current system, which must have a running
+/// ConfigManager
+pub fn start_actors() {
     storage_mngr::start();
     signature_mngr::start();
 
@@ -72,11 +83,6 @@ pub fn run(
     // Start JSON RPC server
     let json_rpc_server_addr = JsonRpcServer::default().start();
     System::current().registry().set(json_rpc_server_addr);
-
-    // Run system
-    system.run();
-
-    Ok(())
 }
 
 /// Function to close the main system
@@ -40,16 +40,16 @@ impl Actor for PeersManager {
                     .map_err(|e, _, _| error!("Couldn't get peers from storage: {}", e))
                     .and_then(|peers_from_storage, act, _| {
                         // peers_from_storage can be None if the storage does not contain that key
-                        if let Some(peers_from_storage) = peers_from_storage {
-                            // Add all the peers from storage
-                            // The add method handles duplicates by overwriting the old values
-                            let peers = peers_from_storage.get_all().unwrap();
+                        if let Some(mut peers_from_storage) = peers_from_storage {
+                            // Restore the new and tried tables from storage, keeping their
+                            // timestamps, and add the peers from config to them
                             info!(
                                 "Adding the following peer addresses from storage: {:?}",
-                                peers
+                                peers_from_storage.get_all().unwrap()
                             );
-                            match act.peers.add(peers) {
-                                Ok(_duplicated_peers) => {}
+                            let peers = act.peers.get_all().unwrap();
+                            match peers_from_storage.add(peers) {
+                                Ok(_duplicated_peers) => act.peers = peers_from_storage,
                                 Err(e) => {
                                     error!("Error when adding peer addresses from storage: {}", e);
                                 }
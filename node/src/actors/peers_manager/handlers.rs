@@ -3,8 +3,7 @@ use log::{debug, error, warn};
 
 use super::PeersManager;
 use crate::actors::messages::{
-    AddPeers, GetRandomPeer, PeersSocketAddrResult, PeersSocketAddrsResult, RemovePeers,
-    RequestPeers,
+    AddPeers, AddTriedPeer, GetRandomPeers, PeersSocketAddrsResult, RemovePeers, RequestPeers,
 };
 
 /// Handler for AddPeers message
@@ -14,7 +13,20 @@ impl Handler<AddPeers> for PeersManager {
     fn handle(&mut self, msg: AddPeers, _: &mut Context<Self>) -> Self::Result {
         // Insert address
         debug!("Adding the following peer addresses: {:?}", msg.addresses);
-        self.peers.add(msg.addresses)
+        match msg.src_address {
+            Some(src_address) => self.peers.add_from(msg.addresses, src_address),
+            None => self.peers.add(msg.addresses),
+        }
+    }
+}
+
+/// Handler for AddTriedPeer message
+impl Handler<AddTriedPeer> for PeersManager {
+    type Result = Result<(), failure::Error>;
+
+    fn handle(&mut self, msg: AddTriedPeer, _: &mut Context<Self>) -> Self::Result {
+        debug!("Moving the peer address {} to the tried table", msg.address);
+        self.peers.mark_success(msg.address)
     }
 }
 
@@ -29,27 +41,35 @@ impl Handler<RemovePeers> for PeersManager {
     }
 }
 
-/// Handler for GetRandomPeer message
-impl Handler<GetRandomPeer> for PeersManager {
-    type Result = PeersSocketAddrResult;
+/// Handler for GetRandomPeers message
+impl Handler<GetRandomPeers> for PeersManager {
+    type Result = PeersSocketAddrsResult;
 
-    fn handle(&mut self, _msg: GetRandomPeer, _: &mut Context<Self>) -> Self::Result {
-        let result = self.peers.get_random();
+    fn handle(&mut self, msg: GetRandomPeers, _: &mut Context<Self>) -> Self::Result {
+        let mut addresses = vec![];
 
-        match result {
-            Ok(Some(address)) => {
-                debug!("Selected a random peer address: {:?}", address);
-                result
-            }
-            Ok(None) => {
-                warn!("Could not select a random peer address because there were none");
-                result
-            }
-            error => {
-                error!("Error selecting a random peer address: {:?}", error);
-                error
+        for _ in 0..msg.n {
+            match self.peers.get_random() {
+                Ok(Some(address)) => {
+                    if !addresses.contains(&address) {
+                        addresses.push(address);
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Error selecting a random peer address: {:?}", e);
+                    return Err(e);
+                }
             }
         }
+
+        if addresses.is_empty() {
+            warn!("Could not select a random peer address because there were none");
+        } else {
+            debug!("Selected random peer addresses: {:?}", addresses);
+        }
+
+        Ok(addresses)
     }
 }
 
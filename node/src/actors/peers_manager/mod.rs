@@ -10,14 +10,16 @@ use log::{debug, error};
 use crate::actors::storage_keys::PEERS_KEY;
 use crate::storage_mngr;
 use witnet_p2p::peers::Peers;
+use witnet_util::timestamp::get_timestamp;
 
 // Internal Actor implementation for PeersManager
 mod actor;
 
 /// Handlers to manage the previous messages using the `peers` library:
 /// * Add peers
+/// * Add tried peer
 /// * Remove peers
-/// * Get random peer
+/// * Get random peers
 /// * Get all peers
 mod handlers;
 
@@ -39,6 +41,12 @@ impl PeersManager {
     fn persist_peers(&self, ctx: &mut Context<Self>, storage_peers_period: Duration) {
         // Schedule the discovery_peers with a given period
         ctx.run_later(storage_peers_period, move |act, ctx| {
+            // Age out the addresses which have not been seen for a long time
+            let stale_peers = act.peers.remove_stale(get_timestamp());
+            if !stale_peers.is_empty() {
+                debug!("Removed {} stale peer addresses", stale_peers.len());
+            }
+
             storage_mngr::put(&PEERS_KEY, &act.peers)
                 .into_actor(act)
                 .and_then(|_, _, _| {
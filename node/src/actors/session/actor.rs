@@ -1,10 +1,12 @@
+use std::time::Instant;
+
 use actix::{
     Actor, ActorContext, ActorFuture, AsyncContext, Context, ContextFutureSpawner, Running, System,
     WrapFuture,
 };
 use log::{debug, error, info, warn};
 
-use witnet_data_structures::types::Message as WitnetMessage;
+use witnet_data_structures::types::{Command, Message as WitnetMessage, Ping};
 use witnet_p2p::sessions::{SessionStatus, SessionType};
 
 use super::{handlers::EveryEpochPayload, Session};
@@ -14,6 +16,7 @@ use crate::actors::{
     messages::{AddBlocks, GetEpoch, Register, Subscribe, Unregister},
     sessions_manager::SessionsManager,
 };
+use crate::signature_mngr;
 use witnet_util::timestamp::pretty_print;
 
 /// Implement actor trait for Session
@@ -38,6 +41,23 @@ impl Actor for Session {
 
         self.subscribe_to_epoch_manager(ctx);
 
+        // Sign the ephemeral key offered to encrypt the session. `AsyncContext::wait` makes sure
+        // that it is signed before any version message is sent or processed
+        if let Some(ephemeral_key) = self.encryption.ephemeral_public_key() {
+            signature_mngr::sign(&ephemeral_key)
+                .into_actor(self)
+                .map_err(|e, act, _ctx| {
+                    warn!(
+                        "Failed to sign the ephemeral key, session with peer {} will not be \
+                         encrypted: {}",
+                        act.remote_addr, e
+                    );
+                    act.encryption.disable();
+                })
+                .map(|keyed_signature, act, _ctx| act.encryption.set_signature(keyed_signature))
+                .wait(ctx);
+        }
+
         // Get SessionsManager address
         let sessions_manager_addr = System::current().registry().get::<SessionsManager>();
 
@@ -74,13 +94,7 @@ impl Actor for Session {
             .and_then(|_, act, _ctx| {
                 // Send version if outbound session
                 if let SessionType::Outbound = act.session_type {
-                    // FIXME(#142): include the checkpoint of the current tip of the local blockchain
-                    let version_msg = WitnetMessage::build_version(
-                        act.magic_number,
-                        act.server_addr,
-                        act.remote_addr,
-                        0,
-                    );
+                    let version_msg = act.build_version_message();
                     act.send_message(version_msg);
                     // Set HandshakeFlag of sent version message
                     act.handshake_flags.version_tx = true;
@@ -108,7 +122,10 @@ impl Actor for Session {
             // Get ChainManager address
             let chain_manager_addr = System::current().registry().get::<ChainManager>();
 
-            chain_manager_addr.do_send(AddBlocks { blocks: vec![] });
+            chain_manager_addr.do_send(AddBlocks {
+                blocks: vec![],
+                sender: None,
+            });
             warn!("Session disconnected during block exchange");
         }
 
@@ -161,4 +178,39 @@ impl Session {
             })
             .wait(ctx);
     }
+
+    /// Periodically send a ping to the peer, disconnecting it if it does not reply in time
+    pub(super) fn ping_peer(&self, ctx: &mut Context<Session>) {
+        ctx.run_later(self.ping_period, |act, ctx| {
+            act.send_ping(ctx);
+            act.ping_peer(ctx);
+        });
+    }
+
+    /// Send a ping to the peer, unless the previous one has not been answered yet
+    fn send_ping(&mut self, ctx: &mut Context<Session>) {
+        if self.pending_ping.is_some() {
+            return;
+        }
+
+        let ping_msg = WitnetMessage::build_ping(self.magic_number);
+        if let Command::Ping(Ping { nonce }) = ping_msg.kind {
+            self.pending_ping = Some((nonce, Instant::now()));
+
+            // Disconnect the peer if this ping is still pending after the timeout
+            ctx.run_later(self.ping_timeout, move |act, ctx| {
+                if let Some((pending_nonce, _)) = act.pending_ping {
+                    if pending_nonce == nonce {
+                        info!(
+                            "Ping timeout expired, disconnecting session with peer {:?} \
+                             (last latency: {:?})",
+                            act.remote_addr, act.latency
+                        );
+                        ctx.stop();
+                    }
+                }
+            });
+        }
+        self.send_message(ping_msg);
+    }
 }
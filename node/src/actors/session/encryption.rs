@@ -0,0 +1,429 @@
+//! Encryption of the session
+//!
+//! Nodes offer their peers to encrypt the session by sending an ephemeral key, signed by the
+//! identity key of the node, in their version message. If both peers offer it, each one of them
+//! encrypts the messages it sends after its verack message, and decrypts the messages received
+//! after the verack message of the peer. See `witnet_crypto::transport` for the details of the
+//! key exchange.
+//!
+//! The keys of the session are bound to both version messages, so tampering with any of them
+//! leaves the peers unable to decrypt each other's messages. Nodes can also require their
+//! sessions to be encrypted, and restrict them to a set of trusted peer identities.
+use std::collections::HashSet;
+
+use log::error;
+
+use witnet_crypto::{
+    hash::{calculate_sha256, Sha256},
+    transport::{self, verify_ephemeral_key, CipherState, EphemeralKey},
+};
+use witnet_data_structures::{
+    chain::{KeyedSignature, PublicKey, PublicKeyHash, Signature},
+    types::EncryptionKey,
+};
+
+use super::HandshakeError;
+use crate::actors::codec::BytesMut;
+
+/// Encryption settings of the sessions, taken from the `connections` configuration
+#[derive(Clone, Debug, Default)]
+pub struct EncryptionPolicy {
+    /// Offer the peers to encrypt the session
+    pub enabled: bool,
+    /// Reject the peers which do not offer to encrypt the session
+    pub required: bool,
+    /// Identities of the only peers allowed to complete the handshake, any if empty
+    pub trusted_peers: HashSet<PublicKeyHash>,
+}
+
+impl EncryptionPolicy {
+    /// Whether unencrypted sessions must be rejected. Trusting only some peers requires
+    /// encryption, because the identity of the peer is only authenticated in encrypted sessions
+    pub fn is_required(&self) -> bool {
+        self.required || !self.trusted_peers.is_empty()
+    }
+}
+
+/// State of the encryption of a session
+pub struct Encryption {
+    /// Ephemeral key of the session, if this node offers to encrypt it
+    ephemeral_key: Option<EphemeralKey>,
+    /// Ephemeral key signed by the identity key of the node, sent in the version message
+    encryption_key: EncryptionKey,
+    /// Cipher for the messages sent to the peer
+    send: Option<CipherState>,
+    /// Cipher for the messages received from the peer
+    receive: Option<CipherState>,
+    /// Cipher for the messages sent to the peer, waiting for the verack message to be sent
+    pending_send: Option<CipherState>,
+    /// Cipher for the messages received from the peer, waiting for its verack message
+    pending_receive: Option<CipherState>,
+    /// Identity key of the peer, once it has been authenticated
+    peer_identity: Option<PublicKey>,
+    /// Version message sent to the peer, serialized, which is bound to the keys of the session
+    local_version: Vec<u8>,
+    /// Reject the peer if the session cannot be encrypted
+    required: bool,
+    /// Identities of the only peers allowed to complete the handshake, any if empty
+    trusted_peers: HashSet<PublicKeyHash>,
+}
+
+impl Encryption {
+    /// Create the encryption state of a new session, generating an ephemeral key if this node
+    /// offers to encrypt the session
+    pub fn new(policy: EncryptionPolicy) -> Self {
+        let required = policy.is_required();
+        let ephemeral_key = if policy.enabled || required {
+            EphemeralKey::generate()
+                .map_err(|e| error!("Failed to generate ephemeral key: {}", e))
+                .ok()
+        } else {
+            None
+        };
+
+        Encryption {
+            ephemeral_key,
+            encryption_key: EncryptionKey::default(),
+            send: None,
+            receive: None,
+            pending_send: None,
+            pending_receive: None,
+            peer_identity: None,
+            local_version: vec![],
+            required,
+            trusted_peers: policy.trusted_peers,
+        }
+    }
+
+    /// Ephemeral public key to be signed by the identity key of the node
+    pub fn ephemeral_public_key(&self) -> Option<Vec<u8>> {
+        self.ephemeral_key.as_ref().map(EphemeralKey::public_key)
+    }
+
+    /// Set the signature of the ephemeral key, so that it can be offered to the peer
+    pub fn set_signature(&mut self, keyed_signature: KeyedSignature) {
+        let ephemeral_key = match self.ephemeral_public_key() {
+            Some(ephemeral_key) => ephemeral_key,
+            None => return,
+        };
+        let Signature::Secp256k1(signature) = keyed_signature.signature;
+        let mut identity_key = vec![keyed_signature.public_key.compressed];
+        identity_key.extend_from_slice(&keyed_signature.public_key.bytes);
+
+        self.encryption_key = EncryptionKey {
+            ephemeral_key,
+            identity_key,
+            signature: signature.der,
+        };
+    }
+
+    /// Do not offer to encrypt the session
+    pub fn disable(&mut self) {
+        self.ephemeral_key = None;
+        self.encryption_key = EncryptionKey::default();
+    }
+
+    /// Encryption key to be sent in the version message, empty if this node does not offer to
+    /// encrypt the session
+    pub fn encryption_key(&self) -> EncryptionKey {
+        self.encryption_key.clone()
+    }
+
+    /// Set the serialized version message sent to the peer, which must be done before
+    /// negotiating the keys of the session
+    pub fn set_local_version(&mut self, bytes: Vec<u8>) {
+        self.local_version = bytes;
+    }
+
+    /// Agree on the keys of the session with the encryption key sent by the peer in its version
+    /// message. `peer_version` is the serialized version message of the peer, and `initiator`
+    /// is true for outbound sessions.
+    ///
+    /// The session stays unencrypted unless both peers offer to encrypt it, which is an error
+    /// if encryption is required. Once encrypted, the identity of the peer must be trusted.
+    pub(super) fn negotiate(
+        &mut self,
+        peer_key: &EncryptionKey,
+        peer_version: &[u8],
+        initiator: bool,
+    ) -> Result<(), HandshakeError> {
+        let ephemeral_key = match &self.ephemeral_key {
+            Some(ephemeral_key) if !self.encryption_key.is_empty() => ephemeral_key,
+            _ if self.required => return Err(HandshakeError::EncryptionRequired),
+            _ => return Ok(()),
+        };
+        if peer_key.is_empty() {
+            if self.required {
+                return Err(HandshakeError::EncryptionRequired);
+            }
+            return Ok(());
+        }
+
+        let identity = verify_ephemeral_key(
+            &peer_key.identity_key,
+            &peer_key.ephemeral_key,
+            &peer_key.signature,
+        )
+        .map_err(|error| HandshakeError::InvalidEncryptionKey { error })?;
+        let identity = PublicKey::from(identity);
+        if !self.trusted_peers.is_empty() && !self.trusted_peers.contains(&identity.pkh()) {
+            return Err(HandshakeError::UntrustedPeer {
+                identity: identity.pkh(),
+            });
+        }
+
+        // Both peers hash the version messages in the same order: first the one sent by the
+        // initiator of the connection. The hashes have a fixed length, so the concatenation is
+        // not ambiguous
+        let Sha256(local_version) = calculate_sha256(&self.local_version);
+        let Sha256(peer_version) = calculate_sha256(peer_version);
+        let mut handshake = Vec::with_capacity(64);
+        if initiator {
+            handshake.extend_from_slice(&local_version);
+            handshake.extend_from_slice(&peer_version);
+        } else {
+            handshake.extend_from_slice(&peer_version);
+            handshake.extend_from_slice(&local_version);
+        }
+        let (send, receive) = ephemeral_key
+            .agree(&peer_key.ephemeral_key, initiator, &handshake)
+            .map_err(|error| HandshakeError::InvalidEncryptionKey { error })?;
+
+        self.pending_send = Some(send);
+        self.pending_receive = Some(receive);
+        self.peer_identity = Some(identity);
+
+        Ok(())
+    }
+
+    /// Start encrypting the messages sent to the peer, once the verack message has been sent
+    pub fn start_sending(&mut self) {
+        if let Some(send) = self.pending_send.take() {
+            self.send = Some(send);
+        }
+    }
+
+    /// Start decrypting the messages received from the peer, once its verack message has been
+    /// received
+    pub fn start_receiving(&mut self) {
+        if let Some(receive) = self.pending_receive.take() {
+            self.receive = Some(receive);
+        }
+    }
+
+    /// Identity key of the peer, if the session is encrypted
+    pub fn peer_identity(&self) -> Option<&PublicKey> {
+        self.peer_identity.as_ref()
+    }
+
+    /// Encrypt a message to be sent to the peer, if the session is encrypted
+    pub fn encrypt(&mut self, bytes: Vec<u8>) -> Result<Vec<u8>, transport::Error> {
+        match &mut self.send {
+            Some(cipher) => cipher.encrypt(&bytes),
+            None => Ok(bytes),
+        }
+    }
+
+    /// Decrypt a message received from the peer, if the session is encrypted
+    pub fn decrypt(&mut self, bytes: BytesMut) -> Result<BytesMut, transport::Error> {
+        match &mut self.receive {
+            Some(cipher) => cipher.decrypt(&bytes).map(BytesMut::from),
+            None => Ok(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secp256k1::{PublicKey as Secp256k1_PublicKey, Secp256k1, SecretKey};
+    use witnet_crypto::signature::sign;
+
+    use super::*;
+
+    fn policy(enabled: bool) -> EncryptionPolicy {
+        EncryptionPolicy {
+            enabled,
+            ..EncryptionPolicy::default()
+        }
+    }
+
+    /// Identity of the peer whose ephemeral key is signed with `sign_ephemeral_key`
+    fn identity_pkh(identity: u8) -> PublicKeyHash {
+        let secret_key = SecretKey::from_slice(&[identity; 32]).unwrap();
+        let public_key = Secp256k1_PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+
+        PublicKey::from(public_key).pkh()
+    }
+
+    /// Sign the ephemeral key of the session as the SignatureManager would do
+    fn sign_ephemeral_key(encryption: &mut Encryption, identity: u8) {
+        let secret_key = SecretKey::from_slice(&[identity; 32]).unwrap();
+        let public_key = Secp256k1_PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+        let Sha256(data) = calculate_sha256(&encryption.ephemeral_public_key().unwrap());
+
+        encryption.set_signature(KeyedSignature {
+            signature: Signature::from(sign(secret_key, &data)),
+            public_key: PublicKey::from(public_key),
+        });
+    }
+
+    /// Negotiate the keys of the session as the handshake would do, given the version messages
+    /// received by each peer
+    fn negotiate(
+        outbound: &mut Encryption,
+        inbound: &mut Encryption,
+        outbound_version: &[u8],
+        inbound_version: &[u8],
+    ) -> Result<(), HandshakeError> {
+        outbound.set_local_version(b"VERSION OUTBOUND".to_vec());
+        inbound.set_local_version(b"VERSION INBOUND".to_vec());
+        inbound.negotiate(&outbound.encryption_key(), outbound_version, false)?;
+        inbound.start_sending();
+        outbound.negotiate(&inbound.encryption_key(), inbound_version, true)?;
+        outbound.start_sending();
+        outbound.start_receiving();
+        inbound.start_receiving();
+
+        Ok(())
+    }
+
+    fn handshake(outbound: &mut Encryption, inbound: &mut Encryption) {
+        negotiate(outbound, inbound, b"VERSION OUTBOUND", b"VERSION INBOUND").unwrap();
+    }
+
+    #[test]
+    fn session_encrypted_if_both_peers_offer_it() {
+        let mut outbound = Encryption::new(policy(true));
+        let mut inbound = Encryption::new(policy(true));
+        sign_ephemeral_key(&mut outbound, 1);
+        sign_ephemeral_key(&mut inbound, 2);
+
+        handshake(&mut outbound, &mut inbound);
+        assert!(inbound.peer_identity().is_some());
+        assert_eq!(
+            outbound.peer_identity().unwrap().bytes[..],
+            inbound.encryption_key().identity_key[1..]
+        );
+
+        let encrypted = outbound.encrypt(b"VERSION".to_vec()).unwrap();
+        assert_ne!(encrypted, b"VERSION".to_vec());
+        assert_eq!(
+            inbound.decrypt(BytesMut::from(encrypted)).unwrap(),
+            BytesMut::from(&b"VERSION"[..])
+        );
+    }
+
+    #[test]
+    fn session_not_encrypted_if_a_peer_does_not_offer_it() {
+        let mut outbound = Encryption::new(policy(true));
+        let mut inbound = Encryption::new(policy(false));
+        sign_ephemeral_key(&mut outbound, 1);
+        assert!(inbound.encryption_key().is_empty());
+
+        handshake(&mut outbound, &mut inbound);
+        assert_eq!(outbound.peer_identity(), None);
+        assert_eq!(inbound.peer_identity(), None);
+        assert_eq!(
+            outbound.encrypt(b"PING".to_vec()).unwrap(),
+            b"PING".to_vec()
+        );
+    }
+
+    #[test]
+    fn forged_encryption_key_rejected() {
+        let mut outbound = Encryption::new(policy(true));
+        let mut inbound = Encryption::new(policy(true));
+        sign_ephemeral_key(&mut outbound, 1);
+        sign_ephemeral_key(&mut inbound, 2);
+
+        // Replace the ephemeral key with another one, keeping the signature
+        let mut forged_key = outbound.encryption_key();
+        forged_key.ephemeral_key = Encryption::new(policy(true))
+            .ephemeral_public_key()
+            .unwrap();
+
+        assert_eq!(
+            inbound.negotiate(&forged_key, b"VERSION OUTBOUND", false),
+            Err(HandshakeError::InvalidEncryptionKey {
+                error: transport::Error::InvalidSignature
+            })
+        );
+    }
+
+    #[test]
+    fn tampered_version_leads_to_different_keys() {
+        let mut outbound = Encryption::new(policy(true));
+        let mut inbound = Encryption::new(policy(true));
+        sign_ephemeral_key(&mut outbound, 1);
+        sign_ephemeral_key(&mut inbound, 2);
+
+        // The version message of the outbound peer was modified on its way to the inbound one
+        negotiate(
+            &mut outbound,
+            &mut inbound,
+            b"VERSION TAMPERED",
+            b"VERSION INBOUND",
+        )
+        .unwrap();
+
+        let encrypted = outbound.encrypt(b"VERSION".to_vec()).unwrap();
+        assert!(inbound.decrypt(BytesMut::from(encrypted)).is_err());
+    }
+
+    #[test]
+    fn required_encryption_rejects_unencrypted_peer() {
+        let mut outbound = Encryption::new(policy(false));
+        let mut inbound = Encryption::new(EncryptionPolicy {
+            required: true,
+            ..EncryptionPolicy::default()
+        });
+        sign_ephemeral_key(&mut inbound, 2);
+        // Requiring encryption implies offering it
+        assert!(!inbound.encryption_key().is_empty());
+
+        assert_eq!(
+            inbound.negotiate(&outbound.encryption_key(), b"VERSION OUTBOUND", false),
+            Err(HandshakeError::EncryptionRequired)
+        );
+        assert_eq!(
+            outbound.negotiate(&inbound.encryption_key(), b"VERSION INBOUND", true),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn untrusted_peer_rejected() {
+        let mut outbound = Encryption::new(policy(true));
+        let mut inbound = Encryption::new(EncryptionPolicy {
+            trusted_peers: [identity_pkh(3)].iter().cloned().collect(),
+            ..EncryptionPolicy::default()
+        });
+        sign_ephemeral_key(&mut outbound, 1);
+        sign_ephemeral_key(&mut inbound, 2);
+
+        assert_eq!(
+            inbound.negotiate(&outbound.encryption_key(), b"VERSION OUTBOUND", false),
+            Err(HandshakeError::UntrustedPeer {
+                identity: identity_pkh(1)
+            })
+        );
+        assert_eq!(inbound.peer_identity(), None);
+    }
+
+    #[test]
+    fn trusted_peer_accepted() {
+        let mut outbound = Encryption::new(policy(true));
+        let mut inbound = Encryption::new(EncryptionPolicy {
+            trusted_peers: [identity_pkh(1)].iter().cloned().collect(),
+            ..EncryptionPolicy::default()
+        });
+        sign_ephemeral_key(&mut outbound, 1);
+        sign_ephemeral_key(&mut inbound, 2);
+
+        handshake(&mut outbound, &mut inbound);
+        assert_eq!(
+            inbound.peer_identity().map(PublicKey::pkh),
+            Some(identity_pkh(1))
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::io::Error;
+use std::{io::Error, time::Duration};
 
 use actix::io::WriteHandler;
 use actix::{
@@ -10,25 +10,39 @@ use futures::future;
 use log::{debug, error, info, trace, warn};
 
 use witnet_data_structures::{
-    builders::from_address,
-    chain::{Block, CheckpointBeacon, Hashable, InventoryEntry, InventoryItem, Transaction},
+    builders::{
+        from_address, COMPACT_BLOCKS_PROTOCOL_VERSION, MIN_PROTOCOL_VERSION,
+        TRANSACTION_ANNOUNCEMENTS_PROTOCOL_VERSION,
+    },
+    chain::{
+        Block, CheckpointBeacon, Hash, Hashable, InventoryEntry, InventoryItem, PublicKey,
+        SignedBlockHeader, Transaction,
+    },
+    compact_block::PartialBlock,
+    error::CompactBlockError,
     proto::ProtobufConvert,
     types::{
-        Address, Command, InventoryAnnouncement, InventoryRequest, LastBeacon,
-        Message as WitnetMessage, Peers, Version,
+        Address, BlockTransactions, Command, CompactBlock, GetBlockTransactions, GetHeaders,
+        Headers, InventoryAnnouncement, InventoryRequest, LastBeacon, Message as WitnetMessage,
+        Peers, Ping, Pong, Version,
     },
 };
-use witnet_p2p::sessions::{SessionStatus, SessionType};
+use witnet_p2p::{
+    bans::Misbehaviour,
+    sessions::{SessionStatus, SessionType},
+};
 
-use super::Session;
+use super::{HandshakeError, PeerVersion, Session};
 use crate::actors::{
-    chain_manager::ChainManager,
+    chain_manager::{ChainManager, ChainManagerError, MAX_HEADERS_SYNC},
     codec::BytesMut,
     inventory_manager::InventoryManager,
     messages::{
-        AddBlocks, AddCandidates, AddPeers, AddTransaction, CloseSession, Consolidate,
-        EpochNotification, GetBlocksEpochRange, GetHighestCheckpointBeacon, GetItem, PeerBeacon,
-        RequestPeers, SendGetPeers, SendInventoryAnnouncement, SendInventoryItem, SendLastBeacon,
+        AddBlocks, AddCandidates, AddHeaders, AddPeers, AddTransaction, CloseSession, Consolidate,
+        EpochNotification, FillPartialBlock, GetBlock, GetBlocksAfterBeacon, GetBlocksEpochRange,
+        GetHighestCheckpointBeacon, GetItem, GetTransaction, PeerBeacon, PeerLatency, RemovePeers,
+        ReportMisbehaviour, RequestBlocks, RequestPeers, SelectTransactionsToRequest,
+        SendGetHeaders, SendGetPeers, SendInventoryAnnouncement, SendInventoryItem, SendLastBeacon,
         SessionUnitResult,
     },
     peers_manager::PeersManager,
@@ -64,12 +78,18 @@ impl Handler<EpochNotification<EveryEpochPayload>> for Session {
         debug!("Periodic epoch notification received {:?}", msg.checkpoint);
         self.current_epoch = Some(msg.checkpoint);
 
+        // Candidates of past epochs are not rebuilt anymore
+        self.compact_blocks.clear();
+
         let now = get_timestamp();
         if self.blocks_timestamp != 0 && now - self.blocks_timestamp > self.blocks_timeout {
             // Get ChainManager address
             let chain_manager_addr = System::current().registry().get::<ChainManager>();
 
-            chain_manager_addr.do_send(AddBlocks { blocks: vec![] });
+            chain_manager_addr.do_send(AddBlocks {
+                blocks: vec![],
+                sender: None,
+            });
             warn!("Timeout for waiting blocks achieved");
             ctx.stop();
         }
@@ -80,9 +100,26 @@ impl Handler<EpochNotification<EveryEpochPayload>> for Session {
 impl StreamHandler<BytesMut, Error> for Session {
     /// This is main event loop for client requests
     fn handle(&mut self, bytes: BytesMut, ctx: &mut Self::Context) {
+        // Decrypt the bytes if the session is encrypted
+        let bytes = match self.encryption.decrypt(bytes) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                warn!(
+                    "Error decrypting message from peer {}: {}",
+                    self.remote_addr, e
+                );
+                // The keys of the session are out of sync, so it cannot continue
+                ctx.stop();
+                return;
+            }
+        };
+
         let result = WitnetMessage::from_pb_bytes(&bytes);
         match result {
-            Err(err) => error!("Error decoding message: {:?}", err),
+            Err(err) => {
+                error!("Error decoding message: {:?}", err);
+                report_misbehaviour(self, Misbehaviour::UndecodableMessage);
+            }
             Ok(msg) => {
                 debug!(
                     "{} Received {} message from session {:?}",
@@ -110,16 +147,31 @@ impl StreamHandler<BytesMut, Error> for Session {
                     //   HANDSHAKE    //
                     ////////////////////
                     // Handle Version message
-                    (
-                        _,
-                        SessionStatus::Unconsolidated,
-                        Command::Version(Version { sender_address, .. }),
-                    ) => {
-                        let msgs = handshake_version(self, &sender_address);
-                        for msg in msgs {
-                            self.send_message(msg);
+                    (_, SessionStatus::Unconsolidated, Command::Version(version)) => {
+                        match handshake_version(self, &version, &bytes) {
+                            Ok(msgs) => {
+                                for msg in msgs {
+                                    self.send_message(msg);
+                                }
+                                // The messages sent after the verack message are encrypted
+                                self.encryption.start_sending();
+                                try_consolidate_session(self, ctx);
+                            }
+                            Err(e) => {
+                                warn!("Rejected handshake with peer {}: {}", self.remote_addr, e);
+                                // Do not try to connect to our own address again
+                                if e == HandshakeError::SelfConnection
+                                    && self.session_type == SessionType::Outbound
+                                {
+                                    let peers_manager_addr =
+                                        System::current().registry().get::<PeersManager>();
+                                    peers_manager_addr.do_send(RemovePeers {
+                                        addresses: vec![self.remote_addr],
+                                    });
+                                }
+                                ctx.stop();
+                            }
                         }
-                        try_consolidate_session(self, ctx);
                     }
                     // Handler Verack message
                     (_, SessionStatus::Unconsolidated, Command::Verack(_)) => {
@@ -127,6 +179,18 @@ impl StreamHandler<BytesMut, Error> for Session {
                         try_consolidate_session(self, ctx);
                     }
                     ////////////////////
+                    //   HEARTBEAT    //
+                    ////////////////////
+                    // Handle Ping message
+                    (_, SessionStatus::Consolidated, Command::Ping(Ping { nonce })) => {
+                        let pong_msg = WitnetMessage::build_pong(self.magic_number, nonce);
+                        self.send_message(pong_msg);
+                    }
+                    // Handle Pong message
+                    (_, SessionStatus::Consolidated, Command::Pong(Pong { nonce })) => {
+                        heartbeat_pong(self, nonce);
+                    }
+                    ////////////////////
                     // PEER DISCOVERY //
                     ////////////////////
                     // Handle GetPeers message
@@ -135,7 +199,7 @@ impl StreamHandler<BytesMut, Error> for Session {
                     }
                     // Handle Peers message
                     (_, SessionStatus::Consolidated, Command::Peers(Peers { peers })) => {
-                        peer_discovery_peers(&peers);
+                        peer_discovery_peers(self, &peers);
                     }
                     ///////////////////////
                     // INVENTORY_REQUEST //
@@ -145,31 +209,7 @@ impl StreamHandler<BytesMut, Error> for Session {
                         SessionStatus::Consolidated,
                         Command::InventoryRequest(InventoryRequest { inventory }),
                     ) => {
-                        let inventory_mngr = System::current().registry().get::<InventoryManager>();
-                        let item_requests: Vec<_> = inventory
-                            .iter()
-                            .filter_map(|item| match item {
-                                InventoryEntry::Block(hash) | InventoryEntry::Tx(hash) => {
-                                    Some(inventory_mngr.send(GetItem { hash: *hash }))
-                                }
-                                _ => None,
-                            })
-                            .collect();
-
-                        future::join_all(item_requests)
-                            .into_actor(self)
-                            .map_err(|e, _, _| error!("Inventory request error: {}", e))
-                            .and_then(|item_responses, session, _| {
-                                for item_response in item_responses {
-                                    match item_response {
-                                        Ok(item) => send_inventory_item_msg(session, item),
-                                        Err(e) => warn!("Inventory result is error: {}", e),
-                                    }
-                                }
-
-                                actix::fut::ok(())
-                            })
-                            .wait(ctx);
+                        inventory_process_request(self, ctx, inventory);
                     }
                     //////////////////////////
                     // TRANSACTION RECEIVED //
@@ -208,12 +248,55 @@ impl StreamHandler<BytesMut, Error> for Session {
                         session_last_beacon_outbound(self, ctx, highest_block_checkpoint);
                     }
 
+                    /////////////////////
+                    // SYNCHRONIZATION //
+                    /////////////////////
+                    (
+                        _,
+                        SessionStatus::Consolidated,
+                        Command::GetHeaders(GetHeaders {
+                            highest_block_checkpoint,
+                        }),
+                    ) => {
+                        session_get_headers(self, ctx, highest_block_checkpoint);
+                    }
+                    (_, SessionStatus::Consolidated, Command::Headers(Headers { headers })) => {
+                        session_headers(self, headers);
+                    }
+
+                    ////////////////////
+                    // COMPACT BLOCKS //
+                    ////////////////////
+                    (_, SessionStatus::Consolidated, Command::CompactBlock(compact_block)) => {
+                        session_compact_block(self, ctx, compact_block);
+                    }
+                    (
+                        _,
+                        SessionStatus::Consolidated,
+                        Command::GetBlockTransactions(GetBlockTransactions {
+                            block_hash,
+                            indexes,
+                        }),
+                    ) => {
+                        session_get_block_transactions(self, ctx, block_hash, indexes);
+                    }
+                    (
+                        _,
+                        SessionStatus::Consolidated,
+                        Command::BlockTransactions(BlockTransactions {
+                            block_hash,
+                            transactions,
+                        }),
+                    ) => {
+                        session_block_transactions(self, block_hash, transactions);
+                    }
+
                     ////////////////////////////
                     // INVENTORY ANNOUNCEMENT //
                     ////////////////////////////
                     // Handle InventoryAnnouncement message
                     (_, SessionStatus::Consolidated, Command::InventoryAnnouncement(inv)) => {
-                        inventory_process_inv(self, &inv);
+                        inventory_process_inv(self, ctx, inv);
                     }
                     /////////////////////
                     // NOT SUPPORTED   //
@@ -253,9 +336,13 @@ impl Handler<SendInventoryAnnouncement> for Session {
             "Sending AnnounceItems message to peer at {:?}",
             self.remote_addr
         );
+        // Do not announce the items which the peer already knows
+        let mut items = msg.items;
+        items.retain(|item| self.known_inventory.insert(inventory_entry_hash(item)));
+
         // Try to create AnnounceItems message with items to be announced
         if let Ok(announce_items_msg) =
-            WitnetMessage::build_inventory_announcement(self.magic_number, msg.items)
+            WitnetMessage::build_inventory_announcement(self.magic_number, items)
         {
             // Send message through the session network connection
             self.send_message(announce_items_msg);
@@ -272,7 +359,34 @@ impl Handler<SendInventoryItem> for Session {
             "Sending SendInventoryItem message to peer at {:?}",
             self.remote_addr
         );
-        send_inventory_item_msg(self, msg.item)
+        match msg.item {
+            // New blocks are relayed as compact blocks to the peers which support them
+            InventoryItem::Block(block) => {
+                // Do not relay the block to a peer which already knows it
+                if !self.known_inventory.insert(block.hash()) {
+                    return;
+                }
+
+                if peer_supports(self, COMPACT_BLOCKS_PROTOCOL_VERSION) {
+                    send_compact_block(self, &block)
+                } else {
+                    send_inventory_item_msg(self, InventoryItem::Block(block))
+                }
+            }
+            // New transactions are announced to the peers which request them
+            InventoryItem::Transaction(transaction) => {
+                let hash = transaction.hash();
+                if !self.known_inventory.insert(hash) {
+                    return;
+                }
+
+                if peer_supports(self, TRANSACTION_ANNOUNCEMENTS_PROTOCOL_VERSION) {
+                    announce_transaction(self, hash)
+                } else {
+                    send_inventory_item_msg(self, InventoryItem::Transaction(transaction))
+                }
+            }
+        }
     }
 }
 
@@ -285,6 +399,32 @@ impl Handler<SendLastBeacon> for Session {
     }
 }
 
+impl Handler<SendGetHeaders> for Session {
+    type Result = SessionUnitResult;
+
+    fn handle(&mut self, SendGetHeaders { beacon }: SendGetHeaders, _ctx: &mut Context<Self>) {
+        debug!("Sending GetHeaders to peer at {:?}", self.remote_addr);
+        let get_headers_msg = WitnetMessage::build_get_headers(self.magic_number, beacon);
+        self.send_message(get_headers_msg);
+    }
+}
+
+impl Handler<RequestBlocks> for Session {
+    type Result = SessionUnitResult;
+
+    fn handle(&mut self, RequestBlocks { hashes }: RequestBlocks, _ctx: &mut Context<Self>) {
+        debug!(
+            "Requesting {} blocks to peer at {:?}",
+            hashes.len(),
+            self.remote_addr
+        );
+        request_inventory(
+            self,
+            hashes.into_iter().map(InventoryEntry::Block).collect(),
+        );
+    }
+}
+
 impl Handler<CloseSession> for Session {
     type Result = SessionUnitResult;
 
@@ -315,6 +455,7 @@ fn update_consolidate(session: &Session, ctx: &mut Context<Session>) {
             address: session.remote_addr,
             potential_new_peer: session.remote_sender_addr.unwrap(),
             session_type: session.session_type,
+            identity: session.encryption.peer_identity().map(PublicKey::pkh),
         })
         .into_actor(session)
         .then(|res, act, ctx| {
@@ -324,8 +465,28 @@ fn update_consolidate(session: &Session, ctx: &mut Context<Session>) {
                         "Successfully consolidated session {:?} in SessionManager",
                         act.remote_addr
                     );
+                    if let Some(peer_version) = &act.peer_version {
+                        info!(
+                            "Peer {} uses {} (protocol version {}, capabilities {:#x}, last epoch {})",
+                            act.remote_addr,
+                            peer_version.user_agent,
+                            peer_version.version,
+                            peer_version.capabilities,
+                            peer_version.last_epoch
+                        );
+                    }
+                    match act.encryption.peer_identity() {
+                        Some(identity) => info!(
+                            "Session with peer {} is encrypted, peer identity: {}",
+                            act.remote_addr,
+                            identity.pkh()
+                        ),
+                        None => debug!("Session with peer {} is not encrypted", act.remote_addr),
+                    }
                     // Set status to consolidate
                     act.status = SessionStatus::Consolidated;
+                    // Start checking that the peer is alive
+                    act.ping_peer(ctx);
 
                     actix::fut::ok(())
                 }
@@ -382,7 +543,7 @@ fn peer_discovery_get_peers(session: &mut Session, ctx: &mut Context<Session>) {
 }
 
 /// Function called when Peers message is received
-fn peer_discovery_peers(peers: &[Address]) {
+fn peer_discovery_peers(session: &Session, peers: &[Address]) {
     // Get peers manager address
     let peers_manager_addr = System::current().registry().get::<PeersManager>();
 
@@ -390,9 +551,21 @@ fn peer_discovery_peers(peers: &[Address]) {
     let addresses = peers.iter().map(from_address).collect();
 
     // Send AddPeers message to the peers manager
+    // The addresses are bucketed by the network group of the peer which announced them
     peers_manager_addr.do_send(AddPeers {
-        // TODO: convert Vec<Address> to Vec<SocketAddr>
         addresses,
+        src_address: Some(session.remote_addr),
+    });
+}
+
+/// Function to report a protocol violation committed by the peer to the SessionsManager
+fn report_misbehaviour(session: &Session, misbehaviour: Misbehaviour) {
+    // Get SessionsManager address
+    let sessions_manager_addr = System::current().registry().get::<SessionsManager>();
+
+    sessions_manager_addr.do_send(ReportMisbehaviour {
+        address: session.remote_addr,
+        misbehaviour,
     });
 }
 
@@ -405,10 +578,13 @@ fn inventory_process_block(session: &mut Session, _ctx: &mut Context<Session>, b
     let block_hash = block.hash();
 
     if Some(block_epoch) == session.current_epoch {
+        session.known_inventory.insert(block_hash);
+
         debug!("Send Candidate");
         // Send a message to the ChainManager to try to add a new candidate
         chain_manager_addr.do_send(AddCandidates {
             blocks: vec![block],
+            sender: Some(session.remote_addr),
         });
     } else {
         // Add block to requested_blocks
@@ -416,6 +592,7 @@ fn inventory_process_block(session: &mut Session, _ctx: &mut Context<Session>, b
             session.requested_blocks.insert(block_hash, block);
         } else {
             error!("Unexpected not requested block");
+            report_misbehaviour(session, Misbehaviour::UnrequestedItem);
         }
 
         if session.requested_blocks.len() == session.requested_block_hashes.len() {
@@ -430,7 +607,10 @@ fn inventory_process_block(session: &mut Session, _ctx: &mut Context<Session>, b
                     // As soon as there is a missing block, stop processing the other
                     // blocks, send a empty message to the ChainManager and close the session
                     blocks_vector.clear();
-                    chain_manager_addr.do_send(AddBlocks { blocks: vec![] });
+                    chain_manager_addr.do_send(AddBlocks {
+                        blocks: vec![],
+                        sender: None,
+                    });
                     warn!("Unexpected missing block");
                 }
             }
@@ -438,6 +618,7 @@ fn inventory_process_block(session: &mut Session, _ctx: &mut Context<Session>, b
             // Send a message to the ChainManager to try to add a new block
             chain_manager_addr.do_send(AddBlocks {
                 blocks: blocks_vector,
+                sender: Some(session.remote_addr),
             });
 
             // Clear requested block structures
@@ -450,10 +631,13 @@ fn inventory_process_block(session: &mut Session, _ctx: &mut Context<Session>, b
 
 /// Function called when Block message is received
 fn inventory_process_transaction(
-    _session: &mut Session,
+    session: &mut Session,
     _ctx: &mut Context<Session>,
     transaction: Transaction,
 ) {
+    // The peer does not need to be told about this transaction
+    session.known_inventory.insert(transaction.hash());
+
     // Get ChainManager address
     let chain_manager_addr = System::current().registry().get::<ChainManager>();
 
@@ -462,32 +646,168 @@ fn inventory_process_transaction(
 }
 
 /// Function to process an InventoryAnnouncement message
-fn inventory_process_inv(session: &mut Session, inv: &InventoryAnnouncement) {
-    // Check how many of the received inventory vectors need to be requested
-    let inv_entries = &inv.inventory;
-
-    session.requested_block_hashes = inv_entries
-        .iter()
-        .map(|inv_entry| match inv_entry.clone() {
-            InventoryEntry::Error(hash)
-            | InventoryEntry::Block(hash)
-            | InventoryEntry::DataRequest(hash)
-            | InventoryEntry::DataResult(hash)
-            | InventoryEntry::Tx(hash) => hash,
+///
+/// Blocks are requested right away, while transactions are only requested if they are not in
+/// the mempool and have not been requested to another peer yet.
+fn inventory_process_inv(
+    session: &mut Session,
+    ctx: &mut Context<Session>,
+    inv: InventoryAnnouncement,
+) {
+    let mut blocks = vec![];
+    let mut transactions = vec![];
+    for inv_entry in inv.inventory {
+        session
+            .known_inventory
+            .insert(inventory_entry_hash(&inv_entry));
+        match inv_entry {
+            InventoryEntry::Tx(hash) => transactions.push(hash),
+            inv_entry => blocks.push(inv_entry),
+        }
+    }
+
+    if !blocks.is_empty() {
+        request_inventory(session, blocks);
+    }
+
+    if !transactions.is_empty() {
+        ChainManager::from_registry()
+            .send(SelectTransactionsToRequest {
+                hashes: transactions,
+            })
+            .into_actor(session)
+            .map_err(|e, _, _| error!("SelectTransactionsToRequest didn't succeeded: {}", e))
+            .and_then(|hashes, act, _ctx| {
+                request_transactions(act, hashes);
+
+                actix::fut::ok(())
+            })
+            .wait(ctx);
+    }
+}
+
+/// Function called when InventoryRequest message is received: send the requested blocks and
+/// transactions
+///
+/// Transactions are read through the ChainManager, as they may be still pending in the mempool.
+fn inventory_process_request(
+    session: &Session,
+    ctx: &mut Context<Session>,
+    inventory: Vec<InventoryEntry>,
+) {
+    let inventory_mngr = InventoryManager::from_registry();
+    let chain_manager = ChainManager::from_registry();
+
+    let mut block_requests = vec![];
+    let mut transaction_requests = vec![];
+    for inv_entry in inventory {
+        match inv_entry {
+            InventoryEntry::Block(hash) => {
+                block_requests.push(inventory_mngr.send(GetItem { hash }))
+            }
+            InventoryEntry::Tx(hash) => {
+                transaction_requests.push(chain_manager.send(GetTransaction { hash }))
+            }
+            _ => {}
+        }
+    }
+
+    future::join_all(block_requests)
+        .into_actor(session)
+        .map_err(|e, _, _| error!("Inventory request error: {}", e))
+        .and_then(|item_responses, session, _| {
+            for item_response in item_responses {
+                match item_response {
+                    Ok(item) => send_inventory_item_msg(session, item),
+                    Err(e) => warn!("Inventory result is error: {}", e),
+                }
+            }
+
+            actix::fut::ok(())
+        })
+        .wait(ctx);
+
+    future::join_all(transaction_requests)
+        .into_actor(session)
+        .map_err(|e, _, _| error!("Inventory request error: {}", e))
+        .and_then(|transaction_responses, session, _| {
+            for transaction_response in transaction_responses {
+                match transaction_response {
+                    Ok((transaction, _)) => {
+                        send_inventory_item_msg(session, InventoryItem::Transaction(transaction))
+                    }
+                    Err(e) => warn!("Inventory result is error: {}", e),
+                }
+            }
+
+            actix::fut::ok(())
         })
-        .collect();
+        .wait(ctx);
+}
+
+/// Function to get the hash of an inventory entry
+fn inventory_entry_hash(inv_entry: &InventoryEntry) -> Hash {
+    match inv_entry {
+        InventoryEntry::Error(hash)
+        | InventoryEntry::Block(hash)
+        | InventoryEntry::DataRequest(hash)
+        | InventoryEntry::DataResult(hash)
+        | InventoryEntry::Tx(hash) => *hash,
+    }
+}
+
+/// Function to request some transactions to the peer
+///
+/// Unlike blocks, the requested transactions are not waited for: if the peer does not send
+/// them, they will be requested to the next peer which announces them.
+fn request_transactions(session: &mut Session, hashes: Vec<Hash>) {
+    if let Ok(inv_req_msg) = WitnetMessage::build_inventory_request(
+        session.magic_number,
+        hashes.into_iter().map(InventoryEntry::Tx).collect(),
+    ) {
+        // Send InventoryRequest message through the session network connection
+        session.send_message(inv_req_msg);
+    }
+}
+
+/// Function to request some inventory entries to the peer, waiting for them until the blocks
+/// timeout
+fn request_inventory(session: &mut Session, inv_entries: Vec<InventoryEntry>) {
+    // Keep waiting for the entries which were requested before
+    for hash in inv_entries.iter().map(inventory_entry_hash) {
+        if !session.requested_block_hashes.contains(&hash) {
+            session.requested_block_hashes.push(hash);
+        }
+    }
 
     session.blocks_timestamp = get_timestamp();
 
     // Try to create InventoryRequest protocol message to request missing inventory vectors
     if let Ok(inv_req_msg) =
-        WitnetMessage::build_inventory_request(session.magic_number, inv_entries.to_vec())
+        WitnetMessage::build_inventory_request(session.magic_number, inv_entries)
     {
         // Send InventoryRequest message through the session network connection
         session.send_message(inv_req_msg);
     }
 }
 
+/// Function called when Pong message is received
+fn heartbeat_pong(session: &mut Session, nonce: u64) {
+    match session.pending_ping {
+        Some((pending_nonce, sent)) if pending_nonce == nonce => {
+            let latency = sent.elapsed();
+            session.pending_ping = None;
+            session.latency = Some(latency);
+
+            SessionsManager::from_registry().do_send(PeerLatency {
+                address: session.remote_addr,
+                latency,
+            });
+        }
+        _ => debug!("Unexpected pong with nonce {}", nonce),
+    }
+}
+
 /// Function called when Verack message is received
 fn handshake_verack(session: &mut Session) {
     let flags = &mut session.handshake_flags;
@@ -498,40 +818,99 @@ fn handshake_verack(session: &mut Session) {
 
     // Set verack_rx flag
     flags.verack_rx = true;
+
+    // The messages received after the verack message are encrypted
+    session.encryption.start_receiving();
 }
 
-/// Function called when Version message is received
-fn handshake_version(session: &mut Session, sender_address: &Address) -> Vec<WitnetMessage> {
-    let flags = &mut session.handshake_flags;
+/// Function to check the fields of a received Version message
+fn check_version(
+    version: &Version,
+    nonce: u64,
+    max_time_skew: Duration,
+    now: i64,
+) -> Result<(), HandshakeError> {
+    if version.nonce == nonce {
+        return Err(HandshakeError::SelfConnection);
+    }
+
+    if version.version < MIN_PROTOCOL_VERSION {
+        return Err(HandshakeError::IncompatibleVersion {
+            version: version.version,
+            min: MIN_PROTOCOL_VERSION,
+        });
+    }
 
-    if flags.version_rx {
+    // A timestamp so far from ours that the difference overflows is also too skewed
+    let skew = version
+        .timestamp
+        .checked_sub(now)
+        .map_or(u64::max_value(), i64::unsigned_abs);
+    let max = max_time_skew.as_secs();
+    if skew > max {
+        return Err(HandshakeError::TimestampSkew { skew, max });
+    }
+
+    Ok(())
+}
+
+/// Function called when Version message is received. `bytes` is the serialized message, which
+/// is bound to the keys of the session
+fn handshake_version(
+    session: &mut Session,
+    version: &Version,
+    bytes: &[u8],
+) -> Result<Vec<WitnetMessage>, HandshakeError> {
+    check_version(
+        version,
+        session.handshake_nonce,
+        session.handshake_max_time_skew,
+        get_timestamp(),
+    )?;
+
+    // The version message of this node must be built before negotiating the keys of the session
+    let version_msg = if session.handshake_flags.version_tx {
+        None
+    } else {
+        Some(session.build_version_message())
+    };
+
+    if session.handshake_flags.version_rx {
         debug!("Version message already received");
+    } else {
+        session.encryption.negotiate(
+            &version.encryption_key,
+            bytes,
+            session.session_type == SessionType::Outbound,
+        )?;
     }
 
-    // Placeholder for version fields verification
-    session.remote_sender_addr = Some(from_address(sender_address));
+    let flags = &mut session.handshake_flags;
+
+    session.remote_sender_addr = Some(from_address(&version.sender_address));
+    session.peer_version = Some(PeerVersion {
+        version: version.version,
+        capabilities: version.capabilities,
+        user_agent: version.user_agent.clone(),
+        last_epoch: version.last_epoch,
+    });
 
     // Set version_rx flag, indicating reception of a version message from the peer
     flags.version_rx = true;
 
+    // The verack message must be the last one, because the messages sent after it are encrypted
     let mut responses: Vec<WitnetMessage> = vec![];
+    if let Some(version_msg) = version_msg {
+        flags.version_tx = true;
+        responses.push(version_msg);
+    }
     if !flags.verack_tx {
         flags.verack_tx = true;
         let verack = WitnetMessage::build_verack(session.magic_number);
         responses.push(verack);
     }
-    if !flags.version_tx {
-        flags.version_tx = true;
-        let version = WitnetMessage::build_version(
-            session.magic_number,
-            session.server_addr,
-            session.remote_addr,
-            0,
-        );
-        responses.push(version);
-    }
 
-    responses
+    Ok(responses)
 }
 
 fn send_inventory_item_msg(session: &mut Session, item: InventoryItem) {
@@ -635,8 +1014,363 @@ fn session_last_beacon_outbound(
     })
 }
 
+/// Function called when GetHeaders message is received: send the headers of the blocks that
+/// follow the beacon, or no headers if the beacon is not part of our chain
+fn session_get_headers(session: &Session, ctx: &mut Context<Session>, beacon: CheckpointBeacon) {
+    ChainManager::from_registry()
+        .send(GetBlocksAfterBeacon {
+            beacon,
+            limit: MAX_HEADERS_SYNC,
+        })
+        .into_actor(session)
+        .map_err(|e, _, _| error!("GetHeaders::AfterBeacon didn't succeeded: {}", e))
+        .and_then(move |res, act, ctx| match res {
+            Ok(blocks) => {
+                let inventory_mngr = InventoryManager::from_registry();
+                let block_requests: Vec<_> = blocks
+                    .into_iter()
+                    .filter_map(|(_epoch, entry)| match entry {
+                        InventoryEntry::Block(hash) => Some(inventory_mngr.send(GetItem { hash })),
+                        _ => None,
+                    })
+                    .collect();
+
+                future::join_all(block_requests)
+                    .into_actor(act)
+                    .map_err(|e, _, _| error!("GetHeaders::GetItem error: {}", e))
+                    .and_then(|item_responses, act, _ctx| {
+                        let mut headers = vec![];
+                        for item_response in item_responses {
+                            match item_response {
+                                Ok(InventoryItem::Block(block)) => {
+                                    headers.push(block.signed_header())
+                                }
+                                Ok(InventoryItem::Transaction(_)) => {
+                                    warn!("GetHeaders::GetItem returned a transaction");
+                                    break;
+                                }
+                                Err(e) => {
+                                    warn!("GetHeaders::GetItem result is error: {}", e);
+                                    break;
+                                }
+                            }
+                        }
+                        send_headers(act, headers);
+
+                        actix::fut::ok(())
+                    })
+                    .wait(ctx);
+
+                actix::fut::ok(())
+            }
+            Err(ChainManagerError::BlockDoesNotExist) => {
+                debug!("GetHeaders beacon {:?} is not part of our chain", beacon);
+                send_headers(act, vec![]);
+
+                actix::fut::ok(())
+            }
+            Err(e) => {
+                error!("GetHeaders::AfterBeacon didn't succeeded: {}", e);
+
+                actix::fut::err(())
+            }
+        })
+        .wait(ctx);
+}
+
+/// Function called when Headers message is received
+fn session_headers(session: &Session, headers: Vec<SignedBlockHeader>) {
+    ChainManager::from_registry().do_send(AddHeaders {
+        headers,
+        sender: Some(session.remote_addr),
+    });
+}
+
+fn send_headers(session: &mut Session, headers: Vec<SignedBlockHeader>) {
+    let headers_msg = WitnetMessage::build_headers(session.magic_number, headers);
+    // Send Headers msg
+    session.send_message(headers_msg);
+}
+
+/// Function to check whether the protocol version of the peer is at least `version`
+fn peer_supports(session: &Session, version: u32) -> bool {
+    session
+        .peer_version
+        .as_ref()
+        .map_or(false, |peer_version| peer_version.version >= version)
+}
+
+fn announce_transaction(session: &mut Session, hash: Hash) {
+    if let Ok(announcement_msg) = WitnetMessage::build_inventory_announcement(
+        session.magic_number,
+        vec![InventoryEntry::Tx(hash)],
+    ) {
+        // Send InventoryAnnouncement msg
+        session.send_message(announcement_msg);
+    }
+}
+
+fn send_compact_block(session: &mut Session, block: &Block) {
+    let compact_block_msg = WitnetMessage::build_compact_block(session.magic_number, block);
+    // Send CompactBlock msg
+    session.send_message(compact_block_msg);
+}
+
+/// Function called when CompactBlock message is received: fill its transactions with the ones
+/// of the transactions pool
+fn session_compact_block(
+    session: &mut Session,
+    ctx: &mut Context<Session>,
+    compact_block: CompactBlock,
+) {
+    let partial = match PartialBlock::new(compact_block) {
+        Ok(partial) => partial,
+        Err(e) => {
+            warn!(
+                "Invalid compact block from peer {}: {}",
+                session.remote_addr, e
+            );
+            report_misbehaviour(session, Misbehaviour::InvalidCandidate);
+            return;
+        }
+    };
+
+    // The missing transactions of this block have already been requested
+    if session.compact_blocks.contains_key(&partial.hash()) {
+        return;
+    }
+
+    ChainManager::from_registry()
+        .send(FillPartialBlock { partial })
+        .into_actor(session)
+        .map_err(|e, _, _| error!("FillPartialBlock didn't succeeded: {}", e))
+        .and_then(|partial, act, _ctx| {
+            compact_block_process_partial(act, partial);
+
+            actix::fut::ok(())
+        })
+        .wait(ctx);
+}
+
+/// Function to process a compact block after filling it with the transactions pool: the block
+/// is processed as a candidate if all its transactions are known, otherwise the missing ones
+/// are requested to the peer
+fn compact_block_process_partial(session: &mut Session, mut partial: PartialBlock) {
+    if partial.missing_indexes().is_empty() {
+        match partial.try_build() {
+            Ok(block) => {
+                compact_block_process_block(session, block);
+                return;
+            }
+            // A transaction of the pool may have the same short id as the one of the block, so
+            // the transactions taken from the pool are requested to the peer
+            Err(CompactBlockError::MerkleRootMismatch) => {
+                if !partial.reset_mempool_transactions() {
+                    warn!(
+                        "Invalid compact block {} from peer {}: transactions do not match the \
+                         merkle root",
+                        partial.hash(),
+                        session.remote_addr
+                    );
+                    report_misbehaviour(session, Misbehaviour::InvalidCandidate);
+                    return;
+                }
+            }
+            Err(e) => {
+                warn!("Could not rebuild compact block {}: {}", partial.hash(), e);
+                return;
+            }
+        }
+    }
+
+    let block_hash = partial.hash();
+    let indexes = partial.missing_indexes();
+    session.compact_blocks.insert(block_hash, partial);
+
+    let get_block_transactions_msg =
+        WitnetMessage::build_get_block_transactions(session.magic_number, block_hash, indexes);
+    // Send GetBlockTransactions msg
+    session.send_message(get_block_transactions_msg);
+}
+
+/// Function to send a block rebuilt from a compact block to the ChainManager as a candidate
+fn compact_block_process_block(session: &mut Session, block: Block) {
+    session.known_inventory.insert(block.hash());
+
+    ChainManager::from_registry().do_send(AddCandidates {
+        blocks: vec![block],
+        sender: Some(session.remote_addr),
+    });
+}
+
+/// Function called when GetBlockTransactions message is received: send the requested
+/// transactions of a block
+fn session_get_block_transactions(
+    session: &Session,
+    ctx: &mut Context<Session>,
+    block_hash: Hash,
+    indexes: Vec<u32>,
+) {
+    ChainManager::from_registry()
+        .send(GetBlock { hash: block_hash })
+        .into_actor(session)
+        .map_err(|e, _, _| error!("GetBlockTransactions::GetBlock didn't succeeded: {}", e))
+        .and_then(move |res, act, _ctx| {
+            match res {
+                Ok(block) => {
+                    let transactions: Option<Vec<Transaction>> = indexes
+                        .iter()
+                        .map(|index| block.txns.get(*index as usize).cloned())
+                        .collect();
+                    match transactions {
+                        Some(transactions) => {
+                            send_block_transactions(act, block_hash, transactions)
+                        }
+                        None => warn!(
+                            "GetBlockTransactions requested an index out of block {}",
+                            block_hash
+                        ),
+                    }
+                }
+                Err(e) => warn!("GetBlockTransactions::GetBlock result is error: {}", e),
+            }
+
+            actix::fut::ok(())
+        })
+        .wait(ctx);
+}
+
+/// Function called when BlockTransactions message is received: complete the compact block
+fn session_block_transactions(
+    session: &mut Session,
+    block_hash: Hash,
+    transactions: Vec<Transaction>,
+) {
+    // The transactions of the blocks of past epochs may arrive after they have been discarded
+    let mut partial = match session.compact_blocks.remove(&block_hash) {
+        Some(partial) => partial,
+        None => {
+            debug!(
+                "Ignoring transactions of unknown compact block {}",
+                block_hash
+            );
+            return;
+        }
+    };
+
+    match partial
+        .add_transactions(transactions)
+        .and_then(|_| partial.try_build())
+    {
+        Ok(block) => compact_block_process_block(session, block),
+        Err(e) => {
+            warn!(
+                "Could not rebuild compact block {} from peer {}: {}",
+                block_hash, session.remote_addr, e
+            );
+            report_misbehaviour(session, Misbehaviour::InvalidCandidate);
+        }
+    }
+}
+
+fn send_block_transactions(
+    session: &mut Session,
+    block_hash: Hash,
+    transactions: Vec<Transaction>,
+) {
+    let block_transactions_msg =
+        WitnetMessage::build_block_transactions(session.magic_number, block_hash, transactions);
+    // Send BlockTransactions msg
+    session.send_message(block_transactions_msg);
+}
+
 fn send_last_beacon(session: &mut Session, beacon: CheckpointBeacon) {
     let beacon_msg = WitnetMessage::build_last_beacon(session.magic_number, beacon);
     // Send LastBeacon msg
     session.send_message(beacon_msg);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use witnet_data_structures::{
+        builders::PROTOCOL_VERSION,
+        types::{EncryptionKey, IpAddress},
+    };
+
+    fn version(version: u32, timestamp: i64, nonce: u64) -> Version {
+        let address = Address {
+            ip: IpAddress::Ipv4 { ip: 0x7F00_0001 },
+            port: 21337,
+        };
+
+        Version {
+            version,
+            timestamp,
+            capabilities: 1,
+            sender_address: address,
+            receiver_address: address,
+            user_agent: "test".to_string(),
+            last_epoch: 0,
+            nonce,
+            encryption_key: EncryptionKey::default(),
+        }
+    }
+
+    #[test]
+    fn check_version_accepts_valid_peer() {
+        let v = version(PROTOCOL_VERSION, 1_000, 1);
+
+        assert_eq!(check_version(&v, 2, Duration::from_secs(60), 1_030), Ok(()));
+    }
+
+    #[test]
+    fn check_version_rejects_self_connection() {
+        let v = version(PROTOCOL_VERSION, 1_000, 7);
+
+        assert_eq!(
+            check_version(&v, 7, Duration::from_secs(60), 1_000),
+            Err(HandshakeError::SelfConnection)
+        );
+    }
+
+    #[test]
+    fn check_version_rejects_old_protocol() {
+        let v = version(MIN_PROTOCOL_VERSION - 1, 1_000, 1);
+
+        assert_eq!(
+            check_version(&v, 2, Duration::from_secs(60), 1_000),
+            Err(HandshakeError::IncompatibleVersion {
+                version: MIN_PROTOCOL_VERSION - 1,
+                min: MIN_PROTOCOL_VERSION,
+            })
+        );
+    }
+
+    #[test]
+    fn check_version_rejects_time_skew() {
+        let v = version(PROTOCOL_VERSION, 1_000, 1);
+
+        assert_eq!(
+            check_version(&v, 2, Duration::from_secs(60), 1_061),
+            Err(HandshakeError::TimestampSkew { skew: 61, max: 60 })
+        );
+        assert_eq!(
+            check_version(&v, 2, Duration::from_secs(60), 939),
+            Err(HandshakeError::TimestampSkew { skew: 61, max: 60 })
+        );
+    }
+
+    #[test]
+    fn check_version_rejects_overflowing_skew() {
+        let v = version(PROTOCOL_VERSION, i64::min_value(), 1);
+
+        assert_eq!(
+            check_version(&v, 2, Duration::from_secs(60), 1_000),
+            Err(HandshakeError::TimestampSkew {
+                skew: u64::max_value(),
+                max: 60
+            })
+        );
+    }
+}
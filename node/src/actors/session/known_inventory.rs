@@ -0,0 +1,83 @@
+//! Inventory known by a peer
+//!
+//! Each session remembers the hashes of the blocks and transactions which the peer has sent or
+//! announced, and the ones which have been sent or announced to it, so that the same item is
+//! not relayed twice to the same peer.
+use std::collections::{HashSet, VecDeque};
+
+use witnet_data_structures::chain::Hash;
+
+/// Maximum number of hashes remembered for each peer
+pub const MAX_KNOWN_INVENTORY: usize = 5000;
+
+/// Bounded set of the hashes known by a peer, which forgets the oldest ones when full
+#[derive(Debug)]
+pub struct KnownInventory {
+    /// Known hashes
+    hashes: HashSet<Hash>,
+    /// Known hashes, in insertion order
+    order: VecDeque<Hash>,
+    /// Maximum number of hashes
+    capacity: usize,
+}
+
+impl KnownInventory {
+    /// Create an empty set which remembers up to `capacity` hashes
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            hashes: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Mark a hash as known by the peer
+    ///
+    /// Returns false if it was already known.
+    pub fn insert(&mut self, hash: Hash) -> bool {
+        if !self.hashes.insert(hash) {
+            return false;
+        }
+
+        self.order.push_back(hash);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(x: u8) -> Hash {
+        Hash::SHA256([x; 32])
+    }
+
+    #[test]
+    fn known_hashes_are_not_inserted_twice() {
+        let mut known = KnownInventory::new(10);
+
+        assert!(known.insert(hash(1)));
+        assert!(!known.insert(hash(1)));
+        assert!(known.insert(hash(2)));
+    }
+
+    #[test]
+    fn oldest_hashes_are_forgotten() {
+        let mut known = KnownInventory::new(2);
+
+        assert!(known.insert(hash(1)));
+        assert!(known.insert(hash(2)));
+        assert!(known.insert(hash(3)));
+
+        // The first hash was forgotten, so it can be inserted again, evicting the second one
+        assert!(!known.insert(hash(3)));
+        assert!(known.insert(hash(1)));
+        assert!(known.insert(hash(2)));
+    }
+}
@@ -1,15 +1,23 @@
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
 
 use actix::io::FramedWrite;
 
 use ansi_term::Color::Green;
 
+use failure::Fail;
+
 use log::{debug, error};
 
 use tokio::{io::WriteHalf, net::TcpStream};
 
+use witnet_crypto::transport;
 use witnet_data_structures::{
-    chain::{Block, Hash},
+    chain::{Block, Hash, PublicKeyHash},
+    compact_block::PartialBlock,
     proto::ProtobufConvert,
     types::Message as WitnetMessage,
 };
@@ -18,10 +26,21 @@ use witnet_p2p::sessions::{SessionStatus, SessionType};
 use crate::actors::codec::P2PCodec;
 use witnet_data_structures::chain::Epoch;
 
+use self::{
+    encryption::Encryption,
+    known_inventory::{KnownInventory, MAX_KNOWN_INVENTORY},
+};
+
+pub use self::encryption::EncryptionPolicy;
+
 mod actor;
 
+mod encryption;
+
 mod handlers;
 
+mod known_inventory;
+
 /// HandshakeFlags
 #[derive(Default)]
 struct HandshakeFlags {
@@ -43,6 +62,48 @@ impl HandshakeFlags {
     }
 }
 
+/// Fields of the version message received from the peer
+#[derive(Debug)]
+struct PeerVersion {
+    /// Protocol version of the peer
+    version: u32,
+    /// Capabilities announced by the peer
+    capabilities: u64,
+    /// User agent of the peer
+    user_agent: String,
+    /// Last epoch of the chain of the peer
+    last_epoch: u32,
+}
+
+/// Reasons to reject the version message of a peer
+#[derive(Debug, PartialEq, Fail)]
+enum HandshakeError {
+    /// The peer uses a protocol version older than the supported ones
+    #[fail(
+        display = "Incompatible protocol version {} (minimum supported: {})",
+        version, min
+    )]
+    IncompatibleVersion { version: u32, min: u32 },
+    /// The clock of the peer differs too much from the local one
+    #[fail(
+        display = "Timestamp differs {} seconds from ours (maximum: {})",
+        skew, max
+    )]
+    TimestampSkew { skew: u64, max: u64 },
+    /// The version message was sent by this same node
+    #[fail(display = "Connection to self")]
+    SelfConnection,
+    /// The encryption key offered by the peer is not valid
+    #[fail(display = "Invalid encryption key: {}", error)]
+    InvalidEncryptionKey { error: transport::Error },
+    /// This node requires encryption, but the session cannot be encrypted
+    #[fail(display = "Encryption required")]
+    EncryptionRequired,
+    /// The identity of the peer is not one of the trusted ones
+    #[fail(display = "Untrusted peer identity {}", identity)]
+    UntrustedPeer { identity: PublicKeyHash },
+}
+
 /// Session representing a TCP connection
 pub struct Session {
     /// Server socket address (local peer)
@@ -81,16 +142,47 @@ pub struct Session {
     /// HashMap with requested blocks
     requested_blocks: HashMap<Hash, Block>,
 
+    /// Compact blocks received from the peer which are waiting for some of their transactions
+    compact_blocks: HashMap<Hash, PartialBlock>,
+
+    /// Hashes of the blocks and transactions known by the peer
+    known_inventory: KnownInventory,
+
     /// Timeout for requested blocks
     blocks_timeout: i64,
 
     /// Timestamp for requested blocks
     blocks_timestamp: i64,
+
+    /// Period between pings sent to the peer
+    ping_period: Duration,
+
+    /// Time to wait for a pong before disconnecting the peer
+    ping_timeout: Duration,
+
+    /// Nonce and sending time of the last ping which has not been answered yet
+    pending_ping: Option<(u64, Instant)>,
+
+    /// Round-trip time of the last answered ping
+    latency: Option<Duration>,
+
+    /// Nonce sent in the version message, used to detect connections to self
+    handshake_nonce: u64,
+
+    /// Maximum clock difference allowed with the peer
+    handshake_max_time_skew: Duration,
+
+    /// Version information sent by the peer in the handshake
+    peer_version: Option<PeerVersion>,
+
+    /// Encryption of the messages exchanged with the peer
+    encryption: Encryption,
 }
 
 /// Session helper methods
 impl Session {
     /// Method to create a new session
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         server_addr: SocketAddr,
         remote_addr: SocketAddr,
@@ -99,6 +191,11 @@ impl Session {
         handshake_timeout: Duration,
         magic_number: u16,
         blocks_timeout: i64,
+        ping_period: Duration,
+        ping_timeout: Duration,
+        handshake_nonce: u64,
+        handshake_max_time_skew: Duration,
+        encryption: EncryptionPolicy,
     ) -> Session {
         Session {
             server_addr,
@@ -113,8 +210,18 @@ impl Session {
             current_epoch: None,
             requested_block_hashes: vec![],
             requested_blocks: HashMap::new(),
+            compact_blocks: HashMap::new(),
+            known_inventory: KnownInventory::new(MAX_KNOWN_INVENTORY),
             blocks_timeout,
             blocks_timestamp: 0,
+            ping_period,
+            ping_timeout,
+            pending_ping: None,
+            latency: None,
+            handshake_nonce,
+            handshake_max_time_skew,
+            peer_version: None,
+            encryption: Encryption::new(encryption),
         }
     }
     /// Method to send a Witnet message to the remote peer
@@ -128,11 +235,37 @@ impl Session {
         debug!("\t{:?}", msg);
         // Convert WitnetMessage into a vector of bytes
         match ProtobufConvert::to_pb_bytes(&msg) {
-            Ok(bytes) => self.framed.write(bytes.into()),
+            // Encrypt the bytes if the session is encrypted
+            Ok(bytes) => match self.encryption.encrypt(bytes) {
+                Ok(bytes) => self.framed.write(bytes.into()),
+                Err(e) => {
+                    error!("Error encrypting message: {}", e);
+                }
+            },
             Err(e) => {
                 error!("Error encoding message: {}", e);
             }
         }
         // Convert bytes into BytestMut and send them
     }
+
+    /// Method to build the version message sent to the peer. The message is recorded, because
+    /// it is bound to the keys of the session
+    fn build_version_message(&mut self) -> WitnetMessage {
+        // FIXME(#142): include the checkpoint of the current tip of the local blockchain
+        let version_msg = WitnetMessage::build_version(
+            self.magic_number,
+            self.server_addr,
+            self.remote_addr,
+            0,
+            self.handshake_nonce,
+            self.encryption.encryption_key(),
+        );
+        match version_msg.to_pb_bytes() {
+            Ok(bytes) => self.encryption.set_local_version(bytes),
+            Err(e) => error!("Error encoding version message: {}", e),
+        }
+
+        version_msg
+    }
 }
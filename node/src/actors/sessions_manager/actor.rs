@@ -1,9 +1,14 @@
 use super::SessionsManager;
+use crate::actors::session::EncryptionPolicy;
+use crate::actors::storage_keys::BANS_KEY;
 use crate::config_mngr;
+use crate::storage_mngr;
 use actix::prelude::*;
 use log;
+use rand::{thread_rng, Rng};
 use witnet_crypto::hash::calculate_sha256;
 use witnet_data_structures::proto::ProtobufConvert;
+use witnet_p2p::bans::Bans;
 
 /// Make actor from `SessionsManager`
 impl Actor for SessionsManager {
@@ -14,6 +19,9 @@ impl Actor for SessionsManager {
     fn started(&mut self, ctx: &mut Self::Context) {
         log::debug!("Sessions Manager actor has been started!");
 
+        // Nonce sent in every version message, used to detect connections to ourselves
+        self.handshake_nonce = thread_rng().gen();
+
         // Send message to config manager and process its response
         config_mngr::get()
             .into_actor(self)
@@ -30,15 +38,55 @@ impl Actor for SessionsManager {
                     config.connections.inbound_limit,
                     config.connections.outbound_limit,
                 );
+                act.sessions.set_outbound_limit_per_network_group(
+                    config.connections.outbound_limit_per_network_group,
+                );
                 act.sessions
                     .set_handshake_timeout(config.connections.handshake_timeout);
                 act.sessions
                     .set_blocks_timeout(config.connections.blocks_timeout);
+                act.sessions.set_ping_timeouts(
+                    config.connections.ping_period,
+                    config.connections.ping_timeout,
+                );
+                act.sessions
+                    .set_handshake_max_time_skew(config.connections.handshake_max_time_skew);
+
+                // Set ban threshold and duration
+                act.ban_threshold = config.connections.ban_threshold;
+                act.ban_duration = config.connections.ban_duration;
+
+                act.encryption = EncryptionPolicy {
+                    enabled: config.connections.encryption,
+                    required: config.connections.require_encryption,
+                    trusted_peers: config.connections.trusted_peers.clone(),
+                };
 
                 let magic = calculate_sha256(&consensus_constants.to_pb_bytes().unwrap());
                 let magic = u16::from(magic.0[0]) << 8 | (u16::from(magic.0[1]));
                 act.sessions.set_magic_number(magic);
 
+                storage_mngr::get::<_, Bans>(&BANS_KEY)
+                    .into_actor(act)
+                    .map_err(|e, _, _| log::error!("Couldn't get bans from storage: {}", e))
+                    .and_then(|bans_from_storage, act, ctx| {
+                        // bans_from_storage can be None if the storage does not contain that key
+                        if let Some(bans_from_storage) = bans_from_storage {
+                            log::info!(
+                                "Loaded the following banned peers from storage: {:?}",
+                                bans_from_storage.get_all()
+                            );
+                            // Keep the peers banned while the bans were being loaded
+                            act.bans.extend(bans_from_storage);
+                        }
+
+                        act.bans_loaded = true;
+                        act.persist_bans(ctx);
+
+                        fut::ok(())
+                    })
+                    .spawn(ctx);
+
                 // The peers bootstrapping process begins upon SessionsManager's start
                 act.bootstrap_peers(ctx, bootstrap_peers_period);
 
@@ -5,18 +5,22 @@ use std::{
 
 use actix::{
     io::FramedWrite, Actor, ActorFuture, Context, ContextFutureSpawner, Handler, Message,
-    StreamHandler, System, WrapFuture,
+    MessageResult, StreamHandler, System, WrapFuture,
 };
-use log::{debug, error, warn};
+use log::{debug, error, info, warn};
 use tokio::{codec::FramedRead, io::AsyncRead};
 
+use witnet_p2p::{peers::is_local_address, sessions::SessionType};
+use witnet_util::timestamp::get_timestamp;
+
 use super::SessionsManager;
 use crate::actors::messages::EpochNotification;
 use crate::actors::{
     codec::P2PCodec,
     messages::{
-        AddPeers, Anycast, Broadcast, Consolidate, Create, PeerBeacon, Register,
-        SessionsUnitResult, Unregister,
+        AddPeers, AddTriedPeer, Anycast, BanPeer, BannedPeer, Broadcast, Consolidate, Create,
+        GetBannedPeers, GetConsolidatedPeers, PeerBeacon, PeerInfo, PeerLatency, Register,
+        ReportMisbehaviour, SessionsUnitResult, UnbanPeer, Unicast, Unregister,
     },
     peers_manager::PeersManager,
     session::Session,
@@ -27,6 +31,14 @@ impl Handler<Create> for SessionsManager {
     type Result = ();
 
     fn handle(&mut self, msg: Create, _ctx: &mut Context<Self>) {
+        // Refuse connections with banned peers
+        if let Ok(peer_addr) = msg.stream.peer_addr() {
+            if self.bans.is_banned(peer_addr.ip(), get_timestamp()) {
+                debug!("Refusing connection with banned peer {}", peer_addr);
+                return;
+            }
+        }
+
         // Get handshake timeout
         let handshake_timeout = self.sessions.handshake_timeout;
 
@@ -39,6 +51,17 @@ impl Handler<Create> for SessionsManager {
         // Get blocks timeout
         let blocks_timeout = self.sessions.blocks_timeout;
 
+        // Get ping period and timeout
+        let ping_period = self.sessions.ping_period;
+        let ping_timeout = self.sessions.ping_timeout;
+
+        // Get handshake nonce and maximum time skew
+        let handshake_nonce = self.handshake_nonce;
+        let handshake_max_time_skew = self.sessions.handshake_max_time_skew;
+
+        // Get the encryption settings of the session
+        let encryption = self.encryption.clone();
+
         // Create a Session actor
         Session::create(move |ctx| {
             // Get server address (if not present, send local address instead)
@@ -62,6 +85,11 @@ impl Handler<Create> for SessionsManager {
                 handshake_timeout,
                 magic_number,
                 blocks_timeout,
+                ping_period,
+                ping_timeout,
+                handshake_nonce,
+                handshake_max_time_skew,
+                encryption,
             )
         });
     }
@@ -101,6 +129,9 @@ impl Handler<Unregister> for SessionsManager {
         let result = self
             .sessions
             .unregister_session(msg.session_type, msg.status, msg.address);
+        self.last_beacons.remove(&msg.address);
+        self.latencies.remove(&msg.address);
+        self.identities.remove(&msg.address);
 
         match &result {
             Ok(_) => debug!(
@@ -126,17 +157,32 @@ impl Handler<Consolidate> for SessionsManager {
         let result = self
             .sessions
             .consolidate_session(msg.session_type, msg.address);
+        if let (Ok(_), Some(identity)) = (&result, msg.identity) {
+            self.identities.insert(msg.address, identity);
+        }
 
         // Get peers manager address
         let peers_manager_addr = System::current().registry().get::<PeersManager>();
 
-        // Send AddPeers message to the peers manager
-        // If the session is outbound, this won't give any new information (except the timestamp
-        // being updated)
-        // If the session is inbound, this might be a valid information to get a new potential peer
-        peers_manager_addr.do_send(AddPeers {
-            addresses: vec![msg.potential_new_peer],
-        });
+        match msg.session_type {
+            // If the session is outbound, the connection to the peer was successful, so its
+            // address is moved to the tried table
+            SessionType::Outbound => {
+                if result.is_ok() {
+                    peers_manager_addr.do_send(AddTriedPeer {
+                        address: msg.address,
+                    });
+                }
+            }
+            // If the session is inbound, this might be a valid information to get a new potential
+            // peer, announced by the peer at the other end of the session
+            SessionType::Inbound => {
+                peers_manager_addr.do_send(AddPeers {
+                    addresses: vec![msg.potential_new_peer],
+                    src_address: Some(msg.address),
+                });
+            }
+        }
 
         match &result {
             Ok(_) => debug!(
@@ -193,6 +239,40 @@ where
     }
 }
 
+/// Handler for Unicast message
+impl<T: 'static> Handler<Unicast<T>> for SessionsManager
+where
+    T: Message + Send + Debug + Display,
+    T::Result: Send,
+    Session: Handler<T>,
+{
+    type Result = ();
+
+    fn handle(&mut self, Unicast { address, command }: Unicast<T>, ctx: &mut Context<Self>) {
+        debug!(
+            "A Unicast<{}> message is now being forwarded to the session with {}",
+            command, address
+        );
+
+        // Request the consolidated outbound session with the peer
+        self.sessions
+            .get_outbound_consolidated_session(address)
+            .map(|session_addr| {
+                session_addr
+                    .send(command)
+                    .into_actor(self)
+                    .then(|res, act, _ctx| act.process_command_response(&res))
+                    .wait(ctx);
+            })
+            .unwrap_or_else(|| {
+                warn!(
+                    "No consolidated outbound session was found with {}",
+                    address
+                );
+            });
+    }
+}
+
 /// Handler for Broadcast message
 impl<T: 'static> Handler<Broadcast<T>> for SessionsManager
 where
@@ -247,6 +327,8 @@ impl Handler<PeerBeacon> for SessionsManager {
     type Result = ();
 
     fn handle(&mut self, msg: PeerBeacon, ctx: &mut Context<Self>) {
+        self.last_beacons.insert(msg.address, msg.beacon);
+
         let all_ready_before = self.beacons.iter().all(|(_k, v)| v.is_some());
         if all_ready_before {
             // We already got all the beacons for this epoch, do nothing
@@ -264,3 +346,129 @@ impl Handler<PeerBeacon> for SessionsManager {
         }
     }
 }
+
+impl Handler<PeerLatency> for SessionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: PeerLatency, _ctx: &mut Context<Self>) {
+        self.latencies.insert(msg.address, msg.latency);
+    }
+}
+
+/// Handler for GetConsolidatedPeers message
+impl Handler<GetConsolidatedPeers> for SessionsManager {
+    type Result = MessageResult<GetConsolidatedPeers>;
+
+    fn handle(&mut self, _msg: GetConsolidatedPeers, _ctx: &mut Context<Self>) -> Self::Result {
+        let inbound = self
+            .sessions
+            .inbound_consolidated
+            .collection
+            .keys()
+            .map(|address| (*address, SessionType::Inbound));
+        let outbound = self
+            .sessions
+            .outbound_consolidated
+            .collection
+            .keys()
+            .map(|address| (*address, SessionType::Outbound));
+
+        let mut peers: Vec<_> = inbound
+            .chain(outbound)
+            .map(|(address, session_type)| PeerInfo {
+                address,
+                session_type,
+                last_beacon: self.last_beacons.get(&address).cloned(),
+                latency_ms: self
+                    .latencies
+                    .get(&address)
+                    .map(|latency| latency.as_secs() * 1000 + u64::from(latency.subsec_millis())),
+                identity: self.identities.get(&address).cloned(),
+            })
+            .collect();
+        peers.sort_by_key(|peer| peer.address.to_string());
+
+        MessageResult(peers)
+    }
+}
+
+/// Handler for ReportMisbehaviour message
+impl Handler<ReportMisbehaviour> for SessionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReportMisbehaviour, ctx: &mut Context<Self>) {
+        let ip = msg.address.ip();
+        // Local peers are usually run by the same operator, and several of them may share the
+        // same IP address, so they are never banned automatically
+        if is_local_address(ip) {
+            warn!(
+                "Local peer {} misbehaved ({:?}), not increasing its ban score",
+                msg.address, msg.misbehaviour
+            );
+            return;
+        }
+
+        let now = get_timestamp();
+        if self.bans.is_banned(ip, now) {
+            // The sessions with this peer are already being closed
+            return;
+        }
+
+        let score = self.bans.add_score(ip, msg.misbehaviour.score(), now);
+        warn!(
+            "Peer {} misbehaved ({:?}), ban score: {}/{}",
+            msg.address, msg.misbehaviour, score, self.ban_threshold
+        );
+
+        if score >= self.ban_threshold {
+            let ban_duration = self.ban_duration;
+            self.ban_ip(ctx, ip, ban_duration);
+        }
+    }
+}
+
+/// Handler for GetBannedPeers message
+impl Handler<GetBannedPeers> for SessionsManager {
+    type Result = MessageResult<GetBannedPeers>;
+
+    fn handle(&mut self, _msg: GetBannedPeers, _ctx: &mut Context<Self>) -> Self::Result {
+        let now = get_timestamp();
+        let mut peers: Vec<_> = self
+            .bans
+            .get_all()
+            .into_iter()
+            .filter(|(_ip, until)| *until > now)
+            .map(|(ip, until)| BannedPeer { ip, until })
+            .collect();
+        peers.sort_by_key(|peer| peer.ip);
+
+        MessageResult(peers)
+    }
+}
+
+/// Handler for BanPeer message
+impl Handler<BanPeer> for SessionsManager {
+    type Result = MessageResult<BanPeer>;
+
+    fn handle(&mut self, msg: BanPeer, ctx: &mut Context<Self>) -> Self::Result {
+        let duration = msg.duration.unwrap_or(self.ban_duration);
+        let until = self.ban_ip(ctx, msg.ip, duration);
+
+        MessageResult(BannedPeer { ip: msg.ip, until })
+    }
+}
+
+/// Handler for UnbanPeer message
+impl Handler<UnbanPeer> for SessionsManager {
+    type Result = MessageResult<UnbanPeer>;
+
+    fn handle(&mut self, msg: UnbanPeer, ctx: &mut Context<Self>) -> Self::Result {
+        let was_banned = self.bans.unban(msg.ip);
+        if was_banned {
+            info!("Lifted the ban of peer {}", msg.ip);
+            self.persist_bans(ctx);
+        }
+
+        MessageResult(was_banned)
+    }
+}
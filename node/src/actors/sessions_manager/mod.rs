@@ -1,5 +1,9 @@
 use log::{debug, error, info, trace, warn};
-use std::{net::SocketAddr, time::Duration};
+use std::{
+    convert::TryFrom,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
 
 use actix::{
     fut::FutureResult, ActorFuture, Addr, AsyncContext, Context, ContextFutureSpawner, Handler,
@@ -8,25 +12,35 @@ use actix::{
 
 use ansi_term::Color::Cyan;
 
-use witnet_p2p::sessions::Sessions;
+use witnet_p2p::{bans::Bans, sessions::Sessions};
+use witnet_util::timestamp::get_timestamp;
 
 use crate::actors::{
     chain_manager::ChainManager,
     connections_manager::ConnectionsManager,
     epoch_manager::EpochManager,
     messages::{
-        Anycast, CloseSession, GetRandomPeer, OutboundTcpConnect, PeersBeacons,
-        PeersSocketAddrResult, SendGetPeers, Subscribe,
+        Anycast, CloseSession, GetRandomPeers, OutboundTcpConnect, PeersBeacons,
+        PeersSocketAddrsResult, SendGetPeers, Subscribe,
     },
     peers_manager::PeersManager,
-    session::Session,
+    session::{EncryptionPolicy, Session},
+    storage_keys::BANS_KEY,
 };
+use crate::storage_mngr;
 use std::collections::{HashMap, HashSet};
-use witnet_data_structures::chain::CheckpointBeacon;
+use witnet_data_structures::chain::{CheckpointBeacon, PublicKeyHash};
 
 mod actor;
 mod handlers;
 
+/// Number of candidate addresses requested to the PeersManager when bootstrapping a new outbound
+/// session, so that the least used network groups can be preferred
+const BOOTSTRAP_CANDIDATES: usize = 8;
+
+/// Longest time during which a peer can be banned: one year
+const MAX_BAN_DURATION: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
 /// SessionsManager actor
 #[derive(Default)]
 pub struct SessionsManager {
@@ -34,6 +48,25 @@ pub struct SessionsManager {
     sessions: Sessions<Addr<Session>>,
     // List of beacons of outbound sessions
     beacons: HashMap<SocketAddr, Option<CheckpointBeacon>>,
+    // Last beacon received from each session, which unlike `beacons` is not cleared every epoch
+    last_beacons: HashMap<SocketAddr, CheckpointBeacon>,
+    // Round-trip time of the last ping answered by each session
+    latencies: HashMap<SocketAddr, Duration>,
+    // Identity of the peer of each encrypted consolidated session
+    identities: HashMap<SocketAddr, PublicKeyHash>,
+    // Nonce identifying this node in the handshake
+    handshake_nonce: u64,
+    // Ban scores of the peers and banned IP addresses
+    bans: Bans,
+    // Whether the banned IP addresses have been loaded from storage, they are not persisted
+    // before that to avoid overwriting them
+    bans_loaded: bool,
+    // Ban score at which a peer gets banned
+    ban_threshold: u32,
+    // Time during which a peer stays banned
+    ban_duration: Duration,
+    // Encryption settings of the sessions
+    encryption: EncryptionPolicy,
 }
 
 impl SessionsManager {
@@ -58,9 +91,11 @@ impl SessionsManager {
 
                 // Start chain of actions
                 peers_manager_addr
-                    // Send GetPeer message to peers manager actor
+                    // Send GetRandomPeers message to peers manager actor
                     // This returns a Request Future, representing an asynchronous message sending process
-                    .send(GetRandomPeer)
+                    .send(GetRandomPeers {
+                        n: BOOTSTRAP_CANDIDATES,
+                    })
                     // Convert a normal future into an ActorFuture
                     .into_actor(act)
                     // Process the response from the peers manager
@@ -101,35 +136,43 @@ impl SessionsManager {
         });
     }
 
-    /// Method to process peers manager RequestPeer response
+    /// Method to process peers manager GetRandomPeers response
     fn process_get_peer_response(
         &mut self,
-        response: Result<PeersSocketAddrResult, MailboxError>,
+        response: Result<PeersSocketAddrsResult, MailboxError>,
     ) -> FutureResult<SocketAddr, (), Self> {
-        response
-            // Unwrap the Result<PeersSocketAddrResult, MailboxError>
+        let candidates = response
+            // Unwrap the Result<PeersSocketAddrsResult, MailboxError>
             .unwrap_or_else(|_| {
                 error!("Failed to communicate with PeersManager");
-                Ok(None)
+                Ok(vec![])
             })
-            // Unwrap the PeersSocketAddrResult
+            // Unwrap the PeersSocketAddrsResult
             .unwrap_or_else(|_| {
                 error!("Error when trying to get a peer address from PeersManager");
-                None
-            })
-            // Check if PeersSocketAddrResult returned `None`
-            .or_else(|| {
-                warn!("Did not obtain any peer addresses from PeersManager");
-                None
-            })
-            // Filter the result checking if outbound address is eligible as new peer
-            .filter(|address: &SocketAddr| {
-                self.sessions.is_outbound_address_eligible(address.clone())
-            })
+                vec![]
+            });
+
+        // Check if PeersSocketAddrsResult returned no addresses
+        if candidates.is_empty() {
+            warn!("Did not obtain any peer addresses from PeersManager");
+            return actix::fut::err(());
+        }
+
+        // Filter out the banned addresses
+        let now = get_timestamp();
+        let candidates: Vec<_> = candidates
+            .into_iter()
+            .filter(|address| !self.bans.is_banned(address.ip(), now))
+            .collect();
+
+        self.sessions
+            // Select an eligible address, preferring the least used network groups
+            .select_outbound_candidate(&candidates)
             // Check if there is a peer after filter
             .or_else(|| {
                 debug!(
-                    "The peer address obtained from PeersManager is not eligible for a new session"
+                    "None of the peer addresses obtained from PeersManager is eligible for a new session"
                 );
                 None
             })
@@ -225,6 +268,43 @@ impl SessionsManager {
             .wait(ctx);
     }
 
+    /// Ban an IP address and close all the sessions with it, for at most `MAX_BAN_DURATION`
+    /// Returns the timestamp when the ban expires
+    fn ban_ip(&mut self, ctx: &mut Context<Self>, ip: IpAddr, duration: Duration) -> i64 {
+        let duration = std::cmp::min(duration, MAX_BAN_DURATION);
+        let seconds = i64::try_from(duration.as_secs()).unwrap_or(i64::max_value());
+        let until = get_timestamp().saturating_add(seconds);
+        self.bans.ban(ip, until);
+        warn!("Banned peer {} until timestamp {}", ip, until);
+
+        for session_addr in self.sessions.get_sessions_by_ip(ip) {
+            session_addr.do_send(CloseSession);
+        }
+
+        self.persist_bans(ctx);
+
+        until
+    }
+
+    /// Method to persist the banned IP addresses into storage
+    /// Does nothing until the bans have been loaded from storage, which persists them again
+    fn persist_bans(&mut self, ctx: &mut Context<Self>) {
+        self.bans.remove_expired(get_timestamp());
+
+        if !self.bans_loaded {
+            return;
+        }
+
+        storage_mngr::put(&BANS_KEY, &self.bans)
+            .into_actor(self)
+            .and_then(|_, _, _| {
+                debug!("SessionsManager successfully persisted bans to storage");
+                actix::fut::ok(())
+            })
+            .map_err(|err, _, _| error!("Sessions manager persist bans to storage failed: {}", err))
+            .spawn(ctx);
+    }
+
     fn clear_beacons(&mut self) {
         self.beacons.clear();
         for socket_addr in self.sessions.outbound_consolidated.collection.keys() {
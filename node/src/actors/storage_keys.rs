@@ -1,8 +1,18 @@
 /// Constant to specify the peers key for the storage
 pub static PEERS_KEY: &'static [u8] = b"peers";
 
+/// Constant to specify the banned peers key for the storage
+pub static BANS_KEY: &'static [u8] = b"bans";
+
 /// Constant to specify the chain state key for the storage
 pub static CHAIN_STATE_KEY: &'static [u8] = b"chain";
 
 /// Constant to specify the secret key key for the storage
 pub static MASTER_KEY: &'static [u8] = b"master_key";
+
+/// Constant to specify the key for the mnemonic of the secret key in the storage
+pub static MASTER_KEY_MNEMONIC: &'static [u8] = b"master_key_mnemonic";
+
+/// Constant to prefix the keys of the transaction pointers in the storage, which are followed by
+/// the hash of the transaction
+pub static TRANSACTION_POINTER_PREFIX: &'static [u8] = b"transaction_pointer";
@@ -5,6 +5,7 @@ use std::default::Default;
 use std::path::PathBuf;
 use std::sync::Arc;
 use witnet_config::{config::Config, loaders::toml};
+use witnet_data_structures::set_environment;
 
 /// Start the configuration manager
 pub fn start() {
@@ -25,6 +26,12 @@ pub fn load_from_file(filename: PathBuf) -> impl Future<Item = (), Error = failu
     addr.send(Load(Source::File(filename))).flatten()
 }
 
+/// Substitute configuration in the manager with the given one.
+pub fn set(config: Config) -> impl Future<Item = (), Error = failure::Error> {
+    let addr = actix::System::current().registry().get::<ConfigManager>();
+    addr.send(Load(Source::Config(Box::new(config)))).flatten()
+}
+
 /// Config manager: Actor that manages the application configuration
 ///
 /// This actor is in charge of reading the configuration for the
@@ -50,6 +57,8 @@ enum Source {
     Default,
     /// The configuration is loaded from the given path
     File(PathBuf),
+    /// The given configuration is used
+    Config(Box<Config>),
 }
 
 impl Default for ConfigManager {
@@ -114,8 +123,10 @@ impl ConfigManager {
         let new_config = match source {
             Source::Default => Config::default(),
             Source::File(filename) => Config::from_partial(&toml::from_file(filename)?),
+            Source::Config(config) => (**config).clone(),
         };
 
+        set_environment(new_config.environment.clone());
         self.config = Arc::new(new_config);
 
         Ok(())
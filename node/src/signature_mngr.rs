@@ -2,23 +2,82 @@
 //!
 //! This module provides a Signature Manager, which, after being
 //! initialized with a key, can be used repeatedly to sign data with
-//! that key.
+//! that key or with keys derived from it.
 use actix::prelude::*;
 use failure;
-use failure::bail;
+use failure::{bail, Fail};
 use futures::future::Future;
 use log;
+use serde::{Deserialize, Serialize};
 
-use crate::{actors::storage_keys::MASTER_KEY, storage_mngr};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    actors::storage_keys::{MASTER_KEY, MASTER_KEY_MNEMONIC},
+    config_mngr, storage_mngr,
+};
 
 use witnet_crypto::{
-    key::{ExtendedSK, MasterKeyGen, SignContext, PK, SK},
-    mnemonic::MnemonicGen,
+    cipher,
+    key::{ChildNumber, ExtendedSK, MasterKeyGen, SignContext, PK, SK},
+    mnemonic::{Lang, Mnemonic, MnemonicGen},
+    pbkdf2::pbkdf2_sha256,
     signature,
 };
 use witnet_data_structures::chain::{
     ExtendedSecretKey, Hash, Hashable, KeyedSignature, PublicKey, Signature,
 };
+use witnet_protected::Protected;
+use witnet_util::parser::{parse_hex_strict, to_hex};
+
+const NONCE_LENGTH: usize = cipher::CHACHA20_POLY1305_NONCE_LENGTH;
+const SALT_LENGTH: usize = 32;
+const HASH_ITER_COUNT: u32 = 10_000;
+/// Purpose of the keys used by the node to sign, as the first level of their derivation path
+const KEY_PURPOSE: u32 = 3;
+/// Coin type of the Witnet keys, as registered in SLIP-0044
+const COIN_TYPE: u32 = 4919;
+
+/// Derivation path of a key, relative to the master key
+pub type KeyPath = Vec<ChildNumber>;
+
+/// Role of a signing identity of the node. Each role signs with its own key derived from the
+/// master key, so that a compromise of one of them does not expose the others
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyRole {
+    /// Sign the beacon of mined blocks
+    Block,
+    /// Sign commit transactions
+    Commit,
+    /// Sign reveal transactions
+    Reveal,
+    /// Sign tally transactions
+    Tally,
+}
+
+impl KeyRole {
+    /// Derivation path of the key used for this role: `m/3'/4919'/0'/<role>'`
+    pub fn path(self) -> KeyPath {
+        let index = match self {
+            KeyRole::Block => 0,
+            KeyRole::Commit => 1,
+            KeyRole::Reveal => 2,
+            KeyRole::Tally => 3,
+        };
+
+        vec![
+            ChildNumber::hardened(KEY_PURPOSE),
+            ChildNumber::hardened(COIN_TYPE),
+            ChildNumber::hardened(0),
+            ChildNumber::hardened(index),
+        ]
+    }
+}
 
 /// Start the signature manager
 pub fn start() {
@@ -49,9 +108,187 @@ where
     addr.send(Sign(data_hash.to_vec())).flatten()
 }
 
+/// Sign a piece of data with the key derived from the master key using the given path.
+///
+/// Derived keys are cached, so only the first signature with each path pays for the derivation
+pub fn sign_with<T>(
+    path: KeyPath,
+    data: &T,
+) -> impl Future<Item = KeyedSignature, Error = failure::Error>
+where
+    T: Hashable,
+{
+    let addr = actix::System::current()
+        .registry()
+        .get::<SignatureManager>();
+    let Hash::SHA256(data_hash) = data.hash();
+
+    addr.send(SignWith(path, data_hash.to_vec())).flatten()
+}
+
+/// Get the public key of the key derived from the master key using the given path
+pub fn derived_public_key(path: KeyPath) -> impl Future<Item = PublicKey, Error = failure::Error> {
+    let addr = actix::System::current()
+        .registry()
+        .get::<SignatureManager>();
+    addr.send(GetDerivedPublicKey(path)).flatten()
+}
+
+/// Get the public key used by the node to sign
+pub fn public_key() -> impl Future<Item = PublicKey, Error = failure::Error> {
+    let addr = actix::System::current()
+        .registry()
+        .get::<SignatureManager>();
+    addr.send(GetPublicKey).flatten()
+}
+
+/// Format of an exported master key
+#[derive(Debug)]
+pub enum KeyExportFormat {
+    /// The BIP39 mnemonic the key was generated from. Only available if the key was generated by
+    /// this node or imported from a mnemonic
+    Mnemonic,
+    /// The extended key encrypted with a password, encoded as hex
+    Encrypted(Protected),
+}
+
+/// Export the master key of the node
+pub fn export_master_key(
+    format: KeyExportFormat,
+) -> impl Future<Item = String, Error = failure::Error> {
+    let addr = actix::System::current()
+        .registry()
+        .get::<SignatureManager>();
+    addr.send(ExportMasterKey(format)).flatten()
+}
+
+/// Replace the master key of the node with an imported one and persist it into the storage.
+/// Fails if the master key is read from the file set in `master_key_path`, because that file
+/// would replace the imported key on the next restart.
+///
+/// Returns the new public key of the node
+pub fn import_master_key(
+    master_key: MasterKey,
+) -> impl Future<Item = PublicKey, Error = failure::Error> {
+    let addr = actix::System::current()
+        .registry()
+        .get::<SignatureManager>();
+    addr.send(SetMasterKey {
+        master_key,
+        persist: true,
+    })
+    .flatten()
+}
+
+/// Errors when decrypting an exported master key
+#[derive(Debug, PartialEq, Fail, Serialize)]
+pub enum MasterKeyError {
+    /// The encrypted key is not hex or it is too short
+    #[fail(display = "Malformed encrypted master key")]
+    Malformed,
+    /// The encrypted key does not match the password, either because the password is wrong or
+    /// because the key was modified
+    #[fail(display = "Wrong password or corrupted encrypted master key")]
+    WrongPassword,
+    /// The decrypted key is not a valid extended key
+    #[fail(display = "The decrypted master key is not valid")]
+    InvalidKey,
+}
+
+/// Master key of the node, along with the mnemonic it was generated from, if known
+#[derive(Clone, Debug)]
+pub struct MasterKey {
+    /// BIP32 extended secret key
+    pub key: ExtendedSK,
+    /// BIP39 mnemonic phrase
+    pub mnemonic: Option<String>,
+}
+
+impl MasterKey {
+    /// Generate the master key from a BIP39 mnemonic phrase, validating its checksum.
+    /// Node keys always use an empty passphrase
+    pub fn from_mnemonic(phrase: &str) -> Result<Self, failure::Error> {
+        let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mnemonic = Mnemonic::from_phrase(phrase.clone(), Lang::English)?;
+        let key = MasterKeyGen::new(mnemonic.seed("")).generate()?;
+
+        Ok(MasterKey {
+            key,
+            mnemonic: Some(phrase),
+        })
+    }
+
+    /// Decrypt a master key exported with `KeyExportFormat::Encrypted`. Fails with a
+    /// `MasterKeyError`
+    pub fn from_encrypted(encrypted: &str, password: &[u8]) -> Result<Self, failure::Error> {
+        let bytes = parse_hex_strict(encrypted.trim()).map_err(|_| MasterKeyError::Malformed)?;
+        if bytes.len() < NONCE_LENGTH + cipher::CHACHA20_POLY1305_TAG_LENGTH + SALT_LENGTH {
+            return Err(MasterKeyError::Malformed.into());
+        }
+        let len = bytes.len();
+        let nonce = &bytes[..NONCE_LENGTH];
+        let data = &bytes[NONCE_LENGTH..len - SALT_LENGTH];
+        let salt = &bytes[len - SALT_LENGTH..];
+        let secret = pbkdf2_sha256(password, salt, HASH_ITER_COUNT);
+        // The authentication tag rejects wrong passwords, which would otherwise decrypt into a
+        // random key
+        let decrypted = Protected::new(
+            cipher::decrypt_chacha20_poly1305(&secret, data, nonce)
+                .map_err(|_| MasterKeyError::WrongPassword)?,
+        );
+        let key =
+            ExtendedSK::from_slice(decrypted.as_ref()).map_err(|_| MasterKeyError::InvalidKey)?;
+
+        Ok(MasterKey {
+            key,
+            mnemonic: None,
+        })
+    }
+
+    /// Read a master key from a file containing either a BIP39 mnemonic phrase or the
+    /// extended key encoded as hex: the secret key followed by the chain code
+    pub fn from_file(path: &Path) -> Result<Self, failure::Error> {
+        let contents = Protected::new(fs::read(path)?);
+        let contents = std::str::from_utf8(contents.as_ref())?.trim();
+
+        if contents.contains(char::is_whitespace) {
+            Self::from_mnemonic(contents)
+        } else {
+            let key = ExtendedSK::from_slice(&parse_hex_strict(contents)?)?;
+
+            Ok(MasterKey {
+                key,
+                mnemonic: None,
+            })
+        }
+    }
+
+    /// Encrypt the extended key with a password, using ChaCha20-Poly1305 with a key derived from
+    /// the password with PBKDF2. The result is the nonce, followed by the encrypted key with its
+    /// authentication tag and by the salt of the key derivation
+    fn encrypt(&self, password: &[u8]) -> Result<String, failure::Error> {
+        let nonce = cipher::generate_random(NONCE_LENGTH)?;
+        let salt = cipher::generate_random(SALT_LENGTH)?;
+        let secret = pbkdf2_sha256(password, &salt, HASH_ITER_COUNT);
+        let key_bytes = Protected::new(self.key.to_bytes().to_vec());
+        let encrypted = cipher::encrypt_chacha20_poly1305(&secret, key_bytes.as_ref(), &nonce);
+
+        let mut bytes = nonce;
+        bytes.extend(encrypted);
+        bytes.extend(salt);
+
+        Ok(to_hex(&bytes))
+    }
+}
+
 #[derive(Debug, Default)]
 struct SignatureManager {
     keypair: Option<(SK, PK)>,
+    master_key: Option<MasterKey>,
+    /// File the master key was read from, if it was not loaded from the storage
+    master_key_path: Option<PathBuf>,
+    /// Keys derived from the master key, by derivation path
+    derived_keys: HashMap<KeyPath, (SK, PK)>,
 }
 
 impl SignatureManager {
@@ -59,58 +296,127 @@ impl SignatureManager {
         let public_key = PK::from_secret_key(&SignContext::signing_only(), &key);
         self.keypair = Some((key, public_key));
     }
+
+    fn derived_keypair(&mut self, path: KeyPath) -> Result<(SK, PK), failure::Error> {
+        if let Some(keypair) = self.derived_keys.get(&path) {
+            return Ok(*keypair);
+        }
+
+        let master_key = match &self.master_key {
+            Some(master_key) => master_key,
+            None => bail!("Signature Manager cannot derive keys because it contains no master key"),
+        };
+        let secret_key = master_key.key.derive(path.clone())?.secret_key;
+        let public_key = PK::from_secret_key(&SignContext::signing_only(), &secret_key);
+        self.derived_keys.insert(path, (secret_key, public_key));
+
+        Ok((secret_key, public_key))
+    }
+}
+
+fn sign_data((secret, public): (SK, PK), data: &[u8]) -> KeyedSignature {
+    let signature = signature::sign(secret, data);
+
+    KeyedSignature {
+        signature: Signature::from(signature),
+        public_key: PublicKey::from(public),
+    }
 }
 
 struct SetKey(SK);
 struct Sign(Vec<u8>);
+struct SignWith(KeyPath, Vec<u8>);
+struct GetPublicKey;
+struct GetDerivedPublicKey(KeyPath);
+struct ExportMasterKey(KeyExportFormat);
+struct SetMasterKey {
+    master_key: MasterKey,
+    persist: bool,
+}
 
-fn persist_master_key(master_key: ExtendedSK) -> impl Future<Item = (), Error = failure::Error> {
-    let master_key = ExtendedSecretKey::from(master_key);
+fn persist_master_key(master_key: MasterKey) -> impl Future<Item = (), Error = failure::Error> {
+    let mnemonic = master_key.mnemonic;
+    let master_key = ExtendedSecretKey::from(master_key.key);
 
-    storage_mngr::put(&MASTER_KEY, &master_key).inspect(|_| {
-        log::debug!("Successfully persisted the extended secret key into storage");
-    })
+    storage_mngr::put(&MASTER_KEY, &master_key)
+        .and_then(move |_| match mnemonic {
+            Some(mnemonic) => {
+                futures::future::Either::A(storage_mngr::put(&MASTER_KEY_MNEMONIC, &mnemonic))
+            }
+            None => futures::future::Either::B(storage_mngr::delete(&MASTER_KEY_MNEMONIC)),
+        })
+        .inspect(|_| {
+            log::debug!("Successfully persisted the extended secret key into storage");
+        })
 }
 
-fn create_master_key() -> Box<dyn Future<Item = SK, Error = failure::Error>> {
+fn create_master_key() -> Box<dyn Future<Item = MasterKey, Error = failure::Error>> {
     log::info!("Generating and persisting a new master key for this node");
 
     // Create a new master key
     let mnemonic = MnemonicGen::new().generate();
-    let seed = mnemonic.seed("");
-    match MasterKeyGen::new(seed).generate() {
+    match MasterKey::from_mnemonic(mnemonic.words()) {
         Ok(master_key) => {
-            let fut = persist_master_key(master_key.clone()).map(move |_| master_key.secret_key);
+            let fut = persist_master_key(master_key.clone()).map(move |_| master_key);
 
             Box::new(fut)
         }
         Err(e) => {
-            let fut = futures::future::err(e.into());
+            let fut = futures::future::err(e);
 
             Box::new(fut)
         }
     }
 }
 
+fn load_master_key_from_storage() -> impl Future<Item = MasterKey, Error = failure::Error> {
+    storage_mngr::get::<_, ExtendedSecretKey>(&MASTER_KEY).and_then(
+        move |master_key_from_storage| {
+            master_key_from_storage.map_or_else(create_master_key, |master_key| {
+                let fut =
+                    storage_mngr::get::<_, String>(&MASTER_KEY_MNEMONIC).map(move |mnemonic| {
+                        MasterKey {
+                            key: master_key.into(),
+                            mnemonic,
+                        }
+                    });
+
+                Box::new(fut)
+            })
+        },
+    )
+}
+
 impl Actor for SignatureManager {
     type Context = Context<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
         log::debug!("Signature Manager actor has been started!");
 
-        storage_mngr::get::<_, ExtendedSecretKey>(&MASTER_KEY)
-            .and_then(move |master_key_from_storage| {
-                master_key_from_storage.map_or_else(create_master_key, |master_key| {
-                    let master_key: ExtendedSK = master_key.into();
-                    let fut = futures::future::ok(master_key.secret_key);
+        config_mngr::get()
+            .and_then(|config| {
+                let master_key_path = config.storage.master_key_path.clone();
+                let master_key = match &master_key_path {
+                    // A master key given in an external file takes priority over the storage
+                    Some(path) => {
+                        log::info!("Reading the master key of this node from {:?}", path);
+                        futures::future::Either::A(futures::future::result(MasterKey::from_file(
+                            path,
+                        )))
+                    }
+                    None => futures::future::Either::B(load_master_key_from_storage()),
+                };
 
-                    Box::new(fut)
-                })
+                master_key.map(move |master_key| (master_key, master_key_path))
             })
             .map_err(|e| log::error!("Couldn't initialize Signature Manager: {}", e))
             .into_actor(self)
-            .map(|secret_key, _, ctx| {
-                ctx.notify(SetKey(secret_key));
+            .map(|(master_key, master_key_path), act, ctx| {
+                act.master_key_path = master_key_path;
+                ctx.notify(SetMasterKey {
+                    master_key,
+                    persist: false,
+                });
             })
             .wait(ctx);
     }
@@ -128,6 +434,26 @@ impl Message for Sign {
     type Result = Result<KeyedSignature, failure::Error>;
 }
 
+impl Message for SignWith {
+    type Result = Result<KeyedSignature, failure::Error>;
+}
+
+impl Message for GetPublicKey {
+    type Result = Result<PublicKey, failure::Error>;
+}
+
+impl Message for GetDerivedPublicKey {
+    type Result = Result<PublicKey, failure::Error>;
+}
+
+impl Message for ExportMasterKey {
+    type Result = Result<String, failure::Error>;
+}
+
+impl Message for SetMasterKey {
+    type Result = Result<PublicKey, failure::Error>;
+}
+
 impl Handler<SetKey> for SignatureManager {
     type Result = <SetKey as Message>::Result;
 
@@ -145,16 +471,202 @@ impl Handler<Sign> for SignatureManager {
 
     fn handle(&mut self, Sign(data): Sign, _ctx: &mut Self::Context) -> Self::Result {
         match self.keypair {
-            Some((secret, public)) => {
-                let signature = signature::sign(secret, &data);
-                let keyed_signature = KeyedSignature {
-                    signature: Signature::from(signature),
-                    public_key: PublicKey::from(public),
-                };
-
-                Ok(keyed_signature)
-            }
+            Some(keypair) => Ok(sign_data(keypair, &data)),
             None => bail!("Signature Manager cannot sign because it contains no key"),
         }
     }
 }
+
+impl Handler<SignWith> for SignatureManager {
+    type Result = <SignWith as Message>::Result;
+
+    fn handle(&mut self, SignWith(path, data): SignWith, _ctx: &mut Self::Context) -> Self::Result {
+        let keypair = self.derived_keypair(path)?;
+
+        Ok(sign_data(keypair, &data))
+    }
+}
+
+impl Handler<GetPublicKey> for SignatureManager {
+    type Result = <GetPublicKey as Message>::Result;
+
+    fn handle(&mut self, _msg: GetPublicKey, _ctx: &mut Self::Context) -> Self::Result {
+        match self.keypair {
+            Some((_secret, public)) => Ok(PublicKey::from(public)),
+            None => bail!("Signature Manager contains no key"),
+        }
+    }
+}
+
+impl Handler<GetDerivedPublicKey> for SignatureManager {
+    type Result = <GetDerivedPublicKey as Message>::Result;
+
+    fn handle(
+        &mut self,
+        GetDerivedPublicKey(path): GetDerivedPublicKey,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        let (_secret, public) = self.derived_keypair(path)?;
+
+        Ok(PublicKey::from(public))
+    }
+}
+
+impl Handler<ExportMasterKey> for SignatureManager {
+    type Result = <ExportMasterKey as Message>::Result;
+
+    fn handle(
+        &mut self,
+        ExportMasterKey(format): ExportMasterKey,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        let master_key = match &self.master_key {
+            Some(master_key) => master_key,
+            None => bail!("Signature Manager contains no master key"),
+        };
+
+        match format {
+            KeyExportFormat::Mnemonic => match &master_key.mnemonic {
+                Some(mnemonic) => Ok(mnemonic.clone()),
+                None => bail!(
+                    "The master key of this node was not generated from a mnemonic, \
+                     export it encrypted instead"
+                ),
+            },
+            KeyExportFormat::Encrypted(password) => master_key.encrypt(password.as_ref()),
+        }
+    }
+}
+
+impl Handler<SetMasterKey> for SignatureManager {
+    type Result = ResponseActFuture<Self, PublicKey, failure::Error>;
+
+    fn handle(&mut self, msg: SetMasterKey, _ctx: &mut Self::Context) -> Self::Result {
+        let SetMasterKey {
+            master_key,
+            persist,
+        } = msg;
+
+        // The persisted key would be silently replaced by the one in the file on restart
+        if let (true, Some(path)) = (persist, &self.master_key_path) {
+            return Box::new(actix::fut::err(failure::format_err!(
+                "The master key of this node is read from {:?}: replace the contents of that \
+                 file or remove `master_key_path` from the configuration to import a new key",
+                path
+            )));
+        }
+
+        let persisted = if persist {
+            futures::future::Either::A(persist_master_key(master_key.clone()))
+        } else {
+            futures::future::Either::B(futures::future::ok(()))
+        };
+
+        let fut = persisted.into_actor(self).map(move |_, act, _ctx| {
+            act.set_key(master_key.key.secret_key);
+            act.master_key = Some(master_key);
+            // Keys derived from the previous master key are no longer valid
+            act.derived_keys.clear();
+
+            log::info!("Signature Manager received a master key and is ready to sign");
+
+            let (_secret, public) = act.keypair.expect("Key was just set");
+            PublicKey::from(public)
+        });
+
+        Box::new(fut)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_master_key_from_mnemonic() {
+        let phrase = "panda eyebrow bullet gorilla call smoke muffin taste mesh discover soft ostrich alcohol speed nation flash devote level hobby quick inner drive ghost inside";
+        let master_key = MasterKey::from_mnemonic(phrase).unwrap();
+
+        // Same key as generating it step by step
+        let mnemonic = Mnemonic::from_phrase(phrase.to_string(), Lang::English).unwrap();
+        let expected = MasterKeyGen::new(mnemonic.seed("")).generate().unwrap();
+        assert_eq!(master_key.key, expected);
+        assert_eq!(master_key.mnemonic, Some(phrase.to_string()));
+
+        // Unknown word
+        let phrase = phrase.replace("panda", "witnet");
+        assert!(MasterKey::from_mnemonic(&phrase).is_err());
+    }
+
+    #[test]
+    fn test_master_key_encrypt_decrypt() {
+        let mnemonic = MnemonicGen::new().generate();
+        let master_key = MasterKey::from_mnemonic(mnemonic.words()).unwrap();
+        let encrypted = master_key.encrypt(b"password").unwrap();
+
+        let decrypted = MasterKey::from_encrypted(&encrypted, b"password").unwrap();
+        assert_eq!(decrypted.key, master_key.key);
+        assert_eq!(decrypted.mnemonic, None);
+
+        let error = MasterKey::from_encrypted(&encrypted, b"other password").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<MasterKeyError>(),
+            Some(&MasterKeyError::WrongPassword)
+        );
+        let error = MasterKey::from_encrypted("abcd", b"password").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<MasterKeyError>(),
+            Some(&MasterKeyError::Malformed)
+        );
+
+        // Any modification is detected, instead of decrypting into another key
+        let mut tampered = parse_hex_strict(&encrypted).unwrap();
+        tampered[NONCE_LENGTH] ^= 1;
+        let error = MasterKey::from_encrypted(&to_hex(&tampered), b"password").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<MasterKeyError>(),
+            Some(&MasterKeyError::WrongPassword)
+        );
+
+        // Invalid hex characters are not skipped
+        let error =
+            MasterKey::from_encrypted(&format!("{}zz", encrypted), b"password").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<MasterKeyError>(),
+            Some(&MasterKeyError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_derived_keys() {
+        let mnemonic = MnemonicGen::new().generate();
+        let master_key = MasterKey::from_mnemonic(mnemonic.words()).unwrap();
+        let mut signature_manager = SignatureManager::default();
+
+        // No master key yet
+        assert!(signature_manager
+            .derived_keypair(KeyRole::Block.path())
+            .is_err());
+
+        signature_manager.master_key = Some(master_key.clone());
+        let block_keypair = signature_manager
+            .derived_keypair(KeyRole::Block.path())
+            .unwrap();
+        let commit_keypair = signature_manager
+            .derived_keypair(KeyRole::Commit.path())
+            .unwrap();
+        assert_ne!(block_keypair, commit_keypair);
+        assert_eq!(signature_manager.derived_keys.len(), 2);
+
+        // Cached keys are the same as derived ones
+        let expected = master_key.key.derive(KeyRole::Block.path()).unwrap();
+        assert_eq!(block_keypair.0, expected.secret_key);
+        assert_eq!(
+            signature_manager
+                .derived_keypair(KeyRole::Block.path())
+                .unwrap(),
+            block_keypair
+        );
+        assert_eq!(signature_manager.derived_keys.len(), 2);
+    }
+}
@@ -1 +1,2 @@
 pub mod actors;
+pub mod simulation;
@@ -0,0 +1,345 @@
+//! Network simulation harness
+//!
+//! Runs several Witnet nodes in the same process, each one in its own thread with its own actix
+//! system, connected to each other on loopback ports. The nodes use the hashmap storage backend,
+//! and their epochs are controlled by the harness with `SetTimestamp` messages instead of the
+//! system clock.
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener},
+    sync::mpsc,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use actix::{Addr, Arbiter, System};
+use futures::{
+    future::Future,
+    stream::Stream,
+    sync::{mpsc as future_mpsc, oneshot},
+};
+
+use witnet_config::config::{Config, StorageBackend};
+use witnet_data_structures::chain::{
+    Block, CheckpointBeacon, Epoch, Hash, KeyedSignature, OutputPointer, Transaction,
+    TransactionBody,
+};
+use witnet_node::{
+    actors::{
+        chain_manager::ChainManager,
+        epoch_manager::EpochManager,
+        messages::{
+            AddTransaction, DataRequestStatus, GetBlock, GetConsolidatedPeers, GetDataRequest,
+            GetHighestCheckpointBeacon, SetTimestamp,
+        },
+        node::start_actors,
+        sessions_manager::SessionsManager,
+    },
+    config_mngr, signature_mngr,
+};
+use witnet_p2p::sessions::SessionType;
+use witnet_util::timestamp::get_timestamp;
+
+mod network;
+
+/// Period between checkpoints of the simulated network, in seconds
+const CHECKPOINTS_PERIOD: u16 = 10;
+
+/// Time given to the nodes to mine and relay a block after the start of each epoch. Blocks are
+/// mined right after the checkpoint
+const EPOCH_WAIT: Duration = Duration::from_secs(2);
+
+/// Maximum time to wait for the nodes to connect to each other
+const CONNECTIONS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Period between the checks of `wait_until`
+const POLL_PERIOD: Duration = Duration::from_millis(100);
+
+/// Transaction to be signed by a node, along with the channel to send the signature back
+type SignatureRequest = (TransactionBody, oneshot::Sender<KeyedSignature>);
+
+/// Node running in its own thread
+pub struct Node {
+    /// Address of the P2P server of the node
+    pub address: SocketAddr,
+    epoch_manager: Addr<EpochManager>,
+    chain_manager: Addr<ChainManager>,
+    sessions_manager: Addr<SessionsManager>,
+    /// Transactions to be signed by the signature manager of the node
+    signature_requests: future_mpsc::UnboundedSender<SignatureRequest>,
+    /// Stops the actix system of the node when dropped
+    stop: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Node {
+    /// Start a node with the given configuration, setting its clock to `timestamp`
+    fn start(config: Config, timestamp: i64) -> Self {
+        let address = config.connections.server_addr;
+        let (addrs_sender, addrs_receiver) = mpsc::channel();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let (signature_requests, signature_requests_receiver) = future_mpsc::unbounded();
+
+        let thread = thread::spawn(move || {
+            let system = System::new(format!("node {}", address));
+
+            config_mngr::start();
+            Arbiter::spawn(config_mngr::set(config).map_err(|_| System::current().stop()));
+            start_actors();
+
+            let epoch_manager = System::current().registry().get::<EpochManager>();
+            epoch_manager.do_send(SetTimestamp { timestamp });
+            addrs_sender
+                .send((
+                    epoch_manager,
+                    System::current().registry().get::<ChainManager>(),
+                    System::current().registry().get::<SessionsManager>(),
+                ))
+                .unwrap();
+
+            // The signature manager can only be reached from the system of the node
+            Arbiter::spawn(signature_requests_receiver.for_each(
+                |(body, sender): SignatureRequest| {
+                    signature_mngr::sign(&body).then(move |signature| {
+                        // Dropping the sender makes `sign_transaction` fail
+                        if let Ok(signature) = signature {
+                            let _ = sender.send(signature);
+                        }
+                        Ok(())
+                    })
+                },
+            ));
+
+            Arbiter::spawn(stopped.then(|_| -> Result<(), ()> {
+                System::current().stop();
+                Ok(())
+            }));
+
+            system.run();
+        });
+        let (epoch_manager, chain_manager, sessions_manager) = addrs_receiver.recv().unwrap();
+
+        Node {
+            address,
+            epoch_manager,
+            chain_manager,
+            sessions_manager,
+            signature_requests,
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    /// Beacon of the last block consolidated by the node
+    pub fn beacon(&self) -> CheckpointBeacon {
+        self.chain_manager
+            .send(GetHighestCheckpointBeacon)
+            .wait()
+            .unwrap()
+            .unwrap()
+    }
+
+    /// Block consolidated by the node, if any
+    pub fn block(&self, hash: Hash) -> Option<Block> {
+        self.chain_manager
+            .send(GetBlock { hash })
+            .wait()
+            .unwrap()
+            .ok()
+    }
+
+    /// Add a transaction to the mempool of the node, which relays it to its peers
+    pub fn add_transaction(&self, transaction: Transaction) -> Result<Hash, failure::Error> {
+        self.chain_manager
+            .send(AddTransaction { transaction })
+            .wait()
+            .unwrap()
+    }
+
+    /// Sign a transaction with the key of the node
+    pub fn sign_transaction(&self, body: TransactionBody) -> Transaction {
+        let (sender, receiver) = oneshot::channel();
+        self.signature_requests
+            .unbounded_send((body.clone(), sender))
+            .unwrap();
+        let signature = receiver
+            .wait()
+            .expect("The node failed to sign the transaction");
+
+        Transaction::new(body, vec![signature])
+    }
+
+    /// Progress of a data request, if the node knows it
+    pub fn data_request(&self, dr_pointer: OutputPointer) -> Option<DataRequestStatus> {
+        self.chain_manager
+            .send(GetDataRequest { dr_pointer })
+            .wait()
+            .unwrap()
+            .ok()
+    }
+
+    /// Number of consolidated outbound sessions of the node
+    pub fn outbound_peers(&self) -> usize {
+        self.sessions_manager
+            .send(GetConsolidatedPeers)
+            .wait()
+            .unwrap()
+            .into_iter()
+            .filter(|peer| peer.session_type == SessionType::Outbound)
+            .count()
+    }
+
+    /// Set the clock of the node, notifying its actors if a new epoch starts
+    fn set_timestamp(&self, timestamp: i64) {
+        self.epoch_manager
+            .send(SetTimestamp { timestamp })
+            .wait()
+            .unwrap();
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        // Dropping the sender stops the system of the node
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Network of nodes connected to each other
+pub struct Network {
+    /// Nodes of the network
+    pub nodes: Vec<Node>,
+    /// Timestamp of the checkpoint zero of the network
+    checkpoint_zero: i64,
+    /// Current epoch of all the nodes
+    epoch: Epoch,
+}
+
+impl Network {
+    /// Start a network of `size` nodes in which the first `miners` nodes mine blocks, and wait
+    /// until every node is connected to all the others
+    pub fn start(size: usize, miners: usize) -> Self {
+        let checkpoint_zero = get_timestamp();
+        let addresses: Vec<SocketAddr> = (0..size).map(|_| free_address()).collect();
+
+        let nodes = addresses
+            .iter()
+            .enumerate()
+            .map(|(i, address)| {
+                let mut config = Config::default();
+                config.connections.server_addr = *address;
+                config.connections.known_peers = addresses
+                    .iter()
+                    .filter(|peer| *peer != address)
+                    .cloned()
+                    .collect();
+                config.connections.outbound_limit = (size - 1) as u16;
+                config.connections.bootstrap_peers_period = Duration::from_secs(1);
+                config.storage.backend = StorageBackend::HashMap;
+                config.consensus_constants.checkpoint_zero_timestamp = checkpoint_zero;
+                config.consensus_constants.checkpoints_period = CHECKPOINTS_PERIOD;
+                config.jsonrpc.enabled = false;
+                config.mining.enabled = i < miners;
+                config.mining.block_delay = Duration::from_secs(0);
+
+                Node::start(config, checkpoint_zero)
+            })
+            .collect();
+
+        let network = Network {
+            nodes,
+            checkpoint_zero,
+            epoch: 0,
+        };
+        assert!(
+            wait_until(CONNECTIONS_TIMEOUT, || network
+                .nodes
+                .iter()
+                .all(|node| node.outbound_peers() == size - 1)),
+            "The nodes did not connect to each other"
+        );
+
+        network
+    }
+
+    /// Start the next epoch in all the nodes, and give them time to mine and relay a block
+    pub fn advance_epoch(&mut self) {
+        self.epoch += 1;
+        let timestamp =
+            self.checkpoint_zero + i64::from(self.epoch) * i64::from(CHECKPOINTS_PERIOD);
+        for node in &self.nodes {
+            node.set_timestamp(timestamp);
+        }
+
+        thread::sleep(EPOCH_WAIT);
+    }
+
+    /// Advance epochs until `condition` holds, up to `max_epochs` times
+    ///
+    /// Returns false if the condition does not hold after the last epoch.
+    pub fn advance_until<F>(&mut self, max_epochs: u32, mut condition: F) -> bool
+    where
+        F: FnMut(&Network) -> bool,
+    {
+        for _ in 0..max_epochs {
+            self.advance_epoch();
+            if condition(self) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Beacons of the last block consolidated by each node
+    pub fn beacons(&self) -> Vec<CheckpointBeacon> {
+        self.nodes.iter().map(Node::beacon).collect()
+    }
+}
+
+/// Poll `condition` until it holds, up to `timeout`
+pub fn wait_until<F>(timeout: Duration, mut condition: F) -> bool
+where
+    F: FnMut() -> bool,
+{
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if condition() {
+            return true;
+        }
+        thread::sleep(POLL_PERIOD);
+    }
+
+    condition()
+}
+
+/// Serve `body` to any HTTP request on a loopback port, returning its URL
+pub fn serve_http(body: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        for mut stream in listener.incoming().filter_map(Result::ok) {
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+
+    url
+}
+
+/// Loopback address with a port which is not in use
+fn free_address() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .unwrap()
+}
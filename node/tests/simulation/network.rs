@@ -0,0 +1,119 @@
+use witnet_data_structures::chain::*;
+
+use super::*;
+
+/// Retrieval script: parse the JSON response and take `main.temp` as a float
+const RETRIEVE_SCRIPT: &[u8] = &[
+    150, 83, 204, 132, 146, 1, 164, 109, 97, 105, 110, 204, 132, 146, 1, 164, 116, 101, 109, 112,
+    204, 130,
+];
+
+/// Aggregation and consensus script: average mean
+const MEAN_SCRIPT: &[u8] = &[145, 146, 102, 32];
+
+/// Body of a data request transaction which retrieves a temperature from `url`, spending the
+/// output `input`
+fn data_request_transaction(input: OutputPointer, url: String) -> TransactionBody {
+    let dr_output = DataRequestOutput {
+        data_request: RADRequest {
+            retrieve: vec![RADRetrieve {
+                kind: RADType::HttpGet,
+                url,
+                script: RETRIEVE_SCRIPT.to_vec(),
+            }],
+            aggregate: RADAggregate {
+                script: MEAN_SCRIPT.to_vec(),
+            },
+            consensus: RADConsensus {
+                script: MEAN_SCRIPT.to_vec(),
+            },
+            ..RADRequest::default()
+        },
+        value: 1030,
+        witnesses: 1,
+        commit_fee: 10,
+        reveal_fee: 10,
+        tally_fee: 10,
+        ..DataRequestOutput::default()
+    };
+
+    TransactionBody::new(
+        0,
+        vec![Input::ValueTransfer(ValueTransferInput {
+            transaction_id: input.transaction_id,
+            output_index: input.output_index,
+        })],
+        vec![Output::DataRequest(dr_output)],
+    )
+}
+
+#[test]
+fn blocks_propagate_to_all_nodes() {
+    // Only the first node mines, so the other ones must receive its blocks
+    let mut network = Network::start(3, 1);
+
+    assert!(network.advance_until(5, |network| network.nodes[0].beacon().checkpoint > 0));
+    let beacon = network.nodes[0].beacon();
+    let block = network.nodes[0].block(beacon.hash_prev_block).unwrap();
+
+    for node in &network.nodes[1..] {
+        assert!(
+            wait_until(EPOCH_WAIT, || node.block(beacon.hash_prev_block).is_some()),
+            "Block {} did not reach node {}",
+            beacon.hash_prev_block,
+            node.address
+        );
+        assert_eq!(node.block(beacon.hash_prev_block), Some(block.clone()));
+    }
+}
+
+#[test]
+fn nodes_converge_on_same_beacon() {
+    // All the nodes mine competing blocks, but they agree on the best one
+    let mut network = Network::start(4, 4);
+
+    assert!(network.advance_until(5, |network| network.nodes[0].beacon().checkpoint > 1));
+    network.advance_epoch();
+
+    let beacons = network.beacons();
+    assert!(beacons[0].checkpoint > 1);
+    assert!(beacons.iter().all(|beacon| *beacon == beacons[0]));
+}
+
+#[test]
+fn data_requests_resolve_end_to_end() {
+    let url = serve_http(r#"{"main":{"temp":21.5}}"#);
+    let mut network = Network::start(3, 3);
+
+    // Fund the data request with the reward of the first block
+    assert!(network.advance_until(5, |network| network.nodes[0].beacon().checkpoint > 0));
+    let beacon = network.nodes[0].beacon();
+    let block = network.nodes[0].block(beacon.hash_prev_block).unwrap();
+    let mint = OutputPointer {
+        transaction_id: block.txns[0].hash(),
+        output_index: 0,
+    };
+
+    // Sign with the key of the node which receives the transaction, as its wallet would do
+    let transaction = network.nodes[0].sign_transaction(data_request_transaction(mint, url));
+    let dr_pointer = OutputPointer {
+        transaction_id: network.nodes[0].add_transaction(transaction).unwrap(),
+        output_index: 0,
+    };
+
+    // Every node sees the tally of the data request
+    assert!(
+        network.advance_until(15, |network| network.nodes.iter().all(|node| node
+            .data_request(dr_pointer.clone())
+            .map_or(false, |status| status.tally.is_some()))),
+        "Data request {} was not resolved",
+        dr_pointer
+    );
+    for node in &network.nodes {
+        let status = node.data_request(dr_pointer.clone()).unwrap();
+        assert_eq!(status.stage, None);
+        assert!(!status.commits.is_empty());
+        assert!(!status.reveals.is_empty());
+        assert!(status.result.is_some());
+    }
+}
@@ -0,0 +1,132 @@
+//! Library for keeping track of misbehaving peers and banning them
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+/// Seconds after which the ban score of a peer decreases by one point, so that occasional
+/// misbehaviour does not add up to a ban: 100 points per hour
+pub const SCORE_DECAY_PERIOD: i64 = 36;
+
+/// Protocol violations which increase the ban score of a peer
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Misbehaviour {
+    /// The peer sent a message which could not be decoded
+    UndecodableMessage,
+    /// The peer sent an item which was not requested
+    UnrequestedItem,
+    /// The peer sent an invalid block candidate
+    InvalidCandidate,
+    /// The peer sent an invalid block during the synchronization
+    InvalidBlock,
+}
+
+impl Misbehaviour {
+    /// Ban score added to a peer for this misbehaviour
+    ///
+    /// Every score is below the default ban threshold of 100, so that a single event, which
+    /// could be caused by a bug or by a peer on a fork, is never enough to ban a peer
+    pub fn score(self) -> u32 {
+        match self {
+            Misbehaviour::UndecodableMessage => 10,
+            Misbehaviour::UnrequestedItem => 5,
+            Misbehaviour::InvalidCandidate => 10,
+            Misbehaviour::InvalidBlock => 50,
+        }
+    }
+}
+
+/// Ban scores of the peers and list of banned IP addresses
+///
+/// Only the list of banned addresses is serialized, the ban scores are reset when the node
+/// restarts. The ban scores decay over time, see [`SCORE_DECAY_PERIOD`](SCORE_DECAY_PERIOD).
+#[derive(Default, Serialize, Deserialize)]
+pub struct Bans {
+    /// Ban score of each peer which has misbehaved, along with the timestamp of its last update
+    #[serde(skip)]
+    scores: HashMap<IpAddr, (u32, i64)>,
+    /// Banned IP addresses along with the timestamp when their ban expires
+    banned: HashMap<IpAddr, i64>,
+}
+
+impl Bans {
+    /// Increase the ban score of an IP address at the given timestamp, after applying the decay
+    /// since its last update
+    /// Returns the new ban score
+    pub fn add_score(&mut self, ip: IpAddr, score: u32, now: i64) -> u32 {
+        let (total, last_update) = self.scores.entry(ip).or_insert((0, now));
+        *total = decayed_score(*total, *last_update, now).saturating_add(score);
+        *last_update = now;
+
+        *total
+    }
+
+    /// Ban an IP address until the given timestamp, resetting its ban score
+    /// If the address was already banned, the ban gets overwritten
+    pub fn ban(&mut self, ip: IpAddr, until: i64) {
+        self.scores.remove(&ip);
+        self.banned.insert(ip, until);
+    }
+
+    /// Lift the ban of an IP address, resetting its ban score
+    /// Returns whether the address was banned
+    pub fn unban(&mut self, ip: IpAddr) -> bool {
+        self.scores.remove(&ip);
+        self.banned.remove(&ip).is_some()
+    }
+
+    /// Check if an IP address is banned at the given timestamp
+    pub fn is_banned(&self, ip: IpAddr, now: i64) -> bool {
+        self.banned
+            .get(&ip)
+            .map(|until| *until > now)
+            .unwrap_or(false)
+    }
+
+    /// Remove the bans which have expired and the ban scores which have decayed to zero at the
+    /// given timestamp
+    /// Returns the addresses which are no longer banned
+    pub fn remove_expired(&mut self, now: i64) -> Vec<IpAddr> {
+        self.scores
+            .retain(|_ip, (score, last_update)| decayed_score(*score, *last_update, now) > 0);
+
+        let expired: Vec<IpAddr> = self
+            .banned
+            .iter()
+            .filter(|(_ip, until)| **until <= now)
+            .map(|(ip, _until)| *ip)
+            .collect();
+
+        for ip in &expired {
+            self.banned.remove(ip);
+        }
+
+        expired
+    }
+
+    /// Add the banned IP addresses of another list
+    /// If an address is banned in both lists, the ban which expires later is kept
+    pub fn extend(&mut self, other: Bans) {
+        for (ip, until) in other.banned {
+            let current = self.banned.entry(ip).or_insert(until);
+            *current = std::cmp::max(*current, until);
+        }
+    }
+
+    /// Get all the banned IP addresses along with the timestamp when their ban expires
+    pub fn get_all(&self) -> Vec<(IpAddr, i64)> {
+        self.banned
+            .iter()
+            .map(|(ip, until)| (*ip, *until))
+            .collect()
+    }
+}
+
+/// Ban score which remains at timestamp `now` from a score last updated at `last_update`
+fn decayed_score(score: u32, last_update: i64, now: i64) -> u32 {
+    let decay = now.saturating_sub(last_update).max(0) / SCORE_DECAY_PERIOD;
+
+    score.saturating_sub(u32::try_from(decay).unwrap_or(u32::max_value()))
+}
@@ -7,6 +7,8 @@
 #![deny(unused_mut)]
 #![deny(missing_docs)]
 
+pub mod bans;
+
 pub mod peers;
 
 pub mod sessions;
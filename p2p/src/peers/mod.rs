@@ -1,85 +1,402 @@
 //! Library for managing a list of available peers
+//!
+//! The addresses are kept in two tables, similar to the address manager of Bitcoin:
+//!
+//! * The __new__ table contains addresses which the node has never connected to successfully.
+//! They are grouped in buckets by the network group of the peer which announced them, so a single
+//! peer cannot fill the table with its own addresses.
+//! * The __tried__ table contains addresses which the node has successfully connected to. They are
+//! grouped in buckets by their own network group.
+//!
+//! The bucket of each address is computed using a random key, so that it cannot be predicted by
+//! an attacker.
+//!
+//! The peers stored in the format used before the new and tried tables, a flat map of addresses,
+//! are loaded into the new table.
 
 use serde::{Deserialize, Serialize};
 
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::hash::{Hash, Hasher};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 use rand::{thread_rng, Rng};
 
 use witnet_util::timestamp::get_timestamp;
 
+/// Number of buckets of the new table
+pub const NEW_BUCKETS: usize = 256;
+
+/// Number of buckets of the tried table
+pub const TRIED_BUCKETS: usize = 64;
+
+/// Maximum number of addresses in each bucket
+pub const BUCKET_SIZE: usize = 64;
+
+/// Number of different buckets of the new table that can be filled with the addresses announced
+/// by the peers of a single network group
+pub const NEW_BUCKETS_PER_SOURCE_GROUP: u64 = 32;
+
+/// Number of different buckets of the tried table that can be filled with the addresses of a
+/// single network group
+const TRIED_BUCKETS_PER_GROUP: u64 = 8;
+
+/// Time after which an address which has not been seen is removed: 30 days
+pub const MAX_ADDRESS_AGE: i64 = 30 * 24 * 60 * 60;
+
+/// Get the network group of an IP address: the /16 prefix for IPv4 addresses and the /32 prefix
+/// for IPv6 addresses
+///
+/// Addresses in the same network group are likely to be controlled by the same entity.
+pub fn network_group(ip: IpAddr) -> Vec<u8> {
+    let ipv4_group = |octets: [u8; 4]| vec![4, octets[0], octets[1]];
+
+    match ip {
+        IpAddr::V4(ip) => ipv4_group(ip.octets()),
+        IpAddr::V6(ip) => match ip.to_ipv4() {
+            Some(ip) => ipv4_group(ip.octets()),
+            None => {
+                let octets = ip.octets();
+                vec![6, octets[0], octets[1], octets[2], octets[3]]
+            }
+        },
+    }
+}
+
+/// Check if an IP address belongs to a loopback, private or link-local range
+///
+/// Peers in these ranges are usually run by the same operator on purpose, so they are not limited
+/// by network group.
+pub fn is_local_address(ip: IpAddr) -> bool {
+    let is_local_ipv4 = |ip: Ipv4Addr| ip.is_loopback() || ip.is_private() || ip.is_link_local();
+
+    match ip {
+        IpAddr::V4(ip) => is_local_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4() {
+            // The unspecified and loopback IPv6 addresses are also IPv4-compatible
+            Some(ipv4) if !ip.is_loopback() && !ip.is_unspecified() => is_local_ipv4(ipv4),
+            _ => {
+                let first_segment = ip.segments()[0];
+                // Unique local (fc00::/7) and link-local (fe80::/10) addresses
+                ip.is_loopback()
+                    || first_segment & 0xfe00 == 0xfc00
+                    || first_segment & 0xffc0 == 0xfe80
+            }
+        },
+    }
+}
+
 /// Peer information being used while listing available Witnet peers
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 struct PeerInfo {
     address: SocketAddr,
-    _timestamp: i64,
+    /// Timestamp of the first time the address was announced or of the last time it was
+    /// connected to, announcing it again does not update it
+    last_seen: i64,
+    /// Timestamp of the last successful connection, 0 if there was none
+    last_success: i64,
 }
 
-/// Peers TBD
-#[derive(Default, Serialize, Deserialize)]
+/// Table of addresses, grouped in buckets
+type Table = Vec<HashMap<SocketAddr, PeerInfo>>;
+
+/// Address manager with new and tried tables
+#[derive(Serialize, Deserialize)]
+#[serde(from = "StoredPeers")]
 pub struct Peers {
-    /// Server sessions
-    peers: HashMap<SocketAddr, PeerInfo>,
+    /// Random key used to select the bucket of the addresses
+    key: u64,
+    /// Addresses which have never been connected to successfully
+    new: Table,
+    /// Addresses which have been connected to successfully
+    tried: Table,
+}
+
+impl Default for Peers {
+    fn default() -> Self {
+        Self {
+            key: thread_rng().gen(),
+            new: vec![HashMap::new(); NEW_BUCKETS],
+            tried: vec![HashMap::new(); TRIED_BUCKETS],
+        }
+    }
+}
+
+/// Peer information of the flat map format
+#[derive(Deserialize)]
+struct LegacyPeerInfo {
+    address: SocketAddr,
+    #[serde(rename = "_timestamp")]
+    timestamp: i64,
+}
+
+/// Formats of the peers found in storage
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredPeers {
+    /// New and tried tables
+    Tables { key: u64, new: Table, tried: Table },
+    /// Flat map of addresses, used before the new and tried tables
+    Legacy {
+        peers: HashMap<SocketAddr, LegacyPeerInfo>,
+    },
+}
+
+impl From<StoredPeers> for Peers {
+    fn from(stored: StoredPeers) -> Self {
+        match stored {
+            StoredPeers::Tables { key, new, tried } => Self { key, new, tried },
+            StoredPeers::Legacy { peers } => {
+                let mut result = Self::default();
+                for info in peers.values() {
+                    if info.address.ip().is_unspecified() {
+                        continue;
+                    }
+
+                    // The addresses of the flat map are used as their own source, like the ones
+                    // added with `add`
+                    result.insert_new(
+                        PeerInfo {
+                            address: info.address,
+                            last_seen: info.timestamp,
+                            last_success: 0,
+                        },
+                        info.address.ip(),
+                    );
+                }
+
+                result
+            }
+        }
+    }
 }
 
 impl Peers {
     /// Add multiple peer addresses and save timestamp
-    /// If an address did already exist, it gets overwritten
-    /// Returns all the overwritten addresses
+    /// If an address did already exist, it is left unchanged
+    /// Returns all the already existing addresses
+    ///
+    /// The addresses are considered to come from a trusted source (the configuration or the
+    /// storage), so each address is used as its own source.
     pub fn add(&mut self, addrs: Vec<SocketAddr>) -> Result<Vec<SocketAddr>, failure::Error> {
-        // Insert address
-        // Note: if the peer address exists, the peer info will be overwritten
         Ok(addrs
             .into_iter()
-            // Filter out unspecified addresses (aka 0.0.0.0)
-            .filter(|address| !address.ip().is_unspecified())
-            .filter_map(|address| {
-                self.peers
-                    .insert(
-                        address,
-                        PeerInfo {
-                            address,
-                            _timestamp: get_timestamp(), //msg.timestamp,
-                        },
-                    )
-                    .map(|v| v.address)
-            })
+            .filter_map(|address| self.add_with_source(address, address.ip()))
+            .collect())
+    }
+
+    /// Add multiple peer addresses announced by the peer at `src`
+    /// If an address did already exist, it is left unchanged
+    /// Returns all the already existing addresses
+    pub fn add_from(
+        &mut self,
+        addrs: Vec<SocketAddr>,
+        src: SocketAddr,
+    ) -> Result<Vec<SocketAddr>, failure::Error> {
+        Ok(addrs
+            .into_iter()
+            .filter_map(|address| self.add_with_source(address, src.ip()))
             .collect())
     }
 
+    /// Move an address to the tried table after a successful connection
+    /// If the address was unknown, it gets added directly to the tried table
+    pub fn mark_success(&mut self, address: SocketAddr) -> Result<(), failure::Error> {
+        let now = get_timestamp();
+
+        let mut info = self.remove_address(&address).unwrap_or_else(|| PeerInfo {
+            address,
+            last_seen: now,
+            last_success: now,
+        });
+        info.last_seen = now;
+        info.last_success = now;
+
+        let bucket = self.tried_bucket(address);
+        if self.tried[bucket].len() >= BUCKET_SIZE {
+            // Move the address with the oldest successful connection back to the new table
+            let oldest = self.tried[bucket]
+                .values()
+                .min_by_key(|info| info.last_success)
+                .map(|info| info.address);
+            if let Some(oldest) = oldest {
+                if let Some(oldest_info) = self.tried[bucket].remove(&oldest) {
+                    self.insert_new(oldest_info, oldest.ip());
+                }
+            }
+        }
+        self.tried[bucket].insert(address, info);
+
+        Ok(())
+    }
+
     /// Remove a peer given an address
     /// Returns the removed addresses
     pub fn remove(&mut self, addrs: &[SocketAddr]) -> Result<Vec<SocketAddr>, failure::Error> {
         Ok(addrs
             .iter()
-            .filter_map(|address| self.peers.remove(&address).map(|info| info.address))
+            .filter_map(|address| self.remove_address(address).map(|info| info.address))
             .collect())
     }
 
+    /// Remove the addresses which have not been seen in `MAX_ADDRESS_AGE` seconds
+    /// Returns the removed addresses
+    pub fn remove_stale(&mut self, now: i64) -> Vec<SocketAddr> {
+        let mut removed = vec![];
+
+        for bucket in self.new.iter_mut().chain(self.tried.iter_mut()) {
+            bucket.retain(|address, info| {
+                let is_stale = now - info.last_seen > MAX_ADDRESS_AGE;
+                if is_stale {
+                    removed.push(*address);
+                }
+
+                !is_stale
+            });
+        }
+
+        removed
+    }
+
     /// Get a random socket address from the peers list
+    ///
+    /// First the table is selected, with the same probability for both tables, then a random
+    /// non-empty bucket of that table, and finally a random address of that bucket.
     pub fn get_random(&mut self) -> Result<Option<SocketAddr>, failure::Error> {
-        // Random index with range [0, len) of the peers vector
-        let index = thread_rng().gen_range(0, std::cmp::max(self.peers.len(), 1));
-
-        // Get element at index
-        let random_addr = self
-            .peers
-            // get peer infos
-            .values()
-            // enumerate them -> (indices, peer info)
-            .enumerate()
-            // filter by index and get address -> Iterator<Option<SocketAddr>>
-            .filter_map(|(i, v)| if i == index { Some(v.address) } else { None })
-            // Get first one, because
-            .next()
-            .map(|v| v.to_owned());
+        let mut rng = thread_rng();
+
+        let new_buckets = non_empty_buckets(&self.new);
+        let tried_buckets = non_empty_buckets(&self.tried);
+
+        let buckets = match (new_buckets.is_empty(), tried_buckets.is_empty()) {
+            (true, true) => return Ok(None),
+            (false, true) => new_buckets,
+            (true, false) => tried_buckets,
+            (false, false) => {
+                if rng.gen() {
+                    new_buckets
+                } else {
+                    tried_buckets
+                }
+            }
+        };
 
-        Ok(random_addr)
+        let bucket = buckets[rng.gen_range(0, buckets.len())];
+        let index = rng.gen_range(0, bucket.len());
+
+        Ok(bucket.values().nth(index).map(|info| info.address))
     }
 
     /// Get all the peers from the list
     pub fn get_all(&self) -> Result<Vec<SocketAddr>, failure::Error> {
-        Ok(self.peers.values().map(|v| v.address).collect())
+        Ok(self
+            .new
+            .iter()
+            .chain(self.tried.iter())
+            .flat_map(|bucket| bucket.keys().cloned())
+            .collect())
+    }
+
+    /// Get the addresses of the tried table
+    pub fn get_tried(&self) -> Vec<SocketAddr> {
+        self.tried
+            .iter()
+            .flat_map(|bucket| bucket.keys().cloned())
+            .collect()
     }
+
+    /// Add an address announced by `src`, returning it if it already existed
+    ///
+    /// The timestamps of an existing address are not updated, otherwise any peer could keep
+    /// unreachable addresses from becoming stale by announcing them again.
+    fn add_with_source(&mut self, address: SocketAddr, src: IpAddr) -> Option<SocketAddr> {
+        // Filter out unspecified addresses (aka 0.0.0.0)
+        if address.ip().is_unspecified() {
+            return None;
+        }
+
+        if self.contains(&address) {
+            return Some(address);
+        }
+
+        self.insert_new(
+            PeerInfo {
+                address,
+                last_seen: get_timestamp(),
+                last_success: 0,
+            },
+            src,
+        );
+
+        None
+    }
+
+    /// Insert an address into the new table, evicting the oldest address of its bucket if it
+    /// is full
+    fn insert_new(&mut self, info: PeerInfo, src: IpAddr) {
+        let bucket = self.new_bucket(info.address, src);
+
+        if self.new[bucket].len() >= BUCKET_SIZE {
+            let oldest = self.new[bucket]
+                .values()
+                .min_by_key(|info| info.last_seen)
+                .map(|info| info.address);
+            if let Some(oldest) = oldest {
+                self.new[bucket].remove(&oldest);
+            }
+        }
+
+        self.new[bucket].insert(info.address, info);
+    }
+
+    /// Check if an address is in any of the tables
+    fn contains(&self, address: &SocketAddr) -> bool {
+        self.new
+            .iter()
+            .chain(self.tried.iter())
+            .any(|bucket| bucket.contains_key(address))
+    }
+
+    /// Remove an address from any of the tables
+    fn remove_address(&mut self, address: &SocketAddr) -> Option<PeerInfo> {
+        self.new
+            .iter_mut()
+            .chain(self.tried.iter_mut())
+            .filter_map(|bucket| bucket.remove(address))
+            .next()
+    }
+
+    /// Bucket of the new table for an address announced by `src`
+    fn new_bucket(&self, address: SocketAddr, src: IpAddr) -> usize {
+        let group = network_group(address.ip());
+        let src_group = network_group(src);
+
+        let slot = self.hash(&(&group, &src_group)) % NEW_BUCKETS_PER_SOURCE_GROUP;
+
+        (self.hash(&(&src_group, slot)) % NEW_BUCKETS as u64) as usize
+    }
+
+    /// Bucket of the tried table for an address
+    fn tried_bucket(&self, address: SocketAddr) -> usize {
+        let group = network_group(address.ip());
+
+        let slot = self.hash(&address) % TRIED_BUCKETS_PER_GROUP;
+
+        (self.hash(&(&group, slot)) % TRIED_BUCKETS as u64) as usize
+    }
+
+    /// Hash some data along with the key of this address manager
+    fn hash<T: Hash>(&self, data: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.key.hash(&mut hasher);
+        data.hash(&mut hasher);
+
+        hasher.finish()
+    }
+}
+
+/// Get the non-empty buckets of a table
+fn non_empty_buckets(table: &Table) -> Vec<&HashMap<SocketAddr, PeerInfo>> {
+    table.iter().filter(|bucket| !bucket.is_empty()).collect()
 }
@@ -3,14 +3,22 @@
 /// Bounded sessions module
 pub mod bounded_sessions;
 
-use std::{net::SocketAddr, time::Duration};
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
 
 use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
 
-use super::{error::SessionsError, sessions::bounded_sessions::BoundedSessions};
+use super::{
+    error::SessionsError,
+    peers::{is_local_address, network_group},
+    sessions::bounded_sessions::BoundedSessions,
+};
 
 /// Session type
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum SessionType {
     /// Inbound session
     Inbound,
@@ -58,6 +66,14 @@ where
     pub magic_number: u16,
     /// Timeout for requested blocks
     pub blocks_timeout: i64,
+    /// Period between pings to each consolidated session
+    pub ping_period: Duration,
+    /// Timeout for the reply to a ping
+    pub ping_timeout: Duration,
+    /// Maximum clock difference allowed with a peer during the handshake
+    pub handshake_max_time_skew: Duration,
+    /// Maximum number of outbound sessions with peers in the same network group
+    pub outbound_limit_per_network_group: Option<u16>,
 }
 
 /// Default trait implementation
@@ -76,6 +92,10 @@ where
             handshake_timeout: Duration::default(),
             magic_number: 0 as u16,
             blocks_timeout: 0 as i64,
+            ping_period: Duration::default(),
+            ping_timeout: Duration::default(),
+            handshake_max_time_skew: Duration::default(),
+            outbound_limit_per_network_group: None,
         }
     }
 }
@@ -125,6 +145,31 @@ where
     pub fn set_blocks_timeout(&mut self, blocks_timeout: i64) {
         self.blocks_timeout = blocks_timeout;
     }
+    /// Method to set the period between pings and the timeout for their replies
+    pub fn set_ping_timeouts(&mut self, ping_period: Duration, ping_timeout: Duration) {
+        self.ping_period = ping_period;
+        self.ping_timeout = ping_timeout;
+    }
+    /// Method to set the maximum clock difference allowed with a peer during the handshake
+    pub fn set_handshake_max_time_skew(&mut self, handshake_max_time_skew: Duration) {
+        self.handshake_max_time_skew = handshake_max_time_skew;
+    }
+    /// Method to set the maximum number of outbound sessions with peers in the same network group
+    pub fn set_outbound_limit_per_network_group(&mut self, limit: u16) {
+        self.outbound_limit_per_network_group = Some(limit);
+    }
+    /// Method to get the number of outbound sessions (consolidated and unconsolidated) with peers
+    /// in the same network group as the given IP address
+    pub fn get_num_outbound_sessions_in_network_group(&self, ip: IpAddr) -> usize {
+        let group = network_group(ip);
+
+        self.outbound_consolidated
+            .collection
+            .keys()
+            .chain(self.outbound_unconsolidated.collection.keys())
+            .filter(|address| network_group(address.ip()) == group)
+            .count()
+    }
     /// Method to check if a socket address is eligible as outbound peer
     pub fn is_outbound_address_eligible(&self, candidate_addr: SocketAddr) -> bool {
         // Check if address is already used as outbound session (consolidated or unconsolidated)
@@ -143,8 +188,35 @@ where
             .map(|address| address == candidate_addr)
             .unwrap_or(false);
 
-        // Return true if the address has not been used as outbound session or server address
-        !is_outbound_consolidated && !is_outbound_unconsolidated && !is_server
+        // Check if the network group of the address has reached its limit of outbound sessions
+        // Local addresses are not limited, as they are likely to be run by the node operator
+        let is_network_group_full = !is_local_address(candidate_addr.ip())
+            && self
+                .outbound_limit_per_network_group
+                .map(|limit| {
+                    self.get_num_outbound_sessions_in_network_group(candidate_addr.ip())
+                        >= limit as usize
+                })
+                .unwrap_or(false);
+
+        // Return true if the address has not been used as outbound session or server address,
+        // and its network group has not reached the limit
+        !is_outbound_consolidated
+            && !is_outbound_unconsolidated
+            && !is_server
+            && !is_network_group_full
+    }
+    /// Method to select an outbound peer among some candidate addresses
+    ///
+    /// Only the eligible addresses are considered, and among them, the ones belonging to the
+    /// network groups with less outbound sessions are preferred. In case of a tie, the first
+    /// candidate is selected.
+    pub fn select_outbound_candidate(&self, candidates: &[SocketAddr]) -> Option<SocketAddr> {
+        candidates
+            .iter()
+            .filter(|address| self.is_outbound_address_eligible(**address))
+            .min_by_key(|address| self.get_num_outbound_sessions_in_network_group(address.ip()))
+            .cloned()
     }
     /// Method to get total number of outbound peers
     pub fn get_num_outbound_sessions(&self) -> usize {
@@ -189,6 +261,13 @@ where
             .nth(index)
             .map(|info| info.reference.clone())
     }
+    /// Method to get the consolidated outbound session with the peer at the given address
+    pub fn get_outbound_consolidated_session(&self, address: SocketAddr) -> Option<T> {
+        self.outbound_consolidated
+            .collection
+            .get(&address)
+            .map(|info| info.reference.clone())
+    }
     /// Method to get all the consolidated sessions (inbound and outbound)
     pub fn get_all_consolidated_sessions<'a>(&'a self) -> impl Iterator<Item = &T> + 'a {
         self.outbound_consolidated
@@ -206,6 +285,20 @@ where
             .map(|info| &info.reference)
     }
 
+    /// Method to get all the sessions (inbound and outbound, consolidated or not) with peers
+    /// at the given IP address
+    pub fn get_sessions_by_ip(&self, ip: IpAddr) -> Vec<T> {
+        self.inbound_unconsolidated
+            .collection
+            .iter()
+            .chain(self.inbound_consolidated.collection.iter())
+            .chain(self.outbound_unconsolidated.collection.iter())
+            .chain(self.outbound_consolidated.collection.iter())
+            .filter(|(address, _info)| address.ip() == ip)
+            .map(|(_address, info)| info.reference.clone())
+            .collect()
+    }
+
     /// Method to insert a new session
     pub fn register_session(
         &mut self,
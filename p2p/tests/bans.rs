@@ -0,0 +1,124 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use witnet_p2p::bans::*;
+
+#[test]
+fn p2p_bans_add_score() {
+    // Create bans struct
+    let mut bans = Bans::default();
+
+    let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    let other_ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+
+    // Scores accumulate for each address
+    assert_eq!(
+        bans.add_score(ip, Misbehaviour::UnrequestedItem.score(), 0),
+        5
+    );
+    assert_eq!(
+        bans.add_score(ip, Misbehaviour::InvalidCandidate.score(), 0),
+        15
+    );
+    assert_eq!(bans.add_score(other_ip, 1, 0), 1);
+
+    // Adding a score does not ban the address
+    assert!(!bans.is_banned(ip, 0));
+    assert_eq!(bans.get_all(), vec![]);
+}
+
+#[test]
+fn p2p_bans_ban() {
+    // Create bans struct
+    let mut bans = Bans::default();
+
+    let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    bans.add_score(ip, 50, 0);
+
+    // Ban address until timestamp 100
+    bans.ban(ip, 100);
+    assert!(bans.is_banned(ip, 99));
+    assert!(!bans.is_banned(ip, 100));
+    assert_eq!(bans.get_all(), vec![(ip, 100)]);
+
+    // The ban resets the score
+    assert_eq!(bans.add_score(ip, 1, 0), 1);
+}
+
+#[test]
+fn p2p_bans_score_decay() {
+    // Create bans struct
+    let mut bans = Bans::default();
+
+    let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    assert_eq!(bans.add_score(ip, 50, 0), 50);
+
+    // The score decreases one point every decay period
+    assert_eq!(bans.add_score(ip, 10, 10 * SCORE_DECAY_PERIOD), 50);
+
+    // The score does not go below zero
+    assert_eq!(bans.add_score(ip, 10, 1_000 * SCORE_DECAY_PERIOD), 10);
+}
+
+#[test]
+fn p2p_bans_unban() {
+    // Create bans struct
+    let mut bans = Bans::default();
+
+    let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    bans.ban(ip, 100);
+
+    // Unban address
+    assert!(bans.unban(ip));
+    assert!(!bans.is_banned(ip, 0));
+
+    // The address is no longer banned
+    assert!(!bans.unban(ip));
+}
+
+#[test]
+fn p2p_bans_remove_expired() {
+    // Create bans struct
+    let mut bans = Bans::default();
+
+    let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    let other_ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+    bans.ban(ip, 100);
+    bans.ban(other_ip, 200);
+
+    // Only the first ban has expired
+    assert_eq!(bans.remove_expired(150), vec![ip]);
+    assert_eq!(bans.get_all(), vec![(other_ip, 200)]);
+}
+
+#[test]
+fn p2p_bans_remove_expired_scores() {
+    // Create bans struct
+    let mut bans = Bans::default();
+
+    let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    bans.add_score(ip, 10, 0);
+
+    // The score has decayed to zero, so it is forgotten
+    bans.remove_expired(10 * SCORE_DECAY_PERIOD);
+    assert_eq!(bans.add_score(ip, 1, 0), 1);
+}
+
+#[test]
+fn p2p_bans_extend() {
+    // Create bans struct
+    let mut bans = Bans::default();
+
+    let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    let other_ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+    bans.ban(ip, 100);
+
+    let mut other_bans = Bans::default();
+    other_bans.ban(ip, 200);
+    other_bans.ban(other_ip, 50);
+    bans.extend(other_bans);
+
+    // The ban which expires later is kept
+    let mut all = bans.get_all();
+    all.sort();
+    assert_eq!(all, vec![(ip, 200), (other_ip, 50)]);
+}
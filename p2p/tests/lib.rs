@@ -1,3 +1,6 @@
+/// Bans library tests
+pub mod bans;
+
 /// Peers library tests
 pub mod peers;
 
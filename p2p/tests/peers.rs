@@ -1,4 +1,6 @@
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use witnet_util::timestamp::get_timestamp;
 
 use witnet_p2p::peers::*;
 
@@ -23,9 +25,9 @@ fn p2p_peers_add() {
     // There is only 1 address
     assert_eq!(peers.get_all().unwrap(), vec![address]);
 
-    // Add 100 addresses more
+    // Add 100 addresses more, each one from a different network group
     let many_peers = (0..100)
-        .map(|i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 1, i)), 8080))
+        .map(|i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, i, 0, 1)), 8080))
         .collect();
     peers.add(many_peers).unwrap();
 
@@ -88,9 +90,9 @@ fn p2p_peers_get_all() {
     // Create peers struct
     let mut peers = Peers::default();
 
-    // Add 100 addresses
+    // Add 100 addresses, each one from a different network group
     let mut many_peers: Vec<_> = (0..100)
-        .map(|i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, i)), 8080))
+        .map(|i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, i, 0, 1)), 8080))
         .collect();
     peers.add(many_peers.clone()).unwrap();
 
@@ -107,3 +109,180 @@ fn p2p_peers_get_all() {
     added_peers.sort_by(sort_by_ip_then_port);
     assert_eq!(many_peers, added_peers);
 }
+
+#[test]
+fn p2p_peers_add_from_is_limited_per_source() {
+    // Create peers struct
+    let mut peers = Peers::default();
+
+    // A single peer announces 20000 addresses from many network groups
+    let src = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)), 21337);
+    let many_peers = (0..20_000u32)
+        .map(|i| {
+            SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::new(11, (i >> 8) as u8, i as u8, 1)),
+                8080,
+            )
+        })
+        .collect();
+    peers.add_from(many_peers, src).unwrap();
+
+    // Only a limited number of buckets can be filled by a single source
+    let total = peers.get_all().unwrap().len();
+    assert!(total <= NEW_BUCKETS_PER_SOURCE_GROUP as usize * BUCKET_SIZE);
+
+    // Addresses announced by peers from another network group are still accepted
+    let other_src = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(172, 16, 1, 1)), 21337);
+    let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(12, 0, 0, 1)), 8080);
+    assert_eq!(peers.add_from(vec![address], other_src).unwrap(), vec![]);
+    assert!(peers.get_all().unwrap().contains(&address));
+}
+
+#[test]
+fn p2p_peers_mark_success() {
+    // Create peers struct
+    let mut peers = Peers::default();
+
+    // Add address
+    let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+    peers.add(vec![address]).unwrap();
+    assert_eq!(peers.get_tried(), vec![]);
+
+    // After a successful connection the address is moved to the tried table
+    peers.mark_success(address).unwrap();
+    assert_eq!(peers.get_tried(), vec![address]);
+    assert_eq!(peers.get_all().unwrap(), vec![address]);
+
+    // Adding it again does not move it back to the new table
+    assert_eq!(peers.add(vec![address]).unwrap(), vec![address]);
+    assert_eq!(peers.get_tried(), vec![address]);
+
+    // Removing it also removes it from the tried table
+    assert_eq!(peers.remove(&[address]).unwrap(), vec![address]);
+    assert_eq!(peers.get_tried(), vec![]);
+}
+
+#[test]
+fn p2p_peers_remove_stale() {
+    // Create peers struct
+    let mut peers = Peers::default();
+
+    // Add addresses
+    let address1 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+    let address2 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)), 8080);
+    peers.add(vec![address1]).unwrap();
+    peers.mark_success(address2).unwrap();
+
+    // Recently seen addresses are kept
+    assert_eq!(peers.remove_stale(get_timestamp()), vec![]);
+    assert_eq!(peers.get_all().unwrap().len(), 2);
+
+    // Addresses not seen in MAX_ADDRESS_AGE are removed from both tables
+    let mut removed = peers.remove_stale(get_timestamp() + MAX_ADDRESS_AGE + 1);
+    removed.sort_by_key(|address| (address.ip(), address.port()));
+    assert_eq!(removed, vec![address1, address2]);
+    assert_eq!(peers.get_all().unwrap(), vec![]);
+}
+
+#[test]
+fn p2p_peers_add_does_not_refresh_address() {
+    // Load an address seen long ago, using the format used before the new and tried tables
+    let mut peers: Peers = serde_json::from_str(
+        r#"{"peers": {"127.0.0.1:8080": {"address": "127.0.0.1:8080", "_timestamp": 0}}}"#,
+    )
+    .unwrap();
+    let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+
+    // Announcing the address again does not prevent it from becoming stale
+    assert_eq!(peers.add(vec![address]).unwrap(), vec![address]);
+    assert_eq!(peers.remove_stale(get_timestamp()), vec![address]);
+}
+
+#[test]
+fn p2p_peers_serialization() {
+    // Create peers struct
+    let mut peers = Peers::default();
+
+    // Add addresses
+    let address1 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+    let address2 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)), 8080);
+    peers.add(vec![address1]).unwrap();
+    peers.mark_success(address2).unwrap();
+
+    // The tables are kept after a serialization round trip
+    let peers: Peers = serde_json::from_str(&serde_json::to_string(&peers).unwrap()).unwrap();
+    let mut all = peers.get_all().unwrap();
+    all.sort_by_key(|address| (address.ip(), address.port()));
+    assert_eq!(all, vec![address1, address2]);
+    assert_eq!(peers.get_tried(), vec![address2]);
+}
+
+#[test]
+fn p2p_peers_legacy_format() {
+    // Flat map of addresses, used before the new and tried tables
+    let peers: Peers = serde_json::from_str(
+        r#"{"peers": {
+            "127.0.0.1:8080": {"address": "127.0.0.1:8080", "_timestamp": 1},
+            "0.0.0.0:8080": {"address": "0.0.0.0:8080", "_timestamp": 1}
+        }}"#,
+    )
+    .unwrap();
+
+    // The addresses are loaded into the new table, except for the unspecified ones
+    let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+    assert_eq!(peers.get_all().unwrap(), vec![address]);
+    assert_eq!(peers.get_tried(), vec![]);
+}
+
+#[test]
+fn p2p_peers_network_group() {
+    let ip = |a, b, c, d| IpAddr::V4(Ipv4Addr::new(a, b, c, d));
+
+    // IPv4 addresses are grouped by their /16 prefix
+    assert_eq!(
+        network_group(ip(10, 0, 1, 1)),
+        network_group(ip(10, 0, 2, 2))
+    );
+    assert_ne!(
+        network_group(ip(10, 0, 1, 1)),
+        network_group(ip(10, 1, 1, 1))
+    );
+
+    // IPv4-mapped IPv6 addresses are in the same group as the IPv4 address
+    let mapped = IpAddr::V6(Ipv4Addr::new(10, 0, 1, 1).to_ipv6_mapped());
+    assert_eq!(network_group(mapped), network_group(ip(10, 0, 3, 3)));
+
+    // IPv6 addresses are grouped by their /32 prefix
+    let ipv6 = |a, b, c| IpAddr::V6(Ipv6Addr::new(0x2001, a, b, c, 0, 0, 0, 1));
+    assert_eq!(
+        network_group(ipv6(0xdb8, 1, 1)),
+        network_group(ipv6(0xdb8, 2, 2))
+    );
+    assert_ne!(
+        network_group(ipv6(0xdb8, 1, 1)),
+        network_group(ipv6(0xdb9, 1, 1))
+    );
+}
+
+#[test]
+fn p2p_peers_is_local_address() {
+    let ip = |a, b, c, d| IpAddr::V4(Ipv4Addr::new(a, b, c, d));
+    let ipv6 = |a| IpAddr::V6(Ipv6Addr::new(a, 0, 0, 0, 0, 0, 0, 1));
+
+    // Loopback, private and link-local addresses
+    assert!(is_local_address(ip(127, 0, 0, 1)));
+    assert!(is_local_address(ip(10, 0, 0, 1)));
+    assert!(is_local_address(ip(172, 16, 0, 1)));
+    assert!(is_local_address(ip(192, 168, 0, 1)));
+    assert!(is_local_address(ip(169, 254, 0, 1)));
+    assert!(is_local_address(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+    assert!(is_local_address(ipv6(0xfd00)));
+    assert!(is_local_address(ipv6(0xfe80)));
+    assert!(is_local_address(IpAddr::V6(
+        Ipv4Addr::new(192, 168, 0, 1).to_ipv6_mapped()
+    )));
+
+    // Public addresses
+    assert!(!is_local_address(ip(20, 0, 0, 1)));
+    assert!(!is_local_address(ipv6(0x2001)));
+}
@@ -82,6 +82,36 @@ fn p2p_sessions_set_handshake_timeout() {
     assert_eq!(sessions.handshake_timeout, handshake_timeout);
 }
 
+/// Check setting the ping period and timeout
+#[test]
+fn p2p_sessions_set_ping_timeouts() {
+    // Create sessions struct
+    let mut sessions = Sessions::<String>::default();
+
+    // Set ping period and timeout
+    let ping_period = Duration::from_secs(30);
+    let ping_timeout = Duration::from_secs(20);
+    sessions.set_ping_timeouts(ping_period, ping_timeout);
+
+    // Check ping period and timeout are now set
+    assert_eq!(sessions.ping_period, ping_period);
+    assert_eq!(sessions.ping_timeout, ping_timeout);
+}
+
+/// Check setting the maximum time skew allowed in the handshake
+#[test]
+fn p2p_sessions_set_handshake_max_time_skew() {
+    // Create sessions struct
+    let mut sessions = Sessions::<String>::default();
+
+    // Set maximum time skew
+    let handshake_max_time_skew = Duration::from_secs(60);
+    sessions.set_handshake_max_time_skew(handshake_max_time_skew);
+
+    // Check maximum time skew is now set
+    assert_eq!(sessions.handshake_max_time_skew, handshake_max_time_skew);
+}
+
 /// Check if addresses are eligible as outbound addresses
 #[test]
 fn p2p_sessions_is_outbound_address_eligible() {
@@ -129,6 +159,128 @@ fn p2p_sessions_is_outbound_address_eligible() {
     assert!(sessions.is_outbound_address_eligible(valid_address_3));
 }
 
+/// Check the limit of outbound sessions in the same network group
+#[test]
+fn p2p_sessions_outbound_limit_per_network_group() {
+    // Create sessions struct
+    let mut sessions = Sessions::<String>::default();
+
+    // Set the limit of outbound sessions per network group
+    sessions.set_outbound_limit_per_network_group(2);
+    assert_eq!(sessions.outbound_limit_per_network_group, Some(2));
+
+    // Register two outbound sessions in the network group 20.0.0.0/16
+    let outbound_address_1 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(20, 0, 0, 1)), 8000);
+    let outbound_address_2 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(20, 0, 1, 1)), 8000);
+    assert!(sessions
+        .register_session(
+            SessionType::Outbound,
+            outbound_address_1,
+            "reference1".to_string()
+        )
+        .is_ok());
+    let candidate = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(20, 0, 2, 1)), 8000);
+    assert!(sessions.is_outbound_address_eligible(candidate));
+    assert!(sessions
+        .register_session(
+            SessionType::Outbound,
+            outbound_address_2,
+            "reference2".to_string()
+        )
+        .is_ok());
+    assert_eq!(
+        sessions.get_num_outbound_sessions_in_network_group(candidate.ip()),
+        2
+    );
+
+    // The network group is full
+    assert!(!sessions.is_outbound_address_eligible(candidate));
+
+    // Addresses from other network groups are still eligible
+    let other_group_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(20, 1, 0, 1)), 8000);
+    assert!(sessions.is_outbound_address_eligible(other_group_address));
+
+    // Inbound sessions do not count towards the limit
+    let inbound_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(20, 1, 0, 2)), 8000);
+    assert!(sessions
+        .register_session(
+            SessionType::Inbound,
+            inbound_address,
+            "reference3".to_string()
+        )
+        .is_ok());
+    assert!(sessions.is_outbound_address_eligible(other_group_address));
+}
+
+/// Check that local addresses are not limited per network group
+#[test]
+fn p2p_sessions_outbound_limit_per_network_group_local_addresses() {
+    // Create sessions struct
+    let mut sessions = Sessions::<String>::default();
+    sessions.set_outbound_limit_per_network_group(1);
+
+    // Register an outbound session in the private network group 192.168.0.0/16
+    let outbound_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)), 8000);
+    assert!(sessions
+        .register_session(
+            SessionType::Outbound,
+            outbound_address,
+            "reference1".to_string()
+        )
+        .is_ok());
+
+    // Other addresses in the same private network group are still eligible
+    let candidate = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 2)), 8000);
+    assert!(sessions.is_outbound_address_eligible(candidate));
+}
+
+/// Check the selection of outbound candidates prefers diverse network groups
+#[test]
+fn p2p_sessions_select_outbound_candidate() {
+    // Create sessions struct
+    let mut sessions = Sessions::<String>::default();
+
+    // No candidates
+    assert_eq!(sessions.select_outbound_candidate(&[]), None);
+
+    // Register an outbound session in the network group 20.0.0.0/16
+    let outbound_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(20, 0, 0, 1)), 8000);
+    assert!(sessions
+        .register_session(
+            SessionType::Outbound,
+            outbound_address,
+            "reference1".to_string()
+        )
+        .is_ok());
+
+    // A candidate from a new network group is preferred
+    let same_group_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(20, 0, 1, 1)), 8000);
+    let new_group_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(20, 1, 0, 1)), 8000);
+    assert_eq!(
+        sessions.select_outbound_candidate(&[same_group_address, new_group_address]),
+        Some(new_group_address)
+    );
+
+    // If all candidates are in used network groups, the first one is selected
+    assert_eq!(
+        sessions.select_outbound_candidate(&[same_group_address]),
+        Some(same_group_address)
+    );
+
+    // Non eligible candidates are never selected
+    assert_eq!(
+        sessions.select_outbound_candidate(&[outbound_address]),
+        None
+    );
+
+    // If the network group is full, the candidates in it are not selected
+    sessions.set_outbound_limit_per_network_group(1);
+    assert_eq!(
+        sessions.select_outbound_candidate(&[same_group_address]),
+        None
+    );
+}
+
 /// Check if the sum of all outbound sessions (consolidated and unconsolidated) is returned
 #[test]
 fn p2p_sessions_get_num_outbound_sessions() {
@@ -293,6 +445,99 @@ fn p2p_sessions_get_random_anycast_session() {
     );
 }
 
+/// Check getting the sessions of an IP address
+#[test]
+fn p2p_sessions_get_outbound_consolidated_session() {
+    // Create sessions struct
+    let mut sessions = Sessions::<String>::default();
+
+    // Register an outbound session
+    let outbound_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8001);
+    assert!(sessions
+        .register_session(
+            SessionType::Outbound,
+            outbound_address,
+            "reference1".to_string()
+        )
+        .is_ok());
+
+    // Check that unconsolidated sessions are not returned
+    assert_eq!(
+        sessions.get_outbound_consolidated_session(outbound_address),
+        None
+    );
+
+    // Consolidate outbound session
+    assert!(sessions
+        .consolidate_session(SessionType::Outbound, outbound_address)
+        .is_ok());
+
+    // Check that the session is returned once it is consolidated
+    assert_eq!(
+        sessions.get_outbound_consolidated_session(outbound_address),
+        Some("reference1".to_string())
+    );
+
+    // Check that there is no session for other addresses
+    let other_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8002);
+    assert_eq!(
+        sessions.get_outbound_consolidated_session(other_address),
+        None
+    );
+}
+
+#[test]
+fn p2p_sessions_get_sessions_by_ip() {
+    // Create sessions struct
+    let mut sessions = Sessions::<String>::default();
+
+    let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    let other_ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+
+    // Check that the function returns no sessions when there are no sessions in the collection
+    assert_eq!(sessions.get_sessions_by_ip(ip), Vec::<String>::new());
+
+    // Register an inbound and a consolidated outbound session with the same IP address
+    assert!(sessions
+        .register_session(
+            SessionType::Inbound,
+            SocketAddr::new(ip, 50000),
+            "reference1".to_string()
+        )
+        .is_ok());
+    assert!(sessions
+        .register_session(
+            SessionType::Outbound,
+            SocketAddr::new(ip, 8001),
+            "reference2".to_string()
+        )
+        .is_ok());
+    assert!(sessions
+        .consolidate_session(SessionType::Outbound, SocketAddr::new(ip, 8001))
+        .is_ok());
+
+    // Register a session with another IP address
+    assert!(sessions
+        .register_session(
+            SessionType::Outbound,
+            SocketAddr::new(other_ip, 8001),
+            "reference3".to_string()
+        )
+        .is_ok());
+
+    // Check that only the sessions with the given IP address are returned
+    let mut references = sessions.get_sessions_by_ip(ip);
+    references.sort();
+    assert_eq!(
+        references,
+        vec!["reference1".to_string(), "reference2".to_string()]
+    );
+    assert_eq!(
+        sessions.get_sessions_by_ip(other_ip),
+        vec!["reference3".to_string()]
+    );
+}
+
 /// Check the registration of sessions
 #[test]
 fn p2p_sessions_register() {
@@ -5,6 +5,7 @@
 #![deny(unused_mut)]
 #![deny(missing_docs)]
 use std::env;
+use std::net::IpAddr;
 use std::path::PathBuf;
 use std::result::Result;
 
@@ -131,6 +132,236 @@ pub(crate) enum CliCommand {
         )]
         output_index: String,
     },
+    #[structopt(name = "getTransaction", about = "Get a transaction by its hash")]
+    GetTransaction {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+        #[structopt(name = "hash", help = "SHA256 transaction hash in string format")]
+        hash: String,
+    },
+    #[structopt(
+        name = "sendTransaction",
+        about = "Send a transaction read from a JSON file, and wait until the node validates it"
+    )]
+    SendTransaction {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+        #[structopt(name = "file", help = "File containing the transaction in JSON format")]
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+    #[structopt(
+        name = "getUtxos",
+        about = "Get the unspent outputs which can be spent by an address"
+    )]
+    GetUtxos {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+        #[structopt(name = "address", help = "Address in bech32 format")]
+        address: String,
+    },
+    #[structopt(name = "getBalance", about = "Get the balance of an address")]
+    GetBalance {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+        #[structopt(name = "address", help = "Address in bech32 format")]
+        address: String,
+    },
+    #[structopt(name = "getNodeStatus", about = "Get the status of the node")]
+    GetNodeStatus {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "getPeers",
+        about = "Get the peers the node is connected to and their last beacons"
+    )]
+    GetPeers {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+    },
+    #[structopt(name = "getKnownPeers", about = "Get the addresses of the known peers")]
+    GetKnownPeers {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "getBannedPeers",
+        about = "Get the IP addresses banned by the node"
+    )]
+    GetBannedPeers {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "banPeer",
+        about = "Ban an IP address, closing all the sessions with it"
+    )]
+    BanPeer {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+        #[structopt(name = "ip", help = "IP address of the peer")]
+        ip: IpAddr,
+        #[structopt(
+            name = "duration",
+            long = "duration",
+            help = "Duration of the ban in seconds, by default the one from the configuration"
+        )]
+        duration: Option<u64>,
+    },
+    #[structopt(name = "unbanPeer", about = "Lift the ban of an IP address")]
+    UnbanPeer {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+        #[structopt(name = "ip", help = "IP address of the peer")]
+        ip: IpAddr,
+    },
+    #[structopt(
+        name = "getPublicKey",
+        about = "Get the public key of the node and its address"
+    )]
+    GetPublicKey {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+        #[structopt(
+            name = "role",
+            long = "role",
+            help = "Get the key used to sign for this role: block, commit, reveal or tally"
+        )]
+        role: Option<String>,
+    },
+    #[structopt(name = "exportMasterKey", about = "Export the master key of the node")]
+    ExportMasterKey {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+        #[structopt(
+            name = "password",
+            long = "password",
+            short = "p",
+            help = "Export the key encrypted with this password instead of as a mnemonic"
+        )]
+        password: Option<String>,
+        #[structopt(
+            name = "output",
+            long = "output",
+            short = "o",
+            help = "Write the exported key to this file instead of stdout"
+        )]
+        #[structopt(parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "importMasterKey",
+        about = "Replace the master key of the node with one read from a file"
+    )]
+    ImportMasterKey {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+        #[structopt(
+            name = "file",
+            help = "File containing a mnemonic or a key exported with a password"
+        )]
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        #[structopt(
+            name = "password",
+            long = "password",
+            short = "p",
+            help = "Password used to export the key, if it is encrypted"
+        )]
+        password: Option<String>,
+    },
 }
 
 pub(crate) fn exec(command: Command) -> Result<(), failure::Error> {
@@ -1,18 +1,26 @@
 use crate::cli::CliCommand;
 use failure::Fail;
-use log::{info, warn};
-use serde::Deserialize;
+use log::info;
 use std::str::FromStr;
 use std::{
-    fmt,
-    io::{self, BufRead, BufReader, Read, Write},
-    net::TcpStream,
+    fmt, fs,
+    io::{self, BufRead},
     path::PathBuf,
 };
 use witnet_config::config::Config;
 use witnet_config::loaders::toml;
-use witnet_data_structures::chain::OutputPointer;
-use witnet_node::actors::json_rpc::json_rpc_methods::GetBlockChainParams;
+use witnet_data_structures::{
+    chain::{CheckpointBeacon, Hash, OutputPointer, Transaction},
+    set_environment,
+};
+use witnet_node::{
+    actors::json_rpc::{
+        auth,
+        client::Client,
+        json_rpc_methods::{BanPeerParams, ExportMasterKeyParams, ImportMasterKeyParams},
+    },
+    signature_mngr::KeyRole,
+};
 
 pub(crate) fn run(last_config: Option<PathBuf>, cmd: CliCommand) -> Result<(), failure::Error> {
     match cmd {
@@ -22,7 +30,7 @@ pub(crate) fn run(last_config: Option<PathBuf>, cmd: CliCommand) -> Result<(), f
             // witnet cli getBlockChain -c witnet.toml
             // The last one takes priority
             let config = config.or(last_config);
-            let mut stream = start_client(config)?;
+            let mut client = start_client(config)?;
             // The request is read from stdin, one line at a time
             let mut request = String::new();
             let stdin = io::stdin();
@@ -33,7 +41,7 @@ pub(crate) fn run(last_config: Option<PathBuf>, cmd: CliCommand) -> Result<(), f
                 if count == 0 {
                     break Ok(());
                 }
-                let response = send_request(&mut stream, &request)?;
+                let response = client.call_raw(&request)?;
                 // The response includes a newline, so use print instead of println
                 print!("{}", response);
             }
@@ -44,20 +52,9 @@ pub(crate) fn run(last_config: Option<PathBuf>, cmd: CliCommand) -> Result<(), f
             limit,
         } => {
             let config = config.or(last_config);
-            let params = GetBlockChainParams {
-                epoch: epoch.unwrap_or_default(),
-                limit: limit.unwrap_or_default(),
-            };
-            let mut stream = start_client(config)?;
-            let response = send_request(
-                &mut stream,
-                &format!(
-                    r#"{{"jsonrpc": "2.0","method": "getBlockChain", "params": {}, "id": 1}}"#,
-                    serde_json::to_string(&params).unwrap()
-                ),
-            )?;
-            info!("{}", response);
-            let block_chain: ResponseBlockChain<'_> = parse_response(&response)?;
+            let mut client = start_client(config)?;
+            let block_chain =
+                client.get_block_chain(epoch.unwrap_or_default(), limit.unwrap_or_default())?;
 
             for (epoch, hash) in block_chain {
                 println!("Block for epoch #{} had digest {}", epoch, hash);
@@ -67,14 +64,10 @@ pub(crate) fn run(last_config: Option<PathBuf>, cmd: CliCommand) -> Result<(), f
         }
         CliCommand::GetBlock { config, hash } => {
             let config = config.or(last_config);
-            let mut stream = start_client(config)?;
-            let request = format!(
-                r#"{{"jsonrpc": "2.0","method": "getBlock", "params": [{:?}], "id": "1"}}"#,
-                hash,
-            );
-            let response = send_request(&mut stream, &request)?;
+            let mut client = start_client(config)?;
+            let block = client.get_block(parse_hash(hash)?)?;
 
-            println!("{}", response);
+            println!("{}", serde_json::to_string(&block)?);
 
             Ok(())
         }
@@ -83,68 +76,236 @@ pub(crate) fn run(last_config: Option<PathBuf>, cmd: CliCommand) -> Result<(), f
             output_index,
         } => {
             let config = config.or(last_config);
-            let mut _stream = start_client(config)?;
+            let mut client = start_client(config)?;
             let output_pointer = OutputPointer::from_str(&output_index)?;
-            let request_payload = serde_json::to_string(&output_pointer)?;
-            let _request = format!(
-                r#"{{"jsonrpc": "2.0","method": "getOutput", "params": [{}], "id": "1"}}"#,
-                request_payload,
+            let output = client.get_output(&output_pointer)?;
+
+            println!("{}", serde_json::to_string(&output)?);
+
+            Ok(())
+        }
+        CliCommand::GetTransaction { config, hash } => {
+            let config = config.or(last_config);
+            let mut client = start_client(config)?;
+            let transaction = client.get_transaction(parse_hash(hash)?)?;
+
+            println!("{}", serde_json::to_string(&transaction)?);
+
+            Ok(())
+        }
+        CliCommand::SendTransaction { config, file } => {
+            let config = config.or(last_config);
+            // Parse the transaction before sending it, to report malformed files early
+            let transaction: Transaction = serde_json::from_str(&fs::read_to_string(&file)?)?;
+            let mut client = start_client(config)?;
+            let hash = client.send_transaction(&transaction)?;
+
+            println!("Transaction accepted: {}", hash);
+
+            Ok(())
+        }
+        CliCommand::GetUtxos { config, address } => {
+            let config = config.or(last_config);
+            let mut client = start_client(config)?;
+            let utxos = client.get_utxos(&address)?;
+
+            println!("{}", serde_json::to_string(&utxos)?);
+
+            Ok(())
+        }
+        CliCommand::GetBalance { config, address } => {
+            let config = config.or(last_config);
+            let mut client = start_client(config)?;
+            let balance = client.get_balance(&address)?;
+
+            println!("Confirmed balance: {}", balance.confirmed);
+            println!("Pending balance: {}", balance.pending);
+
+            Ok(())
+        }
+        CliCommand::GetNodeStatus { config } => {
+            let config = config.or(last_config);
+            let mut client = start_client(config)?;
+            let status = client.get_node_status()?;
+
+            match status.current_epoch {
+                Some(epoch) => println!("Current epoch: {}", epoch),
+                None => println!("Current epoch: unknown"),
+            }
+            println!("Chain beacon: {}", format_beacon(status.chain_beacon));
+            println!("State: {:?}", status.state);
+            println!("Target beacon: {}", format_beacon(status.target_beacon));
+            println!("Mempool size: {}", status.mempool_size);
+            println!("Mining enabled: {}", status.mining_enabled);
+
+            Ok(())
+        }
+        CliCommand::GetPeers { config } => {
+            let config = config.or(last_config);
+            let mut client = start_client(config)?;
+            let peers = client.get_peers()?;
+
+            if peers.is_empty() {
+                println!("No peers connected");
+            }
+            for peer in peers {
+                let latency = match peer.latency_ms {
+                    Some(latency) => format!("{} ms", latency),
+                    None => "unknown".to_string(),
+                };
+                let identity = match peer.identity {
+                    Some(identity) => identity.to_string(),
+                    None => "unencrypted".to_string(),
+                };
+                println!(
+                    "{} ({:?}) last beacon: {} latency: {} identity: {}",
+                    peer.address,
+                    peer.session_type,
+                    format_beacon(peer.last_beacon),
+                    latency,
+                    identity
+                );
+            }
+
+            Ok(())
+        }
+        CliCommand::GetKnownPeers { config } => {
+            let config = config.or(last_config);
+            let mut client = start_client(config)?;
+            let peers = client.get_known_peers()?;
+
+            if peers.is_empty() {
+                println!("No known peers");
+            }
+            for peer in peers {
+                println!("{}", peer);
+            }
+
+            Ok(())
+        }
+        CliCommand::GetBannedPeers { config } => {
+            let config = config.or(last_config);
+            let mut client = start_client(config)?;
+            let peers = client.get_banned_peers()?;
+
+            if peers.is_empty() {
+                println!("No banned peers");
+            }
+            for peer in peers {
+                println!("{} banned until timestamp {}", peer.ip, peer.until);
+            }
+
+            Ok(())
+        }
+        CliCommand::BanPeer {
+            config,
+            ip,
+            duration,
+        } => {
+            let config = config.or(last_config);
+            let mut client = start_client(config)?;
+            let banned_peer = client.ban_peer(&BanPeerParams {
+                ip,
+                duration_seconds: duration,
+            })?;
+
+            println!(
+                "{} banned until timestamp {}",
+                banned_peer.ip, banned_peer.until
             );
-            //let response = send_request(&mut stream, &request)?;
-            let response = "unimplemented yet";
 
-            println!("{}", response);
+            Ok(())
+        }
+        CliCommand::UnbanPeer { config, ip } => {
+            let config = config.or(last_config);
+            let mut client = start_client(config)?;
+
+            if client.unban_peer(ip)? {
+                println!("Lifted the ban of {}", ip);
+            } else {
+                println!("{} was not banned", ip);
+            }
 
             Ok(())
         }
-    }
-}
+        CliCommand::GetPublicKey { config, role } => {
+            let config = config.or(last_config);
+            let role: Option<KeyRole> = role
+                .map(|role| serde_json::from_value(serde_json::Value::String(role)))
+                .transpose()?;
+            let mut client = start_client(config)?;
+            let public_key = client.get_public_key(role)?;
 
-// Response of the getBlockChain JSON-RPC method
-type ResponseBlockChain<'a> = Vec<(u32, &'a str)>;
+            println!("Public key: {}", public_key.public_key);
+            println!("Address: {}", public_key.address);
 
-// Quick and simple JSON-RPC client implementation
+            Ok(())
+        }
+        CliCommand::ExportMasterKey {
+            config,
+            password,
+            output,
+        } => {
+            let config = config.or(last_config);
+            let params = match password {
+                Some(password) => ExportMasterKeyParams::Encrypted { password },
+                None => ExportMasterKeyParams::Mnemonic,
+            };
+            let mut client = start_client(config)?;
+            let master_key = client.export_master_key(&params)?;
 
-/// Generic response which is used to extract the result
-#[derive(Debug, Deserialize)]
-struct JsonRpcResponse<'a, T> {
-    // Lifetimes allow zero-copy string deserialization
-    jsonrpc: &'a str,
-    id: Id<'a>,
-    result: T,
-}
+            match output {
+                Some(path) => {
+                    fs::write(&path, master_key)?;
+                    println!("Master key written to {}", path.display());
+                }
+                None => println!("{}", master_key),
+            }
+
+            Ok(())
+        }
+        CliCommand::ImportMasterKey {
+            config,
+            file,
+            password,
+        } => {
+            let config = config.or(last_config);
+            let contents = fs::read_to_string(&file)?.trim().to_string();
+            let params = match password {
+                Some(password) => ImportMasterKeyParams::Encrypted {
+                    encrypted: contents,
+                    password,
+                },
+                None => ImportMasterKeyParams::Mnemonic { mnemonic: contents },
+            };
+            let mut client = start_client(config)?;
+            let public_key = client.import_master_key(&params)?;
 
-/// A failed request returns an error with code and message
-#[derive(Debug, Deserialize)]
-struct JsonRpcError<'a> {
-    jsonrpc: &'a str,
-    id: Id<'a>,
-    error: ServerError,
+            println!("Master key imported");
+            println!("Public key: {}", public_key.public_key);
+            println!("Address: {}", public_key.address);
+
+            Ok(())
+        }
+    }
 }
 
-/// Id. Can be null, a number, or a string
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
-enum Id<'a> {
-    Null,
-    Number(u64),
-    String(&'a str),
+/// Format a beacon as `#<checkpoint> <block hash>`
+fn format_beacon(beacon: Option<CheckpointBeacon>) -> String {
+    match beacon {
+        Some(beacon) => format!("#{} {}", beacon.checkpoint, beacon.hash_prev_block),
+        None => "none".to_string(),
+    }
 }
 
-/// A failed request returns an error with code and message
-#[derive(Debug, Deserialize, Fail)]
-struct ServerError {
-    code: i32,
-    // This cannot be a &str because the error may outlive the current function
-    message: String,
+/// Parse a hash in hex format
+fn parse_hash(hash: String) -> Result<Hash, failure::Error> {
+    Ok(serde_json::from_value(serde_json::Value::String(hash))?)
 }
 
 #[derive(Debug, Fail)]
 struct ServerDisabled;
 
-#[derive(Debug, Fail)]
-struct ProtocolError(String);
-
 // Required for Fail derive
 impl fmt::Display for ServerDisabled {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -153,71 +314,23 @@ impl fmt::Display for ServerDisabled {
     }
 }
 
-// Required for Fail derive
-impl fmt::Display for ServerError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(&format!("{:?}", self))?;
-        Ok(())
-    }
-}
-
-// Required for Fail derive
-impl fmt::Display for ProtocolError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(&format!(
-            "Incompatible JSON-RPC version used by server: {}",
-            self.0
-        ))?;
-        Ok(())
-    }
-}
-
-fn start_client(config_path: Option<PathBuf>) -> Result<TcpStream, failure::Error> {
+fn start_client(config_path: Option<PathBuf>) -> Result<Client, failure::Error> {
     let config_file = config_path.unwrap_or_else(|| PathBuf::from("witnet.toml"));
     let config = Config::from_partial(&toml::from_file(&config_file)?);
+    set_environment(config.environment.clone());
     if !config.jsonrpc.enabled {
         return Err(ServerDisabled.into());
     }
     let addr = config.jsonrpc.server_address;
     info!("Connecting to JSON-RPC server at {}", addr);
-    let stream = TcpStream::connect(addr);
+    let mut client = Client::connect(addr)?;
 
-    stream.map_err(Into::into)
-}
-
-fn send_request<S: Read + Write>(stream: &mut S, request: &str) -> Result<String, io::Error> {
-    stream.write_all(request.as_bytes())?;
-    // Write missing newline, if needed
-    match bytecount::count(request.as_bytes(), b'\n') {
-        0 => stream.write_all(b"\n")?,
-        1 => {}
-        _ => {
-            warn!("The request contains more than one newline, only the first response will be returned");
-        }
+    // Authenticate using the token from the configuration or the cookie file, if any
+    if let Some(token) = auth::client_token(&config.jsonrpc)? {
+        client.authenticate(&token)?;
     }
-    // Read only one line
-    let mut r = BufReader::new(stream);
-    let mut buf = String::new();
-    r.read_line(&mut buf)?;
-    Ok(buf)
-}
 
-fn parse_response<'a, T: Deserialize<'a>>(response: &'a str) -> Result<T, failure::Error> {
-    match serde_json::from_str::<JsonRpcResponse<'a, T>>(response) {
-        Ok(x) => {
-            // x.id should also be checked if we want to support more than one call at a time
-            if x.jsonrpc != "2.0" {
-                Err(ProtocolError(x.jsonrpc.to_string()).into())
-            } else {
-                Ok(x.result)
-            }
-        }
-        Err(e) => {
-            info!("{}", e);
-            let error_json: JsonRpcError<'a> = serde_json::from_str(response)?;
-            Err(error_json.error.into())
-        }
-    }
+    Ok(client)
 }
 
 #[cfg(test)]
@@ -225,38 +338,19 @@ mod tests {
     use super::*;
 
     #[test]
-    fn parse_invalid() {
-        let nothing: Result<(), _> = parse_response("");
-        assert!(nothing.is_err());
-        let asdf: Result<(), _> = parse_response("asdf");
-        assert!(asdf.is_err());
-    }
-
-    #[test]
-    fn parse_server_error() {
-        let response =
-            r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":1}"#;
-        let block_chain: Result<ResponseBlockChain<'_>, _> = parse_response(&response);
-        assert!(block_chain.is_err());
+    fn parse_hash_hex() {
+        let hash =
+            parse_hash("ed28899af8c3148a4162736af942bc68c4466da93c5124dabfaa7c582af49e30".into())
+                .unwrap();
+        assert_eq!(
+            hash.to_string(),
+            "ed28899af8c3148a4162736af942bc68c4466da93c5124dabfaa7c582af49e30"
+        );
+        assert!(parse_hash("ed28".into()).is_err());
     }
 
     #[test]
-    fn parse_get_block_chain() {
-        let response = r#"{"jsonrpc":"2.0","result":[[0,"ed28899af8c3148a4162736af942bc68c4466da93c5124dabfaa7c582af49e30"],[1,"9c9038cfb31a7050796920f91b17f4a68c7e9a795ee8962916b35d39fc1efefc"]],"id":1}"#;
-        let block_chain: ResponseBlockChain<'_> = parse_response(&response).unwrap();
-        assert_eq!(
-            block_chain[0],
-            (
-                0,
-                "ed28899af8c3148a4162736af942bc68c4466da93c5124dabfaa7c582af49e30"
-            )
-        );
-        assert_eq!(
-            block_chain[1],
-            (
-                1,
-                "9c9038cfb31a7050796920f91b17f4a68c7e9a795ee8962916b35d39fc1efefc"
-            )
-        );
+    fn format_no_beacon() {
+        assert_eq!(format_beacon(None), "none");
     }
 }
@@ -1,3 +1,24 @@
+use failure::Fail;
+
+/// Error returned by `parse_hex_strict`
+#[derive(Debug, PartialEq, Fail)]
+pub enum HexError {
+    /// The string has an odd number of characters
+    #[fail(display = "Odd number of hex characters")]
+    OddLength,
+    /// The string contains a character which is not a hex digit
+    #[fail(
+        display = "Invalid hex character {:?} at position {}",
+        character, index
+    )]
+    InvalidCharacter {
+        /// The invalid character
+        character: char,
+        /// Position of the character in the string
+        index: usize,
+    },
+}
+
 /// Function that parses a hex string into vec
 pub fn parse_hex(hex_asm: &str) -> Vec<u8> {
     let mut hex_bytes = hex_asm
@@ -19,6 +40,35 @@ pub fn parse_hex(hex_asm: &str) -> Vec<u8> {
     bytes
 }
 
+/// Function that parses a hex string into vec, failing on invalid characters instead of skipping
+/// them like `parse_hex`
+pub fn parse_hex_strict(hex: &str) -> Result<Vec<u8>, HexError> {
+    if hex.len() % 2 != 0 {
+        return Err(HexError::OddLength);
+    }
+
+    let digits = hex
+        .chars()
+        .enumerate()
+        .map(|(index, character)| {
+            character
+                .to_digit(16)
+                .map(|digit| digit as u8)
+                .ok_or(HexError::InvalidCharacter { character, index })
+        })
+        .collect::<Result<Vec<u8>, HexError>>()?;
+
+    Ok(digits
+        .chunks(2)
+        .map(|pair| pair[0] << 4 | pair[1])
+        .collect())
+}
+
+/// Function that encodes a slice of bytes as a lowercase hex string
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 #[test]
 fn parse_hex_test() {
     let result = parse_hex("0123456789abcdefABCDEF");
@@ -26,3 +76,30 @@ fn parse_hex_test() {
 
     assert_eq!(result, expected);
 }
+
+#[test]
+fn parse_hex_strict_test() {
+    assert_eq!(
+        parse_hex_strict("0123456789abcdefABCDEF"),
+        Ok(vec![1, 35, 69, 103, 137, 171, 205, 239, 171, 205, 239])
+    );
+    assert_eq!(parse_hex_strict(""), Ok(vec![]));
+    assert_eq!(parse_hex_strict("abc"), Err(HexError::OddLength));
+    assert_eq!(
+        parse_hex_strict("ab-d"),
+        Err(HexError::InvalidCharacter {
+            character: '-',
+            index: 2
+        })
+    );
+    // Multibyte characters are not hex digits either
+    assert!(parse_hex_strict("aé").is_err());
+}
+
+#[test]
+fn to_hex_test() {
+    let bytes = vec![1, 35, 69, 103, 137, 171, 205, 239];
+
+    assert_eq!(to_hex(&bytes), "0123456789abcdef");
+    assert_eq!(parse_hex(&to_hex(&bytes)), bytes);
+}
@@ -8,8 +8,8 @@ use std::collections::{HashMap, HashSet};
 use witnet_data_structures::{
     chain::{
         Block, CheckpointBeacon, Epoch, Hash, Hashable, Input, KeyedSignature, Output,
-        OutputPointer, RADRequest, Transaction, TransactionBody, TransactionType,
-        UnspentOutputsPool,
+        OutputPointer, RADRequest, SignedBlockHeader, Transaction, TransactionBody,
+        TransactionType, UnspentOutputsPool,
     },
     data_request::DataRequestPool,
     error::{BlockError, TransactionError},
@@ -399,15 +399,24 @@ pub fn validate_tally_transaction(
 
 /// Function to validate a block signature
 pub fn validate_block_signature(block: &Block) -> Result<(), failure::Error> {
-    let keyed_signature = &block.proof.block_sig;
+    validate_header_signature(&block.signed_header())
+}
+
+/// Function to validate the signature of a block header
+pub fn validate_header_signature(header: &SignedBlockHeader) -> Result<(), failure::Error> {
+    let keyed_signature = &header.proof.block_sig;
 
     let signature = keyed_signature.signature.clone().try_into()?;
     let public_key = keyed_signature.public_key.clone().try_into()?;
 
-    let Hash::SHA256(message) = block.block_header.beacon.hash();
+    let Hash::SHA256(message) = header.block_header.beacon.hash();
 
-    verify(&public_key, &message, &signature)
-        .map_err(|_| BlockError::VerifySignatureFail { hash: block.hash() }.into())
+    verify(&public_key, &message, &signature).map_err(|_| {
+        BlockError::VerifySignatureFail {
+            hash: header.hash(),
+        }
+        .into()
+    })
 }
 
 /// Function to validate a pkh signature
@@ -616,8 +625,55 @@ pub fn validate_block(
     utxo_set: &UnspentOutputsPool,
     data_request_pool: &DataRequestPool,
 ) -> Result<Diff, failure::Error> {
-    let block_epoch = block.block_header.beacon.checkpoint;
-    let hash_prev_block = block.block_header.beacon.hash_prev_block;
+    validate_block_beacon(
+        block.block_header.beacon,
+        current_epoch,
+        chain_beacon,
+        genesis_block_hash,
+    )?;
+
+    if !verify_poe_block() {
+        Err(BlockError::NotValidPoe)?
+    } else {
+        validate_block_signature(&block)?;
+
+        validate_transactions(&utxo_set, &data_request_pool, &block)
+    }
+}
+
+/// Function to validate a block header received during the synchronization, without its
+/// transactions
+///
+/// The header must follow `chain_beacon`, which is the beacon of the previous header.
+pub fn validate_header(
+    header: &SignedBlockHeader,
+    current_epoch: Epoch,
+    chain_beacon: CheckpointBeacon,
+    genesis_block_hash: Hash,
+) -> Result<(), failure::Error> {
+    validate_block_beacon(
+        header.block_header.beacon,
+        current_epoch,
+        chain_beacon,
+        genesis_block_hash,
+    )?;
+
+    if !verify_poe_block() {
+        Err(BlockError::NotValidPoe)?
+    } else {
+        validate_header_signature(header)
+    }
+}
+
+/// Function to validate that the beacon of a block follows the beacon of the chain
+fn validate_block_beacon(
+    beacon: CheckpointBeacon,
+    current_epoch: Epoch,
+    chain_beacon: CheckpointBeacon,
+    genesis_block_hash: Hash,
+) -> Result<(), failure::Error> {
+    let block_epoch = beacon.checkpoint;
+    let hash_prev_block = beacon.hash_prev_block;
 
     if block_epoch > current_epoch {
         Err(BlockError::BlockFromFuture {
@@ -635,12 +691,8 @@ pub fn validate_block(
         Err(BlockError::PreviousHashNotKnown {
             hash: hash_prev_block,
         })?
-    } else if !verify_poe_block() {
-        Err(BlockError::NotValidPoe)?
     } else {
-        validate_block_signature(&block)?;
-
-        validate_transactions(&utxo_set, &data_request_pool, &block)
+        Ok(())
     }
 }
 
@@ -750,6 +802,70 @@ mod tests {
         assert_eq!(block_reward(1_750_000 * 64), 0);
         assert_eq!(block_reward(1_750_000 * 100), 0);
     }
+
+    #[test]
+    fn test_validate_header_beacon() {
+        let genesis_block_hash = Hash::SHA256([1; 32]);
+        let prev_hash = Hash::SHA256([2; 32]);
+        let chain_beacon = CheckpointBeacon {
+            checkpoint: 10,
+            hash_prev_block: prev_hash,
+        };
+        let header = |checkpoint, hash_prev_block| {
+            let mut header = SignedBlockHeader::default();
+            header.block_header.beacon = CheckpointBeacon {
+                checkpoint,
+                hash_prev_block,
+            };
+
+            header
+        };
+        let block_error = |e: failure::Error| e.downcast::<BlockError>().ok();
+
+        // Headers from the future are rejected
+        let e = validate_header(&header(21, prev_hash), 20, chain_beacon, genesis_block_hash)
+            .unwrap_err();
+        assert_eq!(
+            block_error(e),
+            Some(BlockError::BlockFromFuture {
+                current_epoch: 20,
+                block_epoch: 21,
+            })
+        );
+
+        // Headers older than the previous header are rejected
+        let e = validate_header(&header(9, prev_hash), 20, chain_beacon, genesis_block_hash)
+            .unwrap_err();
+        assert_eq!(
+            block_error(e),
+            Some(BlockError::BlockOlderThanTip {
+                chain_epoch: 10,
+                block_epoch: 9,
+            })
+        );
+
+        // Headers which do not follow the previous header are rejected
+        let unknown_hash = Hash::SHA256([3; 32]);
+        let e = validate_header(
+            &header(11, unknown_hash),
+            20,
+            chain_beacon,
+            genesis_block_hash,
+        )
+        .unwrap_err();
+        assert_eq!(
+            block_error(e),
+            Some(BlockError::PreviousHashNotKnown { hash: unknown_hash })
+        );
+
+        // A header following the previous one only fails because of the missing signature
+        let e = validate_header(&header(11, prev_hash), 20, chain_beacon, genesis_block_hash)
+            .unwrap_err();
+        match block_error(e) {
+            None | Some(BlockError::VerifySignatureFail { .. }) => {}
+            Some(e) => panic!("Unexpected error: {}", e),
+        }
+    }
 }
 
 /// Diffs to apply to an utxo set. This type does not contains a
@@ -0,0 +1,548 @@
+//! Wallet chain index: transactions and balances for the addresses of a wallet
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use witnet_data_structures::chain::{
+    Block, Epoch, Hash, Hashable, Input, Output, OutputPointer, PublicKeyHash, Transaction,
+};
+
+/// Number of epochs after which a transaction which has not been included in a block is no
+/// longer considered pending
+const PENDING_EXPIRY_EPOCHS: Epoch = 20;
+
+/// Output owned by one of the wallet addresses
+#[derive(Clone, Debug, PartialEq)]
+struct WalletOutput {
+    /// Value of the output
+    value: u64,
+    /// Epoch of the block that created the output
+    epoch: Epoch,
+    /// Epoch of the block that spent the output, if any
+    spent: Option<Epoch>,
+}
+
+/// Balance of a wallet
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Balance {
+    /// Sum of the unspent outputs included in consolidated blocks
+    pub confirmed: u64,
+    /// Balance after applying the transactions still waiting to be included in a block
+    pub pending: u64,
+}
+
+/// Entry of the transaction history of a wallet
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TransactionRecord {
+    /// Transaction hash
+    pub hash: Hash,
+    /// Epoch of the block which includes the transaction, `None` while pending
+    pub epoch: Option<Epoch>,
+    /// Hash of the block which includes the transaction, `None` while pending
+    pub block_hash: Option<Hash>,
+    /// Value received by the wallet addresses
+    pub received: u64,
+    /// Value spent from the wallet addresses
+    pub spent: u64,
+}
+
+/// Transaction not yet included in a block
+#[derive(Clone, Debug, PartialEq)]
+struct PendingTransaction {
+    /// Entry of the transaction history
+    record: TransactionRecord,
+    /// Outputs spent by the transaction
+    inputs: Vec<OutputPointer>,
+    /// Epoch of the last indexed block when the transaction was added
+    epoch: Epoch,
+}
+
+/// Index of the transactions touching the addresses of a wallet
+#[derive(Debug, Default)]
+pub struct WalletIndex {
+    /// Addresses being watched
+    pkhs: HashSet<PublicKeyHash>,
+    /// Outputs owned by the watched addresses
+    outputs: HashMap<OutputPointer, WalletOutput>,
+    /// Confirmed transactions, grouped by epoch
+    confirmed: BTreeMap<Epoch, Vec<TransactionRecord>>,
+    /// Transactions not yet included in a block, in the order they were added
+    pending: Vec<PendingTransaction>,
+    /// Hashes of the indexed blocks
+    blocks: BTreeMap<Epoch, Hash>,
+    /// Epoch of the last indexed block
+    last_epoch: Option<Epoch>,
+}
+
+impl WalletIndex {
+    /// Start watching the given addresses.
+    /// Blocks indexed before this call are not scanned again: use `rollback` and index them
+    /// again to find older transactions
+    pub fn watch<I: IntoIterator<Item = PublicKeyHash>>(&mut self, pkhs: I) {
+        self.pkhs.extend(pkhs);
+    }
+
+    /// Epoch of the last indexed block
+    pub fn last_epoch(&self) -> Option<Epoch> {
+        self.last_epoch
+    }
+
+    /// Index all the transactions of a consolidated block
+    pub fn index_block(&mut self, block: &Block) {
+        let epoch = block.block_header.beacon.checkpoint;
+        let block_hash = block.hash();
+        // A block can be received both from a notification and from a rescan
+        if self.blocks.get(&epoch) == Some(&block_hash) {
+            return;
+        }
+        self.blocks.insert(epoch, block_hash);
+
+        for transaction in &block.txns {
+            let hash = transaction.hash();
+            // Once included in a block the transaction is no longer pending, and the pending
+            // transactions which spend any of its inputs will never be
+            let inputs: HashSet<OutputPointer> = transaction
+                .body
+                .inputs
+                .iter()
+                .map(Input::output_pointer)
+                .collect();
+            self.pending.retain(|pending| {
+                pending.record.hash != hash
+                    && !pending.inputs.iter().any(|input| inputs.contains(input))
+            });
+
+            let (received, spent) = self.apply_transaction(transaction, epoch);
+            if received > 0 || spent > 0 {
+                self.confirmed
+                    .entry(epoch)
+                    .or_default()
+                    .push(TransactionRecord {
+                        hash,
+                        epoch: Some(epoch),
+                        block_hash: Some(block_hash),
+                        received,
+                        spent,
+                    });
+            }
+        }
+
+        self.last_epoch = Some(self.last_epoch.map_or(epoch, |last| last.max(epoch)));
+        let last_epoch = self.last_epoch.unwrap_or(epoch);
+        self.pending
+            .retain(|pending| last_epoch.saturating_sub(pending.epoch) < PENDING_EXPIRY_EPOCHS);
+    }
+
+    /// Add a transaction which has not been included in a block yet.
+    /// Returns false if the transaction does not touch any of the wallet addresses, or if it
+    /// has already been indexed from a block
+    pub fn add_pending(&mut self, transaction: &Transaction) -> bool {
+        let (received, spent) = self.transaction_values(transaction);
+        if received == 0 && spent == 0 {
+            return false;
+        }
+
+        let hash = transaction.hash();
+        if self
+            .pending
+            .iter()
+            .any(|pending| pending.record.hash == hash)
+        {
+            return true;
+        }
+        self.pending.push(PendingTransaction {
+            record: TransactionRecord {
+                hash,
+                epoch: None,
+                block_hash: None,
+                received,
+                spent,
+            },
+            inputs: transaction
+                .body
+                .inputs
+                .iter()
+                .map(Input::output_pointer)
+                .collect(),
+            epoch: self.last_epoch.unwrap_or(0),
+        });
+
+        true
+    }
+
+    /// Forget everything indexed from `epoch` onwards, so those blocks can be indexed again.
+    /// Pending transactions are also forgotten, as they may spend the outputs of the forgotten
+    /// blocks
+    pub fn rollback(&mut self, epoch: Epoch) {
+        self.pending.clear();
+        self.outputs.retain(|_, output| output.epoch < epoch);
+        for output in self.outputs.values_mut() {
+            if output.spent.map(|spent| spent >= epoch).unwrap_or(false) {
+                output.spent = None;
+            }
+        }
+        self.confirmed.split_off(&epoch);
+        self.blocks.split_off(&epoch);
+        if self.last_epoch.map(|last| last >= epoch).unwrap_or(false) {
+            self.last_epoch = epoch.checked_sub(1);
+        }
+    }
+
+    /// Current balance of the wallet
+    pub fn balance(&self) -> Balance {
+        let confirmed = self
+            .outputs
+            .values()
+            .filter(|output| output.spent.is_none())
+            .map(|output| output.value)
+            .sum();
+
+        let (received, spent) = self
+            .pending
+            .iter()
+            .fold((0, 0), |(received, spent), pending| {
+                (
+                    received + pending.record.received,
+                    spent + pending.record.spent,
+                )
+            });
+
+        Balance {
+            confirmed,
+            pending: (confirmed + received).saturating_sub(spent),
+        }
+    }
+
+    /// Transaction history, most recent first. Pending transactions go before the confirmed
+    /// ones. Pages are numbered from 0
+    pub fn transactions(&self, limit: u32, page: u32) -> Vec<TransactionRecord> {
+        let limit = limit as usize;
+
+        self.pending
+            .iter()
+            .rev()
+            .map(|pending| &pending.record)
+            .chain(
+                self.confirmed
+                    .values()
+                    .rev()
+                    .flat_map(|records| records.iter().rev()),
+            )
+            .skip(limit.saturating_mul(page as usize))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Update the wallet outputs with the inputs and outputs of a confirmed transaction.
+    /// Returns the values received and spent by the wallet
+    fn apply_transaction(&mut self, transaction: &Transaction, epoch: Epoch) -> (u64, u64) {
+        let mut spent = 0;
+        for input in &transaction.body.inputs {
+            if let Some(output) = self.outputs.get_mut(&input.output_pointer()) {
+                if output.spent.is_none() {
+                    output.spent = Some(epoch);
+                    spent += output.value;
+                }
+            }
+        }
+
+        let transaction_id = transaction.hash();
+        let mut received = 0;
+        for (index, output) in transaction.body.outputs.iter().enumerate() {
+            if self.is_mine(output) {
+                let output_pointer = OutputPointer {
+                    transaction_id,
+                    output_index: index as u32,
+                };
+                self.outputs.insert(
+                    output_pointer,
+                    WalletOutput {
+                        value: output.value(),
+                        epoch,
+                        spent: None,
+                    },
+                );
+                received += output.value();
+            }
+        }
+
+        (received, spent)
+    }
+
+    /// Values received and spent by the wallet in a transaction, without modifying the index.
+    /// Outputs and inputs already indexed from a block are not counted
+    fn transaction_values(&self, transaction: &Transaction) -> (u64, u64) {
+        let transaction_id = transaction.hash();
+        let spent = transaction
+            .body
+            .inputs
+            .iter()
+            .filter_map(|input| self.outputs.get(&input.output_pointer()))
+            .filter(|output| output.spent.is_none())
+            .map(|output| output.value)
+            .sum();
+        let received = transaction
+            .body
+            .outputs
+            .iter()
+            .enumerate()
+            .filter(|(index, output)| {
+                let output_pointer = OutputPointer {
+                    transaction_id,
+                    output_index: *index as u32,
+                };
+                self.is_mine(output) && !self.outputs.contains_key(&output_pointer)
+            })
+            .map(|(_, output)| output.value())
+            .sum();
+
+        (received, spent)
+    }
+
+    /// Check if an output can be spent by one of the wallet addresses
+    fn is_mine(&self, output: &Output) -> bool {
+        output.pkh().map_or(false, |pkh| self.pkhs.contains(&pkh))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use witnet_data_structures::chain::{
+        BlockHeader, CheckpointBeacon, Input, LeadershipProof, TransactionBody, ValueTransferInput,
+        ValueTransferOutput,
+    };
+
+    const MINE: PublicKeyHash = PublicKeyHash { hash: [1; 20] };
+    const OTHER: PublicKeyHash = PublicKeyHash { hash: [2; 20] };
+
+    fn vtt(inputs: Vec<OutputPointer>, outputs: Vec<(PublicKeyHash, u64)>) -> Transaction {
+        let inputs = inputs
+            .into_iter()
+            .map(|pointer| {
+                Input::ValueTransfer(ValueTransferInput {
+                    transaction_id: pointer.transaction_id,
+                    output_index: pointer.output_index,
+                })
+            })
+            .collect();
+        let outputs = outputs
+            .into_iter()
+            .map(|(pkh, value)| Output::ValueTransfer(ValueTransferOutput { pkh, value }))
+            .collect();
+
+        Transaction::new(TransactionBody::new(0, inputs, outputs), vec![])
+    }
+
+    fn block(epoch: Epoch, txns: Vec<Transaction>) -> Block {
+        Block {
+            block_header: BlockHeader {
+                version: 0,
+                beacon: CheckpointBeacon {
+                    checkpoint: epoch,
+                    hash_prev_block: Hash::default(),
+                },
+                hash_merkle_root: Hash::default(),
+            },
+            proof: LeadershipProof::default(),
+            txns,
+        }
+    }
+
+    fn pointer(transaction: &Transaction, output_index: u32) -> OutputPointer {
+        OutputPointer {
+            transaction_id: transaction.hash(),
+            output_index,
+        }
+    }
+
+    #[test]
+    fn index_received_and_spent() {
+        let mut index = WalletIndex::default();
+        index.watch(vec![MINE]);
+
+        let receive = vtt(vec![], vec![(MINE, 10), (OTHER, 5)]);
+        index.index_block(&block(1, vec![receive.clone()]));
+        assert_eq!(index.balance().confirmed, 10);
+
+        let spend = vtt(vec![pointer(&receive, 0)], vec![(OTHER, 7), (MINE, 3)]);
+        index.index_block(&block(2, vec![spend]));
+
+        assert_eq!(
+            index.balance(),
+            Balance {
+                confirmed: 3,
+                pending: 3
+            }
+        );
+        let history = index.transactions(10, 0);
+        assert_eq!(history.len(), 2);
+        assert_eq!((history[0].received, history[0].spent), (3, 10));
+        assert_eq!((history[1].received, history[1].spent), (10, 0));
+        assert_eq!(index.last_epoch(), Some(2));
+    }
+
+    #[test]
+    fn pending_balance() {
+        let mut index = WalletIndex::default();
+        index.watch(vec![MINE]);
+
+        let receive = vtt(vec![], vec![(MINE, 10)]);
+        index.index_block(&block(1, vec![receive.clone()]));
+
+        let spend = vtt(vec![pointer(&receive, 0)], vec![(OTHER, 6), (MINE, 4)]);
+        assert!(index.add_pending(&spend));
+        assert!(!index.add_pending(&vtt(vec![], vec![(OTHER, 1)])));
+        assert_eq!(
+            index.balance(),
+            Balance {
+                confirmed: 10,
+                pending: 4
+            }
+        );
+        assert_eq!(index.transactions(10, 0)[0].epoch, None);
+
+        // Once included in a block the transaction is no longer pending
+        index.index_block(&block(2, vec![spend]));
+        assert_eq!(
+            index.balance(),
+            Balance {
+                confirmed: 4,
+                pending: 4
+            }
+        );
+        assert_eq!(index.transactions(10, 0).len(), 2);
+    }
+
+    #[test]
+    fn pending_after_confirmation_is_ignored() {
+        let mut index = WalletIndex::default();
+        index.watch(vec![MINE]);
+
+        let receive = vtt(vec![], vec![(MINE, 10)]);
+        let spend = vtt(vec![pointer(&receive, 0)], vec![(OTHER, 10)]);
+        index.index_block(&block(1, vec![receive.clone()]));
+        index.index_block(&block(2, vec![spend.clone()]));
+
+        // Mempool notifications arriving after the block do not add pending transactions
+        assert!(!index.add_pending(&receive));
+        assert!(!index.add_pending(&spend));
+        assert_eq!(index.balance(), Balance::default());
+        assert_eq!(index.transactions(10, 0).len(), 2);
+    }
+
+    #[test]
+    fn pending_transactions_most_recent_first() {
+        let mut index = WalletIndex::default();
+        index.watch(vec![MINE]);
+
+        let receives: Vec<_> = (1..=5)
+            .map(|value| vtt(vec![], vec![(MINE, value)]))
+            .collect();
+        for receive in &receives {
+            assert!(index.add_pending(receive));
+        }
+        // Adding a transaction again does not change its position
+        assert!(index.add_pending(&receives[0]));
+
+        let hashes = |records: Vec<TransactionRecord>| {
+            records
+                .into_iter()
+                .map(|record| record.hash)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            hashes(index.transactions(2, 0)),
+            vec![receives[4].hash(), receives[3].hash()]
+        );
+        assert_eq!(hashes(index.transactions(2, 2)), vec![receives[0].hash()]);
+    }
+
+    #[test]
+    fn pending_evicted_by_double_spend_expiry_and_rollback() {
+        let mut index = WalletIndex::default();
+        index.watch(vec![MINE]);
+
+        let receive = vtt(vec![], vec![(MINE, 10)]);
+        index.index_block(&block(1, vec![receive.clone()]));
+
+        // A conflicting transaction spending the same output is included in a block
+        let spend = vtt(vec![pointer(&receive, 0)], vec![(OTHER, 10)]);
+        let conflict = vtt(vec![pointer(&receive, 0)], vec![(OTHER, 5), (MINE, 5)]);
+        assert!(index.add_pending(&spend));
+        index.index_block(&block(2, vec![conflict]));
+        assert_eq!(
+            index.balance(),
+            Balance {
+                confirmed: 5,
+                pending: 5
+            }
+        );
+        assert_eq!(index.transactions(10, 0).len(), 2);
+
+        // Transactions which are never included in a block expire
+        assert!(index.add_pending(&vtt(vec![], vec![(MINE, 1)])));
+        index.index_block(&block(2 + PENDING_EXPIRY_EPOCHS - 1, vec![]));
+        assert_eq!(index.balance().pending, 6);
+        index.index_block(&block(2 + PENDING_EXPIRY_EPOCHS, vec![]));
+        assert_eq!(index.balance().pending, 5);
+
+        // And they are forgotten on rollback
+        assert!(index.add_pending(&vtt(vec![], vec![(MINE, 2)])));
+        index.rollback(3);
+        assert_eq!(
+            index.balance(),
+            Balance {
+                confirmed: 5,
+                pending: 5
+            }
+        );
+    }
+
+    #[test]
+    fn paginate_transactions() {
+        let mut index = WalletIndex::default();
+        index.watch(vec![MINE]);
+
+        for epoch in 0..5 {
+            index.index_block(&block(
+                epoch,
+                vec![vtt(vec![], vec![(MINE, u64::from(epoch))])],
+            ));
+        }
+
+        let epochs = |records: Vec<TransactionRecord>| {
+            records
+                .into_iter()
+                .map(|record| record.epoch.unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(epochs(index.transactions(2, 0)), vec![4, 3]);
+        assert_eq!(epochs(index.transactions(2, 1)), vec![2, 1]);
+        assert_eq!(epochs(index.transactions(2, 2)), vec![0]);
+        assert!(index.transactions(2, 3).is_empty());
+    }
+
+    #[test]
+    fn rollback_restores_spent_outputs() {
+        let mut index = WalletIndex::default();
+        index.watch(vec![MINE]);
+
+        let receive = vtt(vec![], vec![(MINE, 10)]);
+        let spend = vtt(vec![pointer(&receive, 0)], vec![(OTHER, 10)]);
+        index.index_block(&block(1, vec![receive]));
+        index.index_block(&block(3, vec![spend.clone()]));
+        assert_eq!(index.balance().confirmed, 0);
+
+        index.rollback(2);
+        assert_eq!(index.balance().confirmed, 10);
+        assert_eq!(index.transactions(10, 0).len(), 1);
+        assert_eq!(index.last_epoch(), Some(1));
+
+        // Indexing the same block again gives the same result
+        index.index_block(&block(3, vec![spend]));
+        assert_eq!(index.balance().confirmed, 0);
+        assert_eq!(index.transactions(10, 0).len(), 2);
+    }
+}
@@ -7,4 +7,6 @@
 #![deny(unused_mut)]
 #![deny(missing_docs)]
 
+pub mod index;
+
 pub mod server;
@@ -1,5 +1,17 @@
-pub mod server;
+use std::{path::PathBuf, process::exit};
+
 use env_logger::Builder;
+use structopt::StructOpt;
+
+use witnet_wallet::server;
+
+/// Witnet wallet
+#[derive(Debug, StructOpt)]
+struct Cli {
+    /// Path to the configuration file of the node the wallet connects to
+    #[structopt(name = "config", long = "config", short = "c", parse(from_os_str))]
+    config: Option<PathBuf>,
+}
 
 fn main() {
     // Init app logger
@@ -8,5 +20,13 @@ fn main() {
         //.default_format_timestamp(false)
         //.default_format_module_path(false)
         .init();
-    server::websockets_actix_poc();
+
+    let cli = Cli::from_args();
+    if let Err(e) = server::websockets_actix_poc(cli.config) {
+        eprintln!("Error: {}", e);
+        for cause in e.iter_causes() {
+            eprintln!("Cause: {}", cause);
+        }
+        exit(1);
+    }
 }
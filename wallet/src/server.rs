@@ -1,15 +1,11 @@
 //! Websockets JSON-RPC server
 
 use actix::{
-    Actor, ActorFuture, AsyncContext, Context, ContextFutureSpawner, Handler, Message,
-    ResponseActFuture, StreamHandler, Supervised, System, SystemRegistry, SystemService,
-    WrapFuture,
+    Actor, ActorFuture, Addr, AsyncContext, Context, ContextFutureSpawner, Handler, Message,
+    ResponseActFuture, ResponseFuture, Supervised, SyncArbiter, SyncContext, System,
+    SystemRegistry, SystemService, WrapFuture,
 };
-use async_jsonrpc_client::{
-    transports::{shared::EventLoopHandle, tcp::TcpSocket},
-    DuplexTransport, Transport,
-};
-use futures::{future::Future, stream::Stream};
+use futures::future::Future;
 use jsonrpc_pubsub::{PubSubHandler, Session, Subscriber, SubscriptionId};
 use jsonrpc_ws_server::{
     jsonrpc_core,
@@ -22,28 +18,40 @@ use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     net::SocketAddr,
+    path::PathBuf,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc, Mutex,
     },
+    thread,
+    time::Duration,
+};
+
+use witnet_config::{
+    config::{Config, JsonRPC as JsonRpcConfig},
+    loaders::toml,
+};
+use witnet_crypto::{
+    hash::calculate_sha256,
+    key::{ChildNumber as KeyChildNumber, ExtendedSK, MasterKeyGen, SignContext, PK},
+    mnemonic::{Lang, Length, Mnemonic, MnemonicGen},
 };
+use witnet_data_structures::{
+    chain::{
+        Block, Environment, Epoch, Hash, Hashable, PublicKey, PublicKeyHash,
+        Transaction as ChainTransaction,
+    },
+    set_environment,
+};
+use witnet_json_rpc_client::{client_token, Client as NodeClient, ClientError};
+
+use crate::index::{Balance, TransactionRecord, WalletIndex};
 
-/// List of subscriptions from the websockects client (sheikah)
+/// List of subscriptions from the websockects client (sheikah). Indexed by method name, then
+/// subscription id
 // TODO: this is defined twice: once here and once in node/json_rpc_methods?
 pub type Subscriptions = Arc<
-    Mutex<
-        HashMap<
-            &'static str,
-            HashMap<
-                jsonrpc_pubsub::SubscriptionId,
-                (
-                    jsonrpc_pubsub::Sink,
-                    Option<jsonrpc_pubsub::SubscriptionId>,
-                    Value,
-                ),
-            >,
-        >,
-    >,
+    Mutex<HashMap<String, HashMap<jsonrpc_pubsub::SubscriptionId, (jsonrpc_pubsub::Sink, Value)>>>,
 >;
 
 // Helper macro to add multiple JSON-RPC methods at once
@@ -74,6 +82,7 @@ macro_rules! add_methods {
 fn start_ws_jsonrpc_server(
     addr: &SocketAddr,
     registry: SystemRegistry,
+    environment: Environment,
 ) -> Result<Server, jsonrpc_ws_server::Error> {
     // JSON-RPC supported methods
     let mut io = PubSubHandler::new(MetaIoHandler::default());
@@ -92,7 +101,9 @@ fn start_ws_jsonrpc_server(
         ("createWallet", create_wallet),
         ("unlockWallet", unlock_wallet),
         ("getTransactions", get_transactions),
-        ("sendVTT", send_vtt),
+        ("getBalance", get_balance),
+        ("rescan", rescan),
+        ("sendVTT", |r, p| send_vtt(r, p, &environment)),
         ("generateAddress", generate_address),
         ("createDataRequest", create_data_request),
         ("runDataRequest", run_data_request),
@@ -321,9 +332,12 @@ fn create_data_request(
     })))
 }
 
-// TODO: is this pkh?
-#[derive(Serialize)]
-struct Address {}
+/// Address of a wallet, encoded as bech32
+#[derive(Debug, Serialize)]
+struct Address {
+    address: String,
+    path: String,
+}
 
 #[derive(Debug, Deserialize)]
 struct GenerateAddressParams {
@@ -331,26 +345,26 @@ struct GenerateAddressParams {
 }
 
 fn generate_address(
-    _registry: &SystemRegistry,
+    registry: &SystemRegistry,
     params: jsonrpc_ws_server::jsonrpc_core::Result<GenerateAddressParams>,
 ) -> impl Future<Item = Value, Error = jsonrpc_ws_server::jsonrpc_core::Error> {
-    match params {
-        Ok(x) => x,
-        Err(e) => return Box::new(futures::failed(e)),
+    let wallet_id = match params {
+        Ok(x) => x.wallet_id,
+        Err(e) => return futures::future::Either::A(futures::failed(e)),
     };
 
-    let x = Address {};
-    Box::new(futures::done(serde_json::to_value(x).map_err(|e| {
-        let mut err = jsonrpc_core::Error::internal_error();
-        err.message = e.to_string();
-        err
-    })))
+    futures::future::Either::B(
+        registry
+            .get::<WalletManager>()
+            .send(GenerateAddress { wallet_id })
+            .then(index_manager_response),
+    )
 }
 
 #[derive(Debug, Deserialize)]
 struct SendVttParams {
     wallet_id: String,
-    to_address: Vec<u8>,
+    to_address: String,
     amount: u64,
     fee: u64,
     subject: String,
@@ -359,11 +373,18 @@ struct SendVttParams {
 fn send_vtt(
     _registry: &SystemRegistry,
     params: jsonrpc_core::Result<SendVttParams>,
+    environment: &Environment,
 ) -> impl Future<Item = Value, Error = jsonrpc_core::Error> {
-    let _params = match params {
+    let params = match params {
         Ok(x) => x,
         Err(e) => return Box::new(futures::failed(e)),
     };
+    // Reject mistyped addresses and addresses of other environments before sending anything
+    if let Err(e) = PublicKeyHash::from_bech32(environment, &params.to_address) {
+        return Box::new(futures::failed(jsonrpc_core::Error::invalid_params(
+            e.to_string(),
+        )));
+    }
 
     let x = Transaction {};
     Box::new(futures::done(serde_json::to_value(x).map_err(|e| {
@@ -385,20 +406,94 @@ struct GetTransactionsParams {
 struct Transaction {}
 
 fn get_transactions(
-    _registry: &SystemRegistry,
+    registry: &SystemRegistry,
     params: jsonrpc_core::Result<GetTransactionsParams>,
 ) -> impl Future<Item = Value, Error = jsonrpc_core::Error> {
-    let _params = match params {
+    let params = match params {
         Ok(x) => x,
-        Err(e) => return Box::new(futures::failed(e)),
+        Err(e) => return futures::future::Either::A(futures::failed(e)),
     };
 
-    let x: Vec<Transaction> = vec![];
-    Box::new(futures::done(serde_json::to_value(x).map_err(|e| {
-        let mut err = jsonrpc_core::Error::internal_error();
-        err.message = e.to_string();
-        err
-    })))
+    futures::future::Either::B(
+        registry
+            .get::<IndexManager>()
+            .send(GetTransactions {
+                wallet_id: params.wallet_id,
+                limit: params.limit,
+                page: params.page,
+            })
+            .then(index_manager_response),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct GetBalanceParams {
+    wallet_id: String,
+}
+
+fn get_balance(
+    registry: &SystemRegistry,
+    params: jsonrpc_core::Result<GetBalanceParams>,
+) -> impl Future<Item = Value, Error = jsonrpc_core::Error> {
+    let params = match params {
+        Ok(x) => x,
+        Err(e) => return futures::future::Either::A(futures::failed(e)),
+    };
+
+    futures::future::Either::B(
+        registry
+            .get::<IndexManager>()
+            .send(GetBalance {
+                wallet_id: params.wallet_id,
+            })
+            .then(index_manager_response),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct RescanParams {
+    wallet_id: String,
+    #[serde(default)] // default to the genesis epoch
+    epoch: Epoch,
+}
+
+fn rescan(
+    registry: &SystemRegistry,
+    params: jsonrpc_core::Result<RescanParams>,
+) -> impl Future<Item = Value, Error = jsonrpc_core::Error> {
+    let params = match params {
+        Ok(x) => x,
+        Err(e) => return futures::future::Either::A(futures::failed(e)),
+    };
+
+    futures::future::Either::B(
+        registry
+            .get::<IndexManager>()
+            .send(Rescan {
+                wallet_id: params.wallet_id,
+                epoch: params.epoch,
+            })
+            .then(index_manager_response),
+    )
+}
+
+/// Convert the response of the IndexManager or the WalletManager into a JSON-RPC result
+fn index_manager_response<T: Serialize>(
+    res: Result<Result<T, String>, actix::MailboxError>,
+) -> jsonrpc_core::Result<Value> {
+    match res {
+        Ok(Ok(x)) => serde_json::to_value(x).map_err(|e| {
+            let mut err = jsonrpc_core::Error::internal_error();
+            err.message = e.to_string();
+            err
+        }),
+        Ok(Err(e)) => Err(jsonrpc_core::Error::invalid_params(e)),
+        Err(e) => {
+            let mut err = jsonrpc_core::Error::internal_error();
+            err.message = e.to_string();
+            Err(err)
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -513,41 +608,156 @@ fn create_wallet(
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 enum ImportSeedParams {
-    Mnemonics { mnemonics: Mnemonics },
-    Seed { seed: String },
+    Mnemonics {
+        mnemonics: String,
+        #[serde(default)] // default to empty passphrase
+        passphrase: String,
+        #[serde(default)]
+        language: MnemonicsLanguage,
+        #[serde(default)]
+        caption: String,
+    },
+    Seed {
+        seed: String,
+        #[serde(default)]
+        caption: String,
+    },
 }
 
 fn import_seed(
-    _registry: &SystemRegistry,
+    registry: &SystemRegistry,
     params: jsonrpc_core::Result<ImportSeedParams>,
 ) -> impl Future<Item = Value, Error = jsonrpc_core::Error> {
-    let _params = match params {
+    let params = match params {
         Ok(x) => x,
-        Err(e) => return Box::new(futures::failed(e)),
+        Err(e) => return futures::future::Either::A(futures::failed(e)),
     };
 
-    let x = true;
-    Box::new(futures::done(serde_json::to_value(x).map_err(|e| {
-        let mut err = jsonrpc_core::Error::internal_error();
-        err.message = e.to_string();
-        err
-    })))
+    let (caption, master_key) = match params {
+        ImportSeedParams::Mnemonics {
+            mnemonics,
+            passphrase,
+            language,
+            caption,
+        } => {
+            // Fails if any of the words is not in the word list or the checksum is invalid
+            let master_key = Mnemonic::from_phrase(mnemonics, language.into())
+                .map_err(|e| e.to_string())
+                .and_then(|mnemonic| {
+                    MasterKeyGen::new(mnemonic.seed(passphrase.as_str()))
+                        .generate()
+                        .map_err(|e| e.to_string())
+                });
+
+            (caption, master_key)
+        }
+        ImportSeedParams::Seed { seed, caption } => {
+            // Fails if any of the characters is not base58 or the checksum is invalid
+            let master_key = ExtendedSK::from_xprv(&seed).map_err(|e| e.to_string());
+
+            (caption, master_key)
+        }
+    };
+
+    let master_key = match master_key {
+        Ok(x) => x,
+        Err(e) => {
+            return futures::future::Either::A(futures::failed(
+                jsonrpc_core::Error::invalid_params(e),
+            ))
+        }
+    };
+
+    futures::future::Either::B(
+        registry
+            .get::<WalletManager>()
+            .send(ImportWallet {
+                caption,
+                master_key,
+            })
+            .then(index_manager_response),
+    )
+}
+
+/// Language of the mnemonic words
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum MnemonicsLanguage {
+    English,
+}
+
+impl Default for MnemonicsLanguage {
+    fn default() -> Self {
+        MnemonicsLanguage::English
+    }
+}
+
+impl From<MnemonicsLanguage> for Lang {
+    fn from(x: MnemonicsLanguage) -> Self {
+        match x {
+            MnemonicsLanguage::English => Lang::English,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateMnemonicsParams {
+    #[serde(default = "default_mnemonics_length")]
+    length: u8,
+    #[serde(default)]
+    language: MnemonicsLanguage,
+}
+
+impl Default for CreateMnemonicsParams {
+    fn default() -> Self {
+        Self {
+            length: default_mnemonics_length(),
+            language: MnemonicsLanguage::default(),
+        }
+    }
+}
+
+fn default_mnemonics_length() -> u8 {
+    12
 }
 
-// TODO: implemented in PR #432
 #[derive(Debug, Deserialize, Serialize)]
-struct Mnemonics {}
+struct Mnemonics {
+    mnemonics: String,
+}
 
 fn create_mnemonics(
     _registry: &SystemRegistry,
-    params: jsonrpc_core::Result<()>,
+    params: jsonrpc_core::Result<Option<CreateMnemonicsParams>>,
 ) -> impl Future<Item = Value, Error = jsonrpc_core::Error> {
-    match params {
-        Ok(x) => x,
+    let params = match params {
+        Ok(x) => x.unwrap_or_default(),
         Err(e) => return Box::new(futures::failed(e)),
     };
 
-    let x = Mnemonics {};
+    let len = match params.length {
+        12 => Length::Words12,
+        15 => Length::Words15,
+        18 => Length::Words18,
+        21 => Length::Words21,
+        24 => Length::Words24,
+        x => {
+            return Box::new(futures::failed(jsonrpc_core::Error::invalid_params(
+                format!(
+                    "Invalid mnemonics length: {}. Must be one of 12, 15, 18, 21 or 24",
+                    x
+                ),
+            )))
+        }
+    };
+
+    let mnemonic = MnemonicGen::new()
+        .with_len(len)
+        .with_lang(params.language.into())
+        .generate();
+    let x = Mnemonics {
+        mnemonics: mnemonic.words().to_string(),
+    };
     Box::new(futures::done(serde_json::to_value(x).map_err(|e| {
         let mut err = jsonrpc_core::Error::internal_error();
         err.message = e.to_string();
@@ -555,27 +765,26 @@ fn create_mnemonics(
     })))
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 struct WalletInfo {
     id: String,
     caption: String,
 }
 
 fn get_wallet_infos(
-    _registry: &SystemRegistry,
+    registry: &SystemRegistry,
     params: jsonrpc_core::Result<()>,
 ) -> impl Future<Item = Value, Error = jsonrpc_core::Error> {
-    match params {
-        Ok(x) => x,
-        Err(e) => return Box::new(futures::failed(e)),
-    };
+    if let Err(e) = params {
+        return futures::future::Either::A(futures::failed(e));
+    }
 
-    let x: Vec<WalletInfo> = vec![];
-    Box::new(futures::done(serde_json::to_value(x).map_err(|e| {
-        let mut err = jsonrpc_core::Error::internal_error();
-        err.message = e.to_string();
-        err
-    })))
+    futures::future::Either::B(
+        registry
+            .get::<WalletManager>()
+            .send(GetWalletInfos)
+            .then(index_manager_response),
+    )
 }
 
 /// Forwards a JSON-RPC call to the node
@@ -668,8 +877,34 @@ impl Handler<Greeting> for HiActor {
         format!("Hi, {}!", msg.name)
     }
 }
+/// Path of the configuration of the node the wallet connects to, used if no other path is given
+const CONFIG_FILE: &str = "witnet.toml";
+
+/// Read the configuration of the node the wallet connects to, which sets the environment of the
+/// addresses and the address of the node JSON-RPC server. If no path is given, `witnet.toml` is
+/// read from the current directory, and the defaults are used if it does not exist
+fn load_config(config_path: Option<PathBuf>) -> Result<Config, failure::Error> {
+    let config_path = match config_path {
+        Some(config_path) => config_path,
+        None => {
+            let config_path = PathBuf::from(CONFIG_FILE);
+            if !config_path.exists() {
+                info!("{} not found, using the default configuration", CONFIG_FILE);
+                return Ok(Config::default());
+            }
+
+            config_path
+        }
+    };
+
+    Ok(Config::from_partial(&toml::from_file(&config_path)?))
+}
+
 /// poc
-pub fn websockets_actix_poc() {
+pub fn websockets_actix_poc(config_path: Option<PathBuf>) -> Result<(), failure::Error> {
+    let config = load_config(config_path)?;
+    set_environment(config.environment.clone());
+
     // Actix
     let system = System::new("wallet");
     let s = System::current();
@@ -685,15 +920,25 @@ pub fn websockets_actix_poc() {
     // WebSockets server address
     let addr = "127.0.0.1:3030".parse().unwrap();
     // Start server before calling system.run()
-    let _ws_server_handle =
-        start_ws_jsonrpc_server(&addr, registry).expect("Failed to start WebSockets server");
+    let _ws_server_handle = start_ws_jsonrpc_server(&addr, registry, config.environment.clone())
+        .map_err(|e| failure::format_err!("Failed to start WebSockets server: {}", e))?;
 
-    let jsonrpc_ws_client = JsonRpcClient::new("127.0.0.1:1234");
+    let jsonrpc_ws_client = JsonRpcClient::new(config.jsonrpc.clone());
     s.registry().set(jsonrpc_ws_client.start());
+    s.registry().set(IndexManager::default().start());
+    s.registry().set(
+        WalletManager {
+            environment: config.environment.clone(),
+            ..WalletManager::default()
+        }
+        .start(),
+    );
 
     // Because system.run() blocks
     let code = system.run();
     info!("Done, system exited with code {}", code);
+
+    Ok(())
 }
 
 // JavaScript code to send a request:
@@ -710,29 +955,205 @@ socket.addEventListener('open', function (event) {
 
 */
 
+/// Maximum time to wait for a response of the node
+const NODE_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Period between the checks of whether a subscription to the node has been cancelled
+const SUBSCRIPTION_POLL_PERIOD: Duration = Duration::from_secs(1);
+
+/// Time to wait before subscribing again when the connection of a subscription fails
+const SUBSCRIPTION_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Connect to the JSON-RPC server of the node, authenticating if needed
+fn connect_to_node(config: &JsonRpcConfig) -> Result<NodeClient, ClientError> {
+    let mut client = NodeClient::connect(config.server_address)?;
+    client.set_read_timeout(Some(NODE_READ_TIMEOUT))?;
+
+    // Use the token from the configuration or the cookie file, if any
+    if let Some(token) = client_token(config)? {
+        client.authenticate(&token)?;
+    }
+
+    Ok(client)
+}
+
+/// Subscribe to `method`, and call `notify` with the payload of each notification until `stop`
+/// is set
+fn receive_notifications<F>(
+    config: &JsonRpcConfig,
+    method: &str,
+    params: &Value,
+    stop: &AtomicBool,
+    notify: &F,
+) -> Result<(), ClientError>
+where
+    F: Fn(Value),
+{
+    let mut client = connect_to_node(config)?;
+    let id = client.subscribe(method, params.clone())?;
+    info!("Subscribed to {} with id {}", method, id);
+
+    // Wake up periodically to check whether the subscription has been cancelled
+    client.set_read_timeout(Some(SUBSCRIPTION_POLL_PERIOD))?;
+    while !stop.load(Ordering::SeqCst) {
+        match client.next_notification() {
+            Ok(notification) => notify(notification.result),
+            Err(ref e) if e.is_timeout() => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    client.set_read_timeout(Some(NODE_READ_TIMEOUT))?;
+    client.unsubscribe(&id)?;
+    info!("Unsubscribed from {} with id {}", method, id);
+
+    Ok(())
+}
+
+/// Subscription to the notifications of the node, which waits for them in its own thread with
+/// its own connection, and subscribes again when the connection fails.
+///
+/// The subscription is cancelled when this is dropped.
+#[derive(Debug)]
+struct NodeSubscription {
+    stop: Arc<AtomicBool>,
+}
+
+impl NodeSubscription {
+    /// Subscribe to `method` with the filter `params`, calling `notify` with the payload of
+    /// each notification
+    fn start<F>(config: JsonRpcConfig, method: String, params: Value, notify: F) -> Self
+    where
+        F: Fn(Value) + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+
+        thread::spawn(move || {
+            while !thread_stop.load(Ordering::SeqCst) {
+                if let Err(e) =
+                    receive_notifications(&config, &method, &params, &thread_stop, &notify)
+                {
+                    warn!(
+                        "Subscription to {} failed, subscribing again: {}",
+                        method, e
+                    );
+                    thread::sleep(SUBSCRIPTION_RETRY_DELAY);
+                }
+            }
+        });
+
+        Self { stop }
+    }
+}
+
+impl Drop for NodeSubscription {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Number of threads making calls to the node, each one with its own connection
+const NODE_CALLER_THREADS: usize = 4;
+
+/// Synchronous actor which calls the methods of the node.
+///
+/// The calls block until the node answers, for at most `NODE_READ_TIMEOUT`, so it runs in a
+/// `SyncArbiter` instead of blocking the arbiter of the rest of the actors.
+#[derive(Debug)]
+struct NodeCaller {
+    config: JsonRpcConfig,
+    /// Connection used for the calls, opened when needed
+    connection: Option<NodeClient>,
+}
+
+impl NodeCaller {
+    /// Start the threads of the actor
+    fn start(config: JsonRpcConfig) -> Addr<Self> {
+        SyncArbiter::start(NODE_CALLER_THREADS, move || Self {
+            config: config.clone(),
+            connection: None,
+        })
+    }
+
+    /// Connection to the node, which is opened if needed
+    fn connection(&mut self) -> Result<&mut NodeClient, ClientError> {
+        if self.connection.is_none() {
+            self.connection = Some(connect_to_node(&self.config)?);
+        }
+
+        Ok(self.connection.as_mut().unwrap())
+    }
+
+    /// Make a call to the node with `f`, connecting again if the node closed the connection
+    fn call<T, F>(&mut self, f: F) -> Result<T, ClientError>
+    where
+        F: Fn(&mut NodeClient) -> Result<T, ClientError>,
+    {
+        let result = match f(self.connection()?) {
+            Err(ClientError::ConnectionClosed) => {
+                // The node closes the connections which are idle for too long
+                debug!("The node closed the connection, connecting again");
+                self.connection = None;
+                f(self.connection()?)
+            }
+            result => result,
+        };
+
+        match result {
+            Ok(_) | Err(ClientError::Server(_)) => {}
+            // After any other error, such as a timeout, a response could still arrive, so the
+            // connection cannot be used for the next calls
+            Err(_) => self.connection = None,
+        }
+
+        result
+    }
+}
+
+impl Actor for NodeCaller {
+    type Context = SyncContext<Self>;
+}
+
+/// Actor which forwards the calls to the node to the `NodeCaller` and the notifications of the
+/// node to the rest of the actors and to the clients of the wallet
 #[derive(Debug)]
 struct JsonRpcClient {
-    handle: EventLoopHandle,
-    s: TcpSocket,
+    config: JsonRpcConfig,
+    /// Actor making the calls
+    caller: Addr<NodeCaller>,
+    /// Subscriptions made by the wallet itself
+    wallet_subscriptions: Vec<NodeSubscription>,
+    /// Subscriptions made on behalf of the clients of the wallet
+    forwarded_subscriptions: HashMap<SubscriptionId, NodeSubscription>,
     subscriptions: Subscriptions,
-    node_id_to_client_id: HashMap<SubscriptionId, SubscriptionId>,
 }
 
 impl JsonRpcClient {
-    fn new(url: &str) -> Self {
-        let (handle, s) = TcpSocket::new(url).unwrap();
+    fn new(config: JsonRpcConfig) -> Self {
         Self {
-            handle,
-            s,
+            caller: NodeCaller::start(config.clone()),
+            config,
+            wallet_subscriptions: vec![],
+            forwarded_subscriptions: Default::default(),
             subscriptions: Default::default(),
-            node_id_to_client_id: Default::default(),
         }
     }
+
+    /// Subscribe to the notifications of `method`, calling `notify` with each of them
+    fn subscribe<F>(&self, method: &str, params: Value, notify: F) -> NodeSubscription
+    where
+        F: Fn(Value) + Send + 'static,
+    {
+        debug!("Subscribing to method {} with params {}", method, params);
+
+        NodeSubscription::start(self.config.clone(), method.to_string(), params, notify)
+    }
 }
 
 impl Default for JsonRpcClient {
     fn default() -> Self {
-        Self::new("127.0.0.1:1234")
+        Self::new(Config::default().jsonrpc)
     }
 }
 
@@ -741,11 +1162,21 @@ impl Actor for JsonRpcClient {
     type Context = Context<Self>;
 
     /// Method to be executed when the actor is started
-    fn started(&mut self, _ctx: &mut Self::Context) {
+    fn started(&mut self, ctx: &mut Self::Context) {
         debug!(
-            "JsonRpcClient actor has been started at address {}",
-            self.s.addr()
+            "JsonRpcClient actor has been started for node {}",
+            self.config.server_address
         );
+
+        // Keep the wallet index in sync with the blocks consolidated by the node and with the
+        // transactions waiting in its mempool
+        for &method in &["newBlocks", "newTransactions"] {
+            let addr = ctx.address();
+            let subscription = self.subscribe(method, Value::Null, move |item| {
+                addr.do_send(NormalNotification(method.to_string(), item))
+            });
+            self.wallet_subscriptions.push(subscription);
+        }
     }
 }
 
@@ -771,89 +1202,74 @@ impl Message for JsonRpcMsg {
     type Result = Result<Value, String>;
 }
 
-impl Handler<JsonRpcMsg> for JsonRpcClient {
-    type Result = ResponseActFuture<Self, Value, String>;
+impl Handler<JsonRpcMsg> for NodeCaller {
+    type Result = Result<Value, String>;
 
-    fn handle(&mut self, msg: JsonRpcMsg, _ctx: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, msg: JsonRpcMsg, _ctx: &mut SyncContext<Self>) -> Self::Result {
         debug!(
             "Calling node method {} with params {}",
             msg.method, msg.params
         );
-        let fut = self
-            .s
-            .execute(&msg.method, msg.params)
-            .into_actor(self)
-            .then(|x, _act, _ctx| {
-                actix::fut::result(match x {
-                    Ok(x) => Ok(x),
-                    Err(e) => {
-                        warn!("Error: {}", e);
-                        Err(e.to_string())
-                    }
-                })
-            });
 
-        Box::new(fut)
+        self.call(|client| client.call(&msg.method, msg.params.clone()))
+            .map_err(|e| {
+                warn!("Error: {}", e);
+                e.to_string()
+            })
     }
 }
 
-struct JsonRpcSubscribeMsg {
-    method: String,
-    params: Value,
-}
+impl Handler<JsonRpcMsg> for JsonRpcClient {
+    type Result = ResponseFuture<Value, String>;
 
-impl JsonRpcSubscribeMsg {
-    // TODO: remove once used
-    #[allow(unused)]
-    fn new<A: Into<String>, B: Into<Value>>(method: A, params: B) -> Self {
-        Self {
-            method: method.into(),
-            params: params.into(),
-        }
+    fn handle(&mut self, msg: JsonRpcMsg, _ctx: &mut Context<Self>) -> Self::Result {
+        Box::new(
+            self.caller
+                .send(msg)
+                .map_err(|e| e.to_string())
+                .and_then(|res| res),
+        )
     }
 }
 
-impl Message for JsonRpcSubscribeMsg {
-    type Result = Result<String, String>;
+/// Get the blocks of the chain of the node, starting from an epoch
+struct GetBlocks {
+    epoch: Epoch,
+    limit: u32,
 }
 
-impl Handler<JsonRpcSubscribeMsg> for JsonRpcClient {
-    type Result = ResponseActFuture<Self, String, String>;
+impl Message for GetBlocks {
+    type Result = Result<Vec<Block>, String>;
+}
 
-    fn handle(&mut self, msg: JsonRpcSubscribeMsg, _ctx: &mut Context<Self>) -> Self::Result {
-        debug!(
-            "Subscribing to method {} with params {}",
-            msg.method, msg.params
-        );
-        let subscribe = JsonRpcMsg::new(
-            "witnet_subscribe".to_string(),
-            Value::Array(vec![Value::String(msg.method.clone()), msg.params.clone()]),
-        );
-        let fut = self
-            .s
-            .execute(&subscribe.method, subscribe.params)
-            .into_actor(self)
-            .then(|x, act, ctx| match x {
-                Ok(res) => {
-                    info!("Subscribed successfully! Id: {:?}", res);
-                    let res = match res {
-                        Value::String(s) => s,
-                        _ => panic!("Only String subscription ids are supported"),
-                    };
-                    let resc = res.clone();
-                    let fut = act
-                        .s
-                        .subscribe(&res.clone().into())
-                        .map(move |x| NormalNotification(resc.clone(), x));
-                    JsonRpcClient::add_stream(fut, ctx);
-                    actix::fut::ok(res)
-                }
-                Err(e) => {
-                    warn!("Error: {}", e);
-                    actix::fut::err(e.to_string())
-                }
-            });
-        Box::new(fut)
+impl Handler<GetBlocks> for NodeCaller {
+    type Result = Result<Vec<Block>, String>;
+
+    fn handle(&mut self, msg: GetBlocks, _ctx: &mut SyncContext<Self>) -> Self::Result {
+        let block_chain = self
+            .call(|client| client.get_block_chain(i64::from(msg.epoch), msg.limit))
+            .map_err(|e| e.to_string())?;
+
+        block_chain
+            .into_iter()
+            .map(|(_epoch, hash)| {
+                self.call(|client| client.get_block(hash))
+                    .map_err(|e| e.to_string())
+            })
+            .collect()
+    }
+}
+
+impl Handler<GetBlocks> for JsonRpcClient {
+    type Result = ResponseFuture<Vec<Block>, String>;
+
+    fn handle(&mut self, msg: GetBlocks, _ctx: &mut Context<Self>) -> Self::Result {
+        Box::new(
+            self.caller
+                .send(msg)
+                .map_err(|e| e.to_string())
+                .and_then(|res| res),
+        )
     }
 }
 
@@ -881,118 +1297,27 @@ impl JsonRpcForwardSubscribeMsg {
 }
 
 impl Message for JsonRpcForwardSubscribeMsg {
-    type Result = Result<String, String>;
+    type Result = ();
 }
 
 impl Handler<JsonRpcForwardSubscribeMsg> for JsonRpcClient {
-    type Result = ResponseActFuture<Self, String, String>;
+    type Result = ();
 
-    fn handle(
-        &mut self,
-        msg: JsonRpcForwardSubscribeMsg,
-        _ctx: &mut Context<Self>,
-    ) -> Self::Result {
-        debug!(
-            "Subscribing to method {} with params {}",
-            msg.method, msg.params
-        );
-        let subscribe = JsonRpcMsg::new(
-            "witnet_subscribe".to_string(),
-            Value::Array(vec![Value::String(msg.method.clone()), msg.params.clone()]),
-        );
-        let fut = self
-            .s
-            .execute(&subscribe.method, subscribe.params)
-            .into_actor(self)
-            .then(|x, act, ctx| match x {
-                Ok(res) => {
-                    info!("Subscribed successfully! Id: {:?}", res);
-                    let res = match res {
-                        Value::String(s) => s,
-                        _ => panic!("Only String subscription ids are supported"),
-                    };
-                    let resc = res.clone();
-                    let empty_map = HashMap::new();
-                    let fut = act
-                        .s
-                        .subscribe(&res.clone().into())
-                        .map(move |x| ForwardNotification(resc.clone(), x));
-                    JsonRpcClient::add_stream(fut, ctx);
-
-                    if let Ok(mut s) = act.subscriptions.lock() {
-                        let v = s.entry("newBlocks").or_insert(empty_map);
-                        let node_sub_id = res.clone();
-                        v.insert(
-                            msg.id.clone().into(),
-                            (msg.sink, Some(node_sub_id.clone().into()), msg.params),
-                        );
-                        info!("Mapping node id {} to client id {}", node_sub_id, msg.id);
-                        act.node_id_to_client_id
-                            .insert(node_sub_id.into(), msg.id.into());
-                        info!("Subscribed to newBlocks");
-                        info!("This session has {} subscriptions", v.len());
-                    }
-
-                    actix::fut::ok(res)
-                }
-                Err(e) => {
-                    warn!("Error: {}", e);
-                    actix::fut::err(e.to_string())
-                }
-            });
-        Box::new(fut)
-    }
-}
-
-struct JsonRpcUnsubscribe {
-    id: SubscriptionId,
-}
-
-impl Message for JsonRpcUnsubscribe {
-    type Result = Result<Value, String>;
-}
-
-impl Handler<JsonRpcUnsubscribe> for JsonRpcClient {
-    type Result = ResponseActFuture<Self, Value, String>;
-
-    fn handle(&mut self, msg: JsonRpcUnsubscribe, _ctx: &mut Self::Context) -> Self::Result {
-        let id_value = match msg.id.clone() {
-            jsonrpc_pubsub::SubscriptionId::Number(x) => Value::Number(serde_json::Number::from(x)),
-            jsonrpc_pubsub::SubscriptionId::String(s) => Value::String(s),
-        };
-        /*
-        let id_string = match msg.id.clone() {
-            jsonrpc_pubsub::SubscriptionId::Number(x) => panic!("Integer ids are not supported by async_jsonrpc_client"),
-            jsonrpc_pubsub::SubscriptionId::String(s) => s,
-        };
-        */
-        let unsubscribe = JsonRpcMsg::new(
-            "witnet_unsubscribe".to_string(),
-            Value::Array(vec![id_value]),
-        );
-
-        info!("Handler<JsonRpcUnsubscribe> {:?}", msg.id);
+    fn handle(&mut self, msg: JsonRpcForwardSubscribeMsg, ctx: &mut Context<Self>) {
+        let addr = ctx.address();
+        let client_id = msg.id.clone();
+        let subscription = self.subscribe(&msg.method, msg.params.clone(), move |item| {
+            addr.do_send(ForwardNotification(client_id.clone(), item))
+        });
+        self.forwarded_subscriptions
+            .insert(msg.id.clone().into(), subscription);
 
-        // TODO: somehow removing this fixes the closed socket bug?
-        // Stop listening to notifications with this id
-        //self.s.unsubscribe(&id_string.clone().into());
-
-        // Call unsubscribe method
-        let fut = self
-            .s
-            .execute(&unsubscribe.method, unsubscribe.params)
-            .into_actor(self)
-            .then(|res, _act, _ctx| match res {
-                Ok(res) => {
-                    info!("Unsubscribed from node successfully! Res: {:?}", res);
-                    actix::fut::ok(res)
-                }
-                Err(e) => {
-                    warn!("Error: {}", e);
-                    actix::fut::err(e.to_string())
-                }
-            });
-        Box::new(fut)
+        if let Ok(mut s) = self.subscriptions.lock() {
+            info!("Subscribed to {}", msg.method);
+            let v = s.entry(msg.method).or_insert_with(HashMap::new);
+            v.insert(msg.id.into(), (msg.sink, msg.params));
+            info!("There are {} subscriptions to this method", v.len());
+        }
     }
 }
 
@@ -1008,16 +1333,11 @@ impl Message for ForwardUnsubscribe {
 impl Handler<ForwardUnsubscribe> for JsonRpcClient {
     type Result = Result<jsonrpc_core::Value, jsonrpc_core::Error>;
 
-    fn handle(&mut self, msg: ForwardUnsubscribe, ctx: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: ForwardUnsubscribe, _ctx: &mut Self::Context) -> Self::Result {
         info!("Called ForwardUnsubscribe: {:?}", msg.client_id);
         if let Ok(mut s) = self.subscriptions.lock() {
             for (_method, v) in s.iter_mut() {
-                if let Some((_sink, Some(node_id), _sub_params)) = v.remove(&msg.client_id) {
-                    info!("Removing node {:?} => client {:?}", node_id, msg.client_id);
-                    self.node_id_to_client_id.remove(&node_id);
-                    // We also need to unsubscribe from the node
-                    ctx.address().do_send(JsonRpcUnsubscribe { id: node_id });
-                }
+                v.remove(&msg.client_id);
             }
         } else {
             log::error!("Error unsubscribe: failed to acquire lock");
@@ -1026,34 +1346,64 @@ impl Handler<ForwardUnsubscribe> for JsonRpcClient {
             return Err(e);
         }
 
+        // Dropping the subscription also unsubscribes from the node
+        self.forwarded_subscriptions.remove(&msg.client_id);
+
         Ok(jsonrpc_core::Value::Bool(true))
     }
 }
 
+/// Notification of a subscription made by the wallet itself: method and item
 #[derive(Debug)]
 struct NormalNotification(String, jsonrpc_core::Value);
 
-impl StreamHandler<NormalNotification, async_jsonrpc_client::Error> for JsonRpcClient {
+impl Message for NormalNotification {
+    type Result = ();
+}
+
+impl Handler<NormalNotification> for JsonRpcClient {
+    type Result = ();
+
     fn handle(
         &mut self,
-        NormalNotification(id, item): NormalNotification,
+        NormalNotification(method, item): NormalNotification,
         _ctx: &mut Self::Context,
     ) {
-        info!("Got subscription for id {}: {}", id, item);
+        info!("Got notification of {}: {}", method, item);
+        match method.as_str() {
+            "newBlocks" => match serde_json::from_value::<Block>(item) {
+                Ok(block) => IndexManager::from_registry().do_send(IndexBlock(block)),
+                Err(e) => warn!("Failed to parse block from subscription: {}", e),
+            },
+            "newTransactions" => match serde_json::from_value::<ChainTransaction>(item) {
+                Ok(transaction) => {
+                    IndexManager::from_registry().do_send(IndexPendingTransaction(transaction))
+                }
+                Err(e) => warn!("Failed to parse transaction from subscription: {}", e),
+            },
+            _ => warn!("Unexpected notification of method {}: {}", method, item),
+        }
     }
 }
 
+/// Notification of a subscription made on behalf of a client: client subscription id and item
 #[derive(Debug)]
 struct ForwardNotification(String, jsonrpc_core::Value);
 
-impl StreamHandler<ForwardNotification, async_jsonrpc_client::Error> for JsonRpcClient {
+impl Message for ForwardNotification {
+    type Result = ();
+}
+
+impl Handler<ForwardNotification> for JsonRpcClient {
+    type Result = ();
+
     fn handle(
         &mut self,
         ForwardNotification(id, item): ForwardNotification,
         ctx: &mut Self::Context,
     ) {
         info!("Got subscription with id! {} {}", id, item);
-        let idd: SubscriptionId = id.clone().into();
+        let client_id: SubscriptionId = id.into();
         // Now we need to send a notification to the client
         // TODO: SubRes could be imported as SubscriptionResult from witnet_node/json_rpc
         // but importing witnet_node results in a dependency cycle
@@ -1078,26 +1428,425 @@ impl StreamHandler<ForwardNotification, async_jsonrpc_client::Error> for JsonRpc
             }
         }
 
-        if let Some(client_id) = self.node_id_to_client_id.get(&idd) {
-            let r = SubRes {
-                result: item.clone(),
-                subscription: client_id.clone(),
-            };
-            let params = jsonrpc_core::Params::from(r);
-            if let Ok(ss) = self.subscriptions.lock() {
-                for (_method, v) in ss.iter() {
-                    if let Some((sink, Some(node_id), _sub_params)) = v.get(client_id) {
-                        info!(
-                            "Forwarding subscription to parent: {:?} => {:?}",
-                            node_id, client_id
-                        );
-                        sink.notify(params.clone())
-                            .into_actor(self)
-                            .then(|_act, _res, _ctx| actix::fut::ok(()))
-                            .wait(ctx);
-                    }
+        let r = SubRes {
+            result: item,
+            subscription: client_id.clone(),
+        };
+        let params = jsonrpc_core::Params::from(r);
+        if let Ok(ss) = self.subscriptions.lock() {
+            for (_method, v) in ss.iter() {
+                if let Some((sink, _sub_params)) = v.get(&client_id) {
+                    info!("Forwarding subscription to parent: {:?}", client_id);
+                    sink.notify(params.clone())
+                        .into_actor(self)
+                        .then(|_act, _res, _ctx| actix::fut::ok(()))
+                        .wait(ctx);
                 }
             }
         }
     }
 }
+
+/// Maximum number of blocks requested to the node in each step of a rescan
+const RESCAN_BATCH_SIZE: u32 = 100;
+
+/// Actor which keeps the chain index of every wallet
+#[derive(Debug, Default)]
+struct IndexManager {
+    wallets: HashMap<String, WalletIndex>,
+}
+
+impl IndexManager {
+    fn get_wallet(&mut self, wallet_id: &str) -> Result<&mut WalletIndex, String> {
+        self.wallets
+            .get_mut(wallet_id)
+            .ok_or_else(|| format!("Unknown wallet: {}", wallet_id))
+    }
+}
+
+impl Actor for IndexManager {
+    /// Every actor has to provide execution `Context` in which it can run
+    type Context = Context<Self>;
+
+    /// Method to be executed when the actor is started
+    fn started(&mut self, _ctx: &mut Self::Context) {
+        debug!("IndexManager actor has been started!");
+    }
+}
+
+/// Required traits for being able to retrieve actor address from registry
+impl Supervised for IndexManager {}
+impl SystemService for IndexManager {}
+
+/// Index a block in every wallet
+struct IndexBlock(Block);
+
+impl Message for IndexBlock {
+    type Result = ();
+}
+
+impl Handler<IndexBlock> for IndexManager {
+    type Result = ();
+
+    fn handle(&mut self, IndexBlock(block): IndexBlock, _ctx: &mut Context<Self>) {
+        debug!(
+            "Indexing block for epoch #{}",
+            block.block_header.beacon.checkpoint
+        );
+        for index in self.wallets.values_mut() {
+            index.index_block(&block);
+        }
+    }
+}
+
+/// Add a transaction from the mempool of the node to every wallet it touches.
+/// It stays pending until a block including it is indexed
+struct IndexPendingTransaction(ChainTransaction);
+
+impl Message for IndexPendingTransaction {
+    type Result = ();
+}
+
+impl Handler<IndexPendingTransaction> for IndexManager {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        IndexPendingTransaction(transaction): IndexPendingTransaction,
+        _ctx: &mut Context<Self>,
+    ) {
+        for (wallet_id, index) in self.wallets.iter_mut() {
+            if index.add_pending(&transaction) {
+                debug!(
+                    "Pending transaction {} added to wallet {}",
+                    transaction.hash(),
+                    wallet_id
+                );
+            }
+        }
+    }
+}
+
+/// Watch the addresses of a wallet, adding the wallet to the index if needed
+struct Watch {
+    wallet_id: String,
+    pkhs: Vec<PublicKeyHash>,
+}
+
+impl Message for Watch {
+    type Result = ();
+}
+
+impl Handler<Watch> for IndexManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: Watch, ctx: &mut Context<Self>) {
+        let is_new = !self.wallets.contains_key(&msg.wallet_id);
+        self.wallets
+            .entry(msg.wallet_id.clone())
+            .or_default()
+            .watch(msg.pkhs);
+
+        // Blocks consolidated before the wallet was imported may contain its transactions
+        if is_new {
+            ctx.notify(ScanBlocks {
+                wallet_id: msg.wallet_id,
+                epoch: 0,
+            });
+        }
+    }
+}
+
+/// Get the balance of a wallet
+struct GetBalance {
+    wallet_id: String,
+}
+
+impl Message for GetBalance {
+    type Result = Result<Balance, String>;
+}
+
+impl Handler<GetBalance> for IndexManager {
+    type Result = Result<Balance, String>;
+
+    fn handle(&mut self, msg: GetBalance, _ctx: &mut Context<Self>) -> Self::Result {
+        self.get_wallet(&msg.wallet_id).map(|index| index.balance())
+    }
+}
+
+/// Get a page of the transaction history of a wallet
+struct GetTransactions {
+    wallet_id: String,
+    limit: u32,
+    page: u32,
+}
+
+impl Message for GetTransactions {
+    type Result = Result<Vec<TransactionRecord>, String>;
+}
+
+impl Handler<GetTransactions> for IndexManager {
+    type Result = Result<Vec<TransactionRecord>, String>;
+
+    fn handle(&mut self, msg: GetTransactions, _ctx: &mut Context<Self>) -> Self::Result {
+        self.get_wallet(&msg.wallet_id)
+            .map(|index| index.transactions(msg.limit, msg.page))
+    }
+}
+
+/// Forget the index of a wallet from the given epoch onwards and scan those blocks again
+struct Rescan {
+    wallet_id: String,
+    epoch: Epoch,
+}
+
+impl Message for Rescan {
+    type Result = Result<bool, String>;
+}
+
+impl Handler<Rescan> for IndexManager {
+    type Result = Result<bool, String>;
+
+    fn handle(&mut self, msg: Rescan, ctx: &mut Context<Self>) -> Self::Result {
+        self.get_wallet(&msg.wallet_id)?.rollback(msg.epoch);
+        info!(
+            "Rescanning wallet {} from epoch #{}",
+            msg.wallet_id, msg.epoch
+        );
+        ctx.notify(ScanBlocks {
+            wallet_id: msg.wallet_id,
+            epoch: msg.epoch,
+        });
+
+        Ok(true)
+    }
+}
+
+/// Request a batch of blocks to the node and index them in a wallet.
+/// Another batch is requested until the tip of the chain is reached
+struct ScanBlocks {
+    wallet_id: String,
+    epoch: Epoch,
+}
+
+impl Message for ScanBlocks {
+    type Result = Result<(), ()>;
+}
+
+impl Handler<ScanBlocks> for IndexManager {
+    type Result = ResponseActFuture<Self, (), ()>;
+
+    fn handle(&mut self, msg: ScanBlocks, _ctx: &mut Context<Self>) -> Self::Result {
+        let ScanBlocks { wallet_id, epoch } = msg;
+
+        let fut = JsonRpcClient::from_registry()
+            .send(GetBlocks {
+                epoch,
+                limit: RESCAN_BATCH_SIZE,
+            })
+            .map_err(|e| e.to_string())
+            .and_then(|res| res)
+            .into_actor(self)
+            .then(move |res, act, ctx| {
+                let blocks = match res {
+                    Ok(blocks) => blocks,
+                    Err(e) => {
+                        warn!("Failed to rescan wallet {}: {}", wallet_id, e);
+                        return actix::fut::ok(());
+                    }
+                };
+                let index = match act.get_wallet(&wallet_id) {
+                    Ok(index) => index,
+                    Err(e) => {
+                        warn!("Failed to rescan wallet: {}", e);
+                        return actix::fut::ok(());
+                    }
+                };
+
+                for block in &blocks {
+                    index.index_block(block);
+                }
+
+                match blocks.last() {
+                    Some(last) if blocks.len() >= RESCAN_BATCH_SIZE as usize => {
+                        let epoch = last.block_header.beacon.checkpoint + 1;
+                        ctx.notify(ScanBlocks { wallet_id, epoch });
+                    }
+                    _ => info!(
+                        "Finished rescan of wallet {} from epoch #{}",
+                        wallet_id, epoch
+                    ),
+                }
+
+                actix::fut::ok(())
+            });
+
+        Box::new(fut)
+    }
+}
+
+/// Number of addresses of the external key chain watched for every wallet
+const WATCHED_ADDRESSES: u32 = 20;
+
+/// Witnet coin type as registered in SLIP-0044
+const COIN_TYPE: u32 = 4919;
+
+/// Imported wallet
+#[derive(Debug)]
+struct Wallet {
+    info: WalletInfo,
+    master_key: ExtendedSK,
+    /// Index of the next address returned by generateAddress
+    next_address: u32,
+}
+
+/// Actor which keeps the master keys of the imported wallets
+#[derive(Debug, Default)]
+struct WalletManager {
+    wallets: HashMap<String, Wallet>,
+    /// Environment of the node the wallet connects to, which sets the prefix of the addresses
+    environment: Environment,
+}
+
+impl Actor for WalletManager {
+    /// Every actor has to provide execution `Context` in which it can run
+    type Context = Context<Self>;
+
+    /// Method to be executed when the actor is started
+    fn started(&mut self, _ctx: &mut Self::Context) {
+        debug!("WalletManager actor has been started!");
+    }
+}
+
+/// Required traits for being able to retrieve actor address from registry
+impl Supervised for WalletManager {}
+impl SystemService for WalletManager {}
+
+/// Derive the addresses of the external key chain of the first account of a wallet:
+/// m/44'/4919'/0'/0/i
+fn derive_addresses(
+    master_key: &ExtendedSK,
+    indexes: std::ops::Range<u32>,
+) -> Result<Vec<PublicKeyHash>, String> {
+    let external = master_key
+        .derive(vec![
+            KeyChildNumber::hardened(44),
+            KeyChildNumber::hardened(COIN_TYPE),
+            KeyChildNumber::hardened(0),
+            KeyChildNumber::normal(0),
+        ])
+        .map_err(|e| e.to_string())?;
+    let context = SignContext::signing_only();
+
+    indexes
+        .map(|index| {
+            external
+                .child(KeyChildNumber::normal(index))
+                .map(|key| PublicKey::from(PK::from_secret_key(&context, &key.secret_key)).pkh())
+                .map_err(|e| e.to_string())
+        })
+        .collect()
+}
+
+/// Import a wallet from its master key and start indexing its addresses
+struct ImportWallet {
+    caption: String,
+    master_key: ExtendedSK,
+}
+
+impl Message for ImportWallet {
+    type Result = Result<WalletInfo, String>;
+}
+
+impl Handler<ImportWallet> for WalletManager {
+    type Result = Result<WalletInfo, String>;
+
+    fn handle(&mut self, msg: ImportWallet, _ctx: &mut Context<Self>) -> Self::Result {
+        let ImportWallet {
+            caption,
+            master_key,
+        } = msg;
+
+        // The id only depends on the master key, so importing the same seed twice results in
+        // the same wallet
+        let master_pk = PK::from_secret_key(&SignContext::signing_only(), &master_key.secret_key);
+        let id = Hash::from(calculate_sha256(&master_pk.serialize())).to_string();
+        if let Some(wallet) = self.wallets.get(&id) {
+            return Ok(wallet.info.clone());
+        }
+
+        let pkhs = derive_addresses(&master_key, 0..WATCHED_ADDRESSES)?;
+        IndexManager::from_registry().do_send(Watch {
+            wallet_id: id.clone(),
+            pkhs,
+        });
+
+        let info = WalletInfo { id, caption };
+        info!("Imported wallet {}", info.id);
+        self.wallets.insert(
+            info.id.clone(),
+            Wallet {
+                info: info.clone(),
+                master_key,
+                next_address: 0,
+            },
+        );
+
+        Ok(info)
+    }
+}
+
+/// Get the list of imported wallets
+struct GetWalletInfos;
+
+impl Message for GetWalletInfos {
+    type Result = Result<Vec<WalletInfo>, String>;
+}
+
+impl Handler<GetWalletInfos> for WalletManager {
+    type Result = Result<Vec<WalletInfo>, String>;
+
+    fn handle(&mut self, _msg: GetWalletInfos, _ctx: &mut Context<Self>) -> Self::Result {
+        Ok(self
+            .wallets
+            .values()
+            .map(|wallet| wallet.info.clone())
+            .collect())
+    }
+}
+
+/// Get a new address of a wallet, the next unused one of its external key chain
+struct GenerateAddress {
+    wallet_id: String,
+}
+
+impl Message for GenerateAddress {
+    type Result = Result<Address, String>;
+}
+
+impl Handler<GenerateAddress> for WalletManager {
+    type Result = Result<Address, String>;
+
+    fn handle(&mut self, msg: GenerateAddress, _ctx: &mut Context<Self>) -> Self::Result {
+        let wallet = self
+            .wallets
+            .get_mut(&msg.wallet_id)
+            .ok_or_else(|| format!("Unknown wallet: {}", msg.wallet_id))?;
+        let index = wallet.next_address;
+        let pkhs = derive_addresses(&wallet.master_key, index..index + 1)?;
+        wallet.next_address += 1;
+
+        // The first addresses are watched since the wallet was imported
+        if index >= WATCHED_ADDRESSES {
+            IndexManager::from_registry().do_send(Watch {
+                wallet_id: msg.wallet_id,
+                pkhs: pkhs.clone(),
+            });
+        }
+
+        Ok(Address {
+            address: pkhs[0].bech32(&self.environment),
+            path: format!("m/44'/{}'/0'/0/{}", COIN_TYPE, index),
+        })
+    }
+}